@@ -15,6 +15,11 @@ export type Commit = {
 	 * Conflicts are resolved via the Edit Mode mechanism.
 	 */
 	readonly hasConflicts: boolean;
+	/**
+	 * Whether the conflicts of this commit were resolved automatically by reusing recorded resolutions.
+	 * Such commits should be reviewed by the user.
+	 */
+	readonly hasRecordedResolution: boolean;
 	/**
 	 * Represents wether the the commit is considered integrated, local only,
 	 * or local and remote with respect to the branch it belongs to.
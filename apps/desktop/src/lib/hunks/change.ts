@@ -75,7 +75,8 @@ export type Status =
 	| { readonly type: 'Addition'; readonly subject: Addition }
 	| { readonly type: 'Deletion'; readonly subject: Deletion }
 	| { readonly type: 'Modification'; readonly subject: Modification }
-	| { readonly type: 'Rename'; readonly subject: Rename };
+	| { readonly type: 'Rename'; readonly subject: Rename }
+	| { readonly type: 'Copy'; readonly subject: Copy };
 /** Something was added or scheduled to be added.*/
 
 export function isChangeStatus(something: unknown): something is Status {
@@ -119,6 +120,19 @@ export type Rename = {
 	readonly state: ChangeState;
 	readonly flags: Flags | null;
 };
+/**
+ * An entry was copied from `source_path` to its current location, while the source remains.
+ * Note that this may include a content change, as well as a change of the executable bit.
+ */
+export type Copy = {
+	readonly sourcePath: string;
+	readonly sourcePathBytes: number[];
+	/** @private */
+	readonly sourceState: ChangeState;
+	/** @private */
+	readonly state: ChangeState;
+	readonly flags: Flags | null;
+};
 
 /**
  * Something that fully identifies the state of a [`TreeChange`] in the backend.
@@ -6,12 +6,52 @@ import type { Patch } from '$lib/hunks/hunk';
  * of a deletion.
  */
 export type UnifiedDiff =
-	| { readonly type: 'Binary' } // A binary file that can't be diffed.
+	| { readonly type: 'Binary'; readonly subject: BinaryDiff } // A binary file that can't be diffed.
 	| { readonly type: 'TooLarge'; readonly subject: TooLarge }
 	| { readonly type: 'Patch'; readonly subject: Patch };
 
+/** Information about both versions of a binary file, which can't be shown as a patch. */
+export type BinaryDiff = {
+	/** The previous version, or `null` if the file was added. */
+	readonly old: BinaryResource | null;
+	/** The current version, or `null` if the file was deleted. */
+	readonly new: BinaryResource | null;
+	/** If `true`, both versions exist and have the same content. */
+	readonly contentUnchanged: boolean;
+};
+
+/** A version of a binary file. */
+export type BinaryResource = {
+	readonly path: string;
+	/** The hash of the content. */
+	readonly id: string;
+	readonly sizeInBytes: number;
+	/** The MIME type as inferred from the content, if it could be determined. */
+	readonly mimeType: string | null;
+	/** The size of the image in pixels, if the file is an image in a known format. */
+	readonly imageDimensions: { readonly width: number; readonly height: number } | null;
+};
+
 /** The file was too large and couldn't be diffed. */
 export type TooLarge = {
 	/** The size of the file on disk that made it too large. */
 	readonly sizeInBytes: number;
 };
+
+/** Describe the sizes and image dimensions of both versions of a binary file, like `1×1, 29 bytes → 3×2, 33 bytes`. */
+export function describeBinaryDiff(diff: BinaryDiff): string {
+	if (diff.contentUnchanged) {
+		return 'Content unchanged';
+	}
+	return [diff.old, diff.new]
+		.map((resource) => {
+			if (!resource) {
+				return 'none';
+			}
+			const dimensions = resource.imageDimensions
+				? `${resource.imageDimensions.width}×${resource.imageDimensions.height}, `
+				: '';
+			return `${dimensions}${resource.sizeInBytes} bytes`;
+		})
+		.join(' → ');
+}
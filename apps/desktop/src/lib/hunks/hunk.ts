@@ -241,9 +241,22 @@ export type DiffHunk = Prettify<
 		 * Note that the file-portion of the header isn't used here.
 		 */
 		readonly diff: string;
+		/**
+		 * The changed portions of removed and added lines in `diff`.
+		 * Only present if intra-line changes were requested.
+		 */
+		readonly highlights?: LineHighlight[];
 	}
 >;
 
+/** The changed portions of a removed or added line in a `DiffHunk`. */
+export type LineHighlight = {
+	/** The 0-based index of the line in `DiffHunk.diff`, with `0` being the `@@` header. */
+	readonly line: number;
+	/** The sorted byte ranges that changed, relative to the first byte after the `+` or `-` prefix. */
+	readonly ranges: { readonly start: number; readonly end: number }[];
+};
+
 export function isDiffHunk(something: unknown): something is DiffHunk {
 	return (
 		typeof something === 'object' &&
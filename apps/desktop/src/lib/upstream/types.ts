@@ -64,6 +64,8 @@ export type Resolution = {
 	branchId: string;
 	approach: ResolutionApproach;
 	deleteIntegratedBranches: boolean;
+	/** Also delete integrated branches that GitButler created on the push remote. */
+	deleteIntegratedRemoteBranches?: boolean;
 };
 
 export type BaseBranchResolutionApproach = 'rebase' | 'merge' | 'hardReset';
@@ -76,6 +78,8 @@ export type BaseBranchResolution = {
 export type IntegrationOutcome = {
 	archivedBranches: string[];
 	reviewIdsToClose: string[];
+	deletedRemoteBranches: string[];
+	keptRemoteBranches: string[];
 };
 
 export function getBaseBranchResolution(
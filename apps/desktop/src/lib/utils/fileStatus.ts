@@ -17,6 +17,7 @@ export function computeFileStatus(file: AnyFile): FileStatus {
 export function computeChangeStatus(change: TreeChange): FileStatus {
 	switch (change.status.type) {
 		case 'Addition':
+		case 'Copy':
 			return 'A';
 		case 'Deletion':
 			return 'D';
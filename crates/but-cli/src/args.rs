@@ -65,6 +65,9 @@ pub enum Subcommands {
         /// A JSON specification of the changes to commit.
         #[clap(long)]
         diff_spec: Option<String>,
+        /// The options used to compute the hunks in `hunk_headers` or `diff_spec`.
+        #[clap(flatten)]
+        diff_options: DiffOptions,
     },
     /// List all uncommitted working tree changes.
     Status {
@@ -74,6 +77,11 @@ pub enum Subcommands {
         /// Also compute unified diffs for each tree-change.
         #[clap(long, short = 'd')]
         unified_diff: bool,
+        /// Highlight the changed portions of modified lines in unified diffs.
+        #[clap(long, value_enum, requires = "unified_diff")]
+        intra_line: Option<IntraLineDiff>,
+        #[clap(flatten)]
+        diff_options: DiffOptions,
     },
     /// Discard the specified worktree change.
     DiscardChange {
@@ -93,6 +101,11 @@ pub enum Subcommands {
         /// Also compute unified diffs for each tree-change.
         #[clap(long, short = 'd')]
         unified_diff: bool,
+        /// Highlight the changed portions of modified lines in unified diffs.
+        #[clap(long, value_enum, requires = "unified_diff")]
+        intra_line: Option<IntraLineDiff>,
+        #[clap(flatten)]
+        diff_options: DiffOptions,
         /// The revspec to the commit that the returned changes turn the previous commit into.
         current_commit: String,
         /// The revspec to the previous commit that the returned changes transform into current commit.
@@ -130,6 +143,83 @@ pub enum Subcommands {
     StackBranchCommits { id: String, name: String },
 }
 
+/// The granularity at which changes within modified lines are highlighted.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum IntraLineDiff {
+    /// Highlight changed words, as defined by `diff.wordRegex`.
+    Words,
+    /// Highlight changed characters.
+    Characters,
+}
+
+impl From<IntraLineDiff> for but_core::unified_diff::IntraLineDiff {
+    fn from(value: IntraLineDiff) -> Self {
+        match value {
+            IntraLineDiff::Words => but_core::unified_diff::IntraLineDiff::Words,
+            IntraLineDiff::Characters => but_core::unified_diff::IntraLineDiff::Characters,
+        }
+    }
+}
+
+/// Options to control how the hunks of unified diffs are computed.
+#[derive(Debug, clap::Args)]
+pub struct DiffOptions {
+    /// The diff algorithm to use instead of the one configured in `diff.algorithm`.
+    #[clap(long, value_enum)]
+    pub diff_algorithm: Option<DiffAlgorithm>,
+    /// Ignore whitespace when comparing lines.
+    #[clap(long, short = 'w')]
+    pub ignore_all_space: bool,
+    /// Ignore changes in the amount of whitespace.
+    #[clap(long, short = 'b', conflicts_with = "ignore_all_space")]
+    pub ignore_space_change: bool,
+    /// Ignore changes whose lines are all blank.
+    #[clap(long)]
+    pub ignore_blank_lines: bool,
+    /// Shift ambiguous additions and removals so they start at the least indented line.
+    #[clap(long)]
+    pub indent_heuristic: bool,
+}
+
+impl From<&DiffOptions> for but_core::unified_diff::DiffOptions {
+    fn from(value: &DiffOptions) -> Self {
+        use but_core::unified_diff::WhitespaceMode;
+        but_core::unified_diff::DiffOptions {
+            algorithm: value.diff_algorithm.map(Into::into),
+            whitespace: if value.ignore_all_space {
+                WhitespaceMode::IgnoreAll
+            } else if value.ignore_space_change {
+                WhitespaceMode::IgnoreChange
+            } else {
+                WhitespaceMode::Respect
+            },
+            ignore_blank_lines: value.ignore_blank_lines,
+            indent_heuristic: value.indent_heuristic,
+        }
+    }
+}
+
+/// The algorithm used to compute unified diffs.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum DiffAlgorithm {
+    /// The histogram algorithm.
+    Histogram,
+    /// The Myers algorithm.
+    Myers,
+    /// The patience algorithm.
+    Patience,
+}
+
+impl From<DiffAlgorithm> for but_core::unified_diff::DiffAlgorithm {
+    fn from(value: DiffAlgorithm) -> Self {
+        match value {
+            DiffAlgorithm::Histogram => but_core::unified_diff::DiffAlgorithm::Histogram,
+            DiffAlgorithm::Myers => but_core::unified_diff::DiffAlgorithm::Myers,
+            DiffAlgorithm::Patience => but_core::unified_diff::DiffAlgorithm::Patience,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
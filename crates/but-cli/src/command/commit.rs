@@ -1,7 +1,10 @@
 use crate::command::discard_change::IndicesOrHeaders;
-use crate::command::{debug_print, indices_or_headers_to_hunk_headers, path_to_rela_path};
+use crate::command::{
+    WORKTREE_ACCESS_TIMEOUT, debug_print, indices_or_headers_to_hunk_headers, path_to_rela_path,
+};
 use anyhow::bail;
 use but_core::TreeChange;
+use but_core::unified_diff::DiffOptions;
 use but_workspace::DiffSpec;
 use but_workspace::commit_engine::{ReferenceFrame, StackSegmentId, create_commit_and_update_refs};
 use gitbutler_project::Project;
@@ -21,6 +24,7 @@ pub fn commit(
     previous_rela_path: Option<&Path>,
     headers: Option<&[u32]>,
     diff_spec: Option<Vec<DiffSpec>>,
+    diff_options: DiffOptions,
     use_json: bool,
 ) -> anyhow::Result<()> {
     if message.is_none() && !amend {
@@ -46,6 +50,7 @@ pub fn commit(
             parent_id,
             stack_segment_ref,
             changes,
+            diff_options,
             use_json,
         )?;
     } else {
@@ -57,6 +62,7 @@ pub fn commit(
             stack_segment_ref,
             workspace_tip,
             changes,
+            diff_options,
             use_json,
         )?;
     }
@@ -118,6 +124,7 @@ fn commit_with_project(
     parent_id: Option<gix::ObjectId>,
     stack_segment_ref: Option<&str>,
     changes: Vec<DiffSpec>,
+    diff_options: DiffOptions,
     use_json: bool,
 ) -> anyhow::Result<()> {
     let destination = if amend {
@@ -136,7 +143,7 @@ fn commit_with_project(
             stack_segment,
         }
     };
-    let mut guard = project.exclusive_worktree_access();
+    let mut guard = project.try_exclusive_worktree_access(WORKTREE_ACCESS_TIMEOUT)?;
     let outcome = but_workspace::commit_engine::create_commit_and_update_refs_with_project(
         repo,
         project,
@@ -145,6 +152,7 @@ fn commit_with_project(
         None,
         changes,
         0, /* context-lines */
+        diff_options,
         guard.write_permission(),
     )?;
 
@@ -168,6 +176,7 @@ fn commit_without_project(
     stack_segment_ref: Option<&str>,
     workspace_tip: Option<&str>,
     changes: Vec<DiffSpec>,
+    diff_options: DiffOptions,
     use_json: bool,
 ) -> anyhow::Result<()> {
     let destination = if amend {
@@ -203,6 +212,7 @@ fn commit_without_project(
         None,
         changes,
         0,
+        diff_options,
     )?;
 
     if use_json {
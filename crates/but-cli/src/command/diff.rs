@@ -1,4 +1,5 @@
 use crate::command::{UI_CONTEXT_LINES, debug_print, project_from_path, project_repo};
+use but_core::unified_diff::{DiffOptions, IntraLineDiff};
 use but_hunk_dependency::ui::HunkDependencies;
 use but_settings::AppSettings;
 use gitbutler_command_context::CommandContext;
@@ -11,6 +12,8 @@ pub fn commit_changes(
     current_commit: &str,
     previous_commit: Option<&str>,
     unified_diff: bool,
+    diff_options: DiffOptions,
+    intra_line: Option<IntraLineDiff>,
 ) -> anyhow::Result<()> {
     let repo = project_repo(current_dir)?;
     let previous_commit = previous_commit
@@ -21,7 +24,13 @@ pub fn commit_changes(
         but_core::diff::tree_changes(&repo, previous_commit.map(Into::into), commit.into())?;
 
     if unified_diff {
-        debug_print(unified_diff_for_changes(&repo, changes, UI_CONTEXT_LINES)?)
+        debug_print(unified_diff_for_changes(
+            &repo,
+            changes,
+            UI_CONTEXT_LINES,
+            diff_options,
+            intra_line,
+        )?)
     } else {
         debug_print(changes)
     }
@@ -31,12 +40,21 @@ pub fn status(
     current_dir: &Path,
     unified_diff: bool,
     context_lines: u32,
+    diff_options: DiffOptions,
+    intra_line: Option<IntraLineDiff>,
     use_json: bool,
 ) -> anyhow::Result<()> {
     let repo = project_repo(current_dir)?;
     let worktree = but_core::diff::worktree_changes(&repo)?;
     if unified_diff {
-        handle_unified_diff(&repo, worktree, context_lines, use_json)?;
+        handle_unified_diff(
+            &repo,
+            worktree,
+            context_lines,
+            diff_options,
+            intra_line,
+            use_json,
+        )?;
     } else {
         handle_normal_diff(worktree, use_json)?;
     }
@@ -47,9 +65,17 @@ fn handle_unified_diff(
     repo: &gix::Repository,
     worktree: but_core::WorktreeChanges,
     context_lines: u32,
+    diff_options: DiffOptions,
+    intra_line: Option<IntraLineDiff>,
     use_json: bool,
 ) -> anyhow::Result<()> {
-    let diff = unified_diff_for_changes(repo, worktree.changes.clone(), context_lines)?;
+    let diff = unified_diff_for_changes(
+        repo,
+        worktree.changes.clone(),
+        context_lines,
+        diff_options,
+        intra_line,
+    )?;
     if use_json {
         let serializable: but_core::ui::UnifiedWorktreeChanges = (worktree, &diff).into();
         let json = serde_json::to_string_pretty(&serializable)?;
@@ -115,15 +141,20 @@ fn unified_diff_for_changes(
     repo: &gix::Repository,
     changes: Vec<but_core::TreeChange>,
     context_lines: u32,
+    diff_options: DiffOptions,
+    intra_line: Option<IntraLineDiff>,
 ) -> anyhow::Result<Vec<(but_core::TreeChange, but_core::UnifiedDiff)>> {
     changes
         .into_iter()
         .map(|tree_change| {
-            tree_change
-                .unified_diff(repo, context_lines)
-                .map(|diff| (tree_change, diff))
+            let mut diff =
+                tree_change.unified_diff_with_options(repo, context_lines, diff_options)?;
+            if let Some(granularity) = intra_line {
+                diff.highlight_intra_line_changes(repo, granularity)?;
+            }
+            Ok((tree_change, diff))
         })
-        .collect::<Result<Vec<_>, _>>()
+        .collect::<anyhow::Result<Vec<_>>>()
 }
 
 fn intersect_workspace_ranges(
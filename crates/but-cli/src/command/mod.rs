@@ -4,8 +4,11 @@ use but_workspace::{DiffSpec, HunkHeader};
 use gitbutler_project::Project;
 use gix::bstr::{BString, ByteSlice};
 use std::path::Path;
+use std::time::Duration;
 
 pub(crate) const UI_CONTEXT_LINES: u32 = 3;
+/// How long to wait for other processes, like the GitButler app, to release the workspace.
+pub(crate) const WORKTREE_ACCESS_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub fn project_from_path(path: &Path) -> anyhow::Result<Project> {
     Project::from_path(path)
@@ -49,6 +49,7 @@ fn main() -> Result<()> {
             workspace_tip,
             stack_segment_ref,
             diff_spec,
+            diff_options,
         } => {
             let (repo, project) = repo_and_maybe_project(&args, RepositoryOpenMode::Merge)?;
             let diff_spec = parse_diff_spec(diff_spec)?;
@@ -68,6 +69,7 @@ fn main() -> Result<()> {
                     None
                 },
                 diff_spec,
+                diff_options.into(),
                 args.json,
             )
         }
@@ -77,9 +79,20 @@ fn main() -> Result<()> {
         args::Subcommands::Status {
             unified_diff,
             context_lines,
-        } => command::diff::status(&args.current_dir, *unified_diff, *context_lines, args.json),
+            intra_line,
+            diff_options,
+        } => command::diff::status(
+            &args.current_dir,
+            *unified_diff,
+            *context_lines,
+            diff_options.into(),
+            intra_line.map(Into::into),
+            args.json,
+        ),
         args::Subcommands::CommitChanges {
             unified_diff,
+            intra_line,
+            diff_options,
             current_commit,
             previous_commit,
         } => command::diff::commit_changes(
@@ -87,6 +100,8 @@ fn main() -> Result<()> {
             current_commit,
             previous_commit.as_deref(),
             *unified_diff,
+            diff_options.into(),
+            intra_line.map(Into::into),
         ),
         args::Subcommands::Stacks => command::stacks::list(&args.current_dir, args.json),
         args::Subcommands::StackBranches {
@@ -114,6 +114,9 @@ const HEADERS_VERSION_FIELD: &str = "gitbutler-headers-version";
 const HEADERS_CHANGE_ID_FIELD: &str = "gitbutler-change-id";
 /// The name of the header field that stores the amount of conflicted files.
 pub const HEADERS_CONFLICTED_FIELD: &str = "gitbutler-conflicted";
+/// The name of the header field that stores the amount of files whose conflicts were resolved automatically
+/// with previously recorded resolutions, so the commit can be reviewed.
+pub const HEADERS_RECORDED_RESOLUTION_FIELD: &str = "gitbutler-recorded-resolution";
 const HEADERS_VERSION: &str = "2";
 
 impl From<&HeadersV2> for Vec<(BString, BString)> {
@@ -243,4 +246,13 @@ impl<'repo> Commit<'repo> {
     pub fn headers(&self) -> Option<HeadersV2> {
         HeadersV2::try_from_commit(&self.inner)
     }
+
+    /// Return `Some(num_files)` if conflicts in this commit were resolved automatically by reusing
+    /// `num_files` recorded resolutions, which means it should be reviewed.
+    pub fn recorded_resolution_files(&self) -> Option<u64> {
+        self.inner
+            .extra_headers()
+            .find(HEADERS_RECORDED_RESOLUTION_FIELD)
+            .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+    }
 }
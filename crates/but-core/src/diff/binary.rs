@@ -0,0 +1,284 @@
+use crate::unified_diff::{DiffOptions, compute_line_changes, hunks_with_options};
+use crate::{ChangeState, UnifiedDiff};
+use anyhow::Context;
+use bstr::{BStr, BString, ByteSlice};
+use gix::diff::blob::intern::InternedInput;
+use gix::diff::blob::sources::byte_lines_with_terminator;
+use serde::Serialize;
+
+/// Information about both versions of a binary resource, which can't be shown as a patch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryDiff {
+    /// The previous version of the resource, or `None` if it was added.
+    pub old: Option<BinaryResource>,
+    /// The current version of the resource, or `None` if it was deleted.
+    pub new: Option<BinaryResource>,
+    /// If `true`, both versions exist and have the same content, so only the path or the mode of the resource changed.
+    pub content_unchanged: bool,
+}
+
+/// A version of a binary resource.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryResource {
+    /// The worktree-relative path at which the resource is or was located.
+    #[serde(serialize_with = "gitbutler_serde::bstring_lossy::serialize")]
+    pub path: BString,
+    /// The hash of the content, which is also its id in the object database if it is stored there.
+    #[serde(with = "gitbutler_serde::object_id")]
+    pub id: gix::ObjectId,
+    /// The size of the content in bytes.
+    pub size_in_bytes: u64,
+    /// The MIME type as inferred from the content, or `None` if it couldn't be determined.
+    pub mime_type: Option<String>,
+    /// The dimensions of the image, or `None` if the content isn't an image in a format we know.
+    pub image_dimensions: Option<ImageDimensions>,
+}
+
+/// The size of an image in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImageDimensions {
+    /// The width in pixels.
+    pub width: u32,
+    /// The height in pixels.
+    pub height: u32,
+}
+
+/// Turn binary content into text that can be diffed, which is what Git does with the `textconv` program of a diff driver.
+pub trait BinaryToText {
+    /// Return the text representation of `data`, the content of `resource`, or `None` if it can't be converted.
+    fn to_text(
+        &mut self,
+        resource: &BinaryResource,
+        data: &[u8],
+    ) -> anyhow::Result<Option<BString>>;
+}
+
+impl<F> BinaryToText for F
+where
+    F: FnMut(&BinaryResource, &[u8]) -> anyhow::Result<Option<BString>>,
+{
+    fn to_text(
+        &mut self,
+        resource: &BinaryResource,
+        data: &[u8],
+    ) -> anyhow::Result<Option<BString>> {
+        self(resource, data)
+    }
+}
+
+/// A [`BinaryToText`] converter that describes the metadata of a resource, one line per known property,
+/// so changes to its size, type or image dimensions can be diffed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MetadataSummary;
+
+impl BinaryToText for MetadataSummary {
+    fn to_text(
+        &mut self,
+        resource: &BinaryResource,
+        _data: &[u8],
+    ) -> anyhow::Result<Option<BString>> {
+        let mut out = format!("size: {} bytes\n", resource.size_in_bytes);
+        if let Some(mime_type) = &resource.mime_type {
+            out.push_str(&format!("type: {mime_type}\n"));
+        }
+        if let Some(ImageDimensions { width, height }) = resource.image_dimensions {
+            out.push_str(&format!("dimensions: {width}x{height}\n"));
+        }
+        out.push_str(&format!("id: {}\n", resource.id));
+        Ok(Some(out.into()))
+    }
+}
+
+impl BinaryDiff {
+    /// Collect information about `previous_state` and `current_state` of the resource at `path`,
+    /// with the same meaning of all parameters as in [`UnifiedDiff::compute()`].
+    ///
+    /// States with a null id are read from the worktree as they are on disk, without applying any filters.
+    pub fn compute(
+        repo: &gix::Repository,
+        path: &BStr,
+        previous_path: Option<&BStr>,
+        current_state: impl Into<Option<ChangeState>>,
+        previous_state: impl Into<Option<ChangeState>>,
+    ) -> anyhow::Result<Self> {
+        let new = current_state
+            .into()
+            .map(|state| BinaryResource::from_state(repo, path, state))
+            .transpose()?;
+        let old = previous_state
+            .into()
+            .map(|state| BinaryResource::from_state(repo, previous_path.unwrap_or(path), state))
+            .transpose()?;
+        Ok(BinaryDiff {
+            content_unchanged: old
+                .as_ref()
+                .zip(new.as_ref())
+                .is_some_and(|(old, new)| old.id == new.id),
+            old,
+            new,
+        })
+    }
+
+    /// Use `converter` to turn both versions into text and diff them with `context_lines`.
+    /// Return `None` if `converter` couldn't convert one of them.
+    ///
+    /// As the resulting hunks don't apply to the binary content, they can only be displayed.
+    pub fn to_text_diff(
+        &self,
+        repo: &gix::Repository,
+        converter: &mut dyn BinaryToText,
+        context_lines: u32,
+    ) -> anyhow::Result<Option<UnifiedDiff>> {
+        let mut texts = [BString::default(), BString::default()];
+        for (resource, text) in [&self.old, &self.new].into_iter().zip(texts.iter_mut()) {
+            let Some(resource) = resource else {
+                continue;
+            };
+            let data = resource.content(repo)?;
+            match converter.to_text(resource, &data)? {
+                Some(converted) => *text = converted,
+                None => return Ok(None),
+            }
+        }
+        let [old, new] = &texts;
+        let input = InternedInput::new(
+            byte_lines_with_terminator(old.as_bytes()),
+            byte_lines_with_terminator(new.as_bytes()),
+        );
+        let hunks = hunks_with_options(
+            &input,
+            gix::diff::blob::Algorithm::Histogram,
+            context_lines,
+            DiffOptions::default(),
+        );
+        let (lines_added, lines_removed) = compute_line_changes(&hunks);
+        Ok(Some(UnifiedDiff::Patch {
+            hunks,
+            is_result_of_binary_to_text_conversion: true,
+            lines_added,
+            lines_removed,
+        }))
+    }
+}
+
+impl BinaryResource {
+    fn from_state(
+        repo: &gix::Repository,
+        rela_path: &BStr,
+        state: ChangeState,
+    ) -> anyhow::Result<Self> {
+        let data = if state.id.is_null() {
+            read_from_worktree(repo, rela_path)?
+        } else {
+            repo.find_blob(state.id)?.detach().data
+        };
+        let id = if state.id.is_null() {
+            gix::objs::compute_hash(repo.object_hash(), gix::object::Kind::Blob, &data)?
+        } else {
+            state.id
+        };
+        Ok(BinaryResource {
+            path: rela_path.to_owned(),
+            id,
+            size_in_bytes: data.len() as u64,
+            mime_type: infer::get(&data).map(|kind| kind.mime_type().to_owned()),
+            image_dimensions: image_dimensions(&data),
+        })
+    }
+
+    /// Read the content of this resource from the object database, or from the worktree if it isn't stored there.
+    pub fn content(&self, repo: &gix::Repository) -> anyhow::Result<Vec<u8>> {
+        if repo.has_object(self.id) {
+            Ok(repo.find_blob(self.id)?.detach().data)
+        } else {
+            read_from_worktree(repo, self.path.as_bstr())
+        }
+    }
+}
+
+fn read_from_worktree(repo: &gix::Repository, rela_path: &BStr) -> anyhow::Result<Vec<u8>> {
+    let path = repo
+        .workdir()
+        .context("Need a worktree to read binary resources from")?
+        .join(gix::path::from_bstr(rela_path));
+    Ok(std::fs::read(path)?)
+}
+
+/// Read the dimensions from the header of PNG, GIF, JPEG, BMP and WebP images in `data`.
+fn image_dimensions(data: &[u8]) -> Option<ImageDimensions> {
+    let be_u16 = |at: usize| {
+        data.get(at..at + 2)
+            .map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])))
+    };
+    let le_u16 = |at: usize| {
+        data.get(at..at + 2)
+            .map(|b| u32::from(u16::from_le_bytes([b[0], b[1]])))
+    };
+    let be_u32 = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let le_u32 = |at: usize| {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let le_u24 = |at: usize| {
+        data.get(at..at + 3)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], 0]))
+    };
+    let dimensions = |width: Option<u32>, height: Option<u32>| {
+        Some(ImageDimensions {
+            width: width?,
+            height: height?,
+        })
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.get(12..16) == Some(b"IHDR") {
+        dimensions(be_u32(16), be_u32(20))
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        dimensions(le_u16(6), le_u16(8))
+    } else if data.starts_with(b"BM") {
+        if le_u32(14)? == 12 {
+            dimensions(le_u16(18), le_u16(20))
+        } else {
+            // The height is negative for images stored top-down.
+            dimensions(
+                le_u32(18).map(|width| (width as i32).unsigned_abs()),
+                le_u32(22).map(|height| (height as i32).unsigned_abs()),
+            )
+        }
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        match data.get(12..16)? {
+            b"VP8 " if data.get(23..26) == Some(b"\x9d\x01\x2a") => dimensions(
+                le_u16(26).map(|width| width & 0x3fff),
+                le_u16(28).map(|height| height & 0x3fff),
+            ),
+            b"VP8L" if data.get(20) == Some(&0x2f) => {
+                let bits = le_u32(21)?;
+                dimensions(Some((bits & 0x3fff) + 1), Some(((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => dimensions(le_u24(24).map(|w| w + 1), le_u24(27).map(|h| h + 1)),
+            _ => None,
+        }
+    } else if data.starts_with(b"\xff\xd8") {
+        let mut pos = 2;
+        while let Some(&[0xff, marker]) = data.get(pos..pos + 2) {
+            match marker {
+                // Fill bytes may precede a marker.
+                0xff => pos += 1,
+                // Markers without a segment.
+                0x01 | 0xd0..=0xd8 => pos += 2,
+                // Start of frame, except for the markers in that range that mean something else.
+                0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                    return dimensions(be_u16(pos + 7), be_u16(pos + 5));
+                }
+                _ => pos += 2 + usize::try_from(be_u16(pos + 2)?).ok()?,
+            }
+        }
+        None
+    } else {
+        None
+    }
+}
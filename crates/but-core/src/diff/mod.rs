@@ -5,11 +5,14 @@ pub use tree_changes::tree_changes;
 
 mod worktree;
 use crate::{ChangeState, ModeFlags, TreeChange, TreeStatus, TreeStatusKind};
-pub use worktree::worktree_changes;
+pub use worktree::{worktree_changes, worktree_changes_incremental};
 
 /// conversion functions for use in the UI
 pub mod ui;
 
+/// Information about binary resources that can't be diffed as text.
+pub mod binary;
+
 impl TreeStatus {
     /// Learn what kind of status this is, useful if only this information is needed.
     pub fn kind(&self) -> TreeStatusKind {
@@ -18,6 +21,7 @@ impl TreeStatus {
             TreeStatus::Deletion { .. } => TreeStatusKind::Deletion,
             TreeStatus::Modification { .. } => TreeStatusKind::Modification,
             TreeStatus::Rename { .. } => TreeStatusKind::Rename,
+            TreeStatus::Copy { .. } => TreeStatusKind::Copy,
         }
     }
 
@@ -26,13 +30,14 @@ impl TreeStatus {
         match self {
             TreeStatus::Addition { state, .. }
             | TreeStatus::Rename { state, .. }
+            | TreeStatus::Copy { state, .. }
             | TreeStatus::Modification { state, .. } => Some(*state),
             TreeStatus::Deletion { .. } => None,
         }
     }
 
     /// Return the previous state that the change originated from. May be `None` if there is no previous state, for instance after an addition.
-    /// Also provide the path from which the state was possibly obtained.
+    /// Also provide the path from which the state was possibly obtained, which for copies is the path of the copy source.
     pub fn previous_state_and_path(&self) -> Option<(ChangeState, Option<&BStr>)> {
         match self {
             TreeStatus::Addition { .. } => None,
@@ -41,6 +46,11 @@ impl TreeStatus {
                 previous_path,
                 ..
             } => Some((*previous_state, Some(previous_path.as_bstr()))),
+            TreeStatus::Copy {
+                source_state,
+                source_path,
+                ..
+            } => Some((*source_state, Some(source_path.as_bstr()))),
             TreeStatus::Modification { previous_state, .. }
             | TreeStatus::Deletion { previous_state, .. } => Some((*previous_state, None)),
         }
@@ -49,11 +59,15 @@ impl TreeStatus {
 
 impl TreeChange {
     /// Return the path at which this directory entry was previously located, if it was renamed.
+    ///
+    /// Copies have no previous path as their source still exists, use [`TreeStatus::previous_state_and_path()`]
+    /// to learn where they were copied from.
     pub fn previous_path(&self) -> Option<&BStr> {
         match &self.status {
             TreeStatus::Addition { .. }
             | TreeStatus::Deletion { .. }
-            | TreeStatus::Modification { .. } => None,
+            | TreeStatus::Modification { .. }
+            | TreeStatus::Copy { .. } => None,
             TreeStatus::Rename { previous_path, .. } => Some(previous_path.as_ref()),
         }
     }
@@ -93,6 +107,23 @@ impl ModeFlags {
     }
 }
 
+/// Return the copy-tracking configuration if `diff.renames` is set to `copies` in the configuration of `repo`.
+///
+/// Unlike Git by default, unmodified files are considered as copy sources as well, which is what
+/// `--find-copies-harder` does, as otherwise copies of files that didn't change wouldn't be found.
+pub(crate) fn copies_from_config(repo: &gix::Repository) -> Option<gix::diff::rewrites::Copies> {
+    let track_copies = repo
+        .config_snapshot()
+        .string("diff.renames")
+        .is_some_and(|value| {
+            value.eq_ignore_ascii_case(b"copies") || value.eq_ignore_ascii_case(b"copy")
+        });
+    track_copies.then(|| gix::diff::rewrites::Copies {
+        source: gix::diff::rewrites::CopySource::FromSetOfModifiedFilesAndAllSources,
+        ..Default::default()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     mod flags {
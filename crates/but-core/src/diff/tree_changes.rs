@@ -23,6 +23,8 @@ fn id_to_tree(repo: &gix::Repository, id: gix::ObjectId) -> anyhow::Result<gix::
 /// Can be given either a commit or a tree oid.
 ///
 /// Note that we deal with conflicted commits correctly by resolving to the actual tree, not the one with meta-data.
+/// Copies are tracked if `diff.renames` is set to `copies` in the Git configuration of `repo`, with unmodified files
+/// as possible copy sources.
 ///
 /// They are sorted by their current path.
 ///
@@ -37,7 +39,27 @@ pub fn tree_changes(
     let rhs_tree = id_to_tree(repo, rhs)?;
 
     let mut resource_cache = repo.diff_resource_cache_for_tree_diff()?;
-    let changes = repo.diff_tree_to_tree(lhs_tree.as_ref(), &rhs_tree, None)?;
+    let changes = match super::copies_from_config(repo) {
+        // Copies of unmodified files are only found with copy sources Git wouldn't use by default.
+        Some(copies) => {
+            let lhs_tree = lhs_tree.unwrap_or_else(|| repo.empty_tree());
+            let mut changes = Vec::new();
+            lhs_tree
+                .changes()?
+                .options(|opts| {
+                    opts.track_rewrites(Some(gix::diff::Rewrites {
+                        copies: Some(copies),
+                        ..Default::default()
+                    }));
+                })
+                .for_each_to_obtain_tree(&rhs_tree, |change| -> anyhow::Result<_> {
+                    changes.push(change.detach());
+                    Ok(gix::object::tree::diff::Action::Continue)
+                })?;
+            changes
+        }
+        None => repo.diff_tree_to_tree(lhs_tree.as_ref(), &rhs_tree, None)?,
+    };
     let mut stats = gix::object::tree::diff::Stats::default();
     let mut out: Vec<TreeChange> = changes
         .into_iter()
@@ -127,7 +149,7 @@ impl From<gix::object::tree::diff::ChangeDetached> for TreeChange {
                 id,
                 location,
                 diff: _,
-                copy: _,
+                copy,
                 ..
             } => {
                 let previous_state = ChangeState {
@@ -138,13 +160,23 @@ impl From<gix::object::tree::diff::ChangeDetached> for TreeChange {
                     id,
                     kind: entry_mode.kind(),
                 };
+                let flags = ModeFlags::calculate(&previous_state, &state);
                 TreeChange {
                     path: location,
-                    status: TreeStatus::Rename {
-                        previous_path: source_location,
-                        previous_state,
-                        state,
-                        flags: ModeFlags::calculate(&previous_state, &state),
+                    status: if copy {
+                        TreeStatus::Copy {
+                            source_path: source_location,
+                            source_state: previous_state,
+                            state,
+                            flags,
+                        }
+                    } else {
+                        TreeStatus::Rename {
+                            previous_path: source_location,
+                            previous_state,
+                            state,
+                            flags,
+                        }
                     },
                 }
             }
@@ -1,6 +1,6 @@
 use crate::{
     ChangeState, IgnoredWorktreeChange, IgnoredWorktreeTreeChangeStatus, ModeFlags, TreeChange,
-    TreeStatus, UnifiedDiff, WorktreeChanges,
+    TreeStatus, UnifiedDiff, WorktreeChanges, unified_diff::DiffOptions,
 };
 use anyhow::{Context, bail};
 use bstr::{BStr, BString, ByteSlice};
@@ -30,14 +30,145 @@ enum Origin {
 ///
 /// It's equivalent to a `git status` which is "boiled down" into all the changes that one would have to add into `HEAD^{tree}`
 /// to get a commit with a tree equal to the current worktree.
+///
+/// If an fsmonitor hook is configured, only tracked files it reports as changed are checked, see [`crate::fsmonitor`].
 pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChanges> {
-    let rewrites = gix::diff::Rewrites::default(); /* standard Git rewrite handling for everything */
-    debug_assert!(
-        rewrites.copies.is_none(),
-        "TODO: copy tracking needs specific support wherever 'previous_path()' is called."
-    );
+    worktree_changes_at(repo, Vec::new())
+}
+
+/// Like [`worktree_changes()`], but only recompute the changes at `dirty_paths` and keep all other changes of `previous`,
+/// which is what a prior call to [`worktree_changes()`] or this function returned.
+///
+/// `dirty_paths` are worktree-relative, slash-separated paths to files or directories that may have changed since `previous`
+/// was computed, as reported by a filesystem watcher. A directory invalidates all changes underneath it.
+/// Renames and copies in `previous` that involve a dirty path are invalidated on both sides so they can be detected again.
+/// If additions or deletions appear or disappear at dirty paths, all additions and deletions of `previous` are recomputed
+/// as well to pair them up correctly, or a full [`worktree_changes()`] is performed if copies are tracked.
+///
+/// Note that changes to the index or to `HEAD` can affect any path, so a full [`worktree_changes()`] is needed then.
+pub fn worktree_changes_incremental(
+    repo: &gix::Repository,
+    previous: WorktreeChanges,
+    dirty_paths: impl IntoIterator<Item = BString>,
+) -> anyhow::Result<WorktreeChanges> {
+    let mut dirty: Vec<BString> = dirty_paths.into_iter().collect();
+    if dirty.is_empty() {
+        return Ok(previous);
+    }
+    let mut rename_counterparts = Vec::new();
+    for change in &previous.changes {
+        if let Some(previous_path) = rewrite_source(change) {
+            if is_dirty(&dirty, change.path.as_bstr()) || is_dirty(&dirty, previous_path) {
+                rename_counterparts.push(change.path.clone());
+                rename_counterparts.push(previous_path.to_owned());
+            }
+        }
+    }
+    dirty.extend(rename_counterparts);
+    dirty.sort();
+    dirty.dedup();
+
+    let mut fresh = worktree_changes_at(repo, pathspecs(&dirty))?;
+    // Additions and deletions at dirty paths may pair up with additions and deletions elsewhere to form
+    // renames and copies, or stop doing so, which is only detected if the status sees both sides.
+    let affects_rewrites = fresh.changes.iter().any(is_addition_or_deletion)
+        || previous.changes.iter().any(|change| {
+            is_addition_or_deletion(change) && is_dirty(&dirty, change.path.as_bstr())
+        });
+    if affects_rewrites {
+        if super::copies_from_config(repo).is_some() {
+            // Copies may originate from any tracked file, so only a full status can find them.
+            return worktree_changes(repo);
+        }
+        let counterparts: Vec<_> = previous
+            .changes
+            .iter()
+            .filter(|change| {
+                is_addition_or_deletion(change) && !is_dirty(&dirty, change.path.as_bstr())
+            })
+            .map(|change| change.path.clone())
+            .collect();
+        if !counterparts.is_empty() {
+            dirty.extend(counterparts);
+            dirty.sort();
+            dirty.dedup();
+            fresh = worktree_changes_at(repo, pathspecs(&dirty))?;
+        }
+    }
+
+    let WorktreeChanges {
+        mut changes,
+        mut ignored_changes,
+    } = previous;
+    changes.retain(|change| {
+        !is_dirty(&dirty, change.path.as_bstr())
+            && !rewrite_source(change).is_some_and(|previous_path| is_dirty(&dirty, previous_path))
+    });
+    ignored_changes.retain(|change| !is_dirty(&dirty, change.path.as_bstr()));
+
+    changes.extend(fresh.changes);
+    ignored_changes.extend(fresh.ignored_changes);
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    ignored_changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(WorktreeChanges {
+        changes,
+        ignored_changes,
+    })
+}
+
+/// Return the pathspecs that match exactly the `dirty` paths, and everything underneath them.
+fn pathspecs(dirty: &[BString]) -> Vec<BString> {
+    dirty
+        .iter()
+        .map(|path| {
+            let mut spec = BString::from(":(top,literal)");
+            spec.extend_from_slice(path);
+            spec
+        })
+        .collect()
+}
+
+/// Return `true` if `change` could be one side of a rename or copy.
+fn is_addition_or_deletion(change: &TreeChange) -> bool {
+    matches!(
+        change.status,
+        TreeStatus::Addition { .. } | TreeStatus::Deletion { .. }
+    )
+}
+
+/// Return the path that `change` was renamed or copied from, if any.
+fn rewrite_source(change: &TreeChange) -> Option<&BStr> {
+    change
+        .status
+        .previous_state_and_path()
+        .and_then(|(_, path)| path)
+}
+
+/// Return `true` if `path` is one of the `dirty` paths, or is contained in one of them.
+fn is_dirty(dirty: &[BString], path: &BStr) -> bool {
+    dirty.iter().any(|dirty| {
+        path.strip_prefix(dirty.as_slice())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(b"/"))
+    })
+}
+
+/// Return the standard Git rewrite handling for everything, with copy-tracking enabled if `diff.renames` is set to `copies`.
+fn rewrites_from_config(repo: &gix::Repository) -> gix::diff::Rewrites {
+    gix::diff::Rewrites {
+        copies: super::copies_from_config(repo),
+        ..Default::default()
+    }
+}
+
+/// Compute the worktree changes limited to `pathspecs`, or for the whole worktree if there are none.
+fn worktree_changes_at(
+    repo: &gix::Repository,
+    pathspecs: Vec<BString>,
+) -> anyhow::Result<WorktreeChanges> {
+    let rewrites = rewrites_from_config(repo);
     let status_changes = repo
         .status(gix::progress::Discard)?
+        .index(crate::fsmonitor::index_for_status(repo)?)
         .tree_index_track_renames(TrackRenames::Given(rewrites))
         .index_worktree_rewrites(rewrites)
         // Learn about submodule changes, but only do the cheap checks, showing only what we could commit.
@@ -57,7 +188,7 @@ pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChange
                     .set_emit_collapsed(None);
             }
         })
-        .into_iter(None)?;
+        .into_iter(pathspecs)?;
 
     let work_dir = repo.workdir().context("need non-bare repository")?;
     let mut tmp = Vec::new();
@@ -297,6 +428,7 @@ pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChange
                 // This ID is usually null, but might be set if used for comparisons.
                 // However, this wouldn't mean the object exists.
                 dirwalk_entry_id: _,
+                copy,
                 ..
             }) => {
                 let previous_path: BString = source.rela_path().into();
@@ -337,12 +469,7 @@ pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChange
                     Origin::IndexWorktree,
                     TreeChange {
                         path: dirwalk_entry.rela_path,
-                        status: TreeStatus::Rename {
-                            previous_path,
-                            previous_state,
-                            state,
-                            flags: ModeFlags::calculate(&previous_state, &state),
-                        },
+                        status: rewrite_status(copy, previous_path, previous_state, state),
                     },
                 )
             }
@@ -353,6 +480,7 @@ pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChange
                 source_id,
                 entry_mode,
                 id,
+                copy,
                 ..
             }) => {
                 let previous_state = ChangeState {
@@ -367,12 +495,12 @@ pub fn worktree_changes(repo: &gix::Repository) -> anyhow::Result<WorktreeChange
                     Origin::TreeIndex,
                     TreeChange {
                         path: location.into_owned(),
-                        status: TreeStatus::Rename {
-                            previous_path: source_location.into_owned(),
+                        status: rewrite_status(
+                            copy,
+                            source_location.into_owned(),
                             previous_state,
                             state,
-                            flags: ModeFlags::calculate(&previous_state, &state),
-                        },
+                        ),
                     },
                 )
             }
@@ -519,7 +647,13 @@ fn merge_changes(
                 ..
             },
         )
-        | (TreeStatus::Addition { .. }, TreeStatus::Addition { .. }) => {
+        | (TreeStatus::Addition { .. }, TreeStatus::Addition { .. })
+        | (TreeStatus::Copy { .. }, TreeStatus::Addition { .. })
+        | (TreeStatus::Copy { .. }, TreeStatus::Copy { .. })
+        | (
+            TreeStatus::Addition { .. } | TreeStatus::Modification { .. },
+            TreeStatus::Copy { .. },
+        ) => {
             bail!(
                 "BUG: entered unreachable code with tree_index_change = {:?} and index_wt_change = {:?}",
                 tree_index.status.kind(),
@@ -561,7 +695,8 @@ fn merge_changes(
             TreeStatus::Addition {
                 is_untracked: true,
                 state,
-            },
+            }
+            | TreeStatus::Copy { state, .. },
         ) => {
             index_wt.status = TreeStatus::Modification {
                 previous_state: *previous_state,
@@ -639,12 +774,51 @@ fn merge_changes(
                 },
             }));
         }
+        (
+            TreeStatus::Copy {
+                state: state_index, ..
+            },
+            TreeStatus::Modification {
+                state: state_wt, ..
+            },
+        ) => {
+            *state_index = *state_wt;
+            return Ok(single(tree_index));
+        }
+        (TreeStatus::Copy { .. }, TreeStatus::Deletion { .. }) => {
+            // The copy is gone, and its source was never affected.
+            return Ok([None, None]);
+        }
+        (
+            TreeStatus::Copy {
+                source_path,
+                source_state,
+                ..
+            },
+            TreeStatus::Rename {
+                state: state_wt, ..
+            },
+        ) => {
+            // The copy was moved in the worktree, so what's left is a copy to its new location.
+            let state = *state_wt;
+            index_wt.status = TreeStatus::Copy {
+                source_path: std::mem::take(source_path),
+                source_state: *source_state,
+                state,
+                flags: ModeFlags::calculate(source_state, &state),
+            };
+            // Can't be no-op as this is a copy
+            return Ok(single(index_wt));
+        }
         (
             TreeStatus::Rename {
                 state: state_index, ..
             },
             TreeStatus::Addition {
                 state: state_wt, ..
+            }
+            | TreeStatus::Copy {
+                state: state_wt, ..
             },
         ) => {
             return Ok([
@@ -761,6 +935,31 @@ fn into_tree_entry_kind(mode: gix::index::entry::Mode) -> anyhow::Result<EntryKi
         .kind())
 }
 
+/// Turn a rewrite of `previous_state` at `previous_path` into `state` into a rename, or a copy if `copy` is `true`.
+fn rewrite_status(
+    copy: bool,
+    previous_path: BString,
+    previous_state: ChangeState,
+    state: ChangeState,
+) -> TreeStatus {
+    let flags = ModeFlags::calculate(&previous_state, &state);
+    if copy {
+        TreeStatus::Copy {
+            source_path: previous_path,
+            source_state: previous_state,
+            state,
+            flags,
+        }
+    } else {
+        TreeStatus::Rename {
+            previous_path,
+            previous_state,
+            state,
+            flags,
+        }
+    }
+}
+
 /// Most importantly, this function allows to skip over untrackable entries, like named pipes, sockets and character devices, just like Git.
 /// `path` is needed for now while we have to stat the file again to learn about the executable bits.
 // TODO: remove `path` and provide the stat information or at least executable info with `gitoxide` - it has that info.
@@ -801,20 +1000,31 @@ impl TreeChange {
         &self,
         repo: &gix::Repository,
         context_lines: u32,
+    ) -> anyhow::Result<UnifiedDiff> {
+        self.unified_diff_with_options(repo, context_lines, DiffOptions::default())
+    }
+
+    /// Like [`Self::unified_diff()`], but uses `options` to control how hunks are computed.
+    pub fn unified_diff_with_options(
+        &self,
+        repo: &gix::Repository,
+        context_lines: u32,
+        options: DiffOptions,
     ) -> anyhow::Result<UnifiedDiff> {
         let mut diff_filter = crate::unified_diff::filter_from_state(
             repo,
             self.status.state(),
             UnifiedDiff::CONVERSION_MODE,
         )?;
-        self.unified_diff_with_filter(repo, context_lines, &mut diff_filter)
+        self.unified_diff_with_filter(repo, context_lines, options, &mut diff_filter)
     }
 
-    /// Like [`Self::unified_diff()`], but uses `diff_filter` to control the content used for the diff.
+    /// Like [`Self::unified_diff_with_options()`], but uses `diff_filter` to control the content used for the diff.
     pub fn unified_diff_with_filter(
         &self,
         repo: &gix::Repository,
         context_lines: u32,
+        options: DiffOptions,
         diff_filter: &mut gix::diff::blob::Platform,
     ) -> anyhow::Result<UnifiedDiff> {
         match &self.status {
@@ -825,6 +1035,7 @@ impl TreeChange {
                 None,
                 *previous_state,
                 context_lines,
+                options,
                 diff_filter,
             ),
             TreeStatus::Addition {
@@ -837,6 +1048,7 @@ impl TreeChange {
                 *state,
                 None,
                 context_lines,
+                options,
                 diff_filter,
             ),
             TreeStatus::Modification {
@@ -850,6 +1062,7 @@ impl TreeChange {
                 *state,
                 *previous_state,
                 context_lines,
+                options,
                 diff_filter,
             ),
             TreeStatus::Rename {
@@ -864,6 +1077,22 @@ impl TreeChange {
                 *state,
                 *previous_state,
                 context_lines,
+                options,
+                diff_filter,
+            ),
+            TreeStatus::Copy {
+                source_path,
+                source_state,
+                state,
+                flags: _,
+            } => UnifiedDiff::compute_with_filter(
+                repo,
+                self.path.as_bstr(),
+                Some(source_path.as_bstr()),
+                *state,
+                *source_state,
+                context_lines,
+                options,
                 diff_filter,
             ),
         }
@@ -0,0 +1,230 @@
+//! Consult the filesystem monitor configured with `core.fsmonitor`, like Watchman, to learn which tracked files
+//! may have changed, so status computations only have to `stat` these.
+//!
+//! Only hooks that speak version 2 of the `query-fsmonitor` protocol are supported, which is what the Watchman hook
+//! shipped by Git does. The builtin daemon, configured with `core.fsmonitor = true`, isn't supported.
+//! The token to query the hook with is the one Git stored in the `FSMN` extension of the index, along with the
+//! entries that weren't known to be unchanged at that time.
+use anyhow::{Context, bail};
+use bstr::{BStr, BString, ByteSlice};
+use gix::worktree::IndexPersistedOrInMemory;
+use std::collections::HashSet;
+use std::process::Stdio;
+
+/// Return the index of `repo` for use in status computations.
+///
+/// If an fsmonitor hook is configured, all entries that didn't change according to it are marked as
+/// [valid](gix::index::entry::Flags::FSMONITOR_VALID) so they are not compared to the worktree.
+/// Otherwise, or if the hook can't be used, the index is returned as is and all entries will be checked.
+pub fn index_for_status(repo: &gix::Repository) -> anyhow::Result<IndexPersistedOrInMemory> {
+    let index = match index_with_valid_entries(repo) {
+        Ok(Some(index)) => return Ok(IndexPersistedOrInMemory::InMemory(index)),
+        Ok(None) => repo.index_or_empty()?,
+        Err(err) => {
+            tracing::warn!(
+                ?err,
+                "Failed to query fsmonitor, checking all files instead"
+            );
+            repo.index_or_empty()?
+        }
+    };
+    Ok(IndexPersistedOrInMemory::Persisted(index))
+}
+
+/// Return the index of `repo` with entries marked as valid that the fsmonitor hook vouches for,
+/// or `None` if there is no such hook, or if it doesn't know what changed.
+fn index_with_valid_entries(repo: &gix::Repository) -> anyhow::Result<Option<gix::index::File>> {
+    let Some(hook) = configured_hook(repo) else {
+        return Ok(None);
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+    let data = match std::fs::read(repo.index_path()) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let Some(extension) = Extension::from_index_bytes(&data, repo.object_hash().len_in_bytes())
+    else {
+        return Ok(None);
+    };
+    let mut index = repo.open_index()?;
+    if index
+        .checksum()
+        .is_none_or(|checksum| checksum.as_slice() != extension.checksum)
+    {
+        tracing::debug!("Index changed while reading the fsmonitor extension");
+        return Ok(None);
+    }
+    let Some(changed_paths) = query_hook(&hook, workdir, extension.token.as_bstr())? else {
+        return Ok(None);
+    };
+
+    let mut dirty_entries = HashSet::new();
+    extension.dirty_entries.for_each_set_bit(|idx| {
+        dirty_entries.insert(idx);
+        Some(())
+    });
+    let changed_paths: HashSet<&BStr> = changed_paths
+        .iter()
+        .map(|path| path.as_bstr().trim_end_with(|c| c == '/').as_bstr())
+        .collect();
+    for (idx, (entry, path)) in index.entries_mut_with_paths().enumerate() {
+        let is_changed = dirty_entries.contains(&idx)
+            || std::iter::successors(Some(path), |path| {
+                path.rfind_byte(b'/').map(|pos| path[..pos].as_bstr())
+            })
+            .any(|path_or_parent| changed_paths.contains(path_or_parent));
+        if !is_changed {
+            entry
+                .flags
+                .insert(gix::index::entry::Flags::FSMONITOR_VALID);
+        }
+    }
+    Ok(Some(index))
+}
+
+/// Return the hook configured in `core.fsmonitor`, unless it's unset or a boolean to control the builtin daemon.
+fn configured_hook(repo: &gix::Repository) -> Option<BString> {
+    let config = repo.config_snapshot();
+    let hook = config.string("core.fsmonitor")?;
+    if hook.is_empty() || config.boolean("core.fsmonitor").is_some() {
+        return None;
+    }
+    Some(hook.into_owned())
+}
+
+/// Run `hook` in `workdir` to learn which paths changed since `token`, or return `None` if all of them may have.
+fn query_hook(
+    hook: &BStr,
+    workdir: &std::path::Path,
+    token: &BStr,
+) -> anyhow::Result<Option<Vec<BString>>> {
+    let hook_path = gix::path::from_bstr(hook);
+    // Git runs the hook from the worktree, so relative paths to it are relative to the worktree as well.
+    let hook_path = if hook_path.is_relative() && hook_path.components().count() > 1 {
+        workdir.join(hook_path)
+    } else {
+        hook_path.into_owned()
+    };
+    let mut cmd: std::process::Command = gix::command::prepare(hook_path)
+        .command_may_be_shell_script()
+        .arg("2")
+        .arg(gix::path::from_bstr(token).into_owned())
+        .into();
+    let out = cmd
+        .current_dir(workdir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Could not launch fsmonitor hook '{hook}'"))?;
+    if !out.status.success() {
+        bail!("fsmonitor hook '{hook}' failed with {}", out.status);
+    }
+
+    let mut tokens = out.stdout.split_str(b"\0");
+    // The new token would be stored by Git when writing the index, which we don't do.
+    let Some(_new_token) = tokens.next().filter(|token| !token.is_empty()) else {
+        bail!("fsmonitor hook '{hook}' didn't output a token - is it speaking protocol version 2?");
+    };
+    let mut paths = Vec::new();
+    for path in tokens.filter(|path| !path.is_empty()) {
+        // This is how the hook tells us that it doesn't know what changed, maybe because the token is too old.
+        if path == b"/" {
+            return Ok(None);
+        }
+        paths.push(path.into());
+    }
+    Ok(Some(paths))
+}
+
+/// The parts of the `FSMN` index extension we need, as written by Git.
+struct Extension<'a> {
+    /// The opaque token to pass to the hook to learn about changes since the index was written.
+    token: &'a [u8],
+    /// The indices of all entries that weren't known to be unchanged when the index was written.
+    dirty_entries: gix_bitmap::ewah::Vec,
+    /// The checksum of the index the extension was read from.
+    checksum: &'a [u8],
+}
+
+impl<'a> Extension<'a> {
+    /// Read the `FSMN` extension from `data` of an index file, which uses hashes of `hash_len` bytes,
+    /// or return `None` if it isn't present, uses a token format other than version 2 or if the data is unexpected.
+    ///
+    /// Note that `gix` decodes the extension, but doesn't make it accessible, so we have to skip over the
+    /// entries ourselves to find it.
+    fn from_index_bytes(data: &'a [u8], hash_len: usize) -> Option<Self> {
+        let (header, mut entries) = data.split_at_checked(12)?;
+        let (signature, header) = header.split_at(4);
+        if signature != b"DIRC" {
+            return None;
+        }
+        let version = be_u32(&header[..4])?;
+        let num_entries = be_u32(&header[4..])?;
+        if !(2..=4).contains(&version) {
+            return None;
+        }
+
+        const STAT_LEN: usize = 40;
+        const EXTENDED_FLAG: u16 = 0x4000;
+        for _ in 0..num_entries {
+            let flags_pos = STAT_LEN + hash_len;
+            let flags = u16::from_be_bytes(entries.get(flags_pos..flags_pos + 2)?.try_into().ok()?);
+            let mut path_pos = flags_pos + 2;
+            if version >= 3 && flags & EXTENDED_FLAG != 0 {
+                path_pos += 2;
+            }
+            let rest = entries.get(path_pos..)?;
+            entries = if version == 4 {
+                // Skip the varint with the amount of bytes to strip from the previous path, and the path suffix.
+                let varint_len = rest.iter().position(|b| b & 0x80 == 0)? + 1;
+                let suffix_len = rest.get(varint_len..)?.find_byte(0)?;
+                rest.get(varint_len + suffix_len + 1..)?
+            } else {
+                // Paths are NUL-terminated and padded with NULs so the entry size is a multiple of 8.
+                let path_len = rest.find_byte(0)?;
+                let entry_len = (path_pos + path_len + 8) & !7;
+                entries.get(entry_len..)?
+            };
+        }
+
+        let mut extensions = entries;
+        let checksum = data.get(data.len().checked_sub(hash_len)?..)?;
+        while extensions.len() > hash_len {
+            let (signature, rest) = extensions.split_at_checked(4)?;
+            let (size, rest) = rest.split_at_checked(4)?;
+            let (ext, rest) = rest.split_at_checked(be_u32(size)? as usize)?;
+            extensions = rest;
+            match signature {
+                b"FSMN" => return Self::decode(ext, checksum),
+                // With a split index, entry indices refer to a merged view we don't have.
+                b"link" => return None,
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    fn decode(ext: &'a [u8], checksum: &'a [u8]) -> Option<Self> {
+        let (version, ext) = ext.split_at_checked(4)?;
+        if be_u32(version)? != 2 {
+            return None;
+        }
+        let token_len = ext.find_byte(0)?;
+        let (token, ext) = (&ext[..token_len], &ext[token_len + 1..]);
+        let (bitmap_len, ext) = ext.split_at_checked(4)?;
+        let (bitmap, _) = ext.split_at_checked(be_u32(bitmap_len)? as usize)?;
+        let (dirty_entries, _) = gix_bitmap::ewah::decode(bitmap).ok()?;
+        Some(Extension {
+            token,
+            dirty_entries,
+            checksum,
+        })
+    }
+}
+
+fn be_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(data.try_into().ok()?))
+}
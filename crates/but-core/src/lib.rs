@@ -66,6 +66,9 @@ pub mod cmd;
 
 /// Various settings
 pub mod settings;
+
+/// Use a filesystem monitor to avoid checking unchanged files.
+pub mod fsmonitor;
 pub use settings::git::types::GitConfigSettings;
 
 mod repo_ext;
@@ -129,8 +132,8 @@ pub struct Commit<'repo> {
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", content = "subject")]
 pub enum UnifiedDiff {
-    /// The resource was a binary and couldn't be diffed.
-    Binary,
+    /// The resource was a binary and couldn't be diffed, but information about both of its versions is available.
+    Binary(diff::binary::BinaryDiff),
     /// The file was too large and couldn't be diffed.
     TooLarge {
         /// The size of the file on disk that made it too large.
@@ -199,8 +202,8 @@ pub fn open_repo(path: impl Into<PathBuf>) -> anyhow::Result<gix::Repository> {
 ///
 /// ### Note
 ///
-/// For simplicity, copy-tracking is not representable right now, but `copy: bool` could be added
-/// if needed. Copy-tracking is deactivated as well.
+/// Copies are only tracked if `diff.renames` is set to `copies`, and then only from sources that were modified as well,
+/// just like Git does it.
 #[derive(Debug, Clone)]
 pub struct TreeChange {
     /// The *relative* path in the worktree where the entry can be found.
@@ -250,6 +253,20 @@ pub enum TreeStatus {
         /// Derived information based on the mode of both states.
         flags: Option<ModeFlags>,
     },
+    /// An entry was copied from `source_path` to its current location, while the source remains.
+    ///
+    /// Note that this may include any change already documented in [`Modification`](TreeStatus::Modification),
+    /// relative to the state at `source_path`.
+    Copy {
+        /// The path relative to the repository of the entry that was copied.
+        source_path: BString,
+        /// The state of the source that was copied, and which the changes are relative to.
+        source_state: ChangeState,
+        /// The current state, i.e. the copy and its modifications.
+        state: ChangeState,
+        /// Derived information based on the mode of both states.
+        flags: Option<ModeFlags>,
+    },
 }
 
 /// Like [`TreeStatus`], but distilled down to its variant.
@@ -269,6 +286,10 @@ pub enum TreeStatusKind {
     ///
     /// Note that this may include any change already documented in [`Modification`](TreeStatusKind::Modification)
     Rename,
+    /// An entry was copied from `source_path` to its current location, while the source remains.
+    ///
+    /// Note that this may include any change already documented in [`Modification`](TreeStatusKind::Modification)
+    Copy,
 }
 
 /// Something that fully identifies the state of a [`TreeChange`].
@@ -67,6 +67,9 @@ pub struct Branch {
     pub description: Option<String>,
     /// Information about possibly ongoing reviews in various forges.
     pub review: Review,
+    /// The *stash commit* holding uncommitted changes that were shelved while this branch was the tip of its stack.
+    /// Its first parent is the commit the branch pointed to at the time.
+    pub stash: Option<gix::ObjectId>,
 }
 
 /// Basic information to know about a reference we store with the metadata system.
@@ -97,6 +97,17 @@ pub enum TreeStatus {
         state: ChangeState,
         flags: Option<ModeFlags>,
     },
+    Copy {
+        #[serde(rename = "sourcePath")]
+        source_path: BStringForFrontend,
+        /// Something silently carried back and forth between the frontend and the backend.
+        #[serde(rename = "sourcePathBytes")]
+        source_path_bytes: BString,
+        #[serde(rename = "sourceState")]
+        source_state: ChangeState,
+        state: ChangeState,
+        flags: Option<ModeFlags>,
+    },
 }
 
 impl From<TreeStatus> for crate::TreeStatus {
@@ -133,6 +144,18 @@ impl From<TreeStatus> for crate::TreeStatus {
                 state: state.into(),
                 flags: flags.map(Into::into),
             },
+            TreeStatus::Copy {
+                source_path: _lossy,
+                source_path_bytes,
+                source_state,
+                state,
+                flags,
+            } => crate::TreeStatus::Copy {
+                source_path: source_path_bytes,
+                source_state: source_state.into(),
+                state: state.into(),
+                flags: flags.map(Into::into),
+            },
         }
     }
 }
@@ -171,6 +194,18 @@ impl From<crate::TreeStatus> for TreeStatus {
                 state: state.into(),
                 flags: flags.map(Into::into),
             },
+            crate::TreeStatus::Copy {
+                source_path,
+                source_state,
+                state,
+                flags,
+            } => TreeStatus::Copy {
+                source_path: source_path.clone().into(),
+                source_path_bytes: source_path,
+                source_state: source_state.into(),
+                state: state.into(),
+                flags: flags.map(Into::into),
+            },
         }
     }
 }
@@ -1,9 +1,13 @@
 use super::{ChangeState, UnifiedDiff};
+use crate::diff::binary::BinaryDiff;
 use bstr::{BStr, BString, ByteSlice};
 use gix::diff::blob::ResourceKind;
 use gix::diff::blob::platform::prepare_diff::Operation;
 use gix::diff::blob::unified_diff::ContextSize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
 
 /// A hunk as used in a [UnifiedDiff], which also contains all added and removed lines.
 #[derive(Clone, Serialize)]
@@ -32,6 +36,124 @@ pub struct DiffHunk {
     /// Note that the file-portion of the header isn't used here.
     #[serde(serialize_with = "gitbutler_serde::bstring_lossy::serialize")]
     pub diff: BString,
+    /// The changed portions of removed and added lines in `diff`, empty unless computed with
+    /// [`UnifiedDiff::highlight_intra_line_changes()`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<LineHighlight>,
+}
+
+/// The granularity at which changes within modified lines are highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntraLineDiff {
+    /// Split lines into words, which are the matches of `diff.wordRegex` if configured, or runs of non-whitespace otherwise.
+    Words,
+    /// Split lines into individual characters.
+    Characters,
+}
+
+/// The changed portions of a removed or added line in a [`DiffHunk`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineHighlight {
+    /// The 0-based index of the line in [`DiffHunk::diff`], with `0` being the `@@` header.
+    pub line: u32,
+    /// The sorted, non-overlapping byte ranges that changed, relative to the first byte after the `+` or `-` prefix.
+    pub ranges: Vec<Range<u32>>,
+}
+
+/// Options to control how the hunks of a [`UnifiedDiff`] are computed.
+///
+/// The default uses the algorithm configured in `diff.algorithm` and doesn't ignore any change.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffOptions {
+    /// The algorithm to use, or `None` to use the one configured in `diff.algorithm`.
+    pub algorithm: Option<DiffAlgorithm>,
+    /// How changes to whitespace within lines are treated.
+    pub whitespace: WhitespaceMode,
+    /// If `true`, ignore changes whose lines are all blank, like `git diff --ignore-blank-lines`.
+    pub ignore_blank_lines: bool,
+    /// If `true`, shift added or removed blocks of lines that could be placed at multiple positions so they
+    /// start at the least indented line, like `git diff --indent-heuristic`.
+    pub indent_heuristic: bool,
+}
+
+/// The algorithm to use for computing the changed lines of a [`UnifiedDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffAlgorithm {
+    /// The histogram algorithm, which tends to produce the most readable diffs and is the fastest.
+    Histogram,
+    /// The classic Myers algorithm, which isn't guaranteed to produce the smallest diff.
+    Myers,
+    /// The patience algorithm, which matches lines that are unique on both sides first, like `git diff --patience`.
+    Patience,
+}
+
+/// How changes to whitespace are treated when computing a [`UnifiedDiff`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WhitespaceMode {
+    /// All changes are shown.
+    #[default]
+    Respect,
+    /// Ignore changes in the amount of whitespace and whitespace at the end of lines, like `git diff -b`.
+    IgnoreChange,
+    /// Ignore all whitespace when comparing lines, like `git diff -w`.
+    IgnoreAll,
+}
+
+impl WhitespaceMode {
+    /// Return `line` in the form that is compared to other lines.
+    fn normalize<'a>(&self, line: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            WhitespaceMode::Respect => Cow::Borrowed(line),
+            WhitespaceMode::IgnoreChange => {
+                let mut out = Vec::with_capacity(line.len());
+                let mut words = line
+                    .split(u8::is_ascii_whitespace)
+                    .filter(|word| !word.is_empty())
+                    .peekable();
+                if line.first().is_some_and(u8::is_ascii_whitespace) && words.peek().is_some() {
+                    out.push(b' ');
+                }
+                while let Some(word) = words.next() {
+                    out.extend_from_slice(word);
+                    if words.peek().is_some() {
+                        out.push(b' ');
+                    }
+                }
+                Cow::Owned(out)
+            }
+            WhitespaceMode::IgnoreAll => Cow::Owned(
+                line.iter()
+                    .copied()
+                    .filter(|byte| !byte.is_ascii_whitespace())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Return `true` if hunks can't be produced by a plain line-by-line diff with an algorithm provided by `gix`.
+    fn needs_own_diff_machinery(&self) -> bool {
+        self.whitespace != WhitespaceMode::Respect
+            || self.ignore_blank_lines
+            || self.indent_heuristic
+            || self.algorithm == Some(DiffAlgorithm::Patience)
+    }
+
+    /// Return the algorithm to use instead of the `configured` one. The patience algorithm isn't provided by `gix`,
+    /// so the `configured` one is returned for it.
+    fn algorithm_or(&self, configured: gix::diff::blob::Algorithm) -> gix::diff::blob::Algorithm {
+        match self.algorithm {
+            Some(DiffAlgorithm::Histogram) => gix::diff::blob::Algorithm::Histogram,
+            Some(DiffAlgorithm::Myers) => gix::diff::blob::Algorithm::Myers,
+            Some(DiffAlgorithm::Patience) | None => configured,
+        }
+    }
 }
 
 impl std::fmt::Debug for DiffHunk {
@@ -86,14 +208,17 @@ impl UnifiedDiff {
             current_state,
             previous_state,
             context_lines,
+            DiffOptions::default(),
             &mut cache,
         )
     }
 
-    /// Similar to [`Self::compute()`], but uses `diff_filter` to obtain the diff content.
+    /// Similar to [`Self::compute()`], but uses `diff_filter` to obtain the diff content, and `options` to control
+    /// how the hunks are computed.
     ///
     /// This is useful to assure it's clear which content is ultimately used for the produced uni-diff,
     /// as `filter` is responsible for that.
+    #[allow(clippy::too_many_arguments)]
     pub fn compute_with_filter(
         repo: &gix::Repository,
         path: &BStr,
@@ -101,6 +226,7 @@ impl UnifiedDiff {
         current_state: impl Into<Option<ChangeState>>,
         previous_state: impl Into<Option<ChangeState>>,
         context_lines: u32,
+        options: DiffOptions,
         diff_filter: &mut gix::diff::blob::Platform,
     ) -> anyhow::Result<Self> {
         let current_state = current_state.into();
@@ -137,6 +263,22 @@ impl UnifiedDiff {
         let prep = diff_filter.prepare_diff()?;
         Ok(match prep.operation {
             Operation::InternalDiff { algorithm } => {
+                let algorithm = options.algorithm_or(algorithm);
+                if options.needs_own_diff_machinery() {
+                    let hunks = hunks_with_options(
+                        &prep.interned_input(),
+                        algorithm,
+                        context_lines,
+                        options,
+                    );
+                    let (lines_added, lines_removed) = compute_line_changes(&hunks);
+                    return Ok(UnifiedDiff::Patch {
+                        is_result_of_binary_to_text_conversion: prep.old_or_new_is_derived,
+                        hunks,
+                        lines_added,
+                        lines_removed,
+                    });
+                }
                 #[derive(Default)]
                 struct ProduceDiffHunk {
                     hunks: Vec<DiffHunk>,
@@ -158,6 +300,7 @@ impl UnifiedDiff {
                             old_lines: before_hunk_len,
                             new_start: after_hunk_start,
                             new_lines: after_hunk_len,
+                            highlights: Vec::new(),
                             diff: {
                                 let mut buf = Vec::with_capacity(header.len() + hunk.len());
                                 buf.extend_from_slice(header.as_bytes());
@@ -218,14 +361,520 @@ impl UnifiedDiff {
                         size_in_bytes: size,
                     }
                 } else {
-                    UnifiedDiff::Binary
+                    UnifiedDiff::Binary(BinaryDiff::compute(
+                        repo,
+                        path,
+                        previous_path,
+                        current_state,
+                        previous_state,
+                    )?)
                 }
             }
         })
     }
 }
 
-fn compute_line_changes(hunks: &Vec<DiffHunk>) -> (u32, u32) {
+impl UnifiedDiff {
+    /// Compute the changed portions of removed and added lines of all hunks at the given `granularity`, and
+    /// store them in [`DiffHunk::highlights`]. `repo` is used to read `diff.wordRegex`.
+    ///
+    /// Within each block of removed lines followed by added lines, the n-th removed line is paired with the n-th added line.
+    /// Lines without counterpart changed entirely and aren't highlighted.
+    pub fn highlight_intra_line_changes(
+        &mut self,
+        repo: &gix::Repository,
+        granularity: IntraLineDiff,
+    ) -> anyhow::Result<()> {
+        let UnifiedDiff::Patch { hunks, .. } = self else {
+            return Ok(());
+        };
+        let tokenizer = Tokenizer::new(repo, granularity)?;
+        for hunk in hunks {
+            hunk.highlights = tokenizer.highlights(hunk.diff.as_bstr());
+        }
+        Ok(())
+    }
+}
+
+/// Splits lines into the tokens that are compared to find intra-line changes.
+enum Tokenizer {
+    Words(Option<regex::bytes::Regex>),
+    Characters,
+}
+
+impl Tokenizer {
+    fn new(repo: &gix::Repository, granularity: IntraLineDiff) -> anyhow::Result<Self> {
+        Ok(match granularity {
+            IntraLineDiff::Words => {
+                let word_regex = repo
+                    .config_snapshot()
+                    .string("diff.wordRegex")
+                    .map(|regex| {
+                        let regex = regex.to_str()?;
+                        regex::bytes::Regex::new(regex).map_err(|err| {
+                            anyhow::anyhow!("Invalid diff.wordRegex '{regex}': {err}")
+                        })
+                    })
+                    .transpose()?;
+                Tokenizer::Words(word_regex)
+            }
+            IntraLineDiff::Characters => Tokenizer::Characters,
+        })
+    }
+
+    /// Return the ranges of all tokens in `line`, which together cover the whole line.
+    /// Text that isn't part of a word is put into tokens of its own.
+    fn tokens(&self, line: &[u8]) -> Vec<Range<usize>> {
+        let mut out = Vec::new();
+        match self {
+            Tokenizer::Words(Some(regex)) => {
+                let mut last_end = 0;
+                for word in regex.find_iter(line).filter(|word| !word.is_empty()) {
+                    if word.start() > last_end {
+                        out.push(last_end..word.start());
+                    }
+                    out.push(word.range());
+                    last_end = word.end();
+                }
+                if last_end < line.len() {
+                    out.push(last_end..line.len());
+                }
+            }
+            Tokenizer::Words(None) => {
+                let mut start = 0;
+                for (index, byte) in line.iter().enumerate().skip(1) {
+                    if byte.is_ascii_whitespace() != line[index - 1].is_ascii_whitespace() {
+                        out.push(start..index);
+                        start = index;
+                    }
+                }
+                if start < line.len() {
+                    out.push(start..line.len());
+                }
+            }
+            Tokenizer::Characters => {
+                out.extend(line.char_indices().map(|(start, end, _)| start..end));
+            }
+        }
+        out
+    }
+
+    /// Pair removed and added lines in the unified `diff` of a hunk and return the changed portions of each.
+    fn highlights(&self, diff: &BStr) -> Vec<LineHighlight> {
+        let mut out = Vec::new();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (index, line) in diff.lines().enumerate().skip(1 /* header */) {
+            let content = line.get(1..).unwrap_or_default();
+            match line.first() {
+                Some(b'-') => {
+                    if !added.is_empty() {
+                        self.highlight_pairs(&mut out, &mut removed, &mut added);
+                    }
+                    removed.push((index, content));
+                }
+                Some(b'+') => added.push((index, content)),
+                // `\ No newline at end of file` belongs to the line before it.
+                Some(b'\\') => {}
+                _ => self.highlight_pairs(&mut out, &mut removed, &mut added),
+            }
+        }
+        self.highlight_pairs(&mut out, &mut removed, &mut added);
+        out.sort_by_key(|highlight| highlight.line);
+        out
+    }
+
+    /// Diff the tokens of each pair of `removed` and `added` lines and add their changed portions to `out`.
+    /// Both lists are cleared afterwards.
+    fn highlight_pairs(
+        &self,
+        out: &mut Vec<LineHighlight>,
+        removed: &mut Vec<(usize, &[u8])>,
+        added: &mut Vec<(usize, &[u8])>,
+    ) {
+        for ((old_line, old), (new_line, new)) in removed.iter().zip(added.iter()) {
+            let (old_tokens, new_tokens) = (self.tokens(old), self.tokens(new));
+            let input = gix::diff::blob::intern::InternedInput::new(
+                Tokens::new(old, &old_tokens),
+                Tokens::new(new, &new_tokens),
+            );
+            let (mut old_ranges, mut new_ranges) = (Vec::new(), Vec::new());
+            gix::diff::blob::diff(
+                gix::diff::blob::Algorithm::Histogram,
+                &input,
+                |before: Range<u32>, after: Range<u32>| {
+                    push_token_range(&mut old_ranges, &old_tokens, before);
+                    push_token_range(&mut new_ranges, &new_tokens, after);
+                },
+            );
+            for (line, ranges) in [(old_line, old_ranges), (new_line, new_ranges)] {
+                if !ranges.is_empty() {
+                    out.push(LineHighlight {
+                        line: *line as u32,
+                        ranges,
+                    });
+                }
+            }
+        }
+        removed.clear();
+        added.clear();
+    }
+}
+
+/// The bytes of each token of a line, or of each line of a file, for diffing them.
+struct Tokens<'a>(Vec<&'a [u8]>);
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a [u8], tokens: &[Range<usize>]) -> Self {
+        Tokens(tokens.iter().map(|token| &line[token.clone()]).collect())
+    }
+}
+
+impl<'a> gix::diff::blob::intern::TokenSource for Tokens<'a> {
+    type Token = &'a [u8];
+    type Tokenizer = std::vec::IntoIter<&'a [u8]>;
+
+    fn tokenize(&self) -> Self::Tokenizer {
+        self.0.clone().into_iter()
+    }
+
+    fn estimate_tokens(&self) -> u32 {
+        self.0.len() as u32
+    }
+}
+
+/// Add the bytes covered by the `changed` tokens in `tokens` to `ranges`, merging it with the last range if they touch.
+fn push_token_range(ranges: &mut Vec<Range<u32>>, tokens: &[Range<usize>], changed: Range<u32>) {
+    if changed.is_empty() {
+        return;
+    }
+    let start = tokens[changed.start as usize].start as u32;
+    let end = tokens[changed.end as usize - 1].end as u32;
+    match ranges.last_mut() {
+        Some(last) if last.end == start => last.end = end,
+        _ => ranges.push(start..end),
+    }
+}
+
+/// A change as found by the diff algorithm, as ranges of removed lines and of the added lines that replace them.
+type LineChange = (Range<u32>, Range<u32>);
+
+/// Diff the lines of `input` after normalizing them according to `options`, and produce hunks with `context_lines`.
+///
+/// The hunks always show the original lines, and context lines are taken from the previous version of the file,
+/// so lines that only differ in ignored whitespace are shown as they were before.
+pub(crate) fn hunks_with_options(
+    input: &gix::diff::blob::intern::InternedInput<&[u8]>,
+    algorithm: gix::diff::blob::Algorithm,
+    context_lines: u32,
+    options: DiffOptions,
+) -> Vec<DiffHunk> {
+    let before: Vec<&[u8]> = input
+        .before
+        .iter()
+        .map(|token| input.interner[*token])
+        .collect();
+    let after: Vec<&[u8]> = input
+        .after
+        .iter()
+        .map(|token| input.interner[*token])
+        .collect();
+    let normalized_before: Vec<_> = before
+        .iter()
+        .map(|line| options.whitespace.normalize(line))
+        .collect();
+    let normalized_after: Vec<_> = after
+        .iter()
+        .map(|line| options.whitespace.normalize(line))
+        .collect();
+    let normalized = gix::diff::blob::intern::InternedInput::new(
+        Tokens(normalized_before.iter().map(AsRef::as_ref).collect()),
+        Tokens(normalized_after.iter().map(AsRef::as_ref).collect()),
+    );
+
+    let mut changes = Vec::<LineChange>::new();
+    if options.algorithm == Some(DiffAlgorithm::Patience) {
+        patience_diff(
+            &normalized,
+            0..normalized.before.len() as u32,
+            0..normalized.after.len() as u32,
+            &mut changes,
+        );
+    } else {
+        gix::diff::blob::diff(
+            algorithm,
+            &normalized,
+            |before: Range<u32>, after: Range<u32>| changes.push((before, after)),
+        );
+    }
+    if options.indent_heuristic {
+        slide_to_least_indented_line(&mut changes, &normalized, &before, &after);
+    }
+    if options.ignore_blank_lines {
+        let is_blank = |line: &&[u8]| line.iter().all(u8::is_ascii_whitespace);
+        changes.retain(|(old, new)| {
+            !(before[to_usize(old)].iter().all(is_blank)
+                && after[to_usize(new)].iter().all(is_blank))
+        });
+    }
+    render_hunks(&changes, &before, &after, context_lines)
+}
+
+/// Diff the lines in `before` and `after` of `input` with the patience algorithm and append the changes to `changes`.
+///
+/// Lines that occur exactly once on both sides are matched, and the longest sequence of matches that appears in the
+/// same order on both sides is kept. The lines between these anchors are diffed recursively, and if there is no
+/// anchor, the Myers algorithm is used, just like Git does.
+fn patience_diff(
+    input: &gix::diff::blob::intern::InternedInput<&[u8]>,
+    mut before: Range<u32>,
+    mut after: Range<u32>,
+    changes: &mut Vec<LineChange>,
+) {
+    let same_line = |old: u32, new: u32| input.before[old as usize] == input.after[new as usize];
+    while !before.is_empty() && !after.is_empty() && same_line(before.start, after.start) {
+        before.start += 1;
+        after.start += 1;
+    }
+    while !before.is_empty() && !after.is_empty() && same_line(before.end - 1, after.end - 1) {
+        before.end -= 1;
+        after.end -= 1;
+    }
+    if before.is_empty() || after.is_empty() {
+        if !(before.is_empty() && after.is_empty()) {
+            changes.push((before, after));
+        }
+        return;
+    }
+
+    let anchors = unique_common_lines(input, before.clone(), after.clone());
+    if anchors.is_empty() {
+        let (old_offset, new_offset) = (before.start, after.start);
+        gix::diff::blob::diff_with_tokens(
+            gix::diff::blob::Algorithm::Myers,
+            &input.before[to_usize(&before)],
+            &input.after[to_usize(&after)],
+            input.interner.num_tokens(),
+            |old: Range<u32>, new: Range<u32>| {
+                changes.push((
+                    old.start + old_offset..old.end + old_offset,
+                    new.start + new_offset..new.end + new_offset,
+                ))
+            },
+        );
+        return;
+    }
+
+    let (mut old_start, mut new_start) = (before.start, after.start);
+    for (old, new) in anchors {
+        patience_diff(input, old_start..old, new_start..new, changes);
+        (old_start, new_start) = (old + 1, new + 1);
+    }
+    patience_diff(input, old_start..before.end, new_start..after.end, changes);
+}
+
+/// Return the positions of lines in `before` and `after` of `input` that occur exactly once in each,
+/// reduced to the longest sequence whose positions increase on both sides.
+fn unique_common_lines(
+    input: &gix::diff::blob::intern::InternedInput<&[u8]>,
+    before: Range<u32>,
+    after: Range<u32>,
+) -> Vec<(u32, u32)> {
+    // The amount of occurrences and the last position in `before` and `after` of each line.
+    let mut occurrences = HashMap::<_, (u32, u32, u32, u32)>::new();
+    for old in before {
+        let entry = occurrences.entry(input.before[old as usize]).or_default();
+        entry.0 += 1;
+        entry.1 = old;
+    }
+    for new in after {
+        if let Some(entry) = occurrences.get_mut(&input.after[new as usize]) {
+            entry.2 += 1;
+            entry.3 = new;
+        }
+    }
+    let mut matches: Vec<(u32, u32)> = occurrences
+        .into_values()
+        .filter(|(old_count, _, new_count, _)| *old_count == 1 && *new_count == 1)
+        .map(|(_, old, _, new)| (old, new))
+        .collect();
+    matches.sort_unstable();
+
+    // Patience sorting: `piles` holds the index of the match at the top of each pile, and each match remembers
+    // the match at the top of the previous pile when it was placed to reconstruct the longest increasing sequence.
+    let mut piles = Vec::<usize>::new();
+    let mut previous = vec![None; matches.len()];
+    for (index, (_, new)) in matches.iter().enumerate() {
+        let pile = piles.partition_point(|top| matches[*top].1 < *new);
+        previous[index] = pile.checked_sub(1).map(|pile| piles[pile]);
+        match piles.get_mut(pile) {
+            Some(top) => *top = index,
+            None => piles.push(index),
+        }
+    }
+    let mut sequence = Vec::with_capacity(piles.len());
+    let mut next = piles.last().copied();
+    while let Some(index) = next {
+        sequence.push(matches[index]);
+        next = previous[index];
+    }
+    sequence.reverse();
+    sequence
+}
+
+fn to_usize(range: &Range<u32>) -> Range<usize> {
+    range.start as usize..range.end as usize
+}
+
+/// Shift each change that only adds or only removes lines to the position at which its first line is the least indented,
+/// among all positions that produce the same result. If there are multiple, the last one is used, which is also
+/// the position the diff algorithm chooses.
+///
+/// This typically aligns the change with the beginning of a block, instead of placing it across two blocks.
+fn slide_to_least_indented_line(
+    changes: &mut [LineChange],
+    input: &gix::diff::blob::intern::InternedInput<&[u8]>,
+    before: &[&[u8]],
+    after: &[&[u8]],
+) {
+    for index in 0..changes.len() {
+        let (old, new) = changes[index].clone();
+        let previous = index.checked_sub(1).map(|index| changes[index].clone());
+        let next = changes.get(index + 1).cloned();
+        // Lines between changes are unchanged on both sides, so it's enough to look at the side with the lines.
+        let (changed, tokens, lines, lower_bound, upper_bound) = if old.is_empty() {
+            (
+                new,
+                &input.after,
+                after,
+                previous.map_or(0, |(_, new)| new.end),
+                next.map_or(after.len() as u32, |(_, new)| new.start),
+            )
+        } else if new.is_empty() {
+            (
+                old,
+                &input.before,
+                before,
+                previous.map_or(0, |(old, _)| old.end),
+                next.map_or(before.len() as u32, |(old, _)| old.start),
+            )
+        } else {
+            continue;
+        };
+
+        let token = |line: u32| tokens[line as usize];
+        let mut start = changed.start;
+        let mut end = changed.end;
+        while start > lower_bound && token(start - 1) == token(end - 1) {
+            start -= 1;
+            end -= 1;
+        }
+        let mut best_start = start;
+        while end < upper_bound && token(start) == token(end) {
+            start += 1;
+            end += 1;
+            if indentation(lines[start as usize]) <= indentation(lines[best_start as usize]) {
+                best_start = start;
+            }
+        }
+
+        let (old, new) = &mut changes[index];
+        for range in [old, new] {
+            if best_start >= changed.start {
+                let shift = best_start - changed.start;
+                *range = range.start + shift..range.end + shift;
+            } else {
+                let shift = changed.start - best_start;
+                *range = range.start - shift..range.end - shift;
+            }
+        }
+    }
+}
+
+/// Return the width of the leading whitespace of `line`, with tabs advancing to the next multiple of 8.
+/// Blank lines have no indentation.
+fn indentation(line: &[u8]) -> usize {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return 0;
+    }
+    line.iter()
+        .take_while(|byte| **byte == b' ' || **byte == b'\t')
+        .fold(0, |width, byte| {
+            if *byte == b'\t' {
+                width + 8 - width % 8
+            } else {
+                width + 1
+            }
+        })
+}
+
+/// Produce hunks from `changes` to `before` that yield `after`, merging changes whose `context_lines` would overlap.
+fn render_hunks(
+    changes: &[LineChange],
+    before: &[&[u8]],
+    after: &[&[u8]],
+    context_lines: u32,
+) -> Vec<DiffHunk> {
+    fn push_line(diff: &mut BString, prefix: u8, line: &[u8]) {
+        diff.push(prefix);
+        diff.extend_from_slice(line);
+        if !line.ends_with(b"\n") {
+            diff.push(b'\n');
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut remaining = changes;
+    while let Some((first_old, first_new)) = remaining.first() {
+        let len = remaining
+            .windows(2)
+            .position(|pair| pair[1].0.start - pair[0].0.end > 2 * context_lines)
+            .map_or(remaining.len(), |position| position + 1);
+        let (hunk_changes, rest) = remaining.split_at(len);
+        remaining = rest;
+
+        let (last_old, last_new) = hunk_changes.last().expect("at least one change");
+        let old_start = first_old.start.saturating_sub(context_lines);
+        let new_start = first_new.start - (first_old.start - old_start);
+        let old_end = (last_old.end + context_lines).min(before.len() as u32);
+        let new_end = last_new.end + (old_end - last_old.end);
+
+        let mut diff = BString::from(format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            old_end - old_start,
+            new_start + 1,
+            new_end - new_start
+        ));
+        let mut old_pos = old_start;
+        for (old, new) in hunk_changes {
+            for line in &before[old_pos as usize..old.start as usize] {
+                push_line(&mut diff, b' ', line);
+            }
+            for line in &before[to_usize(old)] {
+                push_line(&mut diff, b'-', line);
+            }
+            for line in &after[to_usize(new)] {
+                push_line(&mut diff, b'+', line);
+            }
+            old_pos = old.end;
+        }
+        for line in &before[old_pos as usize..old_end as usize] {
+            push_line(&mut diff, b' ', line);
+        }
+        out.push(DiffHunk {
+            old_start: old_start + 1,
+            old_lines: old_end - old_start,
+            new_start: new_start + 1,
+            new_lines: new_end - new_start,
+            diff,
+            highlights: Vec::new(),
+        });
+    }
+    out
+}
+
+pub(crate) fn compute_line_changes(hunks: &Vec<DiffHunk>) -> (u32, u32) {
     let mut lines_added = 0;
     let mut lines_removed = 0;
     for hunk in hunks {
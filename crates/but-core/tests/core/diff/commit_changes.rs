@@ -174,6 +174,98 @@ fn without_previous_tree() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn copies_with_copy_tracking() -> anyhow::Result<()> {
+    let repo = repo("copied-in-tree")?;
+    let previous_commit_id = repo.rev_parse_single("@~1")?;
+    let current_commit_id = repo.rev_parse_single("@")?;
+    let changes = but_core::diff::tree_changes(
+        &repo,
+        Some(previous_commit_id.into()),
+        current_commit_id.into(),
+    )?;
+    insta::assert_debug_snapshot!(changes, @r#"
+    (
+        [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(31807751f2f8d3dd2179bd0c05cf9000da11c6dc),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+            TreeChange {
+                path: "original",
+                status: Modification {
+                    previous_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(3bb459b831ea471b9cd1cbb7c6d54a74251a711b),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        Stats {
+            lines_added: 2,
+            lines_removed: 1,
+            files_changed: 2,
+        },
+    )
+    "#);
+    Ok(())
+}
+
+#[test]
+fn copies_of_unchanged_files_with_copy_tracking() -> anyhow::Result<()> {
+    let repo = repo("copied-unchanged-source-in-tree")?;
+    let previous_commit_id = repo.rev_parse_single("@~1")?;
+    let current_commit_id = repo.rev_parse_single("@")?;
+    let changes = but_core::diff::tree_changes(
+        &repo,
+        Some(previous_commit_id.into()),
+        current_commit_id.into(),
+    )?;
+    insta::assert_debug_snapshot!(changes, @r#"
+    (
+        [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(31807751f2f8d3dd2179bd0c05cf9000da11c6dc),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        Stats {
+            lines_added: 1,
+            lines_removed: 1,
+            files_changed: 1,
+        },
+    )
+    "#);
+    Ok(())
+}
+
 #[test]
 fn changes_between_conflicted_and_normal_commit() -> anyhow::Result<()> {
     let repo = conflict_repo("normal-and-artificial")?;
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use but_core::diff;
-use but_core::{UnifiedDiff, WorktreeChanges};
+use but_core::{TreeChange, TreeStatus, UnifiedDiff, WorktreeChanges};
 use but_testsupport::gix_testtools;
 
 #[test]
@@ -926,6 +926,135 @@ fn added_modified_in_worktree() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn incremental_only_recomputes_dirty_paths() -> Result<()> {
+    let repo = repo("added-modified-in-worktree")?;
+    let nothing = WorktreeChanges {
+        changes: vec![],
+        ignored_changes: vec![],
+    };
+    let actual = diff::worktree_changes_incremental(&repo, nothing, Some("modified".into()))?;
+    insta::assert_debug_snapshot!(actual, @r#"
+    WorktreeChanges {
+        changes: [
+            TreeChange {
+                path: "modified",
+                status: Modification {
+                    previous_state: ChangeState {
+                        id: Sha1(deba01fc8d98200761c46eb139f11ac244cf6eb5),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(0000000000000000000000000000000000000000),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        ignored_changes: [],
+    }
+    "#);
+
+    let full = diff::worktree_changes(&repo)?;
+    let actual = diff::worktree_changes_incremental(
+        &repo,
+        full.clone(),
+        ["added".into(), "modified".into()],
+    )?;
+    assert_eq!(
+        format!("{actual:?}"),
+        format!("{full:?}"),
+        "recomputing paths that didn't change yields the same result"
+    );
+    Ok(())
+}
+
+#[test]
+fn incremental_removes_changes_in_dirty_directories() -> Result<()> {
+    let repo = repo("added-modified-in-worktree")?;
+    let mut previous = diff::worktree_changes(&repo)?;
+    let expected = format!("{previous:?}");
+
+    let mut stale = previous.changes[0].clone();
+    stale.path = "dir/stale".into();
+    previous.changes.push(stale);
+    let actual = diff::worktree_changes_incremental(&repo, previous, Some("dir".into()))?;
+    assert_eq!(
+        format!("{actual:?}"),
+        expected,
+        "a dirty directory invalidates all changes within, and nothing changed there on disk"
+    );
+    Ok(())
+}
+
+#[test]
+fn incremental_pairs_new_additions_with_previous_deletions() -> Result<()> {
+    let repo = repo("renamed-in-worktree")?;
+    let full = diff::worktree_changes(&repo)?;
+    let (deletion, _addition) = rename_as_deletion_and_addition(&full);
+
+    // The file was deleted first, and only its new location is dirty now.
+    let previous = WorktreeChanges {
+        changes: vec![deletion],
+        ignored_changes: vec![],
+    };
+    let actual = diff::worktree_changes_incremental(&repo, previous, Some("new-name".into()))?;
+    assert_eq!(
+        format!("{actual:?}"),
+        format!("{full:?}"),
+        "the previous deletion is paired with the new addition, just like a full status does"
+    );
+    Ok(())
+}
+
+#[test]
+fn incremental_pairs_new_deletions_with_previous_additions() -> Result<()> {
+    let repo = repo("renamed-in-worktree")?;
+    let full = diff::worktree_changes(&repo)?;
+    let (_deletion, addition) = rename_as_deletion_and_addition(&full);
+
+    // The file was copied first, and only the removal of the original is dirty now.
+    let previous = WorktreeChanges {
+        changes: vec![addition],
+        ignored_changes: vec![],
+    };
+    let actual = diff::worktree_changes_incremental(&repo, previous, Some("to-be-renamed".into()))?;
+    assert_eq!(
+        format!("{actual:?}"),
+        format!("{full:?}"),
+        "the previous addition is paired with the new deletion, just like a full status does"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn fsmonitor_limits_checks_to_reported_paths() -> Result<()> {
+    let repo = repo_unix("fsmonitor")?;
+    let actual = diff::worktree_changes(&repo)?;
+    assert_eq!(
+        changed_paths(&actual),
+        ["dir/in-reported-dir", "reported", "untracked"],
+        "`unreported` also changed, but isn't checked as the hook didn't report it. \
+         Untracked files are still found."
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn fsmonitor_can_report_everything_as_changed() -> Result<()> {
+    let repo = repo_unix("fsmonitor-reports-everything")?;
+    let actual = diff::worktree_changes(&repo)?;
+    assert_eq!(
+        changed_paths(&actual),
+        ["dir/in-reported-dir", "reported", "unreported", "untracked"],
+        "all tracked files are checked if the hook doesn't know what changed"
+    );
+    Ok(())
+}
+
 #[test]
 fn modified_in_index() -> Result<()> {
     let repo = repo("modified-in-index")?;
@@ -1199,6 +1328,181 @@ fn renamed_in_worktree_with_executable_bit() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn copied_in_worktree() -> Result<()> {
+    let repo = repo("copied-in-worktree")?;
+    let actual = diff::worktree_changes(&repo)?;
+    insta::assert_debug_snapshot!(actual, @r#"
+    WorktreeChanges {
+        changes: [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(0000000000000000000000000000000000000000),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+            TreeChange {
+                path: "original",
+                status: Modification {
+                    previous_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(0000000000000000000000000000000000000000),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        ignored_changes: [],
+    }
+    "#);
+
+    let [
+        UnifiedDiff::Patch {
+            hunks: ref copy_hunks,
+            ..
+        },
+        UnifiedDiff::Patch {
+            hunks: ref source_hunks,
+            ..
+        },
+    ] = unified_diffs(actual, &repo)?[..]
+    else {
+        unreachable!("need hunks")
+    };
+    insta::assert_snapshot!(copy_hunks[0].diff, @r"
+    @@ -1,6 +1,6 @@
+     1
+     2
+    -3
+    +three
+     4
+     5
+     6
+    ");
+    insta::assert_snapshot!(source_hunks[0].diff, @r"
+    @@ -8,3 +8,4 @@
+     8
+     9
+     10
+    +11
+    ");
+    Ok(())
+}
+
+#[test]
+fn copied_in_index() -> Result<()> {
+    let repo = repo("copied-in-index")?;
+    let actual = diff::worktree_changes(&repo)?;
+    insta::assert_debug_snapshot!(actual, @r#"
+    WorktreeChanges {
+        changes: [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(31807751f2f8d3dd2179bd0c05cf9000da11c6dc),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+            TreeChange {
+                path: "original",
+                status: Modification {
+                    previous_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(3bb459b831ea471b9cd1cbb7c6d54a74251a711b),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        ignored_changes: [],
+    }
+    "#);
+    Ok(())
+}
+
+#[test]
+fn copied_unchanged_source_in_worktree() -> Result<()> {
+    let repo = repo("copied-unchanged-source-in-worktree")?;
+    let actual = diff::worktree_changes(&repo)?;
+    insta::assert_debug_snapshot!(actual, @r#"
+    WorktreeChanges {
+        changes: [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(0000000000000000000000000000000000000000),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        ignored_changes: [],
+    }
+    "#);
+    Ok(())
+}
+
+#[test]
+fn copied_unchanged_source_in_index() -> Result<()> {
+    let repo = repo("copied-unchanged-source-in-index")?;
+    let actual = diff::worktree_changes(&repo)?;
+    insta::assert_debug_snapshot!(actual, @r#"
+    WorktreeChanges {
+        changes: [
+            TreeChange {
+                path: "copy",
+                status: Copy {
+                    source_path: "original",
+                    source_state: ChangeState {
+                        id: Sha1(f00c965d8307308469e537302baa73048488f162),
+                        kind: Blob,
+                    },
+                    state: ChangeState {
+                        id: Sha1(31807751f2f8d3dd2179bd0c05cf9000da11c6dc),
+                        kind: Blob,
+                    },
+                    flags: None,
+                },
+            },
+        ],
+        ignored_changes: [],
+    }
+    "#);
+    Ok(())
+}
+
 #[test]
 fn modified_in_index_and_worktree_mod_mod() -> Result<()> {
     let repo = repo("modified-in-index-and-worktree-mod-mod")?;
@@ -1753,6 +2057,50 @@ fn modified_in_index_and_worktree_add_rename() -> Result<()> {
     Ok(())
 }
 
+/// Split the only change in `changes`, a rename, into the deletion of its source and the addition of its destination.
+fn rename_as_deletion_and_addition(changes: &WorktreeChanges) -> (TreeChange, TreeChange) {
+    let [
+        TreeChange {
+            path,
+            status:
+                TreeStatus::Rename {
+                    previous_path,
+                    previous_state,
+                    state,
+                    ..
+                },
+        },
+    ] = changes.changes.as_slice()
+    else {
+        panic!("expected a single rename, got {changes:?}");
+    };
+    (
+        TreeChange {
+            path: previous_path.clone(),
+            status: TreeStatus::Deletion {
+                previous_state: *previous_state,
+            },
+        },
+        TreeChange {
+            path: path.clone(),
+            status: TreeStatus::Addition {
+                state: *state,
+                is_untracked: true,
+            },
+        },
+    )
+}
+
+fn changed_paths(changes: &WorktreeChanges) -> Vec<String> {
+    let mut paths: Vec<_> = changes
+        .changes
+        .iter()
+        .map(|change| change.path.to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
 fn unified_diffs(
     worktree: WorktreeChanges,
     repo: &gix::Repository,
@@ -1,4 +1,9 @@
+use but_core::diff::binary::{BinaryResource, MetadataSummary};
+use but_core::unified_diff::{
+    DiffAlgorithm, DiffOptions, IntraLineDiff, LineHighlight, WhitespaceMode,
+};
 use but_core::{ChangeState, UnifiedDiff, unified_diff};
+use gix::bstr::BString;
 use gix::object::tree::EntryKind;
 
 #[test]
@@ -126,7 +131,7 @@ fn big_file_20_in_worktree() -> anyhow::Result<()> {
         3,
     )?;
     match actual {
-        UnifiedDiff::Binary | UnifiedDiff::Patch { .. } => {
+        UnifiedDiff::Binary(_) | UnifiedDiff::Patch { .. } => {
             unreachable!("Should be considered too large")
         }
         UnifiedDiff::TooLarge { size_in_bytes } => {
@@ -157,13 +162,106 @@ fn binary_file_in_worktree() -> anyhow::Result<()> {
         UnifiedDiff::TooLarge { .. } | UnifiedDiff::Patch { .. } => {
             unreachable!("Should be considered binary, but was {actual:?}");
         }
-        UnifiedDiff::Binary => {
-            // There is no more information here, binary files aren't diffed.
+        UnifiedDiff::Binary(diff) => {
+            assert!(diff.old.is_none(), "the file is untracked");
+            let new = diff.new.expect("the file exists in the worktree");
+            assert_eq!(new.size_in_bytes, 4);
+            assert_eq!(new.mime_type, None, "it's not a format we know");
+            assert_eq!(new.image_dimensions, None);
+            assert!(!diff.content_unchanged);
         }
     }
     Ok(())
 }
 
+#[test]
+fn binary_image_modified_in_worktree() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("binary-image-modified-in-worktree")?;
+    let change = &but_core::diff::worktree_changes(&repo)?.changes[0];
+    let UnifiedDiff::Binary(diff) = change.unified_diff(&repo, 3)? else {
+        unreachable!("images are binary")
+    };
+    insta::assert_debug_snapshot!(diff, @r#"
+    BinaryDiff {
+        old: Some(
+            BinaryResource {
+                path: "image.png",
+                id: Sha1(d1ed0c8713020f5bda68b22792b0512681d2c0f9),
+                size_in_bytes: 29,
+                mime_type: Some(
+                    "image/png",
+                ),
+                image_dimensions: Some(
+                    ImageDimensions {
+                        width: 1,
+                        height: 1,
+                    },
+                ),
+            },
+        ),
+        new: Some(
+            BinaryResource {
+                path: "image.png",
+                id: Sha1(04b7167e59da5f3b4246fd053e3bddaf4ea42b9d),
+                size_in_bytes: 33,
+                mime_type: Some(
+                    "image/png",
+                ),
+                image_dimensions: Some(
+                    ImageDimensions {
+                        width: 3,
+                        height: 2,
+                    },
+                ),
+            },
+        ),
+        content_unchanged: false,
+    }
+    "#);
+
+    let hunks = extract_patch(
+        diff.to_text_diff(&repo, &mut MetadataSummary, 3)?
+            .expect("metadata can always be converted"),
+    );
+    insta::assert_snapshot!(hunks[0].diff, @r"
+    @@ -1,4 +1,4 @@
+    -size: 29 bytes
+    +size: 33 bytes
+     type: image/png
+    -dimensions: 1x1
+    -id: d1ed0c8713020f5bda68b22792b0512681d2c0f9
+    +dimensions: 3x2
+    +id: 04b7167e59da5f3b4246fd053e3bddaf4ea42b9d
+    ");
+
+    let hunks = extract_patch(
+        diff.to_text_diff(
+            &repo,
+            &mut |resource: &BinaryResource, data: &[u8]| -> anyhow::Result<Option<BString>> {
+                Ok(Some(
+                    format!("{} has {} bytes\n", resource.path, data.len()).into(),
+                ))
+            },
+            0,
+        )?
+        .expect("the closure converts everything"),
+    );
+    insta::assert_snapshot!(hunks[0].diff, @r"
+    @@ -1,1 +1,1 @@
+    -image.png has 29 bytes
+    +image.png has 33 bytes
+    ");
+
+    fn refuse(_: &BinaryResource, _: &[u8]) -> anyhow::Result<Option<BString>> {
+        Ok(None)
+    }
+    assert!(
+        diff.to_text_diff(&repo, &mut refuse, 3)?.is_none(),
+        "without text for all versions, there is no diff"
+    );
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn symlink_modified_in_worktree() -> anyhow::Result<()> {
@@ -232,9 +330,203 @@ fn submodule_added() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn intra_line_highlights_by_whitespace_separated_words() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("modified-words-in-worktree")?;
+    let changes = but_core::diff::worktree_changes(&repo)?.changes;
+    let mut diff = changes[0].unified_diff(&repo, 3)?;
+    diff.highlight_intra_line_changes(&repo, IntraLineDiff::Words)?;
+
+    let hunks = extract_patch(diff);
+    assert_eq!(
+        hunks[0].highlights,
+        [
+            LineHighlight {
+                line: 1,
+                ranges: vec![4..9, 23..26],
+            },
+            LineHighlight {
+                line: 2,
+                ranges: vec![4..10, 24..27],
+            },
+        ],
+        "'value' became 'result', and 'b);' became 'c);', while the unchanged line isn't highlighted"
+    );
+    Ok(())
+}
+
+#[test]
+fn intra_line_highlights_respect_word_regex() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("modified-words-with-word-regex-in-worktree")?;
+    let changes = but_core::diff::worktree_changes(&repo)?.changes;
+    let mut diff = changes[0].unified_diff(&repo, 3)?;
+    diff.highlight_intra_line_changes(&repo, IntraLineDiff::Words)?;
+
+    let hunks = extract_patch(diff);
+    assert_eq!(
+        hunks[0].highlights,
+        [
+            LineHighlight {
+                line: 1,
+                ranges: vec![4..9, 23..24],
+            },
+            LineHighlight {
+                line: 2,
+                ranges: vec![4..10, 24..25],
+            },
+        ],
+        "punctuation is split from identifiers, so only 'b' and 'c' changed at the end"
+    );
+    Ok(())
+}
+
+#[test]
+fn no_intra_line_highlights_by_default() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("modified-words-in-worktree")?;
+    let changes = but_core::diff::worktree_changes(&repo)?.changes;
+    let hunks = extract_patch(changes[0].unified_diff(&repo, 3)?);
+    assert!(hunks[0].highlights.is_empty());
+    Ok(())
+}
+
+#[test]
+fn whitespace_modes_and_blank_lines() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("modified-whitespace-in-worktree")?;
+    let change = &but_core::diff::worktree_changes(&repo)?.changes[0];
+    let hunk_ranges = |options: DiffOptions| -> anyhow::Result<Vec<_>> {
+        Ok(
+            extract_patch(change.unified_diff_with_options(&repo, 0, options)?)
+                .into_iter()
+                .map(|hunk| {
+                    (
+                        hunk.old_start,
+                        hunk.old_lines,
+                        hunk.new_start,
+                        hunk.new_lines,
+                    )
+                })
+                .collect(),
+        )
+    };
+
+    assert_eq!(
+        hunk_ranges(DiffOptions::default())?,
+        [(2, 2, 2, 3), (5, 1, 6, 1)],
+        "by default, all whitespace changes are visible"
+    );
+    assert_eq!(
+        hunk_ranges(DiffOptions {
+            whitespace: WhitespaceMode::IgnoreChange,
+            ..Default::default()
+        })?,
+        [(3, 1, 3, 2), (5, 1, 6, 1)],
+        "the changed indentation is ignored, but not the removal of whitespace"
+    );
+    assert_eq!(
+        hunk_ranges(DiffOptions {
+            whitespace: WhitespaceMode::IgnoreAll,
+            ..Default::default()
+        })?,
+        [(4, 0, 4, 1), (5, 1, 6, 1)],
+        "only the added blank line and the actual change remain"
+    );
+    assert_eq!(
+        hunk_ranges(DiffOptions {
+            whitespace: WhitespaceMode::IgnoreAll,
+            ignore_blank_lines: true,
+            ..Default::default()
+        })?,
+        [(5, 1, 6, 1)],
+        "the added blank line is ignored as well"
+    );
+
+    let hunks = extract_patch(change.unified_diff_with_options(
+        &repo,
+        1,
+        DiffOptions {
+            whitespace: WhitespaceMode::IgnoreChange,
+            ..Default::default()
+        },
+    )?);
+    insta::assert_debug_snapshot!(hunks, @r#"
+    [
+        DiffHunk("@@ -2,5 +2,6 @@
+             let a = 1;
+        -    let b = 2;
+        +    let b=2;
+        +
+             let c = 3;
+        -    println!("{a}");
+        +    println!("{a} {b} {c}");
+         }
+        "),
+    ]
+    "#);
+    Ok(())
+}
+
+#[test]
+fn indent_heuristic_starts_added_block_at_least_indented_line() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("block-added-after-similar-block-in-worktree")?;
+    let change = &but_core::diff::worktree_changes(&repo)?.changes[0];
+    let hunks = extract_patch(change.unified_diff_with_options(
+        &repo,
+        0,
+        DiffOptions {
+            indent_heuristic: true,
+            ..Default::default()
+        },
+    )?);
+    insta::assert_debug_snapshot!(hunks, @r#"
+    [
+        DiffHunk("@@ -4,0 +4,3 @@
+        +if b {
+        +    foo();
+        +}
+        "),
+    ]
+    "#);
+    Ok(())
+}
+
+#[test]
+fn patience_algorithm_anchors_lines_that_are_unique_on_both_sides() -> anyhow::Result<()> {
+    let repo = crate::diff::worktree_changes::repo("reordered-lines-in-worktree")?;
+    let change = &but_core::diff::worktree_changes(&repo)?.changes[0];
+    let hunk_ranges = |algorithm: DiffAlgorithm| -> anyhow::Result<Vec<_>> {
+        Ok(extract_patch(change.unified_diff_with_options(
+            &repo,
+            0,
+            DiffOptions {
+                algorithm: Some(algorithm),
+                ..Default::default()
+            },
+        )?)
+        .into_iter()
+        .map(|hunk| {
+            (
+                hunk.old_start,
+                hunk.old_lines,
+                hunk.new_start,
+                hunk.new_lines,
+            )
+        })
+        .collect())
+    };
+
+    assert_eq!(
+        hunk_ranges(DiffAlgorithm::Patience)?,
+        [(1, 1, 1, 0), (3, 0, 2, 2), (4, 1, 5, 0)],
+        "`b` and `a` are kept as they are unique, which is what `git diff --patience` does as well"
+    );
+    Ok(())
+}
+
 fn extract_patch(diff: UnifiedDiff) -> Vec<unified_diff::DiffHunk> {
     match diff {
-        UnifiedDiff::Binary | UnifiedDiff::TooLarge { .. } => unreachable!("should have patches"),
+        UnifiedDiff::Binary(_) | UnifiedDiff::TooLarge { .. } => {
+            unreachable!("should have patches")
+        }
         UnifiedDiff::Patch { hunks, .. } => hunks,
     }
 }
@@ -66,4 +66,25 @@ git init many-in-tree
   rm file-to-link && ln -s link-target file-to-link
 
   git add . && git commit -m "change"
-)
\ No newline at end of file
+)
+git init copied-in-tree
+(cd copied-in-tree
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  cp original copy
+  echo 11 >>original
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+  git add . && git commit -m "copy and modify the source"
+)
+
+git init copied-unchanged-source-in-tree
+(cd copied-unchanged-source-in-tree
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+  git add . && git commit -m "copy and keep the source"
+)
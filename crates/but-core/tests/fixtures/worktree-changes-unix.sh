@@ -66,3 +66,34 @@ git init symlink-change-in-worktree
   git add . && git commit -m "init"
   rm symlink && ln -s changed-target symlink
 )
+
+git init fsmonitor
+(cd fsmonitor
+  # Stands in for Watchman, reporting the paths listed in `.git/fsmonitor-changes` as changed.
+  cat >.git/fsmonitor-hook <<'HOOK'
+#!/bin/sh
+printf 'next-token\0'
+cat .git/fsmonitor-changes 2>/dev/null || true
+HOOK
+  chmod +x .git/fsmonitor-hook
+  git config core.fsmonitor .git/fsmonitor-hook
+
+  echo content >reported
+  echo content >unreported
+  mkdir dir && echo content >dir/in-reported-dir
+  git add . && git commit -m "init"
+  # Write the fsmonitor extension, with a token, and mark all entries as unchanged.
+  git update-index --fsmonitor
+  git status
+
+  echo change >reported
+  echo change >unreported
+  echo change >dir/in-reported-dir
+  echo content >untracked
+  printf 'reported\0dir/\0' >.git/fsmonitor-changes
+)
+
+cp -Rv fsmonitor fsmonitor-reports-everything
+(cd fsmonitor-reports-everything
+  printf '/\0' >.git/fsmonitor-changes
+)
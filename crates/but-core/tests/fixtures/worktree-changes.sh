@@ -295,3 +295,88 @@ cat <<EOF >>.git/config
 	textconv = "shift; echo ho"
 EOF
 )
+
+git init modified-words-in-worktree
+(cd modified-words-in-worktree
+  printf 'let value = compute(a, b);\nunchanged\n' >file
+  git add . && git commit -m "init"
+  printf 'let result = compute(a, c);\nunchanged\n' >file
+)
+
+git init modified-words-with-word-regex-in-worktree
+(cd modified-words-with-word-regex-in-worktree
+  git config diff.wordRegex '[[:alnum:]_]+|[^[:space:]]'
+  printf 'let value = compute(a, b);\nunchanged\n' >file
+  git add . && git commit -m "init"
+  printf 'let result = compute(a, c);\nunchanged\n' >file
+)
+
+git init modified-whitespace-in-worktree
+(cd modified-whitespace-in-worktree
+  printf 'fn main() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n    println!("{a}");\n}\n' >file
+  git add . && git commit -m "init"
+  printf 'fn main() {\n  let a = 1;\n    let b=2;\n\n    let c = 3;\n    println!("{a} {b} {c}");\n}\n' >file
+)
+
+git init block-added-after-similar-block-in-worktree
+(cd block-added-after-similar-block-in-worktree
+  printf 'if a {\n    foo();\n}\n' >file
+  git add . && git commit -m "init"
+  printf 'if a {\n    foo();\n}\nif b {\n    foo();\n}\n' >file
+)
+
+git init copied-in-worktree
+(cd copied-in-worktree
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  cp original copy
+  echo 11 >>original
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+)
+
+git init copied-in-index
+(cd copied-in-index
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  cp original copy
+  echo 11 >>original
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+  git add .
+)
+
+git init copied-unchanged-source-in-worktree
+(cd copied-unchanged-source-in-worktree
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+)
+
+git init copied-unchanged-source-in-index
+(cd copied-unchanged-source-in-index
+  git config diff.renames copies
+  printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+  git add . && git commit -m "init"
+
+  printf '1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n' >copy
+  git add .
+)
+
+git init binary-image-modified-in-worktree
+(cd binary-image-modified-in-worktree
+  printf '\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0' >image.png
+  git add . && git commit -m "init"
+  printf '\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x03\0\0\0\x02\x08\x06\0\0\0\0\0\0\0' >image.png
+)
+
+git init reordered-lines-in-worktree
+(cd reordered-lines-in-worktree
+  printf 'f\nb\na\nf\n' >file
+  git add . && git commit -m "init"
+  printf 'b\nf\nf\na\n' >file
+)
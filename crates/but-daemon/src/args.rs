@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Parser)]
+#[clap(
+    name = "but-daemon",
+    about = "A headless GitButler that serves workspace operations over a local socket",
+    version = option_env!("GIX_VERSION")
+)]
+pub struct Args {
+    /// Enable tracing for debug and performance information printed to stderr.
+    #[clap(short = 'd', long)]
+    pub trace: bool,
+    /// The path of the Unix-domain socket to listen on.
+    ///
+    /// Defaults to `but-daemon.sock` in the app data directory if unset.
+    #[clap(long, env = "GITBUTLER_DAEMON_SOCKET", value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+    /// The location of the directory to contain app data.
+    ///
+    /// Defaults to the standard location on this platform if unset.
+    #[clap(short = 'a', long, env = "GITBUTLER_CLI_DATA_DIR")]
+    pub app_data_dir: Option<PathBuf>,
+    /// The location of the directory containing the application settings.
+    ///
+    /// Defaults to the same directory the desktop application uses if unset.
+    #[clap(long, env = "GITBUTLER_DAEMON_CONFIG_DIR")]
+    pub config_dir: Option<PathBuf>,
+    /// A suffix like `dev` to refer to projects of the development version of the application.
+    ///
+    /// The production version is used if unset.
+    #[clap(short = 's', long)]
+    pub app_suffix: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clap() {
+        use clap::CommandFactory;
+        Args::command().debug_assert();
+    }
+}
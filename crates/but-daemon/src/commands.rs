@@ -0,0 +1,597 @@
+//! The command surface of the daemon, which mirrors the commands of the desktop application.
+//!
+//! Each method receives its parameters as JSON object with camel-cased keys, just like the
+//! frontend passes them to the respective `tauri` command.
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use but_core::unified_diff::DiffOptions;
+use but_settings::AppSettingsWithDiskSync;
+use but_workspace::commit_engine;
+use but_workspace::commit_engine::StackSegmentId;
+use gitbutler_branch_actions::upstream_integration::{BaseBranchResolution, Resolution};
+use gitbutler_command_context::CommandContext;
+use gitbutler_oplog::{OplogExt, SnapshotExt};
+use gitbutler_project as projects;
+use gitbutler_project::ProjectId;
+use gitbutler_stack::StackId;
+use gitbutler_user as users;
+use gitbutler_watcher::Subscriptions;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::protocol;
+
+/// All methods the daemon understands.
+pub const METHODS: &[&str] = &[
+    "stacks",
+    "stack_details",
+    "create_commit_from_worktree_changes",
+    "integrate_upstream",
+    "list_snapshots",
+    "restore_snapshot",
+    "watch",
+    "unwatch",
+];
+
+/// A project that is watched on behalf of clients.
+struct Watched {
+    /// The watcher of the project, stopping when dropped.
+    watcher: gitbutler_watcher::WatcherHandle,
+    /// An active lock to signal that the entire project is locked for as long as we watch it.
+    exclusive_access: gitbutler_project::access::LockFile,
+    /// The amount of sessions that watch the project, which is stopped to be watched when it drops to zero.
+    sessions: usize,
+}
+
+impl Drop for Watched {
+    fn drop(&mut self) {
+        if let Err(err) = self.exclusive_access.unlock() {
+            tracing::error!(err = ?err, "Failed to release the project-wide lock");
+        }
+    }
+}
+
+/// The state shared by all connections.
+pub struct Daemon {
+    projects: projects::Controller,
+    users: users::Controller,
+    settings: AppSettingsWithDiskSync,
+    watched: parking_lot::Mutex<BTreeMap<ProjectId, Watched>>,
+    subscriptions: Subscriptions,
+}
+
+/// The state of a single client.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// The projects the client wants notifications for.
+    pub watched: BTreeSet<ProjectId>,
+}
+
+/// Lifecycle
+impl Daemon {
+    /// Create a new instance that serves projects known to `projects`.
+    pub fn new(
+        projects: projects::Controller,
+        users: users::Controller,
+        settings: AppSettingsWithDiskSync,
+    ) -> Arc<Self> {
+        Arc::new(Daemon {
+            projects,
+            users,
+            settings,
+            watched: Default::default(),
+            subscriptions: Subscriptions::default(),
+        })
+    }
+
+    /// Return the registry to which the changes of all watched projects are published.
+    pub fn subscriptions(&self) -> &Subscriptions {
+        &self.subscriptions
+    }
+
+    /// Stop watching all projects that `session` watched, unless other sessions still watch them.
+    pub fn end_session(&self, session: &mut Session) {
+        for project_id in std::mem::take(&mut session.watched) {
+            self.unwatch(project_id);
+        }
+    }
+}
+
+/// An error during [dispatch](Daemon::dispatch()).
+#[derive(Debug)]
+pub enum DispatchError {
+    /// `method` isn't known.
+    UnknownMethod(String),
+    /// The parameters didn't match what the method expects.
+    InvalidParams(serde_json::Error),
+    /// The method itself failed.
+    Failed(anyhow::Error),
+}
+
+impl From<DispatchError> for protocol::Error {
+    fn from(err: DispatchError) -> Self {
+        match err {
+            DispatchError::UnknownMethod(method) => protocol::Error::protocol(
+                protocol::code::METHOD_NOT_FOUND,
+                format!(
+                    "Unknown method '{method}', expected one of {}",
+                    METHODS.join(", ")
+                ),
+            ),
+            DispatchError::InvalidParams(err) => {
+                protocol::Error::protocol(protocol::code::INVALID_PARAMS, err.to_string())
+            }
+            DispatchError::Failed(err) => err.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for DispatchError {
+    fn from(err: anyhow::Error) -> Self {
+        DispatchError::Failed(err)
+    }
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, DispatchError> {
+    // Allow methods without required parameters to be called without `params`.
+    let params = if params.is_null() {
+        Value::Object(Default::default())
+    } else {
+        params
+    };
+    serde_json::from_value(params).map_err(DispatchError::InvalidParams)
+}
+
+fn to_value(value: impl serde::Serialize) -> Result<Value, DispatchError> {
+    serde_json::to_value(value).map_err(|err| DispatchError::Failed(err.into()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectParams {
+    project_id: ProjectId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StacksParams {
+    project_id: ProjectId,
+    filter: Option<but_workspace::StacksFilter>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StackDetailsParams {
+    project_id: ProjectId,
+    stack_id: StackId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateCommitParams {
+    project_id: ProjectId,
+    stack_id: StackId,
+    parent_id: Option<String>,
+    worktree_changes: Vec<but_workspace::DiffSpec>,
+    message: String,
+    stack_branch_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntegrateUpstreamParams {
+    project_id: ProjectId,
+    resolutions: Vec<Resolution>,
+    base_branch_resolution: Option<BaseBranchResolution>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListSnapshotsParams {
+    project_id: ProjectId,
+    limit: usize,
+    sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestoreSnapshotParams {
+    project_id: ProjectId,
+    sha: String,
+}
+
+/// Dispatch
+impl Daemon {
+    /// Call `method` with `params` on behalf of the client with `session` and return its serialized result.
+    ///
+    /// Note that this is blocking, and expected to be called on a thread that can block.
+    pub fn dispatch(
+        &self,
+        session: &mut Session,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, DispatchError> {
+        match method {
+            "stacks" => {
+                let StacksParams { project_id, filter } = self::params(params)?;
+                to_value(self.stacks(project_id, filter)?)
+            }
+            "stack_details" => {
+                let StackDetailsParams {
+                    project_id,
+                    stack_id,
+                } = self::params(params)?;
+                to_value(self.stack_details(project_id, stack_id)?)
+            }
+            "create_commit_from_worktree_changes" => {
+                let params: CreateCommitParams = self::params(params)?;
+                to_value(self.create_commit_from_worktree_changes(params)?)
+            }
+            "integrate_upstream" => {
+                let IntegrateUpstreamParams {
+                    project_id,
+                    resolutions,
+                    base_branch_resolution,
+                } = self::params(params)?;
+                to_value(self.integrate_upstream(
+                    project_id,
+                    &resolutions,
+                    base_branch_resolution,
+                )?)
+            }
+            "list_snapshots" => {
+                let ListSnapshotsParams {
+                    project_id,
+                    limit,
+                    sha,
+                } = self::params(params)?;
+                to_value(self.list_snapshots(project_id, limit, sha)?)
+            }
+            "restore_snapshot" => {
+                let RestoreSnapshotParams { project_id, sha } = self::params(params)?;
+                self.restore_snapshot(project_id, sha)?;
+                Ok(Value::Null)
+            }
+            "watch" => {
+                let ProjectParams { project_id } = self::params(params)?;
+                if !session.watched.contains(&project_id) {
+                    self.watch(project_id)?;
+                    session.watched.insert(project_id);
+                }
+                Ok(Value::Null)
+            }
+            "unwatch" => {
+                let ProjectParams { project_id } = self::params(params)?;
+                if session.watched.remove(&project_id) {
+                    self.unwatch(project_id);
+                }
+                Ok(Value::Null)
+            }
+            unknown => Err(DispatchError::UnknownMethod(unknown.to_owned())),
+        }
+    }
+}
+
+/// Commands
+impl Daemon {
+    fn open(&self, project_id: ProjectId) -> Result<CommandContext> {
+        let project = self.projects.get(project_id)?;
+        CommandContext::open(&project, self.settings.get()?.clone())
+    }
+
+    fn stacks(
+        &self,
+        project_id: ProjectId,
+        filter: Option<but_workspace::StacksFilter>,
+    ) -> Result<Vec<but_workspace::ui::StackEntry>> {
+        let ctx = self.open(project_id)?;
+        let repo = ctx.gix_repo()?;
+        but_workspace::stacks(
+            &ctx,
+            &ctx.project().gb_dir(),
+            &repo,
+            filter.unwrap_or_default(),
+        )
+    }
+
+    fn stack_details(
+        &self,
+        project_id: ProjectId,
+        stack_id: StackId,
+    ) -> Result<but_workspace::ui::StackDetails> {
+        let ctx = self.open(project_id)?;
+        but_workspace::stack_details(&ctx.project().gb_dir(), stack_id, &ctx)
+    }
+
+    fn create_commit_from_worktree_changes(
+        &self,
+        CreateCommitParams {
+            project_id,
+            stack_id,
+            parent_id,
+            worktree_changes,
+            message,
+            stack_branch_name,
+        }: CreateCommitParams,
+    ) -> Result<commit_engine::ui::CreateCommitOutcome> {
+        let project = self.projects.get(project_id)?;
+        let repo = but_core::open_repo_for_merging(project.worktree_path())?;
+        // If parent_id was not set but a stack branch name was provided, pick the current head of that branch as parent.
+        let parent_commit_id = match parent_id {
+            Some(hex) => Some(gix::ObjectId::from_hex(hex.as_bytes())?),
+            None => match repo.try_find_reference(&stack_branch_name)? {
+                Some(mut r) => Some(r.peel_to_commit()?.id),
+                None => None,
+            },
+        };
+        let ctx = CommandContext::open(&project, self.settings.get()?.clone())?;
+        let mut guard = project.exclusive_worktree_access();
+        let snapshot_tree = ctx.prepare_snapshot(guard.read_permission());
+        let outcome = commit_engine::create_commit_and_update_refs_with_project(
+            &repo,
+            &project,
+            Some(stack_id),
+            commit_engine::Destination::NewCommit {
+                parent_commit_id,
+                message: message.clone(),
+                stack_segment: Some(StackSegmentId {
+                    stack_id,
+                    segment_ref: format!("refs/heads/{stack_branch_name}").try_into()?,
+                }),
+            },
+            None,
+            worktree_changes,
+            ctx.app_settings().context_lines,
+            DiffOptions::default(),
+            guard.write_permission(),
+        );
+
+        let _ = snapshot_tree.and_then(|snapshot_tree| {
+            ctx.snapshot_commit_creation(
+                snapshot_tree,
+                outcome.as_ref().err(),
+                message,
+                None,
+                guard.write_permission(),
+            )
+        });
+
+        let outcome = outcome?;
+        if !outcome.rejected_specs.is_empty() {
+            tracing::warn!(?outcome.rejected_specs, "Failed to commit at least one hunk");
+        }
+        Ok(outcome.into())
+    }
+
+    fn integrate_upstream(
+        &self,
+        project_id: ProjectId,
+        resolutions: &[Resolution],
+        base_branch_resolution: Option<BaseBranchResolution>,
+    ) -> Result<gitbutler_branch_actions::upstream_integration::IntegrationOutcome> {
+        let ctx = self.open(project_id)?;
+        let outcome = gitbutler_branch_actions::integrate_upstream(
+            &ctx,
+            resolutions,
+            base_branch_resolution,
+        )?;
+        self.recalculate_virtual_branches(&ctx);
+        Ok(outcome)
+    }
+
+    fn list_snapshots(
+        &self,
+        project_id: ProjectId,
+        limit: usize,
+        sha: Option<String>,
+    ) -> Result<Vec<gitbutler_oplog::entry::Snapshot>> {
+        let ctx = self.open(project_id)?;
+        ctx.list_snapshots(limit, sha.map(|hex| hex.parse()).transpose()?)
+    }
+
+    fn restore_snapshot(&self, project_id: ProjectId, sha: String) -> Result<()> {
+        let ctx = self.open(project_id)?;
+        let mut guard = ctx.project().exclusive_worktree_access();
+        ctx.restore_snapshot(sha.parse()?, guard.write_permission())?;
+        Ok(())
+    }
+
+    /// Start watching `project_id` for one more session, and publish all of its changes to our subscriptions.
+    fn watch(&self, project_id: ProjectId) -> Result<()> {
+        let mut watched = self.watched.lock();
+        if let Some(watched) = watched.get_mut(&project_id) {
+            watched.sessions += 1;
+            return Ok(());
+        }
+        let project = self.projects.get(project_id)?;
+        let exclusive_access = project.try_exclusive_access()?;
+        // Changes reach clients through the subscriptions only.
+        let handler =
+            gitbutler_watcher::Handler::new(self.projects.clone(), self.users.clone(), |_| Ok(()))
+                .with_subscriptions(self.subscriptions.clone());
+        let watcher = gitbutler_watcher::watch_in_background(
+            handler,
+            project.path.clone(),
+            project_id,
+            &project.file_monitor,
+            self.settings.clone(),
+        )
+        .context("Failed to start watching the project")?;
+        watched.insert(
+            project_id,
+            Watched {
+                watcher,
+                exclusive_access,
+                sessions: 1,
+            },
+        );
+        tracing::debug!("Watching {} projects", watched.len());
+        Ok(())
+    }
+
+    /// Stop watching `project_id` for one session, and stop the watcher if it was the last one.
+    fn unwatch(&self, project_id: ProjectId) {
+        let mut watched = self.watched.lock();
+        if let Some(project) = watched.get_mut(&project_id) {
+            project.sessions -= 1;
+            if project.sessions == 0 {
+                watched.remove(&project_id);
+            }
+        }
+    }
+
+    /// Like the application after mutating commands, trigger a fresh list of virtual branches
+    /// to be sent, but only if we are watching the project of `ctx`.
+    fn recalculate_virtual_branches(&self, ctx: &CommandContext) {
+        if ctx.app_settings().feature_flags.v3 {
+            return;
+        }
+        let project_id = ctx.project().id;
+        if let Some(watched) = self.watched.lock().get(&project_id) {
+            if let Err(error) =
+                watched
+                    .watcher
+                    .post(gitbutler_watcher::Action::CalculateVirtualBranches(
+                        project_id,
+                    ))
+            {
+                tracing::error!(?error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gitbutler_testsupport::Suite;
+    use serde_json::json;
+
+    use super::*;
+
+    fn daemon(suite: &Suite) -> Arc<Daemon> {
+        Daemon::new(
+            suite.projects.clone(),
+            suite.users.clone(),
+            AppSettingsWithDiskSync::new(suite.local_app_data()).unwrap(),
+        )
+    }
+
+    fn sessions(daemon: &Daemon, project_id: ProjectId) -> Option<usize> {
+        daemon
+            .watched
+            .lock()
+            .get(&project_id)
+            .map(|watched| watched.sessions)
+    }
+
+    #[test]
+    fn dispatch_known_method() {
+        let suite = Suite::default();
+        let case = suite.new_case();
+        let daemon = daemon(&suite);
+
+        let snapshots = daemon
+            .dispatch(
+                &mut Session::default(),
+                "list_snapshots",
+                json!({ "projectId": case.project.id, "limit": 10 }),
+            )
+            .unwrap();
+        assert_eq!(snapshots, json!([]), "nothing happened yet");
+    }
+
+    #[test]
+    fn dispatch_errors() {
+        let suite = Suite::default();
+        let daemon = daemon(&suite);
+        let mut session = Session::default();
+
+        let err = protocol::Error::from(
+            daemon
+                .dispatch(&mut session, "unknown", Value::Null)
+                .unwrap_err(),
+        );
+        assert_eq!(err.code, protocol::code::METHOD_NOT_FOUND);
+
+        let err = protocol::Error::from(
+            daemon
+                .dispatch(&mut session, "list_snapshots", json!({ "limit": 1 }))
+                .unwrap_err(),
+        );
+        assert_eq!(
+            err.code,
+            protocol::code::INVALID_PARAMS,
+            "projectId is missing"
+        );
+
+        let err = protocol::Error::from(
+            daemon
+                .dispatch(
+                    &mut session,
+                    "list_snapshots",
+                    json!({ "projectId": ProjectId::generate(), "limit": 1 }),
+                )
+                .unwrap_err(),
+        );
+        assert_eq!(
+            err.code,
+            protocol::code::SERVER_ERROR,
+            "the project doesn't exist"
+        );
+
+        let err = protocol::Error::from(
+            daemon
+                .dispatch(
+                    &mut session,
+                    "watch",
+                    json!({ "projectId": ProjectId::generate() }),
+                )
+                .unwrap_err(),
+        );
+        assert_eq!(err.code, protocol::code::SERVER_ERROR);
+        assert!(session.watched.is_empty(), "failed watches aren't recorded");
+    }
+
+    #[tokio::test]
+    async fn watches_are_counted_per_session() {
+        let suite = Suite::default();
+        let case = suite.new_case();
+        let daemon = daemon(&suite);
+        let project_id = case.project.id;
+        let params = json!({ "projectId": project_id });
+        let (mut a, mut b) = (Session::default(), Session::default());
+
+        for _ in 0..2 {
+            daemon.dispatch(&mut a, "watch", params.clone()).unwrap();
+        }
+        assert_eq!(
+            sessions(&daemon, project_id),
+            Some(1),
+            "watching twice from the same session counts once"
+        );
+        daemon.dispatch(&mut b, "watch", params.clone()).unwrap();
+        assert_eq!(sessions(&daemon, project_id), Some(2));
+
+        for _ in 0..2 {
+            daemon.dispatch(&mut a, "unwatch", params.clone()).unwrap();
+        }
+        assert_eq!(
+            sessions(&daemon, project_id),
+            Some(1),
+            "the project is still watched for the other session"
+        );
+        assert!(a.watched.is_empty());
+        assert_eq!(b.watched, BTreeSet::from([project_id]));
+
+        daemon.end_session(&mut b);
+        assert_eq!(
+            sessions(&daemon, project_id),
+            None,
+            "the last session is gone, so is the watcher"
+        );
+        assert!(b.watched.is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! A headless GitButler, making the workspace commands of the application available to editors, scripts
+//! and tests through a Unix-domain socket.
+//!
+//! ### Protocol
+//!
+//! Clients send [JSON-RPC 2.0](https://www.jsonrpc.org/specification) requests, one per line, and receive
+//! one response per line for each request that has an `id`. Parameters are passed as object with the same
+//! camel-cased names the frontend uses for the respective command, see [`commands::METHODS`].
+//!
+//! After calling `watch` with a `projectId`, the client also receives notifications for all changes in
+//! the project, named just like the events the application sends to its frontend, e.g.
+//! `project://<id>/worktree_changes`. `unwatch` stops these notifications, and the project is no longer
+//! watched once no client watches it anymore. All watches of a client end when it disconnects.
+#![deny(rust_2018_idioms)]
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+mod args;
+use args::Args;
+
+mod commands;
+mod protocol;
+#[cfg(unix)]
+mod server;
+
+fn main() -> Result<()> {
+    let args: Args = clap::Parser::parse();
+
+    if args.trace {
+        trace::init()?;
+    }
+    gitbutler_project::configure_git2();
+
+    let app_data_dir = app_data_dir(args.app_suffix.as_deref(), args.app_data_dir.as_deref())?;
+    let config_dir = match args.config_dir {
+        Some(dir) => dir,
+        None => dirs_next::config_dir()
+            .context("no config-directory available on this platform")?
+            .join("gitbutler"),
+    };
+    std::fs::create_dir_all(&config_dir).context("Failed to assure the config-dir exists")?;
+    let socket_path = args
+        .socket
+        .unwrap_or_else(|| app_data_dir.join("but-daemon.sock"));
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(async move {
+            let settings = but_settings::AppSettingsWithDiskSync::new(&config_dir)?;
+            let daemon = commands::Daemon::new(
+                gitbutler_project::Controller::from_path(app_data_dir.clone()),
+                gitbutler_user::Controller::from_path(&app_data_dir),
+                settings,
+            );
+            serve(daemon, &socket_path).await
+        })
+}
+
+#[cfg(unix)]
+async fn serve(daemon: std::sync::Arc<commands::Daemon>, socket_path: &Path) -> Result<()> {
+    server::run(daemon, socket_path).await
+}
+
+#[cfg(not(unix))]
+async fn serve(_daemon: std::sync::Arc<commands::Daemon>, _socket_path: &Path) -> Result<()> {
+    anyhow::bail!("The daemon is only supported on platforms with Unix-domain sockets")
+}
+
+fn app_data_dir(app_suffix: Option<&str>, app_data_dir: Option<&Path>) -> Result<PathBuf> {
+    let path = if let Some(dir) = app_data_dir {
+        dir.to_owned()
+    } else {
+        dirs_next::data_dir()
+            .map(|dir| {
+                dir.join(format!(
+                    "com.gitbutler.app{}",
+                    app_suffix
+                        .map(|suffix| format!(".{suffix}"))
+                        .unwrap_or_default()
+                ))
+            })
+            .context("no data-directory available on this platform")?
+    };
+    std::fs::create_dir_all(&path).context("Failed to assure the designated data-dir exists")?;
+    tracing::debug!("Using projects from '{}'", path.display());
+    Ok(path)
+}
+
+mod trace {
+    use tracing::metadata::LevelFilter;
+    use tracing_subscriber::Layer;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    pub fn init() -> anyhow::Result<()> {
+        tracing_subscriber::registry()
+            .with(
+                tracing_forest::ForestLayer::from(
+                    tracing_forest::printer::PrettyPrinter::new().writer(std::io::stderr),
+                )
+                .with_filter(LevelFilter::DEBUG),
+            )
+            .init();
+        Ok(())
+    }
+}
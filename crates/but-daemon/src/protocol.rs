@@ -0,0 +1,193 @@
+//! The wire format of the daemon, which is [JSON-RPC 2.0](https://www.jsonrpc.org/specification)
+//! with one message per line.
+use gitbutler_error::error::AnyhowContextExt;
+use gitbutler_watcher::Change;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The only protocol version we speak.
+pub const VERSION: &str = "2.0";
+
+/// Error codes as predefined by the JSON-RPC specification.
+pub mod code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// Used for all errors produced by GitButler itself, with details in the error `data`.
+    pub const SERVER_ERROR: i64 = -32000;
+}
+
+/// A call of `method` with `params`, as sent by a client.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// The id to echo back in the response, or `None` if the client doesn't want a response.
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// The answer to a [`Request`].
+#[derive(Debug, Serialize)]
+pub struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+    Result(Value),
+    Error(Error),
+}
+
+impl Response {
+    /// A successful response to the request identified by `id`.
+    pub fn result(id: Value, result: Value) -> Self {
+        Response {
+            jsonrpc: VERSION,
+            id,
+            outcome: Outcome::Result(result),
+        }
+    }
+
+    /// A failed response to the request identified by `id`.
+    pub fn error(id: Value, error: Error) -> Self {
+        Response {
+            jsonrpc: VERSION,
+            id,
+            outcome: Outcome::Error(error),
+        }
+    }
+}
+
+/// An error as transmitted to the client.
+#[derive(Debug, Serialize)]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl Error {
+    /// Create an error that isn't produced by GitButler, but by the protocol layer.
+    pub fn protocol(code: i64, message: impl Into<String>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    /// Extract our [context](gitbutler_error::error::Context) just like the frontend would see it,
+    /// and pass the GitButler error code as `data`.
+    fn from(err: anyhow::Error) -> Self {
+        let ctx = err.custom_context_or_root_cause();
+        Error {
+            code: code::SERVER_ERROR,
+            message: ctx
+                .message
+                .map(|msg| msg.into_owned())
+                .unwrap_or_else(|| err.to_string()),
+            data: Some(serde_json::json!({ "code": ctx.code.to_string() })),
+        }
+    }
+}
+
+/// A message sent by the daemon without being asked for, to inform about a [`Change`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    jsonrpc: &'static str,
+    /// The name of the event, using the same names as the events sent to the frontend.
+    pub method: String,
+    pub params: Value,
+}
+
+impl Notification {
+    fn new(method: String, params: Value) -> Self {
+        Notification {
+            jsonrpc: VERSION,
+            method,
+            params,
+        }
+    }
+}
+
+impl From<Change> for Notification {
+    fn from(value: Change) -> Self {
+        match value {
+            Change::GitFetch(project_id) => Notification::new(
+                format!("project://{}/git/fetch", project_id),
+                serde_json::json!({}),
+            ),
+            Change::GitHead {
+                project_id,
+                head,
+                operating_mode,
+            } => Notification::new(
+                format!("project://{}/git/head", project_id),
+                serde_json::json!({ "head": head, "operatingMode": operating_mode }),
+            ),
+            Change::GitActivity(project_id) => Notification::new(
+                format!("project://{}/git/activity", project_id),
+                serde_json::json!({}),
+            ),
+            Change::VirtualBranches {
+                project_id,
+                virtual_branches,
+            } => Notification::new(
+                format!("project://{}/virtual-branches", project_id),
+                serde_json::json!(virtual_branches),
+            ),
+            Change::UncommitedFiles { project_id, files } => Notification::new(
+                format!("project://{}/uncommited-files", project_id),
+                serde_json::json!(files),
+            ),
+            Change::WorktreeChanges {
+                project_id,
+                changes,
+            } => Notification::new(
+                format!("project://{}/worktree_changes", project_id),
+                serde_json::json!(&but_core::ui::WorktreeChanges::from(changes)),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_has_either_result_or_error() {
+        let res = Response::result(1.into(), Value::Null);
+        assert_eq!(
+            serde_json::to_string(&res).unwrap(),
+            r#"{"jsonrpc":"2.0","id":1,"result":null}"#
+        );
+
+        let res = Response::error(
+            "a".into(),
+            Error::protocol(code::METHOD_NOT_FOUND, "not found"),
+        );
+        assert_eq!(
+            serde_json::to_string(&res).unwrap(),
+            r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"not found"}}"#
+        );
+    }
+
+    #[test]
+    fn request_without_id_or_params() {
+        let req: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"ping"}"#).unwrap();
+        assert!(req.id.is_none());
+        assert_eq!(req.params, Value::Null);
+    }
+}
@@ -0,0 +1,298 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use gitbutler_watcher::subscription::Filter;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::commands::{Daemon, Session};
+use crate::protocol::{self, Notification, Request, Response};
+
+/// Listen on `socket_path` and serve each connection with `daemon` until the process is stopped.
+///
+/// A stale socket left behind by a previous instance is removed, but we refuse to start if
+/// another daemon is still listening on it.
+pub async fn run(daemon: Arc<Daemon>, socket_path: &Path) -> Result<()> {
+    if socket_path.exists() {
+        if UnixStream::connect(socket_path).await.is_ok() {
+            anyhow::bail!(
+                "Another daemon is already listening on '{}'",
+                socket_path.display()
+            );
+        }
+        std::fs::remove_file(socket_path).with_context(|| {
+            format!(
+                "Failed to remove stale socket at '{}'",
+                socket_path.display()
+            )
+        })?;
+    }
+    let listener = UnixListener::bind(socket_path)
+        .with_context(|| format!("Failed to listen on '{}'", socket_path.display()))?;
+    tracing::info!("Listening on '{}'", socket_path.display());
+
+    let res = tokio::select! {
+        res = accept_loop(daemon, listener) => res,
+        res = tokio::signal::ctrl_c() => res.context("Failed to wait for Ctrl-C"),
+    };
+    std::fs::remove_file(socket_path).ok();
+    res
+}
+
+async fn accept_loop(daemon: Arc<Daemon>, listener: UnixListener) -> Result<()> {
+    loop {
+        let (stream, _addr) = listener.accept().await?;
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(daemon, stream).await {
+                tracing::warn!(?err, "Connection failed");
+            }
+            tracing::debug!("Connection closed");
+        });
+    }
+}
+
+/// Answer all requests of a single client, and forward notifications of the projects it watches.
+async fn serve_connection(daemon: Arc<Daemon>, stream: UnixStream) -> Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let session = Arc::new(parking_lot::Mutex::new(Session::default()));
+    let mut changes = daemon
+        .subscriptions()
+        .subscribe(filter_for(&session.lock()));
+    let res: Result<()> = async {
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let Some(line) = line? else {
+                        break;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let response = handle_line(&daemon, &line, &session).await;
+                    // Adjust the filter before responding so the client sees all changes once `watch` returns.
+                    changes.set_filter(filter_for(&session.lock()));
+                    if let Some(response) = response {
+                        write_message(&mut write, &response).await?;
+                    }
+                }
+                event = changes.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    if event.missed > 0 {
+                        tracing::warn!(missed = event.missed, "Client is too slow and missed notifications");
+                    }
+                    write_message(&mut write, &Notification::from(event.change)).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+    daemon.end_session(&mut session.lock());
+    res
+}
+
+/// Return a filter that lets only changes of the projects watched in `session` pass.
+fn filter_for(session: &Session) -> Filter {
+    Filter {
+        project_ids: Some(session.watched.clone()),
+        ..Default::default()
+    }
+}
+
+/// Parse and execute the request in `line`, and return the response to send, if any.
+async fn handle_line(
+    daemon: &Arc<Daemon>,
+    line: &str,
+    session: &Arc<parking_lot::Mutex<Session>>,
+) -> Option<Response> {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => {
+            return Some(Response::error(
+                serde_json::Value::Null,
+                protocol::Error::protocol(protocol::code::PARSE_ERROR, err.to_string()),
+            ));
+        }
+    };
+    if request.jsonrpc != protocol::VERSION {
+        return request.id.map(|id| {
+            Response::error(
+                id,
+                protocol::Error::protocol(
+                    protocol::code::INVALID_REQUEST,
+                    format!("Only JSON-RPC {} is supported", protocol::VERSION),
+                ),
+            )
+        });
+    }
+
+    let Request {
+        id, method, params, ..
+    } = request;
+    let outcome = tokio::task::spawn_blocking({
+        let daemon = daemon.clone();
+        let session = session.clone();
+        let method = method.clone();
+        move || daemon.dispatch(&mut session.lock(), &method, params)
+    })
+    .await;
+
+    let outcome = match outcome {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(protocol::Error::from(err)),
+        Err(join_err) => Err(protocol::Error::from(anyhow::Error::from(join_err))),
+    };
+    if let Err(err) = &outcome {
+        tracing::debug!(method, ?err, "Request failed");
+    }
+    id.map(|id| match outcome {
+        Ok(value) => Response::result(id, value),
+        Err(err) => Response::error(id, err),
+    })
+}
+
+async fn write_message(
+    out: &mut (impl AsyncWrite + Unpin),
+    message: &impl Serialize,
+) -> Result<()> {
+    let mut buf = serde_json::to_vec(message)?;
+    buf.push(b'\n');
+    out.write_all(&buf).await?;
+    out.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use but_settings::AppSettingsWithDiskSync;
+    use gitbutler_project::ProjectId;
+    use gitbutler_testsupport::Suite;
+    use gitbutler_watcher::Change;
+    use serde_json::{Value, json};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    use super::*;
+
+    /// A client which reads one message per line.
+    struct Client {
+        lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+        write: tokio::net::unix::OwnedWriteHalf,
+    }
+
+    impl Client {
+        async fn send(&mut self, message: Value) {
+            write_message(&mut self.write, &message).await.unwrap();
+        }
+
+        async fn recv(&mut self) -> Value {
+            let line = tokio::time::timeout(Duration::from_secs(10), self.lines.next_line())
+                .await
+                .expect("a message in time")
+                .unwrap()
+                .expect("connection still open");
+            serde_json::from_str(&line).unwrap()
+        }
+
+        /// Receive the response to the request with `id`, skipping all notifications.
+        async fn response(&mut self, id: u64) -> Value {
+            loop {
+                let message = self.recv().await;
+                if message.get("id") == Some(&json!(id)) {
+                    return message;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let suite = Suite::default();
+        let case = suite.new_case();
+        let project_id = case.project.id;
+        let daemon = Daemon::new(
+            suite.projects.clone(),
+            suite.users.clone(),
+            AppSettingsWithDiskSync::new(suite.local_app_data()).unwrap(),
+        );
+        let tmp = tempfile::tempdir().unwrap();
+        let socket_path = tmp.path().join("daemon.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        tokio::spawn(accept_loop(daemon.clone(), listener));
+
+        let (read, write) = UnixStream::connect(&socket_path)
+            .await
+            .unwrap()
+            .into_split();
+        let mut client = Client {
+            lines: BufReader::new(read).lines(),
+            write,
+        };
+
+        client
+            .send(json!({"jsonrpc": "2.0", "id": 1, "method": "list_snapshots", "params": {"projectId": project_id, "limit": 5}}))
+            .await;
+        assert_eq!(
+            client.response(1).await,
+            json!({"jsonrpc": "2.0", "id": 1, "result": []})
+        );
+
+        client
+            .send(json!({"jsonrpc": "2.0", "id": 2, "method": "unknown"}))
+            .await;
+        assert_eq!(
+            client.response(2).await["error"]["code"],
+            json!(protocol::code::METHOD_NOT_FOUND)
+        );
+
+        client.write.write_all(b"not json\n").await.unwrap();
+        assert_eq!(
+            client.recv().await["error"]["code"],
+            json!(protocol::code::PARSE_ERROR)
+        );
+
+        client
+            .send(json!({"jsonrpc": "2.0", "id": 3, "method": "watch", "params": {"projectId": project_id}}))
+            .await;
+        assert_eq!(client.response(3).await["result"], Value::Null);
+
+        daemon
+            .subscriptions()
+            .publish(&Change::GitFetch(ProjectId::generate()));
+        daemon
+            .subscriptions()
+            .publish(&Change::GitFetch(project_id));
+        let expected = format!("project://{project_id}/git/fetch");
+        loop {
+            let notification = client.recv().await;
+            let method = notification["method"].as_str().unwrap();
+            assert!(
+                method.starts_with(&format!("project://{project_id}/")),
+                "only changes of watched projects are sent, got {method}"
+            );
+            if method == expected {
+                break;
+            }
+        }
+
+        drop(client);
+        for _ in 0..100 {
+            if daemon.subscriptions().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(
+            daemon.subscriptions().is_empty(),
+            "the subscription ends with the connection"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use but_workspace::StackId;
+use gix::bstr::{BStr, BString};
+use gix::prelude::ObjectIdExt as _;
+use std::collections::HashMap;
+
+/// Associate lines of files in the workspace commit with the commits in the workspace that introduced them.
+///
+/// Unlike [`WorkspaceRanges`](crate::WorkspaceRanges), this doesn't track zero-context hunks through all commits of each stack,
+/// but uses `git blame` to find the commit that last touched each line. This makes it robust to reformatting commits,
+/// and renames are followed as well.
+pub struct WorkspaceBlame {
+    /// The commit to start blaming from, typically the workspace commit at `HEAD`.
+    workspace_commit_id: gix::ObjectId,
+    /// The time of the common merge base, as no commit before it can be in the workspace.
+    since: gix::date::Time,
+    /// The cache to diff the versions of blamed files, reused across files.
+    resource_cache: gix::diff::blob::Platform,
+    /// All commits in the workspace, along with the stack that contains them.
+    stack_ids_by_commit: HashMap<gix::ObjectId, StackId>,
+    /// The blame of all files that were looked at, to blame each file only once.
+    entries_by_path: HashMap<BString, Vec<gix::blame::BlameEntry>>,
+}
+
+impl WorkspaceBlame {
+    /// Prepare blaming files in `workspace_commit_id`, which merges all `stacks` of the workspace.
+    ///
+    /// `common_merge_base` is expected to be the merge base that all `stacks` have in common, as would be created with [gix::Repository::merge_base_octopus()].
+    /// Lines introduced by commits reachable from it are not associated with any commit, and history older than it isn't blamed.
+    pub fn try_from_stacks(
+        repo: &gix::Repository,
+        workspace_commit_id: gix::ObjectId,
+        stacks: &[but_workspace::ui::StackEntry],
+        common_merge_base: gix::ObjectId,
+    ) -> anyhow::Result<Self> {
+        let git2_repo = git2::Repository::open(repo.path())?;
+        let mut stack_ids_by_commit = HashMap::new();
+        for stack in stacks {
+            for commit_id in crate::commits_in_stack_base_to_tip_without_merge_bases(
+                stack.tip.attach(repo),
+                &git2_repo,
+                common_merge_base,
+            )? {
+                stack_ids_by_commit.insert(commit_id, stack.id);
+            }
+        }
+        Ok(WorkspaceBlame {
+            workspace_commit_id,
+            stack_ids_by_commit,
+            since: repo.find_commit(common_merge_base)?.time()?,
+            resource_cache: repo.diff_resource_cache_for_tree_diff()?,
+            entries_by_path: HashMap::new(),
+        })
+    }
+
+    /// Find the commits in the workspace that introduced the `lines` starting at the 1-based line `start`
+    /// of the file at `path` in the workspace commit, along with the stack that contains them.
+    ///
+    /// If `lines` is 0, lines are added after `start`, which is then the only line that is considered.
+    /// Return an empty list if none of the lines were introduced by commits in the workspace.
+    pub fn intersection(
+        &mut self,
+        repo: &gix::Repository,
+        path: &BStr,
+        start: u32,
+        lines: u32,
+    ) -> anyhow::Result<Vec<(StackId, gix::ObjectId)>> {
+        if start == 0 {
+            // Lines added at the top of the file don't depend on anything.
+            return Ok(Vec::new());
+        }
+        let first_line = start - 1;
+        let end_line = first_line + lines.max(1);
+
+        self.blame_file(repo, path)?;
+        let mut out = Vec::new();
+        for entry in &self.entries_by_path[path] {
+            let entry_end_line = entry.start_in_blamed_file + entry.len.get();
+            if entry.start_in_blamed_file >= end_line || entry_end_line <= first_line {
+                continue;
+            }
+            let Some(stack_id) = self.stack_ids_by_commit.get(&entry.commit_id) else {
+                continue;
+            };
+            let dependency = (*stack_id, entry.commit_id);
+            if !out.contains(&dependency) {
+                out.push(dependency);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Blame the file at `path` unless it was blamed already.
+    fn blame_file(&mut self, repo: &gix::Repository, path: &BStr) -> anyhow::Result<()> {
+        if self.entries_by_path.contains_key(path) {
+            return Ok(());
+        }
+        let outcome = gix::blame::file(
+            &repo.objects,
+            self.workspace_commit_id,
+            repo.commit_graph_if_enabled()?,
+            &mut self.resource_cache,
+            path,
+            gix::blame::Options {
+                diff_algorithm: repo.diff_algorithm()?,
+                range: Default::default(),
+                since: Some(self.since),
+                rewrites: Some(Default::default()),
+            },
+        )?;
+        self.entries_by_path
+            .insert(path.to_owned(), outcome.entries);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for WorkspaceBlame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkspaceBlame")
+            .field("workspace_commit_id", &self.workspace_commit_id)
+            .field("since", &self.since)
+            .field("stack_ids_by_commit", &self.stack_ids_by_commit)
+            .field("entries_by_path", &self.entries_by_path)
+            .finish_non_exhaustive()
+    }
+}
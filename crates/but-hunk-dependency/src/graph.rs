@@ -0,0 +1,193 @@
+use but_workspace::StackId;
+use gix::bstr::BString;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// A directed acyclic graph of all commits in the workspace, with an edge from each commit to the commits
+/// whose hunks it overlaps with, and that thus have to come before it.
+///
+/// It's obtained with [`WorkspaceRanges::commit_dependency_graph()`](crate::WorkspaceRanges::commit_dependency_graph()).
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDependencyGraph {
+    /// All commits of all stacks, in the order of the stacks in the workspace, and from base to tip within each stack.
+    pub commits: Vec<CommitNode>,
+    /// All dependencies between `commits`, sorted and without duplicates.
+    pub dependencies: Vec<CommitDependency>,
+}
+
+/// A commit in the [`CommitDependencyGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitNode {
+    /// The stack that contains `commit_id`.
+    pub stack_id: StackId,
+    /// The commit itself.
+    #[serde(serialize_with = "gitbutler_serde::object_id::serialize")]
+    pub commit_id: gix::ObjectId,
+}
+
+/// An edge in the [`CommitDependencyGraph`], signalling that `commit_id` has to come after `depends_on`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDependency {
+    /// The commit that overlaps with the hunks of `depends_on`.
+    #[serde(serialize_with = "gitbutler_serde::object_id::serialize")]
+    pub commit_id: gix::ObjectId,
+    /// The commit that `commit_id` depends on.
+    #[serde(serialize_with = "gitbutler_serde::object_id::serialize")]
+    pub depends_on: gix::ObjectId,
+    /// Whether both commits are in the same stack or not.
+    pub kind: DependencyKind,
+    /// The paths at which the hunks of both commits overlap, sorted.
+    #[serde(serialize_with = "gitbutler_serde::bstring_vec_lossy::serialize")]
+    pub paths: Vec<BString>,
+}
+
+/// Describes the relation of two commits in a [`CommitDependency`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DependencyKind {
+    /// Both commits are in the same stack, and the dependent commit can't be placed below the one it depends on
+    /// without conflicting.
+    Stack,
+    /// The commits are in different stacks whose changes overlap, so they can't be merged into the workspace without conflicts.
+    /// The dependent commit is the one in the stack that comes later in the workspace.
+    CrossStack,
+}
+
+impl CommitDependencyGraph {
+    /// Return all dependencies of `commit_id`, i.e. the commits that have to come before it.
+    pub fn dependencies_of(
+        &self,
+        commit_id: gix::ObjectId,
+    ) -> impl Iterator<Item = &CommitDependency> + '_ {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.commit_id == commit_id)
+    }
+
+    /// Return all dependents of `commit_id`, i.e. the commits that have to come after it.
+    pub fn dependents_of(
+        &self,
+        commit_id: gix::ObjectId,
+    ) -> impl Iterator<Item = &CommitDependency> + '_ {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.depends_on == commit_id)
+    }
+
+    /// Return all dependencies within a stack that would be violated if its commits were placed in the order
+    /// of `commits_from_base_to_tip`, which is when a commit is placed below a commit it depends on.
+    ///
+    /// Commits that aren't in `commits_from_base_to_tip` are ignored, and if the returned list isn't empty,
+    /// rebasing the stack in this order will produce conflicted commits.
+    pub fn violated_by_order(
+        &self,
+        commits_from_base_to_tip: &[gix::ObjectId],
+    ) -> Vec<&CommitDependency> {
+        let position = |id: gix::ObjectId| commits_from_base_to_tip.iter().position(|c| *c == id);
+        self.dependencies
+            .iter()
+            .filter(|dependency| dependency.kind == DependencyKind::Stack)
+            .filter(|dependency| {
+                matches!(
+                    (position(dependency.commit_id), position(dependency.depends_on)),
+                    (Some(commit), Some(depends_on)) if commit < depends_on
+                )
+            })
+            .collect()
+    }
+
+    /// Render the graph in the Graphviz DOT format, with one cluster per stack and cross-stack dependencies drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph {\n");
+        let mut stack_ids = Vec::new();
+        for node in &self.commits {
+            if !stack_ids.contains(&node.stack_id) {
+                stack_ids.push(node.stack_id);
+            }
+        }
+        for (index, stack_id) in stack_ids.iter().enumerate() {
+            writeln!(out, "  subgraph cluster_{index} {{").ok();
+            writeln!(out, "    label=\"{stack_id}\";").ok();
+            for node in self
+                .commits
+                .iter()
+                .filter(|node| node.stack_id == *stack_id)
+            {
+                writeln!(
+                    out,
+                    "    \"{id}\" [label=\"{short_id}\"];",
+                    id = node.commit_id,
+                    short_id = node.commit_id.to_hex_with_len(7)
+                )
+                .ok();
+            }
+            out.push_str("  }\n");
+        }
+        for dependency in &self.dependencies {
+            let style = match dependency.kind {
+                DependencyKind::Stack => "solid",
+                DependencyKind::CrossStack => "dashed",
+            };
+            writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [style={style}];",
+                dependency.commit_id, dependency.depends_on
+            )
+            .ok();
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Collects dependencies by path, to turn them into a [`CommitDependencyGraph`] once all of them are known.
+#[derive(Debug, Default)]
+pub(crate) struct Builder {
+    commits: Vec<CommitNode>,
+    paths_by_edge: BTreeMap<(gix::ObjectId, gix::ObjectId), (DependencyKind, BTreeSet<BString>)>,
+}
+
+impl Builder {
+    pub fn add_commit(&mut self, stack_id: StackId, commit_id: gix::ObjectId) {
+        self.commits.push(CommitNode {
+            stack_id,
+            commit_id,
+        });
+    }
+
+    pub fn add_dependency(
+        &mut self,
+        commit_id: gix::ObjectId,
+        depends_on: gix::ObjectId,
+        kind: DependencyKind,
+        path: &BString,
+    ) {
+        let (_, paths) = self
+            .paths_by_edge
+            .entry((commit_id, depends_on))
+            .or_insert_with(|| (kind, BTreeSet::new()));
+        paths.insert(path.to_owned());
+    }
+
+    pub fn build(self) -> CommitDependencyGraph {
+        CommitDependencyGraph {
+            commits: self.commits,
+            dependencies: self
+                .paths_by_edge
+                .into_iter()
+                .map(
+                    |((commit_id, depends_on), (kind, paths))| CommitDependency {
+                        commit_id,
+                        depends_on,
+                        kind,
+                        paths: paths.into_iter().collect(),
+                    },
+                )
+                .collect(),
+        }
+    }
+}
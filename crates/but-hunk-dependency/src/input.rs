@@ -28,6 +28,8 @@ pub struct InputCommit {
 pub struct InputFile {
     /// The worktree-relative path to the file.
     pub path: BString,
+    /// The worktree-relative path to the file before it was renamed, if this is a rename.
+    pub previous_path: Option<BString>,
     /// The hunks that changed in this file.
     pub hunks: Vec<InputDiffHunk>,
     /// The kind of change of the parent file.
@@ -65,6 +67,7 @@ impl InputDiffHunk {
             new_start,
             new_lines,
             diff: _,
+            highlights: _,
         }: &but_core::unified_diff::DiffHunk,
     ) -> Self {
         InputDiffHunk {
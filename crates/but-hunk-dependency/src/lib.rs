@@ -64,7 +64,8 @@
 //!
 //! ### Associate all `WorktreeHunks` to their `IntroducingCommits` in a `Workspace` TODO/Still unclear
 //!
-//! TODO: This *should* work with a blame-based-algorithm, as `git blame` can already do this. More testing required.
+//! [`WorkspaceBlame`] is a first blame-based implementation which associates each line touched by a `WorktreeHunk` with its `IntroducingCommit`.
+//! It can be used instead of [`WorkspaceRanges`] to compare both on real repositories.
 //!
 //! A `Workspace` is the result of a merge of two or more `Branches`. This means its *worktree* is also the combination of two or more branches. If it is only one `Branch`,
 //!
@@ -129,7 +130,7 @@
 mod input;
 
 use anyhow::Context;
-use but_core::{TreeChange, UnifiedDiff};
+use but_core::{TreeChange, TreeStatus, UnifiedDiff};
 use gitbutler_oxidize::{ObjectIdExt, OidExt};
 use gitbutler_repo::logging::{LogUntil, RepositoryExt};
 use gix::prelude::ObjectIdExt as _;
@@ -138,6 +139,12 @@ pub use input::{InputCommit, InputDiffHunk, InputFile, InputStack};
 mod ranges;
 pub use ranges::{CalculationError, HunkRange, WorkspaceRanges};
 
+mod blame;
+pub use blame::WorkspaceBlame;
+
+mod graph;
+pub use graph::{CommitDependency, CommitDependencyGraph, CommitNode, DependencyKind};
+
 /// Types and conversions for use in `tauri`.
 pub mod ui;
 
@@ -184,14 +191,24 @@ pub fn tree_changes_to_input_files(
     changes: Vec<TreeChange>,
 ) -> anyhow::Result<Vec<InputFile>> {
     let mut files = Vec::new();
-    for change in changes {
+    for mut change in changes {
+        // Copies don't depend on the commits that changed their source, but everything that changes them later
+        // depends on all of their lines.
+        if let TreeStatus::Copy { state, .. } = change.status {
+            change.status = TreeStatus::Addition {
+                state,
+                is_untracked: false,
+            };
+        }
         let diff = change.unified_diff(repo, 0)?;
         let UnifiedDiff::Patch { hunks, .. } = diff else {
             unreachable!("Test repos don't have file-size issue")
         };
         let change_type = change.status.kind();
+        let previous_path = change.previous_path().map(ToOwned::to_owned);
         files.push(InputFile {
             path: change.path,
+            previous_path,
             hunks: hunks.iter().map(InputDiffHunk::from_unified_diff).collect(),
             change_type,
         })
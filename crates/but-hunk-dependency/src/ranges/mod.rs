@@ -1,3 +1,4 @@
+use crate::graph::{self, CommitDependencyGraph, DependencyKind};
 use crate::{InputCommit, InputDiffHunk, InputStack};
 use but_core::TreeStatusKind;
 use but_workspace::StackId;
@@ -16,6 +17,7 @@ use paths::PathRanges;
 #[derive(Debug)]
 pub struct WorkspaceRanges {
     paths: HashMap<BString, Vec<HunkRange>>,
+    graph: CommitDependencyGraph,
     /// Errors that occurred while computing the fields in this instance.
     pub errors: Vec<CalculationError>,
 }
@@ -40,14 +42,23 @@ struct StackRanges {
 /// A struct for collecting hunk ranges by path, before they get merged into a single dimension
 /// representing the workspace view.
 impl StackRanges {
+    /// If `previous_path` is set, the file was renamed to `path`, and its ranges are moved over to keep
+    /// the commits that touched it under its previous path.
     fn add(
         &mut self,
         stack_id: StackId,
         commit_id: gix::ObjectId,
         path: BString,
+        previous_path: Option<BString>,
         change_type: TreeStatusKind,
         diffs: Vec<InputDiffHunk>,
     ) -> anyhow::Result<()> {
+        if let Some(previous_ranges) = previous_path
+            .filter(|previous_path| *previous_path != path)
+            .and_then(|previous_path| self.paths.remove(&previous_path))
+        {
+            self.paths.insert(path.clone(), previous_ranges);
+        }
         self.paths
             .entry(path)
             .or_default()
@@ -80,6 +91,7 @@ impl WorkspaceRanges {
     pub fn try_from_stacks(input_stacks: Vec<InputStack>) -> anyhow::Result<WorkspaceRanges> {
         let mut stacks = vec![];
         let mut errors = vec![];
+        let mut graph = graph::Builder::default();
         for input_stack in input_stacks {
             let mut stack_ranges = StackRanges {
                 ..Default::default()
@@ -90,12 +102,14 @@ impl WorkspaceRanges {
             } = input_stack;
             for commit in commits {
                 let InputCommit { commit_id, files } = commit;
+                graph.add_commit(stack_id, commit_id);
                 for file in files {
                     if let Some(error) = stack_ranges
                         .add(
                             stack_id,
                             commit_id,
                             file.path.clone(),
+                            file.previous_path,
                             file.change_type,
                             file.hunks,
                         )
@@ -110,22 +124,39 @@ impl WorkspaceRanges {
                     }
                 }
             }
+            for (path, path_ranges) in &stack_ranges.paths {
+                for (commit_id, dependencies) in &path_ranges.commit_dependencies {
+                    for depends_on in dependencies {
+                        graph.add_dependency(*commit_id, *depends_on, DependencyKind::Stack, path);
+                    }
+                }
+            }
             stacks.push(stack_ranges);
         }
-        let paths = stacks
+        let paths: HashMap<_, _> = stacks
             .iter()
             .flat_map(StackRanges::unique_paths)
             .unique()
-            .collect_vec();
+            .map(|path| {
+                let ranges = combine_path_ranges(&path, &stacks);
+                (path, ranges)
+            })
+            .collect();
+        for path in paths.keys() {
+            add_cross_stack_dependencies(&mut graph, &stacks, path);
+        }
         Ok(WorkspaceRanges {
-            paths: paths
-                .iter()
-                .map(|path| (path.clone(), combine_path_ranges(path, &stacks)))
-                .collect(),
+            paths,
+            graph: graph.build(),
             errors,
         })
     }
 
+    /// Return the graph of dependencies between all commits in the workspace, as derived from their overlapping hunks.
+    pub fn commit_dependency_graph(&self) -> &CommitDependencyGraph {
+        &self.graph
+    }
+
     /// Finds commits that intersect with a given path and range combination.
     pub fn intersection(&self, path: &BString, start: u32, lines: u32) -> Option<Vec<&HunkRange>> {
         if let Some(hunk_range) = self.paths.get(path) {
@@ -146,6 +177,99 @@ impl WorkspaceRanges {
     }
 }
 
+/// Add a dependency for each pair of commits in different `stacks` whose ranges at `path` overlap in the
+/// common base of all stacks. The commit of the stack that comes later in `stacks` is the one that depends on the other.
+fn add_cross_stack_dependencies(
+    graph: &mut graph::Builder,
+    stacks: &[StackRanges],
+    path: &BString,
+) {
+    let base_ranges = stacks
+        .iter()
+        .map(|stack| {
+            stack
+                .paths
+                .get(path)
+                .map(|path_ranges| base_ranges(&path_ranges.hunk_ranges))
+                .unwrap_or_default()
+        })
+        .collect_vec();
+    for (index, ranges) in base_ranges.iter().enumerate() {
+        for later_ranges in &base_ranges[index + 1..] {
+            for (dependency, dependency_base) in ranges {
+                for (dependent, dependent_base) in later_ranges {
+                    if !dependency_base.overlaps(dependent_base) {
+                        continue;
+                    }
+                    graph.add_dependency(
+                        dependent.commit_id,
+                        dependency.commit_id,
+                        DependencyKind::CrossStack,
+                        path,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The lines of the common base of all stacks that a [`HunkRange`] replaced.
+#[derive(Debug, Clone, Copy)]
+struct BaseRange {
+    /// The first line (1-based) of the base that was replaced, or the line before which lines were inserted.
+    start: u32,
+    /// The amount of lines of the base that were replaced, or 0 if lines were only inserted.
+    lines: u32,
+    /// If `true`, the whole file was deleted.
+    is_deletion: bool,
+}
+
+impl BaseRange {
+    /// Return `true` if both ranges change the same lines of the base, or insert lines at the same position.
+    fn overlaps(&self, other: &BaseRange) -> bool {
+        if self.is_deletion || other.is_deletion {
+            return true;
+        }
+        let inserts_into = |insertion: &BaseRange, range: &BaseRange| {
+            range.start < insertion.start && insertion.start < range.start + range.lines
+        };
+        match (self.lines, other.lines) {
+            (0, 0) => self.start == other.start,
+            (0, _) => inserts_into(self, other),
+            (_, 0) => inserts_into(other, self),
+            (_, _) => {
+                self.start < other.start + other.lines && other.start < self.start + self.lines
+            }
+        }
+    }
+}
+
+/// Map the sorted `hunk_ranges` of a single stack back to the lines they replaced in the base of the stack,
+/// undoing the line shifts of all ranges that precede them.
+fn base_ranges(hunk_ranges: &[HunkRange]) -> Vec<(HunkRange, BaseRange)> {
+    let mut line_shift = 0i32;
+    hunk_ranges
+        .iter()
+        .map(|range| {
+            let replaced_lines = (i64::from(range.lines) - i64::from(range.line_shift)).max(0);
+            let mut start = range.start.saturating_add_signed(-line_shift);
+            if range.lines == 0 && replaced_lines > 0 {
+                // Pure deletions point at the line before the deleted ones.
+                start += 1;
+            }
+            line_shift += range.line_shift;
+            (
+                *range,
+                BaseRange {
+                    start,
+                    lines: u32::try_from(replaced_lines).unwrap_or(u32::MAX),
+                    is_deletion: range.change_type == TreeStatusKind::Deletion,
+                },
+            )
+        })
+        .collect()
+}
+
 /// Combines ranges from muiltiple branches/stacks into a single vector
 /// with adjusted line numbers. For this to work it is required that changes
 /// between stacks are not overlapping, which is already a hard requirement.
@@ -1,7 +1,7 @@
 use crate::input::InputFile;
 use crate::ranges::WorkspaceRanges;
 use crate::ranges::tests::{id_from_hex_char, input_hunk_from_unified_diff};
-use crate::{InputCommit, InputDiffHunk, InputStack};
+use crate::{CommitDependency, DependencyKind, InputCommit, InputDiffHunk, InputStack};
 use but_core::TreeStatusKind;
 use but_workspace::StackId;
 use gix::bstr::BString;
@@ -22,6 +22,7 @@ fn workspace_simple() -> anyhow::Result<()> {
             commits_from_base_to_tip: vec![InputCommit {
                 commit_id: commit1_id,
                 files: vec![InputFile {
+                    previous_path: None,
                     path: path.clone(),
                     change_type: TreeStatusKind::Modification,
                     hunks: vec![InputDiffHunk {
@@ -38,6 +39,7 @@ fn workspace_simple() -> anyhow::Result<()> {
             commits_from_base_to_tip: vec![InputCommit {
                 commit_id: commit2_id,
                 files: vec![InputFile {
+                    previous_path: None,
                     change_type: TreeStatusKind::Modification,
                     path: path.clone(),
                     hunks: vec![
@@ -89,6 +91,79 @@ fn workspace_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn ranges_follow_renames() -> anyhow::Result<()> {
+    let previous_path = BString::from("/old.txt");
+    let path = BString::from("/new.txt");
+
+    let stack_id = StackId::generate();
+    let commit_a_id = id_from_hex_char('a');
+    let commit_b_id = id_from_hex_char('b');
+    let commit_c_id = id_from_hex_char('c');
+
+    let workspace_ranges = WorkspaceRanges::try_from_stacks(vec![InputStack {
+        stack_id,
+        commits_from_base_to_tip: vec![
+            InputCommit {
+                commit_id: commit_a_id, // Modify the file under its previous name
+                files: vec![InputFile {
+                    path: previous_path.clone(),
+                    previous_path: None,
+                    change_type: TreeStatusKind::Modification,
+                    hunks: vec![InputDiffHunk {
+                        old_start: 2,
+                        old_lines: 1,
+                        new_start: 2,
+                        new_lines: 1,
+                    }],
+                }],
+            },
+            InputCommit {
+                commit_id: commit_b_id, // Rename the file without changing it
+                files: vec![InputFile {
+                    path: path.clone(),
+                    previous_path: Some(previous_path.clone()),
+                    change_type: TreeStatusKind::Rename,
+                    hunks: vec![],
+                }],
+            },
+            InputCommit {
+                commit_id: commit_c_id, // Modify the file under its new name
+                files: vec![InputFile {
+                    path: path.clone(),
+                    previous_path: None,
+                    change_type: TreeStatusKind::Modification,
+                    hunks: vec![InputDiffHunk {
+                        old_start: 5,
+                        old_lines: 1,
+                        new_start: 5,
+                        new_lines: 1,
+                    }],
+                }],
+            },
+        ],
+    }])?;
+
+    assert!(
+        workspace_ranges
+            .intersection(&previous_path, 2, 1)
+            .is_none(),
+        "the previous path isn't tracked anymore"
+    );
+
+    let dependencies = workspace_ranges.intersection(&path, 2, 1).unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(
+        dependencies[0].commit_id, commit_a_id,
+        "the commit that changed the file under its previous name is still known"
+    );
+
+    let dependencies = workspace_ranges.intersection(&path, 5, 1).unwrap();
+    assert_eq!(dependencies.len(), 1);
+    assert_eq!(dependencies[0].commit_id, commit_c_id);
+    Ok(())
+}
+
 #[test]
 fn gracefully_handle_invalid_input_commits() -> anyhow::Result<()> {
     let path = BString::from("/test.txt");
@@ -105,6 +180,7 @@ fn gracefully_handle_invalid_input_commits() -> anyhow::Result<()> {
             InputCommit {
                 commit_id: commit_a_id, // Delete file
                 files: vec![InputFile {
+                    previous_path: None,
                     path: path.clone(),
                     change_type: TreeStatusKind::Deletion,
                     hunks: vec![InputDiffHunk {
@@ -118,6 +194,7 @@ fn gracefully_handle_invalid_input_commits() -> anyhow::Result<()> {
             InputCommit {
                 commit_id: commit_b_id, // Delete file, again
                 files: vec![InputFile {
+                    previous_path: None,
                     path: path.clone(),
                     change_type: TreeStatusKind::Deletion,
                     hunks: vec![InputDiffHunk {
@@ -131,6 +208,7 @@ fn gracefully_handle_invalid_input_commits() -> anyhow::Result<()> {
             InputCommit {
                 commit_id: commit_c_id, // Re-add file
                 files: vec![InputFile {
+                    previous_path: None,
                     path: path.clone(),
                     change_type: TreeStatusKind::Addition,
                     hunks: vec![InputDiffHunk {
@@ -161,3 +239,137 @@ fn gracefully_handle_invalid_input_commits() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn modification(path: &BString, hunks: Vec<InputDiffHunk>) -> Vec<InputFile> {
+    vec![InputFile {
+        path: path.clone(),
+        previous_path: None,
+        change_type: TreeStatusKind::Modification,
+        hunks,
+    }]
+}
+
+#[test]
+fn overlapping_stacks_are_cross_stack_dependencies() -> anyhow::Result<()> {
+    let path = BString::from("/test.txt");
+    let commit1_id = id_from_hex_char('1');
+    let commit2_id = id_from_hex_char('2');
+    let commit3_id = id_from_hex_char('3');
+
+    let workspace_ranges = WorkspaceRanges::try_from_stacks(vec![
+        InputStack {
+            stack_id: StackId::generate(),
+            commits_from_base_to_tip: vec![InputCommit {
+                commit_id: commit1_id, // Insert 3 lines at the top and modify line 5 of the base
+                files: modification(
+                    &path,
+                    vec![
+                        InputDiffHunk {
+                            old_start: 0,
+                            old_lines: 0,
+                            new_start: 1,
+                            new_lines: 3,
+                        },
+                        InputDiffHunk {
+                            old_start: 5,
+                            old_lines: 1,
+                            new_start: 8,
+                            new_lines: 1,
+                        },
+                    ],
+                ),
+            }],
+        },
+        InputStack {
+            stack_id: StackId::generate(),
+            commits_from_base_to_tip: vec![
+                InputCommit {
+                    commit_id: commit2_id, // Modify line 5 of the base as well
+                    files: modification(
+                        &path,
+                        vec![InputDiffHunk {
+                            old_start: 5,
+                            old_lines: 1,
+                            new_start: 5,
+                            new_lines: 1,
+                        }],
+                    ),
+                },
+                InputCommit {
+                    commit_id: commit3_id, // Modify line 20 of the base, which nobody else touches
+                    files: modification(
+                        &path,
+                        vec![InputDiffHunk {
+                            old_start: 20,
+                            old_lines: 1,
+                            new_start: 20,
+                            new_lines: 1,
+                        }],
+                    ),
+                },
+            ],
+        },
+    ])?;
+
+    assert_eq!(
+        workspace_ranges.commit_dependency_graph().dependencies,
+        [CommitDependency {
+            commit_id: commit2_id,
+            depends_on: commit1_id,
+            kind: DependencyKind::CrossStack,
+            paths: vec![path],
+        }],
+        "the commit of the later stack depends on the one it overlaps with in the base, \
+        even though the lines at the top moved it in the first stack"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn stacks_touching_different_lines_are_independent() -> anyhow::Result<()> {
+    let path = BString::from("/test.txt");
+
+    let workspace_ranges = WorkspaceRanges::try_from_stacks(vec![
+        InputStack {
+            stack_id: StackId::generate(),
+            commits_from_base_to_tip: vec![InputCommit {
+                commit_id: id_from_hex_char('1'), // Remove lines 2 and 3 of the base
+                files: modification(
+                    &path,
+                    vec![InputDiffHunk {
+                        old_start: 2,
+                        old_lines: 2,
+                        new_start: 1,
+                        new_lines: 0,
+                    }],
+                ),
+            }],
+        },
+        InputStack {
+            stack_id: StackId::generate(),
+            commits_from_base_to_tip: vec![InputCommit {
+                commit_id: id_from_hex_char('2'), // Replace line 4 of the base with two lines
+                files: modification(
+                    &path,
+                    vec![InputDiffHunk {
+                        old_start: 4,
+                        old_lines: 1,
+                        new_start: 4,
+                        new_lines: 2,
+                    }],
+                ),
+            }],
+        },
+    ])?;
+
+    assert!(
+        workspace_ranges
+            .commit_dependency_graph()
+            .dependencies
+            .is_empty(),
+        "the changes are next to each other in the base, but don't overlap"
+    );
+
+    Ok(())
+}
@@ -1,28 +1,74 @@
-use but_core::UnifiedDiff;
 use but_core::unified_diff::DiffHunk;
+use but_core::{TreeStatusKind, UnifiedDiff};
 use gitbutler_command_context::CommandContext;
 use gitbutler_oxidize::OidExt;
 use gitbutler_stack::StackId;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// The algorithm to associate hunks with the commits they depend on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Engine {
+    /// Track the zero-context hunks of all commits in the workspace, see [`crate::WorkspaceRanges`].
+    #[default]
+    Ranges,
+    /// Blame the lines touched by each hunk, see [`crate::WorkspaceBlame`].
+    Blame,
+}
+
 /// Compute hunk-dependencies for the UI knowing the `worktree_dir` for changes
-/// and `gitbutler_dir` for obtaining stack information.
+/// and `gitbutler_dir` for obtaining stack information, using `engine`.
 pub fn hunk_dependencies_for_workspace_changes_by_worktree_dir(
     ctx: &CommandContext,
     worktree_dir: &Path,
     gitbutler_dir: &Path,
+    engine: Engine,
 ) -> anyhow::Result<HunkDependencies> {
     let repo = gix::open(worktree_dir).map_err(anyhow::Error::from)?;
     let worktree_changes = but_core::diff::worktree_changes(&repo)?;
     let stacks = but_workspace::stacks(ctx, gitbutler_dir, &repo, Default::default())?;
+    let common_merge_base = gitbutler_stack::VirtualBranchesHandle::new(gitbutler_dir)
+        .get_default_target()?
+        .sha;
+    match engine {
+        Engine::Ranges => {
+            let input_stacks = crate::workspace_stacks_to_input_stacks(
+                &repo,
+                &stacks,
+                common_merge_base.to_gix(),
+            )?;
+            let ranges = crate::WorkspaceRanges::try_from_stacks(input_stacks)?;
+            HunkDependencies::try_from_workspace_ranges(&repo, ranges, worktree_changes.changes)
+        }
+        Engine::Blame => {
+            let blame = crate::WorkspaceBlame::try_from_stacks(
+                &repo,
+                repo.head_id()?.detach(),
+                &stacks,
+                common_merge_base.to_gix(),
+            )?;
+            HunkDependencies::try_from_workspace_blame(&repo, blame, worktree_changes.changes)
+        }
+    }
+}
+
+/// Compute the graph of dependencies between all commits in the workspace knowing the `worktree_dir` of the repository
+/// and `gitbutler_dir` for obtaining stack information.
+pub fn commit_dependency_graph_by_worktree_dir(
+    ctx: &CommandContext,
+    worktree_dir: &Path,
+    gitbutler_dir: &Path,
+) -> anyhow::Result<crate::CommitDependencyGraph> {
+    let repo = gix::open(worktree_dir).map_err(anyhow::Error::from)?;
+    let stacks = but_workspace::stacks(ctx, gitbutler_dir, &repo, Default::default())?;
     let common_merge_base = gitbutler_stack::VirtualBranchesHandle::new(gitbutler_dir)
         .get_default_target()?
         .sha;
     let input_stacks =
         crate::workspace_stacks_to_input_stacks(&repo, &stacks, common_merge_base.to_gix())?;
     let ranges = crate::WorkspaceRanges::try_from_stacks(input_stacks)?;
-    HunkDependencies::try_from_workspace_ranges(&repo, ranges, worktree_changes.changes)
+    Ok(ranges.commit_dependency_graph().clone())
 }
 
 /// A way to represent all hunk dependencies that would make it possible to know what can be applied, and were.
@@ -51,9 +97,14 @@ impl HunkDependencies {
             let UnifiedDiff::Patch { hunks, .. } = unidiff else {
                 continue;
             };
+            // The hunks are relative to the file before it was renamed.
+            let path = change
+                .previous_path()
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| change.path.clone());
             for hunk in hunks {
                 if let Some(intersections) =
-                    ranges.intersection(&change.path, hunk.old_start, hunk.old_lines)
+                    ranges.intersection(&path, hunk.old_start, hunk.old_lines)
                 {
                     let locks: Vec<_> = intersections
                         .into_iter()
@@ -72,6 +123,47 @@ impl HunkDependencies {
             errors: ranges.errors,
         })
     }
+
+    /// Calculate all hunk dependencies using a prepared [`crate::WorkspaceBlame`].
+    pub fn try_from_workspace_blame(
+        repo: &gix::Repository,
+        mut blame: crate::WorkspaceBlame,
+        worktree_changes: Vec<but_core::TreeChange>,
+    ) -> anyhow::Result<HunkDependencies> {
+        let mut diffs = Vec::<(String, DiffHunk, Vec<HunkLock>)>::new();
+        for change in worktree_changes {
+            if matches!(
+                change.status.kind(),
+                TreeStatusKind::Addition | TreeStatusKind::Copy
+            ) {
+                // Nothing to blame for new files, and copies don't depend on the commits that changed their source.
+                continue;
+            }
+            let unidiff = change.unified_diff(repo, 0 /* zero context lines */)?;
+            let UnifiedDiff::Patch { hunks, .. } = unidiff else {
+                continue;
+            };
+            let path = change.previous_path().unwrap_or(change.path.as_ref());
+            for hunk in hunks {
+                let locks: Vec<_> = blame
+                    .intersection(repo, path, hunk.old_start, hunk.old_lines)?
+                    .into_iter()
+                    .map(|(stack_id, commit_id)| HunkLock {
+                        stack_id,
+                        commit_id,
+                    })
+                    .collect();
+                if !locks.is_empty() {
+                    diffs.push((change.path.to_string(), hunk, locks));
+                }
+            }
+        }
+
+        Ok(HunkDependencies {
+            diffs,
+            errors: Vec::new(),
+        })
+    }
 }
 
 /// A commit that owns this lock, along with the stack that owns it.
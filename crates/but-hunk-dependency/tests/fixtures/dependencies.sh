@@ -525,3 +525,35 @@ update line 8 again
 9
 " > file
 )
+
+git clone remote renamed-file-with-worktree-changes
+(cd renamed-file-with-worktree-changes
+  git branch existing-branch
+  $CLI project add --switch-to-workspace "$(git rev-parse --symbolic-full-name @{u})"
+
+  $CLI branch create --set-default my_stack
+  seq 1 9 > file
+  commit_stack "my_stack" "add file"
+  seq 1 9 | sed 's/^5$/update line 5/' > file
+  commit_stack "my_stack" "modify line 5"
+  mv file renamed
+  commit_stack "my_stack" "rename file"
+
+  seq 1 9 | sed 's/^5$/update line 5 again/' > renamed
+)
+
+git clone remote reformatted-file-with-worktree-changes
+(cd reformatted-file-with-worktree-changes
+  git branch existing-branch
+  $CLI project add --switch-to-workspace "$(git rev-parse --symbolic-full-name @{u})"
+
+  $CLI branch create --set-default my_stack
+  for i in $(seq 1 20); do echo "item($i);"; done > file
+  commit_stack "my_stack" "add file"
+  sed -i.bak 's/^item(11);$/  item(11, updated);/' file && rm file.bak
+  commit_stack "my_stack" "update line 11"
+  sed -i.bak 's/^item/  item/' file && rm file.bak
+  commit_stack "my_stack" "reformat all lines but the already formatted one"
+
+  sed -i.bak -e 's/^  item(5);$/  item(5, changed);/' -e 's/^  item(11, updated);$/  item(11, changed);/' file && rm file.bak
+)
@@ -71,6 +71,88 @@ fn hunk_dependencies_json_sample() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn blame_engine_finds_a_subset_of_range_locks() -> anyhow::Result<()> {
+    use but_hunk_dependency::ui::Engine;
+    let name = "complex-file-manipulation-multiple-hunks-with-changes";
+    let (ranges, _ctx) = hunk_dependencies_for_workspace_with_engine(name, Engine::Ranges)?;
+    let (blame, _ctx) = hunk_dependencies_for_workspace_with_engine(name, Engine::Blame)?;
+
+    assert!(!blame.diffs.is_empty(), "the blame engine finds locks too");
+    for (path, hunk, blame_locks) in &blame.diffs {
+        let (_, _, range_locks) = ranges
+            .diffs
+            .iter()
+            .find(|(range_path, range_hunk, _)| {
+                range_path == path
+                    && range_hunk.old_start == hunk.old_start
+                    && range_hunk.new_start == hunk.new_start
+            })
+            .expect("the blame engine doesn't find locks for hunks without range locks");
+        assert!(
+            blame_locks.iter().all(|lock| range_locks.contains(lock)),
+            "blame only finds the commits that last changed the lines, which are a subset of the commits that changed them at all"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn blame_engine_follows_renames() -> anyhow::Result<()> {
+    use but_hunk_dependency::ui::Engine;
+    let (actual, ctx) = hunk_dependencies_for_workspace_with_engine(
+        "renamed-file-with-worktree-changes",
+        Engine::Blame,
+    )?;
+
+    assert!(actual.errors.is_empty());
+    let [(path, hunk, locks)] = &actual.diffs[..] else {
+        panic!("expected a single hunk, got {:?}", actual.diffs);
+    };
+    assert_eq!(path, "renamed");
+    assert_eq!(hunk.new_start, 5);
+    assert_eq!(
+        commit_ids(locks),
+        [commit_by_message(&ctx.repo, "modify line 5")?],
+        "the line was last changed before the file was renamed"
+    );
+    Ok(())
+}
+
+#[test]
+fn blame_engine_sees_through_reformatting() -> anyhow::Result<()> {
+    use but_hunk_dependency::ui::Engine;
+    let (actual, ctx) = hunk_dependencies_for_workspace_with_engine(
+        "reformatted-file-with-worktree-changes",
+        Engine::Blame,
+    )?;
+
+    assert!(actual.errors.is_empty());
+    let locks_at = |new_start: u32| {
+        actual
+            .diffs
+            .iter()
+            .find(|(path, hunk, _)| path == "file" && hunk.new_start == new_start)
+            .map(|(_, _, locks)| commit_ids(locks))
+            .expect("there is a hunk for each changed line")
+    };
+    assert_eq!(actual.diffs.len(), 2);
+    assert_eq!(
+        locks_at(5),
+        [commit_by_message(
+            &ctx.repo,
+            "reformat all lines but the already formatted one"
+        )?],
+        "the reformatting commit is the last one to change the line"
+    );
+    assert_eq!(
+        locks_at(11),
+        [commit_by_message(&ctx.repo, "update line 11")?],
+        "the reformatting commit didn't touch the line, so the commit before it is used"
+    );
+    Ok(())
+}
+
 #[test]
 fn complex_file_manipulation_with_uncommitted_changes() -> anyhow::Result<()> {
     let (actual, _ctx) =
@@ -213,10 +295,11 @@ fn dependencies_ignore_merge_commits() -> anyhow::Result<()> {
 mod util {
     use but_core::unified_diff::DiffHunk;
     use but_hunk_dependency::ui::{
-        HunkDependencies, HunkLock, hunk_dependencies_for_workspace_changes_by_worktree_dir,
+        Engine, HunkDependencies, HunkLock, hunk_dependencies_for_workspace_changes_by_worktree_dir,
     };
     use gitbutler_command_context::CommandContext;
     use gitbutler_stack::StackId;
+    use gix::bstr::ByteSlice;
     use itertools::Itertools;
     use std::collections::HashSet;
     use std::path::PathBuf;
@@ -246,24 +329,51 @@ mod util {
         to_simplify
     }
 
+    pub fn commit_ids(locks: &[HunkLock]) -> Vec<gix::ObjectId> {
+        locks.iter().map(|lock| lock.commit_id).collect()
+    }
+
+    /// Find the commit reachable from `HEAD` in `repo` whose message is `message`.
+    pub fn commit_by_message(
+        repo: &gix::Repository,
+        message: &str,
+    ) -> anyhow::Result<gix::ObjectId> {
+        for info in repo.head_id()?.ancestors().all()? {
+            let id = info?.id;
+            if repo.find_commit(id)?.message_raw()?.trim() == message.as_bytes() {
+                return Ok(id);
+            }
+        }
+        anyhow::bail!("Could not find a commit with message '{message}'")
+    }
+
     pub fn hunk_dependencies_for_workspace(
         name: &str,
+    ) -> anyhow::Result<(HunkDependencies, TestContext)> {
+        hunk_dependencies_for_workspace_with_engine(name, Engine::Ranges)
+    }
+
+    pub fn hunk_dependencies_for_workspace_with_engine(
+        name: &str,
+        engine: Engine,
     ) -> anyhow::Result<(HunkDependencies, TestContext)> {
         let script_name = "../../../but-hunk-dependency/tests/fixtures/dependencies.sh";
         let ctx = test_ctx_at(script_name, name)?;
         let command_context = gitbutler_testsupport::read_only::fixture(script_name, name)?;
-        let deps = hunk_dependencies_for_workspace_by_ctx(&ctx, &command_context)?;
+        let deps = hunk_dependencies_for_workspace_by_ctx(&ctx, &command_context, engine)?;
         Ok((deps, ctx))
     }
 
     fn hunk_dependencies_for_workspace_by_ctx(
         ctx: &TestContext,
         command_context: &CommandContext,
+        engine: Engine,
     ) -> anyhow::Result<HunkDependencies> {
         hunk_dependencies_for_workspace_changes_by_worktree_dir(
             command_context,
             ctx.repo.workdir().expect("We don't support bare repos"),
             &ctx.gitbutler_dir,
+            engine,
         )
     }
 
@@ -336,6 +446,7 @@ mod util {
     }
 }
 use util::{
-    hunk_dependencies_for_workspace, simplify_stack_ids_in_string, stack_ids_by_diffs,
+    commit_by_message, commit_ids, hunk_dependencies_for_workspace,
+    hunk_dependencies_for_workspace_with_engine, simplify_stack_ids_in_string, stack_ids_by_diffs,
     to_stable_string,
 };
@@ -276,6 +276,59 @@ fn every_commit_is_sequentially_dependent() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn commit_dependency_graph_of_sequentially_dependent_commits() -> anyhow::Result<()> {
+    let graph = commit_dependency_graph_for_workspace_named("sequentially-dependent-commits")?;
+    assert_eq!(graph.commits.len(), 6);
+    assert_eq!(graph.dependencies.len(), 5);
+    for commits in graph.commits.windows(2) {
+        let dependencies: Vec<_> = graph.dependencies_of(commits[1].commit_id).collect();
+        assert_eq!(dependencies.len(), 1);
+        assert_eq!(dependencies[0].depends_on, commits[0].commit_id);
+        assert_eq!(dependencies[0].kind, DependencyKind::Stack);
+        assert_eq!(dependencies[0].paths, ["file"]);
+    }
+
+    let commits_from_base_to_tip: Vec<_> =
+        graph.commits.iter().map(|node| node.commit_id).collect();
+    assert!(
+        graph
+            .violated_by_order(&commits_from_base_to_tip)
+            .is_empty()
+    );
+    let mut reordered = commits_from_base_to_tip.clone();
+    reordered.swap(0, 1);
+    let violations = graph.violated_by_order(&reordered);
+    assert_eq!(
+        violations.len(),
+        1,
+        "only the swapped commits are out of order"
+    );
+    assert_eq!(violations[0].commit_id, commits_from_base_to_tip[1]);
+
+    let dot = graph.to_dot();
+    assert_eq!(dot.matches("subgraph").count(), 1);
+    assert_eq!(dot.matches(" -> ").count(), 5);
+    Ok(())
+}
+
+#[test]
+fn commit_dependency_graph_of_sequentially_dependent_commits_multi_stack() -> anyhow::Result<()> {
+    let graph =
+        commit_dependency_graph_for_workspace_named("sequentially-dependent-commits-multi-stack")?;
+    assert_eq!(graph.commits.len(), 12);
+    assert_eq!(graph.dependencies.len(), 10);
+    assert!(
+        graph
+            .dependencies
+            .iter()
+            .all(|dependency| dependency.kind == DependencyKind::Stack),
+        "the stacks touch different files, so there are no cross-stack dependencies"
+    );
+    assert_eq!(graph.to_dot().matches("subgraph").count(), 2);
+    Ok(())
+}
+
 #[test]
 fn every_commit_is_sequentially_dependent_multi_stack() -> anyhow::Result<()> {
     let actual =
@@ -866,9 +919,23 @@ fn dependencies_handle_complex_branch_checkout() -> anyhow::Result<()> {
 
 mod util {
     use crate::{WorkspaceDigest, intersect_workspace_ranges};
+    use but_hunk_dependency::CommitDependencyGraph;
     use gitbutler_oxidize::OidExt;
     use gitbutler_stack::VirtualBranchesHandle;
 
+    pub fn commit_dependency_graph_for_workspace_named(
+        name: &str,
+    ) -> anyhow::Result<CommitDependencyGraph> {
+        let ctx = test_ctx(name)?;
+        let input_stacks = but_hunk_dependency::workspace_stacks_to_input_stacks(
+            &ctx.repo,
+            &ctx.stacks_entries,
+            ctx.common_merge_base,
+        )?;
+        let ranges = but_hunk_dependency::WorkspaceRanges::try_from_stacks(input_stacks)?;
+        Ok(ranges.commit_dependency_graph().clone())
+    }
+
     pub fn worktree_ranges_digest_for_workspace_named(
         name: &str,
     ) -> anyhow::Result<WorkspaceDigest> {
@@ -916,4 +983,7 @@ mod util {
     }
 }
 
-use crate::workspace_dependencies::util::worktree_ranges_digest_for_workspace_named;
+use crate::workspace_dependencies::util::{
+    commit_dependency_graph_for_workspace_named, worktree_ranges_digest_for_workspace_named,
+};
+use but_hunk_dependency::DependencyKind;
@@ -25,9 +25,12 @@ pub enum EmptyCommit {
 pub(crate) mod function {
     use crate::cherry_pick::{EmptyCommit, PickMode};
     use crate::commit::CommitterMode;
+    use crate::resolution::RecordedResolutions;
     use anyhow::{Context, bail};
     use bstr::BString;
-    use but_core::commit::{HEADERS_CONFLICTED_FIELD, HeadersV2, TreeKind};
+    use but_core::commit::{
+        HEADERS_CONFLICTED_FIELD, HEADERS_RECORDED_RESOLUTION_FIELD, HeadersV2, TreeKind,
+    };
     use gix::object::tree::EntryKind;
     use gix::prelude::ObjectIdExt;
     use serde::Serialize;
@@ -46,10 +49,37 @@ pub(crate) mod function {
         commit_to_rebase: gix::ObjectId,
         pick_mode: PickMode,
         empty_commit: EmptyCommit,
+    ) -> anyhow::Result<gix::ObjectId> {
+        cherry_pick_one_with_resolutions(
+            repo,
+            base,
+            commit_to_rebase,
+            pick_mode,
+            empty_commit,
+            None,
+        )
+    }
+
+    /// Like [`cherry_pick_one()`], but if the pick conflicts, try to resolve all conflicts with `recorded_resolutions`
+    /// before producing a conflicted commit.
+    pub(crate) fn cherry_pick_one_with_resolutions(
+        repo: &gix::Repository,
+        base: gix::ObjectId,
+        commit_to_rebase: gix::ObjectId,
+        pick_mode: PickMode,
+        empty_commit: EmptyCommit,
+        recorded_resolutions: Option<&RecordedResolutions>,
     ) -> anyhow::Result<gix::ObjectId> {
         let base = but_core::Commit::from_id(base.attach(repo))?;
         let to_rebase = but_core::Commit::from_id(commit_to_rebase.attach(repo))?;
-        Ok(cherry_pick_one_inner(base, to_rebase, pick_mode, empty_commit)?.detach())
+        Ok(cherry_pick_one_inner(
+            base,
+            to_rebase,
+            pick_mode,
+            empty_commit,
+            recorded_resolutions,
+        )?
+        .detach())
     }
 
     fn cherry_pick_one_inner<'repo>(
@@ -57,6 +87,7 @@ pub(crate) mod function {
         commit_to_rebase: but_core::Commit<'repo>,
         pick_mode: PickMode,
         empty_commit: EmptyCommit,
+        recorded_resolutions: Option<&RecordedResolutions>,
     ) -> anyhow::Result<gix::Id<'repo>> {
         if commit_to_rebase.parents.len() > 1 {
             bail!("Cannot yet cherry-pick merge-commits - use rebasing for that")
@@ -72,12 +103,50 @@ pub(crate) mod function {
 
         let conflict_kind = gix::merge::tree::TreatAsUnresolved::forced_resolution();
         if cherry_pick.has_unresolved_conflicts(conflict_kind) {
+            if let Some(recorded_resolutions) = recorded_resolutions {
+                match resolve_with_recorded_resolutions(
+                    &base,
+                    &commit_to_rebase,
+                    recorded_resolutions,
+                ) {
+                    Ok(Some((resolved_tree_id, num_files))) => {
+                        return commit_from_unconflicted_tree(
+                            base,
+                            commit_to_rebase,
+                            resolved_tree_id,
+                            empty_commit,
+                            Some(num_files),
+                        );
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::warn!(
+                            "Could not apply recorded resolutions to commit {}: {err:#}",
+                            commit_to_rebase.id
+                        );
+                    }
+                }
+            }
             commit_from_conflicted_tree(base, commit_to_rebase, tree_id, cherry_pick, conflict_kind)
         } else {
-            commit_from_unconflicted_tree(base, commit_to_rebase, tree_id, empty_commit)
+            commit_from_unconflicted_tree(base, commit_to_rebase, tree_id, empty_commit, None)
         }
     }
 
+    /// Redo the merge of `to_rebase` onto `new_base` and resolve all of its conflicts with `recorded_resolutions`,
+    /// returning the resolved tree and the amount of resolved files, or `None` if not all conflicts were recorded.
+    fn resolve_with_recorded_resolutions<'repo>(
+        new_base: &but_core::Commit<'repo>,
+        to_rebase: &but_core::Commit<'repo>,
+        recorded_resolutions: &RecordedResolutions,
+    ) -> anyhow::Result<Option<(gix::Id<'repo>, usize)>> {
+        let repo = to_rebase.id.repo;
+        let (base, ours, theirs) = find_cherry_pick_trees(new_base, to_rebase)?;
+        Ok(recorded_resolutions
+            .resolve(repo, base.detach(), ours.detach(), theirs.detach())?
+            .map(|(tree_id, num_files)| (tree_id.attach(repo), num_files)))
+    }
+
     fn set_parent(
         to_rebase: &mut gix::objs::Commit,
         new_parent: gix::ObjectId,
@@ -156,6 +225,7 @@ pub(crate) mod function {
         to_rebase: but_core::Commit<'repo>,
         resolved_tree_id: gix::Id<'repo>,
         empty_commit: EmptyCommit,
+        recorded_resolution_files: Option<usize>,
     ) -> anyhow::Result<gix::Id<'repo>> {
         let repo = head.id.repo;
         // Remove empty commits
@@ -183,6 +253,19 @@ pub(crate) mod function {
                 .extra_headers
                 .extend(Vec::<(BString, BString)>::from(&HeadersV2::default()));
         }
+        // Mark commits that were resolved automatically so they can be reviewed.
+        if let Some(num_files) = recorded_resolution_files {
+            if let Some(pos) = new_commit
+                .extra_headers()
+                .find_pos(HEADERS_RECORDED_RESOLUTION_FIELD)
+            {
+                new_commit.extra_headers.remove(pos);
+            }
+            new_commit.extra_headers.push((
+                HEADERS_RECORDED_RESOLUTION_FIELD.into(),
+                num_files.to_string().into(),
+            ));
+        }
         set_parent(&mut new_commit, head.id.detach())?;
         Ok(crate::commit::create(repo, new_commit, CommitterMode::Update)?.attach(repo))
     }
@@ -14,11 +14,15 @@ use tracing::instrument;
 pub mod cherry_pick;
 use crate::cherry_pick::{EmptyCommit, PickMode};
 pub use cherry_pick::function::cherry_pick_one;
+use cherry_pick::function::cherry_pick_one_with_resolutions;
 
 /// Utilities to create commits (and deal with signing)
 pub mod commit;
 /// Utilities around merging
 pub mod merge;
+/// Recording and reusing conflict resolutions
+pub mod resolution;
+use crate::resolution::RecordedResolutions;
 
 /// An instruction for [`RebaseBuilder::rebase()`].
 #[derive(Debug, Clone)]
@@ -71,6 +75,7 @@ pub struct Rebase<'repo> {
     base_substitute: Option<gix::ObjectId>,
     steps: Vec<RebaseStep>,
     rebase_noops: bool,
+    recorded_resolutions: Option<RecordedResolutions>,
 }
 
 impl<'repo> Rebase<'repo> {
@@ -99,6 +104,7 @@ impl<'repo> Rebase<'repo> {
             base_substitute,
             steps: Vec::new(),
             rebase_noops: true, // default to always rebasing
+            recorded_resolutions: None,
         })
     }
 
@@ -121,6 +127,14 @@ impl<'repo> Rebase<'repo> {
         self
     }
 
+    /// Resolve conflicts of picked commits with `resolutions` if all conflicts of a commit were recorded before.
+    /// Commits resolved that way are marked with a header so they can be reviewed.
+    /// Default is to not use recorded resolutions.
+    pub fn recorded_resolutions(&mut self, resolutions: RecordedResolutions) -> &mut Self {
+        self.recorded_resolutions = Some(resolutions);
+        self
+    }
+
     /// Performs a rebase on top of a given base, according to the provided steps, or fails if no step was provided.
     /// It does not actually create new git references nor does it update existing ones, it only deals with
     /// altering commits and providing the information needed to update refs.
@@ -149,6 +163,7 @@ impl<'repo> Rebase<'repo> {
             self.base_substitute,
             std::mem::take(&mut self.steps),
             pick_mode,
+            self.recorded_resolutions.as_ref(),
         )
     }
 }
@@ -218,6 +233,7 @@ fn rebase(
     base_substitute: Option<gix::ObjectId>,
     steps: Vec<RebaseStep>,
     pick_mode: PickMode,
+    recorded_resolutions: Option<&RecordedResolutions>,
 ) -> Result<RebaseOutput> {
     let (mut references, mut commit_mapping) = (
         vec![],
@@ -264,12 +280,13 @@ fn rebase(
                 } else {
                     match &mut cursor {
                         Some(cursor) => {
-                            let mut new_commit = cherry_pick_one(
+                            let mut new_commit = cherry_pick_one_with_resolutions(
                                 repo,
                                 *cursor,
                                 commit_id,
                                 pick_mode,
                                 EmptyCommit::Keep,
+                                recorded_resolutions,
                             )?;
                             if let Some(new_message) = new_message {
                                 new_commit = reword_commit(repo, new_commit, new_message.clone())?;
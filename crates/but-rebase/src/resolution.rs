@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use bstr::{BString, ByteSlice};
+use but_core::commit::TreeKind;
+use gitbutler_oxidize::GixRepositoryExt;
+use gix::object::tree::EntryKind;
+
+/// A store of conflict resolutions that were done by hand, to automatically resolve the same conflicts
+/// once they are encountered again, similar to `git rerere`.
+///
+/// A conflicted file is identified by its *preimage*, the blob with conflict markers that is produced when merging
+/// its base, our and their version without favoring any side. Its *postimage* is the content of the file after the
+/// conflict was resolved.
+#[derive(Debug, Clone)]
+pub struct RecordedResolutions {
+    dir: PathBuf,
+}
+
+impl RecordedResolutions {
+    /// Store resolutions in `dir`, which will be created when the first resolution is recorded.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        RecordedResolutions { dir: dir.into() }
+    }
+
+    /// Record how all conflicted files of `conflicted_commit` were resolved, taking their resolved content from `resolved_tree`.
+    /// Files that don't exist in `resolved_tree` are skipped.
+    ///
+    /// Returns the number of recorded resolutions.
+    pub fn record(
+        &self,
+        conflicted_commit: &but_core::Commit<'_>,
+        resolved_tree: gix::ObjectId,
+    ) -> Result<usize> {
+        if !conflicted_commit.is_conflicted() {
+            return Ok(0);
+        }
+        let repo = conflicted_commit.id.repo;
+        let Some((_merge, preimages)) = conflicted_preimages(
+            repo,
+            conflicted_commit.tree_id_or_kind(TreeKind::Base)?.detach(),
+            conflicted_commit.tree_id_or_kind(TreeKind::Ours)?.detach(),
+            conflicted_commit
+                .tree_id_or_kind(TreeKind::Theirs)?
+                .detach(),
+        )?
+        else {
+            return Ok(0);
+        };
+
+        let resolved_tree = repo.find_tree(resolved_tree)?;
+        let mut num_recorded = 0;
+        for Preimage { path, id, .. } in preimages {
+            let Some(entry) = resolved_tree.lookup_entry(path.split_str("/"))? else {
+                continue;
+            };
+            let postimage = entry.object()?;
+            std::fs::create_dir_all(&self.dir)?;
+            let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+            file.write_all(&postimage.data)?;
+            file.persist(self.path_for(&id))?;
+            num_recorded += 1;
+        }
+        Ok(num_recorded)
+    }
+
+    /// Merge `ours` and `theirs` with the common ancestor `base`, and resolve all conflicts with recorded resolutions.
+    ///
+    /// Returns the id of the resolved tree along with the number of files that were resolved, or `None` if there were
+    /// conflicts without a recorded resolution.
+    pub fn resolve(
+        &self,
+        repo: &gix::Repository,
+        base: gix::ObjectId,
+        ours: gix::ObjectId,
+        theirs: gix::ObjectId,
+    ) -> Result<Option<(gix::ObjectId, usize)>> {
+        let Some((mut merge, preimages)) = conflicted_preimages(repo, base, ours, theirs)? else {
+            return Ok(None);
+        };
+        if preimages.is_empty() {
+            return Ok(None);
+        }
+        for Preimage { path, id, kind } in &preimages {
+            let Some(postimage) = self.lookup(id)? else {
+                return Ok(None);
+            };
+            let postimage = repo.write_blob(postimage)?;
+            merge.tree.upsert(path, *kind, postimage)?;
+        }
+        Ok(Some((merge.tree.write()?.detach(), preimages.len())))
+    }
+
+    fn lookup(&self, preimage: &gix::oid) -> Result<Option<Vec<u8>>> {
+        let path = self.path_for(preimage);
+        match std::fs::read(&path) {
+            Ok(postimage) => Ok(Some(postimage)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err)
+                .with_context(|| format!("Could not read resolution at '{}'", path.display())),
+        }
+    }
+
+    fn path_for(&self, preimage: &gix::oid) -> PathBuf {
+        self.dir.join(preimage.to_hex().to_string())
+    }
+}
+
+/// A conflicted file with conflict markers.
+struct Preimage {
+    path: BString,
+    id: gix::ObjectId,
+    kind: EntryKind,
+}
+
+/// Merge `ours` and `theirs` without favoring a side, and return the merge along with the preimages of all conflicted files.
+/// Return `None` if there is a conflict that doesn't manifest as file with conflict markers, as these can't be recorded.
+fn conflicted_preimages(
+    repo: &gix::Repository,
+    base: gix::ObjectId,
+    ours: gix::ObjectId,
+    theirs: gix::ObjectId,
+) -> Result<Option<(gix::merge::tree::Outcome<'_>, Vec<Preimage>)>> {
+    use gix::index::entry::Stage;
+    let unresolved = gix::merge::tree::TreatAsUnresolved::git();
+    let mut merge = repo.merge_trees(
+        base,
+        ours,
+        theirs,
+        repo.default_merge_labels(),
+        repo.tree_merge_options()?,
+    )?;
+    if !merge.has_unresolved_conflicts(unresolved) {
+        return Ok(Some((merge, Vec::new())));
+    }
+
+    let merged_tree_id = merge.tree.write()?;
+    let mut index = repo.index_from_tree(&merged_tree_id)?;
+    merge.index_changed_after_applying_conflicts(
+        &mut index,
+        unresolved,
+        gix::merge::tree::apply_index_entries::RemovalMode::Mark,
+    );
+    let conflicted_paths: BTreeSet<BString> = index
+        .entries()
+        .iter()
+        .filter(|entry| entry.stage() != Stage::Unconflicted)
+        .map(|entry| entry.path(&index).to_owned())
+        .collect();
+    if conflicted_paths.is_empty() {
+        return Ok(None);
+    }
+
+    let merged_tree = repo.find_tree(merged_tree_id)?;
+    let mut preimages = Vec::with_capacity(conflicted_paths.len());
+    for path in conflicted_paths {
+        let Some(entry) = merged_tree.lookup_entry(path.split_str("/"))? else {
+            return Ok(None);
+        };
+        if !entry.mode().is_blob() || !entry.object()?.data.contains_str("<<<<<<<") {
+            return Ok(None);
+        }
+        preimages.push(Preimage {
+            path,
+            id: entry.object_id(),
+            kind: entry.mode().kind(),
+        });
+    }
+    Ok(Some((merge, preimages)))
+}
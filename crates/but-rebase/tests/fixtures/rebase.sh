@@ -47,3 +47,27 @@ git init merge-in-the-middle
   git checkout with-inner-merge && git merge --no-ff B
   echo seq 10 >'added-after-with-inner-merge' && git add . && git commit -m "on top of inner merge"
 )
+
+git init merge-drivers
+(cd merge-drivers
+  printf '%s\n' "*.union merge=union" "*.lock merge=keep-ours" >.gitattributes
+  git config merge.keep-ours.driver true
+  echo base >file.union && echo base >file.lock && git add . && git commit -m "base"
+
+  git checkout -b theirs
+  { echo base; echo theirs; } >file.union && echo theirs >file.lock && git commit -am "theirs"
+
+  git checkout main
+  { echo base; echo ours; } >file.union && echo ours >file.lock && git commit -am "ours"
+)
+
+git init recorded-resolution
+(cd recorded-resolution
+  echo base >file && git add . && git commit -m "base"
+
+  git checkout -b theirs
+  echo theirs >file && git commit -am "theirs"
+
+  git checkout main
+  echo ours >file && git commit -am "ours"
+)
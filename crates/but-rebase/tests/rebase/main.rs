@@ -3,7 +3,7 @@ use crate::utils::{
 };
 use anyhow::Result;
 use bstr::ByteSlice;
-use but_rebase::{Rebase, RebaseStep};
+use but_rebase::{Rebase, RebaseStep, resolution::RecordedResolutions};
 use but_testsupport::{assure_stable_env, visualize_commit_graph};
 use gix::prelude::ObjectIdExt;
 
@@ -623,6 +623,96 @@ fn pick_the_first_commit_with_no_parents_for_squashing() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn merge_drivers_from_attributes_are_honored() -> Result<()> {
+    assure_stable_env();
+    let (repo, _tmp) = fixture_writable("merge-drivers")?;
+    let ours = repo.rev_parse_single("main")?.detach();
+    let theirs = repo.rev_parse_single("theirs")?.detach();
+    let mut builder = Rebase::new(&repo, ours, None)?;
+    let out = builder
+        .steps([RebaseStep::Pick {
+            commit_id: theirs,
+            new_message: None,
+        }])?
+        .rebase()?;
+    assure_nonconflicting(&repo, &out)?;
+
+    let tree = repo.find_commit(out.top_commit)?.tree()?;
+    let content = |path: &str| -> Result<String> {
+        let entry = tree
+            .lookup_entry_by_path(path)?
+            .expect("file is present in the merged tree");
+        Ok(entry.object()?.data.to_str_lossy().into_owned())
+    };
+    assert_eq!(
+        content("file.union")?,
+        "base\nours\ntheirs\n",
+        "the builtin union driver keeps both sides"
+    );
+    assert_eq!(
+        content("file.lock")?,
+        "ours\n",
+        "the configured driver is run, and it keeps our side"
+    );
+    Ok(())
+}
+
+#[test]
+fn recorded_resolutions_are_reused() -> Result<()> {
+    assure_stable_env();
+    let (repo, tmp) = fixture_writable("recorded-resolution")?;
+    let ours = repo.rev_parse_single("main")?.detach();
+    let theirs = repo.rev_parse_single("theirs")?.detach();
+    let resolutions = RecordedResolutions::at(tmp.path().join("rerere"));
+    let pick_theirs = || RebaseStep::Pick {
+        commit_id: theirs,
+        new_message: None,
+    };
+
+    let out = Rebase::new(&repo, ours, None)?
+        .recorded_resolutions(resolutions.clone())
+        .steps([pick_theirs()])?
+        .rebase()?;
+    assert_eq!(
+        conflicted(&repo, &out),
+        [true],
+        "nothing was recorded yet, so the conflict remains"
+    );
+
+    let conflicted_commit = but_core::Commit::from_id(out.top_commit.attach(&repo))?;
+    let resolved_blob = repo.write_blob("resolved\n")?;
+    let mut resolved_tree = repo.edit_tree(repo.find_commit(ours)?.tree_id()?)?;
+    resolved_tree.upsert("file", gix::object::tree::EntryKind::Blob, resolved_blob)?;
+    let resolved_tree = resolved_tree.write()?.detach();
+    assert_eq!(
+        resolutions.record(&conflicted_commit, resolved_tree)?,
+        1,
+        "the only conflicting file was recorded"
+    );
+
+    let out = Rebase::new(&repo, ours, None)?
+        .recorded_resolutions(resolutions)
+        .steps([pick_theirs()])?
+        .rebase()?;
+    assert_eq!(
+        conflicted(&repo, &out),
+        [false],
+        "the same conflict is now resolved automatically"
+    );
+    let commit = but_core::Commit::from_id(out.top_commit.attach(&repo))?;
+    assert_eq!(
+        commit.recorded_resolution_files(),
+        Some(1),
+        "the commit is marked for review"
+    );
+    assert_eq!(
+        commit.tree, resolved_tree,
+        "the recorded resolution was used"
+    );
+    Ok(())
+}
+
 pub mod utils {
     use anyhow::Result;
     use but_rebase::RebaseOutput;
@@ -155,6 +155,7 @@ pub fn get_status(repo: &gix::Repository) -> anyhow::Result<Vec<gix::status::Ite
 
     let status_changes = repo
         .status(gix::progress::Discard)?
+        .index(but_core::fsmonitor::index_for_status(repo)?)
         .tree_index_track_renames(TrackRenames::Disabled)
         .index_worktree_rewrites(None)
         .index_worktree_submodules(gix::status::Submodule::Given {
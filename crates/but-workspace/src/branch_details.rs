@@ -101,6 +101,7 @@ pub fn branch_details(
                 parent_ids: commit.parent_ids().map(|id| id.to_gix()).collect(),
                 message: commit.message().unwrap_or_default().into(),
                 has_conflicts: false,
+                has_recorded_resolution: false,
                 state: CommitState::LocalAndRemote(commit.id().to_gix()),
                 created_at: u128::try_from(commit.time().seconds()).unwrap_or(0) * 1000,
                 author,
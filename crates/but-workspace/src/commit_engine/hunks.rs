@@ -130,6 +130,7 @@ impl From<DiffHunk> for HunkHeader {
             new_lines,
             // TODO(performance): if difflines are discarded, we could also just not compute them.
             diff: _,
+            highlights: _,
         }: DiffHunk,
     ) -> Self {
         HunkHeader {
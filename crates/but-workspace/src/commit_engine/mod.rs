@@ -4,6 +4,7 @@ use crate::{DiffSpec, commit_engine::reference_frame::InferenceMode};
 use anyhow::{Context, bail};
 use bstr::BString;
 use but_core::RepositoryExt;
+use but_core::unified_diff::DiffOptions;
 use but_rebase::RebaseOutput;
 use but_rebase::commit::CommitterMode;
 use but_rebase::merge::ConflictErrorContext;
@@ -172,7 +173,8 @@ pub enum RejectionReason {
 ///
 /// If `move_source` is `Some(source)`, all changes are considered to originate from the given commit to move out of, otherwise they originate from the worktree.
 /// `context_lines` is the amount of lines of context included in each [`HunkHeader`], and the value that will be used to recover the existing hunks,
-/// so that the hunks can be matched.
+/// so that the hunks can be matched. The same is true for `diff_options`, which have to be the ones used to compute the hunks
+/// in `changes`, so hunks of a diff that ignores whitespace are found and applied with the worktree content they cover.
 ///
 /// Return additional information that helps to understand to what extent the commit was created, as the commit might not contain all the [`DiffSpecs`](DiffSpec)
 /// that were requested if they failed to apply.
@@ -203,6 +205,7 @@ pub fn create_commit(
     move_source: Option<MoveSourceCommit>,
     changes: Vec<DiffSpec>,
     context_lines: u32,
+    diff_options: DiffOptions,
 ) -> anyhow::Result<CreateCommitOutcome> {
     let parents = match &destination {
         Destination::NewCommit {
@@ -230,7 +233,14 @@ pub fn create_commit(
         rejected_specs,
         destination_tree,
         changed_tree_pre_cherry_pick,
-    } = create_tree(repo, &destination, move_source, changes, context_lines)?;
+    } = create_tree(
+        repo,
+        &destination,
+        move_source,
+        changes,
+        context_lines,
+        diff_options,
+    )?;
     let new_commit = if let Some(new_tree) = destination_tree {
         match destination {
             Destination::NewCommit {
@@ -319,6 +329,7 @@ pub fn create_commit_and_update_refs(
     move_source: Option<MoveSourceCommit>,
     changes: Vec<DiffSpec>,
     context_lines: u32,
+    diff_options: DiffOptions,
 ) -> anyhow::Result<CreateCommitOutcome> {
     let mut out = create_commit(
         repo,
@@ -326,6 +337,7 @@ pub fn create_commit_and_update_refs(
         move_source,
         changes.clone(),
         context_lines,
+        diff_options,
     )?;
 
     let Some(new_commit) = out.new_commit else {
@@ -613,6 +625,7 @@ pub fn create_commit_and_update_refs_with_project(
     move_source: Option<MoveSourceCommit>,
     changes: Vec<DiffSpec>,
     context_lines: u32,
+    diff_options: DiffOptions,
     _perm: &mut WorktreeWritePermission,
 ) -> anyhow::Result<CreateCommitOutcome> {
     let vbh = VirtualBranchesHandle::new(project.gb_dir());
@@ -648,6 +661,7 @@ pub fn create_commit_and_update_refs_with_project(
         move_source,
         changes,
         context_lines,
+        diff_options,
     )?;
 
     vbh.write_file(&vb)?;
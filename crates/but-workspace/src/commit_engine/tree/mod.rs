@@ -1,6 +1,7 @@
 use crate::commit_engine::{Destination, MoveSourceCommit, RejectionReason, apply_hunks};
 use crate::{DiffSpec, HunkHeader};
 use bstr::{BStr, ByteSlice};
+use but_core::unified_diff::DiffOptions;
 use but_core::{RepositoryExt, UnifiedDiff};
 use gix::filter::plumbing::pipeline::convert::ToGitOutcome;
 use gix::merge::tree::TreatAsUnresolved;
@@ -33,6 +34,7 @@ pub fn create_tree(
     move_source: Option<MoveSourceCommit>,
     changes: Vec<DiffSpec>,
     context_lines: u32,
+    diff_options: DiffOptions,
 ) -> anyhow::Result<CreateTreeOutcome> {
     let target_tree = match destination {
         Destination::NewCommit {
@@ -75,7 +77,13 @@ pub fn create_tree(
                             .into()
                     })
                     .unwrap_or(target_tree);
-                apply_worktree_changes(changes_base_tree, repo, &mut changes, context_lines)?
+                apply_worktree_changes(
+                    changes_base_tree,
+                    repo,
+                    &mut changes,
+                    context_lines,
+                    diff_options,
+                )?
             };
 
             let Some(tree_with_changes) =
@@ -158,6 +166,7 @@ fn apply_worktree_changes<'repo>(
     repo: &'repo gix::Repository,
     changes: &mut [PossibleChange],
     context_lines: u32,
+    diff_options: DiffOptions,
 ) -> anyhow::Result<(Option<gix::Id<'repo>>, gix::ObjectId)> {
     let base_tree = actual_base_tree.attach(repo).object()?.peel_to_tree()?;
     let mut base_tree_editor = base_tree.edit()?;
@@ -228,8 +237,12 @@ fn apply_worktree_changes<'repo>(
                 "BUG: if this changes, the uses of worktree filters need a review"
             );
             // TODO(perf): avoid computing the unified diff here, we only need hunks with, usually with zero context.
-            let UnifiedDiff::Patch { hunks, .. } =
-                worktree_change.unified_diff_with_filter(repo, context_lines, &mut diff_filter)?
+            let UnifiedDiff::Patch { hunks, .. } = worktree_change.unified_diff_with_filter(
+                repo,
+                context_lines,
+                diff_options,
+                &mut diff_filter,
+            )?
             else {
                 into_err_spec(possible_change, RejectionReason::FileToLargeOrBinary);
                 continue;
@@ -244,7 +257,12 @@ fn apply_worktree_changes<'repo>(
                 let UnifiedDiff::Patch {
                     hunks: hunks_no_context,
                     ..
-                } = worktree_change.unified_diff_with_filter(repo, 0, &mut diff_filter)?
+                } = worktree_change.unified_diff_with_filter(
+                    repo,
+                    0,
+                    diff_options,
+                    &mut diff_filter,
+                )?
                 else {
                     into_err_spec(possible_change, RejectionReason::FileToLargeOrBinary);
                     continue;
@@ -0,0 +1,485 @@
+use anyhow::Context;
+use bstr::ByteSlice;
+use but_core::RefMetadata;
+use but_core::ref_metadata::{Branch, ValueInfo, Workspace};
+use gix::object::tree::EntryKind;
+use gix::refs::transaction::PreviousValue;
+use gix::refs::{FullName, FullNameRef};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+
+/// The reference pointing to the commit that holds all metadata, so it can be pushed and fetched like any other ref.
+pub const METADATA_REF: &str = "refs/gitbutler/meta";
+
+/// The suffix of each file in the metadata tree, so metadata of `refs/heads/a` and `refs/heads/a/b` can coexist
+/// as blob `refs/heads/a.toml` next to tree `refs/heads/a`.
+const ENTRY_SUFFIX: &str = ".toml";
+
+/// All metadata stored for a single reference.
+#[derive(Default, Debug, Clone, PartialEq)]
+struct Entry {
+    workspace: Option<Workspace>,
+    branch: Option<Branch>,
+}
+
+impl Entry {
+    fn is_empty(&self) -> bool {
+        self.workspace.is_none() && self.branch.is_none()
+    }
+}
+
+/// An implementation to read and write metadata from the Git repository itself, so it can be shared with
+/// `git push` and survives the loss of the application data directory.
+///
+/// Each reference with metadata is represented by a TOML file in the tree of the commit at [`METADATA_REF`],
+/// with its path being the full reference name with a `.toml` suffix. Each write creates a new commit on top of the previous one.
+///
+/// Like [`VirtualBranchesTomlMetadata`](crate::VirtualBranchesTomlMetadata), it's meant to be a short-lived item
+/// that writes changes on drop, and logs write failures.
+pub struct GitRefMetadata {
+    repo: gix::Repository,
+    /// The commit `entries` were read from, if there was one.
+    commit_id: Option<gix::ObjectId>,
+    entries: BTreeMap<FullName, Entry>,
+    changed: bool,
+}
+
+impl GitRefMetadata {
+    /// Read all metadata stored in `repo`, or start out empty if there is none yet.
+    pub fn from_repo(repo: &gix::Repository) -> anyhow::Result<Self> {
+        let mut entries = BTreeMap::new();
+        let commit_id = match repo.try_find_reference(METADATA_REF)? {
+            Some(mut reference) => {
+                let commit = reference.peel_to_commit()?;
+                let mut recorder = gix::traverse::tree::Recorder::default();
+                commit.tree()?.traverse().breadthfirst(&mut recorder)?;
+                for record in recorder.records.into_iter().filter(|r| r.mode.is_blob()) {
+                    let Some(name) = record.filepath.strip_suffix(ENTRY_SUFFIX.as_bytes()) else {
+                        tracing::warn!(
+                            "Ignoring file without '{ENTRY_SUFFIX}' suffix in metadata: '{}'",
+                            record.filepath
+                        );
+                        continue;
+                    };
+                    let ref_name = FullName::try_from(name.as_bstr()).with_context(|| {
+                        format!("Invalid reference name in metadata: '{}'", record.filepath)
+                    })?;
+                    let blob = repo.find_blob(record.oid)?;
+                    let entry: storage::Entry = toml::from_str(
+                        std::str::from_utf8(&blob.data).context("metadata must be UTF-8")?,
+                    )
+                    .with_context(|| format!("Could not parse metadata of '{ref_name}'"))?;
+                    entries.insert(ref_name, entry.try_into()?);
+                }
+                Some(commit.id)
+            }
+            None => None,
+        };
+        Ok(GitRefMetadata {
+            repo: repo.clone(),
+            commit_id,
+            entries,
+            changed: false,
+        })
+    }
+
+    /// Like [`from_repo()`](Self::from_repo()), but if `repo` doesn't have any metadata yet, import everything
+    /// from the `virtual_branches.toml` file at `toml_path`, if it exists.
+    ///
+    /// The file is left untouched so it remains usable by code that doesn't use this store yet.
+    pub fn from_repo_or_migrate_toml(
+        repo: &gix::Repository,
+        toml_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Self> {
+        let mut store = Self::from_repo(repo)?;
+        let toml_path = toml_path.as_ref();
+        if store.commit_id.is_none() && toml_path.is_file() {
+            let toml_store = crate::VirtualBranchesTomlMetadata::from_path(toml_path)?;
+            let num_imported = store.import(&toml_store)?;
+            tracing::info!(
+                "Migrated metadata of {num_imported} references from '{}'",
+                toml_path.display()
+            );
+            store.write_if_changed()?;
+        }
+        Ok(store)
+    }
+
+    /// Copy all metadata from `other` into this store, overwriting metadata of the same kind for the same references.
+    /// Return the amount of copied values.
+    pub fn import(&mut self, other: &impl RefMetadata) -> anyhow::Result<usize> {
+        let mut num_imported = 0;
+        for res in other.iter() {
+            let (ref_name, value) = res?;
+            let entry = self.entries.entry(ref_name).or_default();
+            if let Some(ws) = value.downcast_ref::<Workspace>() {
+                entry.workspace = Some(ws.clone());
+            } else if let Some(branch) = value.downcast_ref::<Branch>() {
+                entry.branch = Some(branch.clone());
+            } else {
+                continue;
+            }
+            num_imported += 1;
+            self.changed = true;
+        }
+        Ok(num_imported)
+    }
+
+    /// Write all changes as new commit to [`METADATA_REF`], if there are any.
+    ///
+    /// This fails if the reference was changed by someone else since it was read.
+    pub fn write_if_changed(&mut self) -> anyhow::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let repo = &self.repo;
+        let mut tree = repo.empty_tree().edit()?;
+        for (ref_name, entry) in self.entries.iter().filter(|(_, entry)| !entry.is_empty()) {
+            let data = toml::to_string(&storage::Entry::from(entry))?;
+            let blob_id = repo.write_blob(data.as_bytes())?;
+            let mut path = ref_name.as_bstr().to_owned();
+            path.extend_from_slice(ENTRY_SUFFIX.as_bytes());
+            tree.upsert(path.as_bstr(), EntryKind::Blob, blob_id)?;
+        }
+        let tree_id = tree.write()?.detach();
+
+        let signature = standard_signature();
+        let commit = gix::objs::Commit {
+            message: "update GitButler metadata".into(),
+            tree: tree_id,
+            author: signature.clone(),
+            committer: signature,
+            encoding: None,
+            parents: self.commit_id.into_iter().collect(),
+            extra_headers: Vec::new(),
+        };
+        let commit_id = repo.write_object(&commit)?.detach();
+        repo.reference(
+            METADATA_REF,
+            commit_id,
+            match self.commit_id {
+                Some(previous_id) => PreviousValue::MustExistAndMatch(previous_id.into()),
+                None => PreviousValue::MustNotExist,
+            },
+            "GitButler: update metadata",
+        )
+        .with_context(|| {
+            format!("Could not update '{METADATA_REF}' - was it changed elsewhere?")
+        })?;
+        self.commit_id = Some(commit_id);
+        self.changed = false;
+        Ok(())
+    }
+}
+
+impl Drop for GitRefMetadata {
+    fn drop(&mut self) {
+        if let Err(err) = self.write_if_changed() {
+            tracing::error!("Could not write back changes to metadata in Git: {err:?}");
+        }
+    }
+}
+
+impl RefMetadata for GitRefMetadata {
+    type Handle<T> = GitRefMetadataHandle<T>;
+
+    fn iter(&self) -> impl Iterator<Item = anyhow::Result<(FullName, Box<dyn Any>)>> + '_ {
+        self.entries.iter().flat_map(|(ref_name, entry)| {
+            let workspace = entry
+                .workspace
+                .clone()
+                .map(|ws| Ok((ref_name.clone(), Box::new(ws) as Box<dyn Any>)));
+            let branch = entry
+                .branch
+                .clone()
+                .map(|branch| Ok((ref_name.clone(), Box::new(branch) as Box<dyn Any>)));
+            workspace.into_iter().chain(branch)
+        })
+    }
+
+    fn workspace(&self, ref_name: &FullNameRef) -> anyhow::Result<Self::Handle<Workspace>> {
+        let value = self
+            .entries
+            .get(&ref_name.to_owned())
+            .and_then(|entry| entry.workspace.clone());
+        Ok(GitRefMetadataHandle::new(ref_name, value))
+    }
+
+    fn branch(&self, ref_name: &FullNameRef) -> anyhow::Result<Self::Handle<Branch>> {
+        let value = self
+            .entries
+            .get(&ref_name.to_owned())
+            .and_then(|entry| entry.branch.clone());
+        Ok(GitRefMetadataHandle::new(ref_name, value))
+    }
+
+    fn set_workspace(&mut self, value: &Self::Handle<Workspace>) -> anyhow::Result<()> {
+        let entry = self.entries.entry(value.ref_name.clone()).or_default();
+        if entry.workspace.as_ref() != Some(&value.value) {
+            entry.workspace = Some(value.value.clone());
+            self.changed = true;
+        }
+        Ok(())
+    }
+
+    fn set_branch(&mut self, value: &Self::Handle<Branch>) -> anyhow::Result<()> {
+        let entry = self.entries.entry(value.ref_name.clone()).or_default();
+        if entry.branch.as_ref() != Some(&value.value) {
+            entry.branch = Some(value.value.clone());
+            self.changed = true;
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, ref_name: &FullNameRef) -> anyhow::Result<bool> {
+        let existed = self
+            .entries
+            .remove(&ref_name.to_owned())
+            .is_some_and(|entry| !entry.is_empty());
+        self.changed |= existed;
+        Ok(existed)
+    }
+}
+
+pub struct GitRefMetadataHandle<T> {
+    is_default: bool,
+    ref_name: FullName,
+    value: T,
+}
+
+impl<T: Default> GitRefMetadataHandle<T> {
+    fn new(ref_name: &FullNameRef, value: Option<T>) -> Self {
+        GitRefMetadataHandle {
+            is_default: value.is_none(),
+            ref_name: ref_name.to_owned(),
+            value: value.unwrap_or_default(),
+        }
+    }
+}
+
+impl<T> AsRef<FullNameRef> for GitRefMetadataHandle<T> {
+    fn as_ref(&self) -> &FullNameRef {
+        self.ref_name.as_ref()
+    }
+}
+
+impl<T> Deref for GitRefMetadataHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for GitRefMetadataHandle<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<T> ValueInfo for GitRefMetadataHandle<T> {
+    fn is_default(&self) -> bool {
+        self.is_default
+    }
+}
+
+/// The metadata commit isn't made by the user, so it's always attributed to GitButler.
+fn standard_signature() -> gix::actor::Signature {
+    gix::actor::Signature {
+        name: "GitButler".into(),
+        email: "gitbutler@gitbutler.com".into(),
+        time: gix::date::Time::now_local_or_utc(),
+    }
+}
+
+/// The serialized form of the metadata, decoupled from the types in `but_core` so these can change freely.
+mod storage {
+    use anyhow::Context;
+    use but_core::ref_metadata;
+    use gix::refs::FullName;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Entry {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        workspace: Option<Workspace>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<Branch>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Workspace {
+        #[serde(default)]
+        ref_info: RefInfo,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_ref: Option<String>,
+        #[serde(default)]
+        stacks: Vec<WorkspaceStack>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct WorkspaceStack {
+        branches: Vec<WorkspaceStackBranch>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct WorkspaceStackBranch {
+        ref_name: String,
+        #[serde(default)]
+        archived: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Branch {
+        #[serde(default)]
+        ref_info: RefInfo,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pull_request: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        review_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stash: Option<String>,
+    }
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct RefInfo {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        created_at: Option<Time>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        updated_at: Option<Time>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Time {
+        seconds: gix::date::SecondsSinceUnixEpoch,
+        offset: gix::date::OffsetInSeconds,
+    }
+
+    impl From<&super::Entry> for Entry {
+        fn from(super::Entry { workspace, branch }: &super::Entry) -> Self {
+            Entry {
+                workspace: workspace.as_ref().map(|ws| Workspace {
+                    ref_info: (&ws.ref_info).into(),
+                    target_ref: ws.target_ref.as_ref().map(|name| name.to_string()),
+                    stacks: ws
+                        .stacks
+                        .iter()
+                        .map(|stack| WorkspaceStack {
+                            branches: stack
+                                .branches
+                                .iter()
+                                .map(|branch| WorkspaceStackBranch {
+                                    ref_name: branch.ref_name.to_string(),
+                                    archived: branch.archived,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                }),
+                branch: branch.as_ref().map(|branch| Branch {
+                    ref_info: (&branch.ref_info).into(),
+                    description: branch.description.clone(),
+                    pull_request: branch.review.pull_request,
+                    review_id: branch.review.review_id.clone(),
+                    stash: branch.stash.map(|id| id.to_string()),
+                }),
+            }
+        }
+    }
+
+    impl TryFrom<Entry> for super::Entry {
+        type Error = anyhow::Error;
+
+        fn try_from(Entry { workspace, branch }: Entry) -> Result<Self, Self::Error> {
+            let workspace = workspace
+                .map(|ws| -> anyhow::Result<_> {
+                    Ok(ref_metadata::Workspace {
+                        ref_info: ws.ref_info.into(),
+                        target_ref: ws.target_ref.map(full_name).transpose()?,
+                        stacks: ws
+                            .stacks
+                            .into_iter()
+                            .map(|stack| -> anyhow::Result<_> {
+                                Ok(ref_metadata::WorkspaceStack {
+                                    branches: stack
+                                        .branches
+                                        .into_iter()
+                                        .map(|branch| -> anyhow::Result<_> {
+                                            Ok(ref_metadata::WorkspaceStackBranch {
+                                                ref_name: full_name(branch.ref_name)?,
+                                                archived: branch.archived,
+                                            })
+                                        })
+                                        .collect::<anyhow::Result<_>>()?,
+                                })
+                            })
+                            .collect::<anyhow::Result<_>>()?,
+                    })
+                })
+                .transpose()?;
+            let branch = branch
+                .map(|branch| -> anyhow::Result<_> {
+                    Ok(ref_metadata::Branch {
+                        ref_info: branch.ref_info.into(),
+                        description: branch.description,
+                        review: ref_metadata::Review {
+                            pull_request: branch.pull_request,
+                            review_id: branch.review_id,
+                        },
+                        stash: branch
+                            .stash
+                            .map(|hex| {
+                                gix::ObjectId::from_hex(hex.as_bytes()).with_context(|| {
+                                    format!("Invalid stash commit id in metadata: '{hex}'")
+                                })
+                            })
+                            .transpose()?,
+                    })
+                })
+                .transpose()?;
+            Ok(super::Entry { workspace, branch })
+        }
+    }
+
+    impl From<&ref_metadata::RefInfo> for RefInfo {
+        fn from(info: &ref_metadata::RefInfo) -> Self {
+            RefInfo {
+                created_at: info.created_at.map(Into::into),
+                updated_at: info.updated_at.map(Into::into),
+            }
+        }
+    }
+
+    impl From<RefInfo> for ref_metadata::RefInfo {
+        fn from(info: RefInfo) -> Self {
+            ref_metadata::RefInfo {
+                created_at: info.created_at.map(Into::into),
+                updated_at: info.updated_at.map(Into::into),
+            }
+        }
+    }
+
+    impl From<gix::date::Time> for Time {
+        fn from(time: gix::date::Time) -> Self {
+            Time {
+                seconds: time.seconds,
+                offset: time.offset,
+            }
+        }
+    }
+
+    impl From<Time> for gix::date::Time {
+        fn from(time: Time) -> Self {
+            gix::date::Time::new(time.seconds, time.offset)
+        }
+    }
+
+    fn full_name(name: String) -> anyhow::Result<FullName> {
+        FullName::try_from(name.clone())
+            .with_context(|| format!("Invalid reference name in metadata: '{name}'"))
+    }
+}
@@ -57,6 +57,7 @@ pub fn head(ctx: &CommandContext) -> Result<git2::Oid> {
     let repo: &git2::Repository = ctx.repo();
 
     let mut stacks: Vec<Stack> = vb_state.list_stacks_in_workspace()?;
+    let stack_targets = vb_state.list_stack_targets()?;
 
     let target_commit = repo.find_commit(target.sha)?;
     let mut workspace_tree = repo.find_real_tree(&target_commit, Default::default())?;
@@ -64,9 +65,16 @@ pub fn head(ctx: &CommandContext) -> Result<git2::Oid> {
 
     let gix_repo = ctx.gix_repo_for_merging()?;
     let (merge_options_fail_fast, conflict_kind) = gix_repo.merge_options_fail_fast()?;
-    let merge_tree_id = git2_to_gix_object_id(repo.find_commit(target.sha)?.tree_id());
+    let default_merge_tree_id = git2_to_gix_object_id(repo.find_commit(target.sha)?.tree_id());
     for stack in stacks.iter_mut() {
         stack.migrate_change_ids(ctx).ok(); // If it fails thats ok - best effort migration
+        // Stacks with their own target only contribute their changes on top of that target.
+        let merge_tree_id = match stack_targets.get(&stack.id) {
+            Some(stack_target) => {
+                git2_to_gix_object_id(repo.find_commit(stack_target.sha)?.tree_id())
+            }
+            None => default_merge_tree_id,
+        };
         let branch_head = repo.find_commit(stack.head_oid(&gix_repo)?.to_git2())?;
         let branch_tree_id =
             git2_to_gix_object_id(repo.find_real_tree(&branch_head, Default::default())?.id());
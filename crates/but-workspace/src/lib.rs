@@ -45,6 +45,7 @@ pub use tree_manipulation::MoveChangesResult;
 pub use tree_manipulation::discard_worktree_changes::discard_workspace_changes;
 pub use tree_manipulation::move_between_commits::move_changes_between_commits;
 pub use tree_manipulation::remove_changes_from_commit_in_stack::remove_changes_from_commit_in_stack;
+pub use tree_manipulation::split_commit::split_commit_in_stack;
 pub mod head;
 pub use head::{head, merge_worktree_with_workspace};
 mod relapath;
@@ -52,17 +53,7 @@ mod relapath;
 /// 🚧utilities for applying and unapplying branches 🚧.
 pub mod branch;
 
-/// 🚧Deal with worktree changes 🚧.
-mod stash {
-    /// Information about a stash which is associated with the tip of a stack.
-    #[derive(Debug, Copy, Clone)]
-    pub enum StashStatus {
-        /// The parent reference is still present, but it doesn't point to the first parent of the *stash commit* anymore.
-        Desynced,
-        /// The parent reference could not be found. Maybe it was removed, maybe it was renamed.
-        Orphaned,
-    }
-}
+pub mod stash;
 pub use stash::StashStatus;
 
 mod commit;
@@ -86,6 +77,9 @@ pub use stacks::{
 mod virtual_branches_metadata;
 pub use virtual_branches_metadata::VirtualBranchesTomlMetadata;
 
+mod git_ref_metadata;
+pub use git_ref_metadata::{GitRefMetadata, METADATA_REF};
+
 mod branch_details;
 pub use branch_details::{branch_details, branch_details_v3};
 
@@ -94,6 +88,9 @@ pub use branch_details::{branch_details, branch_details_v3};
 #[serde(rename_all = "camelCase")]
 pub struct DiffSpec {
     /// The previous location of the entry, the source of a rename if there was one.
+    ///
+    /// Copies are identified by `path_bytes` alone as their source remains, but their `hunk_headers` are relative to
+    /// the copy source, just like they are relative to the source of a rename.
     pub previous_path_bytes: Option<BString>,
     /// The worktree-relative path to the worktree file with the content to commit.
     ///
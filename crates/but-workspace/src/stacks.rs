@@ -488,12 +488,12 @@ fn local_and_remote_commits(
     stack: &Stack,
 ) -> anyhow::Result<Vec<ui::Commit>> {
     let state = state_handle(&ctx.project().gb_dir());
-    let default_target = state
-        .get_default_target()
-        .context("failed to get default target")?;
+    let target = state
+        .get_stack_target(stack.id)
+        .context("failed to get target of stack")?;
     let cache = repo.commit_graph_if_enabled()?;
     let mut graph = repo.revision_graph(cache.as_ref());
-    let mut check_commit = IsCommitIntegrated::new(ctx, &default_target, repo, &mut graph)?;
+    let mut check_commit = IsCommitIntegrated::new(ctx, &target, repo, &mut graph)?;
 
     let branch_commits = stack_branch.commits(ctx, stack)?;
     let mut local_and_remote: Vec<ui::Commit> = vec![];
@@ -546,6 +546,7 @@ fn local_and_remote_commits(
             parent_ids: commit.parents().map(|p| p.id().to_gix()).collect(),
             message: commit.message_bstr().into(),
             has_conflicts: commit.is_conflicted(),
+            has_recorded_resolution: commit.has_recorded_resolution(),
             state,
             created_at,
             author: commit.author().into(),
@@ -0,0 +1,294 @@
+//! Shelve uncommitted changes on top of a stack, and bring them back later.
+//!
+//! A *stash commit* has the tip of the stack it was created for as first parent, and contains the stashed
+//! changes in its tree. It's referenced from the [branch metadata](but_core::ref_metadata::Branch::stash)
+//! of the top-most branch of the stack, and additionally by a reference below [`STASH_REF_PREFIX`] so it
+//! isn't garbage-collected.
+use crate::commit_engine::{self, Destination, RejectionReason};
+use crate::{DiffSpec, discard_workspace_changes, tree_manipulation::utils::update_wd_to_tree};
+use anyhow::{Context, bail};
+use bstr::{BString, ByteSlice};
+use but_core::unified_diff::DiffOptions;
+use but_core::{RefMetadata, RepositoryExt, TreeChange, ref_metadata};
+use gix::merge::tree::TreatAsUnresolved;
+use gix::refs::transaction::PreviousValue;
+
+/// Types for use in the frontend with serialization support.
+pub mod ui;
+
+/// The prefix of references that keep *stash commits* alive.
+pub const STASH_REF_PREFIX: &str = "refs/gitbutler/stashes/";
+
+/// Information about a stash which is associated with the tip of a stack.
+#[derive(Debug, Copy, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StashStatus {
+    /// The parent reference is still present, but it doesn't point to the first parent of the *stash commit* anymore.
+    Desynced,
+    /// The parent reference could not be found. Maybe it was removed, maybe it was renamed.
+    Orphaned,
+    /// The *stash commit* doesn't exist in the repository, for instance because metadata was copied without
+    /// the references that keep stashes alive. Such a stash can't be applied, only removed.
+    Missing,
+}
+
+/// A stash as returned by [`list()`].
+#[derive(Debug, Clone)]
+pub struct Stash {
+    /// The name of the branch the stash sits on top of.
+    pub ref_name: gix::refs::FullName,
+    /// The *stash commit* itself.
+    pub commit_id: gix::ObjectId,
+    /// The changes the stash would apply to the worktree, i.e. those between the stash and its first parent.
+    /// Empty if the *stash commit* is [missing](StashStatus::Missing).
+    pub changes: Vec<TreeChange>,
+    /// `None` if the stash still sits on top of `ref_name`, or information about why it doesn't.
+    pub status: Option<StashStatus>,
+}
+
+/// The result of [`create()`].
+#[derive(Debug)]
+pub struct CreateOutcome {
+    /// The newly created *stash commit*, or `None` if all changes were rejected.
+    pub stash_commit: Option<gix::ObjectId>,
+    /// Changes that couldn't be stashed, and which remain in the worktree.
+    pub rejected_specs: Vec<(RejectionReason, DiffSpec)>,
+}
+
+/// Control what to do with a stash after it was [applied](apply()).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ApplyMode {
+    /// Keep the stash so it can be applied again.
+    Apply,
+    /// Remove the stash, but only if all of its changes could be applied.
+    Pop,
+}
+
+/// The result of [`apply()`].
+#[derive(Debug)]
+pub struct ApplyOutcome {
+    /// Whole-file changes of the stash that conflicted with the worktree and thus weren't applied.
+    pub rejected_specs: Vec<(RejectionReason, DiffSpec)>,
+    /// `true` if the stash was removed after applying it.
+    pub removed: bool,
+}
+
+/// Stash `changes` from the worktree on top of the branch `ref_name`, which is assumed to be the top-most branch of its stack,
+/// and remove them from the worktree.
+/// `context_lines` are the amount of lines used to produce the hunks in `changes`.
+///
+/// It's an error if there already is a stash for `ref_name` - apply or remove it first.
+/// Changes that can't be stashed, for instance because they don't apply to the tip of `ref_name`, remain in the worktree.
+pub fn create(
+    repo: &gix::Repository,
+    ref_name: &gix::refs::FullNameRef,
+    changes: Vec<DiffSpec>,
+    context_lines: u32,
+    meta: &mut impl RefMetadata,
+) -> anyhow::Result<CreateOutcome> {
+    let mut branch = meta.branch(ref_name)?;
+    if let Some(existing) = branch.stash {
+        bail!(
+            "'{}' already has a stash at {existing} - apply or remove it first",
+            ref_name.shorten()
+        );
+    }
+    let tip = repo
+        .find_reference(ref_name)?
+        .peel_to_id_in_place()?
+        .detach();
+    let outcome = commit_engine::create_commit(
+        repo,
+        Destination::NewCommit {
+            parent_commit_id: Some(tip),
+            stack_segment: None,
+            message: format!("GitButler stash on '{}'", ref_name.shorten()),
+        },
+        None,
+        changes.clone(),
+        context_lines,
+        DiffOptions::default(),
+    )?;
+    let Some(stash_commit) = outcome.new_commit else {
+        return Ok(CreateOutcome {
+            stash_commit: None,
+            rejected_specs: outcome.rejected_specs,
+        });
+    };
+
+    repo.reference(
+        stash_ref_name(ref_name)?,
+        stash_commit,
+        PreviousValue::Any,
+        "GitButler: stash changes",
+    )?;
+    branch.stash = Some(stash_commit);
+    meta.set_branch(&branch)?;
+
+    let stashed_changes = changes.into_iter().filter(|change| {
+        !outcome
+            .rejected_specs
+            .iter()
+            .any(|(_, rejected)| rejected == change)
+    });
+    let not_removed = discard_workspace_changes(repo, stashed_changes, context_lines)?;
+    if !not_removed.is_empty() {
+        tracing::warn!(
+            ?not_removed,
+            "Stashed changes could not be removed from the worktree"
+        );
+    }
+    Ok(CreateOutcome {
+        stash_commit: Some(stash_commit),
+        rejected_specs: outcome.rejected_specs,
+    })
+}
+
+/// List all stashes known to `meta`, along with the changes they contain.
+pub fn list(repo: &gix::Repository, meta: &impl RefMetadata) -> anyhow::Result<Vec<Stash>> {
+    let mut out = Vec::new();
+    for res in meta.iter() {
+        let (ref_name, value) = res?;
+        let Some(commit_id) = value
+            .downcast_ref::<ref_metadata::Branch>()
+            .and_then(|branch| branch.stash)
+        else {
+            continue;
+        };
+        let Some(commit) = repo.try_find_object(commit_id)? else {
+            out.push(Stash {
+                ref_name,
+                commit_id,
+                changes: Vec::new(),
+                status: Some(StashStatus::Missing),
+            });
+            continue;
+        };
+        let parent_id = commit
+            .try_into_commit()?
+            .parent_ids()
+            .next()
+            .map(|id| id.detach());
+        let status = match repo.try_find_reference(ref_name.as_ref())? {
+            None => Some(StashStatus::Orphaned),
+            Some(mut reference) => (Some(reference.peel_to_id_in_place()?.detach()) != parent_id)
+                .then_some(StashStatus::Desynced),
+        };
+        let (changes, _stats) = but_core::diff::tree_changes(repo, parent_id, commit_id)?;
+        out.push(Stash {
+            ref_name,
+            commit_id,
+            changes,
+            status,
+        });
+    }
+    Ok(out)
+}
+
+/// Apply the stash of `ref_name` to the worktree, merging it with all uncommitted changes that are already present.
+/// Changes to files that conflict are not applied and returned as rejected instead.
+///
+/// With [`ApplyMode::Pop`], the stash is removed afterwards, unless some of its changes were rejected.
+/// If the *stash commit* is [missing](StashStatus::Missing), the stash is removed and an error is returned.
+pub fn apply(
+    repo: &gix::Repository,
+    ref_name: &gix::refs::FullNameRef,
+    mode: ApplyMode,
+    meta: &mut impl RefMetadata,
+) -> anyhow::Result<ApplyOutcome> {
+    let branch = meta.branch(ref_name)?;
+    let stash_commit = branch
+        .stash
+        .with_context(|| format!("'{}' doesn't have a stash", ref_name.shorten()))?;
+    let Some(stash_object) = repo.try_find_object(stash_commit)? else {
+        remove(repo, ref_name, meta)?;
+        bail!(
+            "The stash commit {stash_commit} of '{}' doesn't exist anymore - the stash was removed",
+            ref_name.shorten()
+        );
+    };
+    let stash_commit = stash_object.try_into_commit()?;
+    let base_tree = match stash_commit.parent_ids().next() {
+        Some(parent_id) => repo.find_commit(parent_id)?.tree_id()?.detach(),
+        None => gix::ObjectId::empty_tree(repo.object_hash()),
+    };
+    let worktree_tree = but_status::create_wd_tree(repo, 0)?;
+
+    let mut rejected_specs = Vec::new();
+    let mut stash_tree = stash_commit.tree_id()?.detach();
+    let new_worktree_tree = loop {
+        let mut merge = repo.merge_trees(
+            base_tree,
+            worktree_tree,
+            stash_tree,
+            repo.default_merge_labels(),
+            repo.tree_merge_options()?,
+        )?;
+        let conflicting_paths: Vec<BString> = merge
+            .conflicts
+            .iter()
+            .filter(|c| c.is_unresolved(TreatAsUnresolved::git()))
+            .map(|c| c.theirs.location().to_owned())
+            .collect();
+        if conflicting_paths.is_empty() {
+            break merge.tree.write()?.detach();
+        }
+
+        // Undo the stashed changes to conflicting paths so everything else can still be applied.
+        let base_tree = repo.find_tree(base_tree)?;
+        let mut editor = repo.edit_tree(stash_tree)?;
+        for path in conflicting_paths {
+            match base_tree.lookup_entry(path.split_str("/"))? {
+                Some(entry) => {
+                    editor.upsert(path.as_bstr(), entry.mode().kind(), entry.object_id())?
+                }
+                None => editor.remove(path.as_bstr())?,
+            };
+            rejected_specs.push((
+                RejectionReason::CherryPickMergeConflict,
+                DiffSpec {
+                    previous_path_bytes: None,
+                    path_bytes: path,
+                    hunk_headers: Vec::new(),
+                },
+            ));
+        }
+        let reduced_stash_tree = editor.write()?.detach();
+        if reduced_stash_tree == stash_tree {
+            bail!("BUG: conflicts persisted after removing conflicting paths from the stash");
+        }
+        stash_tree = reduced_stash_tree;
+    };
+    update_wd_to_tree(repo, new_worktree_tree)?;
+
+    let removed = mode == ApplyMode::Pop && rejected_specs.is_empty();
+    if removed {
+        remove(repo, ref_name, meta)?;
+    }
+    Ok(ApplyOutcome {
+        rejected_specs,
+        removed,
+    })
+}
+
+/// Remove the stash of `ref_name` without applying it, and return `true` if there was one.
+/// This also works if the *stash commit* is [missing](StashStatus::Missing).
+pub fn remove(
+    repo: &gix::Repository,
+    ref_name: &gix::refs::FullNameRef,
+    meta: &mut impl RefMetadata,
+) -> anyhow::Result<bool> {
+    let mut branch = meta.branch(ref_name)?;
+    if branch.stash.take().is_none() {
+        return Ok(false);
+    }
+    meta.set_branch(&branch)?;
+    if let Some(stash_ref) = repo.try_find_reference(stash_ref_name(ref_name)?.as_ref())? {
+        stash_ref.delete()?;
+    }
+    Ok(true)
+}
+
+fn stash_ref_name(ref_name: &gix::refs::FullNameRef) -> anyhow::Result<gix::refs::FullName> {
+    Ok(format!("{STASH_REF_PREFIX}{}", ref_name.shorten()).try_into()?)
+}
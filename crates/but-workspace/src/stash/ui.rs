@@ -0,0 +1,94 @@
+#![allow(missing_docs)]
+use crate::commit_engine::RejectionReason;
+use crate::stash::StashStatus;
+use gitbutler_serde::BStringForFrontend;
+use serde::Serialize;
+
+/// The JSON serializable type of [super::Stash].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stash {
+    /// The name of the branch the stash sits on top of.
+    #[serde(with = "gitbutler_serde::bstring_lossy")]
+    pub ref_name: bstr::BString,
+    /// The *stash commit* itself.
+    #[serde(with = "gitbutler_serde::object_id")]
+    pub commit_id: gix::ObjectId,
+    /// The changes the stash would apply to the worktree.
+    pub changes: Vec<but_core::ui::TreeChange>,
+    /// `None` if the stash still sits on top of its branch.
+    pub status: Option<StashStatus>,
+}
+
+impl From<super::Stash> for Stash {
+    fn from(
+        super::Stash {
+            ref_name,
+            commit_id,
+            changes,
+            status,
+        }: super::Stash,
+    ) -> Self {
+        Stash {
+            ref_name: ref_name.into_inner(),
+            commit_id,
+            changes: changes.into_iter().map(Into::into).collect(),
+            status,
+        }
+    }
+}
+
+/// The JSON serializable type of [super::CreateOutcome].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOutcome {
+    /// Paths that contained at least one change that couldn't be stashed, along with the reason for the rejection.
+    pub paths_to_rejected_changes: Vec<(RejectionReason, BStringForFrontend)>,
+    /// The newly created *stash commit*, if there was one.
+    #[serde(with = "gitbutler_serde::object_id_opt")]
+    pub stash_commit: Option<gix::ObjectId>,
+}
+
+impl From<super::CreateOutcome> for CreateOutcome {
+    fn from(
+        super::CreateOutcome {
+            stash_commit,
+            rejected_specs,
+        }: super::CreateOutcome,
+    ) -> Self {
+        CreateOutcome {
+            paths_to_rejected_changes: rejected_specs
+                .into_iter()
+                .map(|(reason, spec)| (reason, spec.path_bytes.into()))
+                .collect(),
+            stash_commit,
+        }
+    }
+}
+
+/// The JSON serializable type of [super::ApplyOutcome].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyOutcome {
+    /// Paths whose stashed changes conflicted with the worktree, along with the reason for the rejection.
+    pub paths_to_rejected_changes: Vec<(RejectionReason, BStringForFrontend)>,
+    /// `true` if the stash was removed after applying it.
+    pub removed: bool,
+}
+
+impl From<super::ApplyOutcome> for ApplyOutcome {
+    fn from(
+        super::ApplyOutcome {
+            rejected_specs,
+            removed,
+        }: super::ApplyOutcome,
+    ) -> Self {
+        ApplyOutcome {
+            paths_to_rejected_changes: rejected_specs
+                .into_iter()
+                .map(|(reason, spec)| (reason, spec.path_bytes.into()))
+                .collect(),
+            removed,
+        }
+    }
+}
@@ -12,7 +12,8 @@ pub struct MoveChangesResult {
 pub(super) mod discard_worktree_changes;
 pub(super) mod move_between_commits;
 pub(super) mod remove_changes_from_commit_in_stack;
+pub(super) mod split_commit;
 
 mod file;
 pub(crate) mod hunk;
-mod utils;
+pub(crate) mod utils;
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result, bail};
+use but_core::commit::HeadersV2;
+use but_rebase::commit::CommitterMode;
+use but_rebase::{Rebase, RebaseStep};
+use gitbutler_command_context::CommandContext;
+use gitbutler_stack::{StackId, VirtualBranchesHandle};
+use gix::prelude::ObjectIdExt;
+
+use crate::{
+    DiffSpec,
+    stack_ext::StackExt,
+    tree_manipulation::utils::{
+        ChangesSource, create_tree_without_diff, rebase_mapping_with_overrides,
+    },
+};
+
+use super::MoveChangesResult;
+
+/// Split the commit `source_commit_id` into as many sequential commits as there are `groups`, each with the given message
+/// and containing the changes of the respective group.
+///
+/// The first of the new commits takes the place of `source_commit_id` and retains its change-id, while all following commits
+/// receive a new one. Changes that aren't mentioned in any group end up in the first commit, so that the tree of the last commit
+/// is always the tree of `source_commit_id`.
+///
+/// All descendants of `source_commit_id` in the stack are rebased on top of the last new commit, and the
+/// [replaced commits](MoveChangesResult::replaced_commits) map `source_commit_id` to the first of the new commits.
+///
+/// `context_lines` is the amount of lines of context that were used to produce the hunks in the `groups`.
+///
+/// ## Assumptions
+///
+/// Just like [`remove_changes_from_commit_in_stack()`](crate::remove_changes_from_commit_in_stack), this only updates the
+/// given stack, so you may want to call `update_workspace_commit` afterwards.
+pub fn split_commit_in_stack(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    source_commit_id: gix::ObjectId,
+    groups: Vec<(String, Vec<DiffSpec>)>,
+    context_lines: u32,
+) -> Result<MoveChangesResult> {
+    if groups.len() < 2 {
+        bail!("A commit must be split into at least two commits");
+    }
+    if groups.iter().any(|(_, changes)| changes.is_empty()) {
+        bail!("Each commit to split into must receive at least one change");
+    }
+    for (idx, (_, changes)) in groups.iter().enumerate() {
+        let overlaps = groups[idx + 1..].iter().any(|(_, later_changes)| {
+            changes
+                .iter()
+                .any(|a| later_changes.iter().any(|b| changes_overlap(a, b)))
+        });
+        if overlaps {
+            bail!("Each change must only be part of one commit to split into");
+        }
+    }
+
+    let vb_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
+    let stack = vb_state.get_stack(stack_id)?;
+    let repository = ctx.gix_repo()?;
+
+    let source_commit = but_core::Commit::from_id(source_commit_id.attach(&repository))?;
+    if source_commit.is_conflicted() {
+        bail!("Cannot split conflicted commit {source_commit_id}");
+    }
+    let mut parent_id = match source_commit.parents.as_slice() {
+        [parent_id] => *parent_id,
+        _ => bail!("Can only split commits with exactly one parent"),
+    };
+
+    // The tree of each new commit is the tree of the source commit without the changes of all later groups.
+    let mut trees = Vec::with_capacity(groups.len());
+    for num_earlier_groups in 1..groups.len() {
+        let (tree, dropped_diffs) = create_tree_without_diff(
+            &repository,
+            ChangesSource::Commit {
+                id: source_commit_id,
+            },
+            groups
+                .iter()
+                .skip(num_earlier_groups)
+                .flat_map(|(_, changes)| changes.iter().cloned()),
+            context_lines,
+        )?;
+        if !dropped_diffs.is_empty() {
+            bail!("Failed to extract described changes from commit {source_commit_id}");
+        }
+        trees.push(tree);
+    }
+    trees.push(source_commit.tree);
+
+    let mut new_commits = Vec::with_capacity(groups.len());
+    for (idx, ((message, _changes), tree)) in groups.into_iter().zip(trees).enumerate() {
+        let mut commit = (*source_commit).clone();
+        commit.tree = tree;
+        commit.message = message.into();
+        commit.parents = vec![parent_id].into();
+        if idx > 0 {
+            HeadersV2::default().set_in_commit(&mut commit);
+        }
+        parent_id = but_rebase::commit::create(&repository, commit, CommitterMode::Update)?;
+        new_commits.push(parent_id);
+    }
+
+    let mut steps = stack.as_rebase_steps(ctx, &repository)?;
+    let position = steps
+        .iter()
+        .position(|step| step.commit_id() == Some(&source_commit_id))
+        .with_context(|| format!("Commit {source_commit_id} isn't part of the stack"))?;
+    steps.splice(
+        position..=position,
+        new_commits.iter().map(|commit_id| RebaseStep::Pick {
+            commit_id: *commit_id,
+            new_message: None,
+        }),
+    );
+
+    let base = stack.merge_base(ctx)?;
+    let mut rebase = Rebase::new(&repository, base, None)?;
+    rebase.steps(steps)?;
+    rebase.rebase_noops(false);
+    let result = rebase.rebase()?;
+    let commit_mapping = rebase_mapping_with_overrides(
+        &result,
+        new_commits
+            .first()
+            .map(|first_commit| (source_commit_id, *first_commit)),
+    );
+
+    let mut stack = stack;
+    stack.set_heads_from_rebase_output(ctx, result.references)?;
+
+    Ok(MoveChangesResult {
+        replaced_commits: commit_mapping.into_iter().collect(),
+    })
+}
+
+/// Return `true` if `a` and `b` refer to at least some of the same changes.
+fn changes_overlap(a: &DiffSpec, b: &DiffSpec) -> bool {
+    if a.path_bytes != b.path_bytes {
+        return false;
+    }
+    // No hunks means the whole file.
+    if a.hunk_headers.is_empty() || b.hunk_headers.is_empty() {
+        return true;
+    }
+    let ranges_intersect = |a_start: u32, a_lines: u32, b_start: u32, b_lines: u32| {
+        a_start < b_start + b_lines && b_start < a_start + a_lines
+    };
+    a.hunk_headers.iter().any(|a| {
+        b.hunk_headers.iter().any(|b| {
+            a == b
+                || ranges_intersect(a.old_start, a.old_lines, b.old_start, b.old_lines)
+                || ranges_intersect(a.new_start, a.new_lines, b.new_start, b.new_lines)
+        })
+    })
+}
@@ -146,11 +146,11 @@ pub(crate) fn update_wd_to_tree(
                 // Work tree has the file but the source tree doesn't.
                 std::fs::remove_file(path_check.verified_path(&change.path)?)?;
             }
-            TreeStatus::Addition { .. } => {
+            TreeStatus::Addition { .. } | TreeStatus::Copy { .. } => {
                 let entry = source_tree
                     .lookup_entry(change.path.clone().split_str("/"))?
                     .context("path must exist")?;
-                // Work tree doesn't have the file but the source tree does.
+                // Work tree doesn't have the file but the source tree does, and copy sources are left alone.
                 write_entry(
                     change.path.as_bstr(),
                     &entry,
@@ -139,6 +139,9 @@ pub struct Commit {
     /// GitButler will perform rebasing/reordering etc without interruptions and flag commits as conflicted if needed.
     /// Conflicts are resolved via the Edit Mode mechanism.
     pub has_conflicts: bool,
+    /// Whether the conflicts of this commit were resolved automatically by reusing recorded resolutions.
+    /// Such commits should be reviewed by the user.
+    pub has_recorded_resolution: bool,
     /// Represents whether the commit is considered integrated, local only,
     /// or local and remote with respect to the branch it belongs to.
     /// Note that remote only commits in the context of a branch are expressed with the [`UpstreamCommit`] struct instead of this.
@@ -157,6 +160,7 @@ impl TryFrom<gix::Commit<'_>> for Commit {
             parent_ids: commit.parent_ids().map(|id| id.detach()).collect(),
             message: commit.message_raw_sloppy().into(),
             has_conflicts: false,
+            has_recorded_resolution: false,
             state: CommitState::LocalAndRemote(commit.id),
             created_at: u128::try_from(commit.time()?.seconds)? * 1000,
             author: commit.author()?.into(),
@@ -181,6 +181,7 @@ impl RefMetadata for VirtualBranchesTomlMetadata {
                     pull_request: branch.pr_number,
                     review_id: branch.review_id.clone(),
                 },
+                stash: branch.stash,
             },
         })
     }
@@ -305,6 +306,7 @@ impl RefMetadata for VirtualBranchesTomlMetadata {
                     pr_number,
                     archived,
                     review_id,
+                    stash,
                     ..
                 } = stack
                     .heads
@@ -320,6 +322,7 @@ impl RefMetadata for VirtualBranchesTomlMetadata {
                 *description = value.description.clone();
                 *pr_number = value.review.pull_request;
                 *review_id = value.review.review_id.clone();
+                *stash = value.stash;
                 stack.in_workspace = stack_branch.is_some();
                 if let Some(stack_branch) = stack_branch {
                     *archived = stack_branch.archived;
@@ -513,14 +516,17 @@ fn branch_to_stack_branch(
         ref_info: _, // TODO: should change parent stack if it's the top.
         description,
         review,
+        stash,
     }: &Branch,
     archived: bool,
 ) -> gitbutler_stack::StackBranch {
-    gitbutler_stack::StackBranch::new_with_zero_head(
+    let mut branch = gitbutler_stack::StackBranch::new_with_zero_head(
         ref_name.shorten().to_string(),
         description.clone(),
         review.pull_request,
         review.review_id.clone(),
         archived,
-    )
+    );
+    branch.stash = *stash;
+    branch
 }
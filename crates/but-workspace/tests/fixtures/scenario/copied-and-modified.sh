@@ -0,0 +1,14 @@
+#!/usr/bin/env bash
+
+### Description
+# A file that was copied and modified in two places, with copy-tracking enabled. The source of the copy was modified as well.
+set -eu -o pipefail
+
+git init
+git config diff.renames copies
+printf '1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n' >original
+git add . && git commit -m "init"
+
+cp original copy
+echo 11 >>original
+printf '1\n2\nthree\n4\n5\n6\n7\n8\nnine\n10\n' >copy
@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+
+### Description
+# A file with a line whose indentation changed, directly followed by a line whose content changed.
+set -eu -o pipefail
+
+git init
+printf 'fn main() {\n    let a = 1;\n    let b = 2;\n    println!("{a}");\n}\n' >file
+git add . && git commit -m "init"
+
+printf 'fn main() {\n    let a = 1;\n  let b = 2;\n    println!("{a} {b}");\n}\n' >file
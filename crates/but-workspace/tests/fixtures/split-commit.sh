@@ -0,0 +1,36 @@
+#!/usr/bin/env bash
+set -eu -o pipefail
+CLI=${1:?The first argument is the GitButler CLI}
+
+
+git init remote
+(cd remote
+  echo a > file
+  git add . && git commit -m "init"
+)
+
+export GITBUTLER_CLI_DATA_DIR=../user/gitbutler/app-data
+
+# Scenario:
+# - commit 2 (a-branch-2), adds file4
+# - commit 1 (my_stack), adds file1, file2 and file3
+git clone remote split
+(cd split
+  git config user.name "Author"
+  git config user.email "author@example.com"
+
+  git branch existing-branch
+  $CLI project add --switch-to-workspace "$(git rev-parse --symbolic-full-name @{u})"
+
+  $CLI branch create --set-default my_stack
+
+  echo change1 > file1
+  echo change2 > file2
+  echo change3 > file3
+  $CLI branch commit my_stack -m "commit 1"
+
+  $CLI branch series my_stack -s "a-branch-2"
+
+  echo change4 > file4
+  $CLI branch commit my_stack -m "commit 2"
+)
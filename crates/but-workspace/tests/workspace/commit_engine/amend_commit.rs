@@ -3,6 +3,7 @@ use crate::utils::{
     read_only_in_memory_scenario, visualize_commit, visualize_tree, writable_scenario,
     writable_scenario_with_ssh_key, write_local_config, write_sequence,
 };
+use but_core::unified_diff::DiffOptions;
 use but_testsupport::assure_stable_env;
 use but_workspace::{DiffSpec, HunkHeader, commit_engine::Destination};
 
@@ -168,6 +169,7 @@ fn new_file_and_deletion_onto_merge_commit_with_hunks() -> anyhow::Result<()> {
             },
         ],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, vec![]);
     let tree = visualize_tree(&repo, &outcome)?;
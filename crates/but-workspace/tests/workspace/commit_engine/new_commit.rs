@@ -4,6 +4,7 @@ use crate::utils::{
     to_change_specs_whole_file, visualize_tree, writable_scenario, writable_scenario_with_ssh_key,
     write_sequence,
 };
+use but_core::unified_diff::{DiffOptions, WhitespaceMode};
 use but_testsupport::assure_stable_env;
 use but_workspace::{DiffSpec, commit_engine};
 use commit_engine::Destination;
@@ -111,6 +112,7 @@ fn from_unborn_head_with_selection() -> anyhow::Result<()> {
             hunk_headers: vec![hunk_header("-1,0", "+1,1")],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     let tree = visualize_tree(&repo, &outcome)?;
@@ -136,6 +138,7 @@ fn from_unborn_head_with_selection() -> anyhow::Result<()> {
             hunk_headers: vec![hunk_header("-0,0", "+4,3")],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(
         outcome.rejected_specs,
@@ -165,6 +168,7 @@ fn from_unborn_head_with_selection() -> anyhow::Result<()> {
             ],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(
         outcome.rejected_specs,
@@ -262,6 +266,7 @@ fn unborn_with_added_submodules() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(worktree_changes),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     assert_eq!(
@@ -457,6 +462,7 @@ fn renames_with_selections() -> anyhow::Result<()> {
             diff_spec(None, "link", None),
         ],
         UI_CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, [], "everything was assigned");
 
@@ -546,6 +552,7 @@ fn modification_with_complex_selection() -> anyhow::Result<()> {
             ],
         )],
         UI_CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, [], "everything was assigned");
 
@@ -589,6 +596,7 @@ fn modification_with_complex_selection() -> anyhow::Result<()> {
             ],
         )],
         UI_CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, [], "everything was assigned");
 
@@ -601,6 +609,99 @@ fn modification_with_complex_selection() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn hunk_of_diff_ignoring_whitespace() -> anyhow::Result<()> {
+    assure_stable_env();
+
+    let repo = read_only_in_memory_scenario("whitespace-and-content-modifications")?;
+    let ignore_whitespace = DiffOptions {
+        whitespace: WhitespaceMode::IgnoreAll,
+        ..Default::default()
+    };
+    let change = &but_core::diff::worktree_changes(&repo)?.changes[0];
+    let but_core::UnifiedDiff::Patch { hunks, .. } =
+        change.unified_diff_with_options(&repo, 0, ignore_whitespace)?
+    else {
+        unreachable!("we know it's a patch")
+    };
+    insta::assert_debug_snapshot!(hunks, @r#"
+    [
+        DiffHunk("@@ -4,1 +4,1 @@
+        -    println!("{a}");
+        +    println!("{a} {b}");
+        "),
+    ]
+    "#);
+
+    let destination = Destination::NewCommit {
+        parent_commit_id: Some(repo.head_id()?.into()),
+        message: "commit only the content change".into(),
+        stack_segment: None,
+    };
+    let specs = vec![DiffSpec {
+        previous_path_bytes: None,
+        path_bytes: "file".into(),
+        hunk_headers: hunks.into_iter().map(Into::into).collect(),
+    }];
+    let outcome = commit_engine::create_commit(
+        &repo,
+        destination.clone(),
+        None,
+        specs.clone(),
+        0,
+        DiffOptions::default(),
+    )?;
+    assert_eq!(
+        outcome.new_commit, None,
+        "without ignoring whitespace, the hunk is part of a bigger one and can't be found"
+    );
+
+    let outcome =
+        commit_engine::create_commit(&repo, destination, None, specs, 0, ignore_whitespace)?;
+    assert_eq!(outcome.rejected_specs, [], "everything was assigned");
+    insta::assert_snapshot!(visualize_tree(&repo, &outcome)?, @r#"
+    d58834f
+    └── file:100644:074e8bd "fn main() {\n    let a = 1;\n    let b = 2;\n    println!(\"{a} {b}\");\n}\n"
+    "#);
+    Ok(())
+}
+
+#[test]
+fn hunk_of_copy() -> anyhow::Result<()> {
+    assure_stable_env();
+
+    let repo = read_only_in_memory_scenario("copied-and-modified")?;
+    let changes = but_core::diff::worktree_changes(&repo)?.changes;
+    let but_core::TreeStatus::Copy { source_path, .. } = &changes[0].status else {
+        unreachable!("copy-tracking is enabled in the repository configuration")
+    };
+    assert_eq!(source_path, "original");
+
+    let outcome = commit_engine::create_commit(
+        &repo,
+        Destination::NewCommit {
+            parent_commit_id: Some(repo.head_id()?.into()),
+            message: "commit the copy with only one of its changes".into(),
+            stack_segment: None,
+        },
+        None,
+        vec![DiffSpec {
+            previous_path_bytes: None,
+            path_bytes: "copy".into(),
+            hunk_headers: vec![hunk_header("-3,1", "+3,1")],
+        }],
+        0,
+        DiffOptions::default(),
+    )?;
+    assert_eq!(outcome.rejected_specs, [], "everything was assigned");
+    insta::assert_snapshot!(visualize_tree(&repo, &outcome)?, @r#"
+    979af9f
+    ├── copy:100644:3180775 "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n"
+    └── original:100644:f00c965 "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n"
+    "#);
+    Ok(())
+}
+
 #[test]
 fn submodule_typechanges() -> anyhow::Result<()> {
     assure_stable_env();
@@ -670,6 +771,7 @@ fn submodule_typechanges() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(worktree_changes),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     assert_eq!(
@@ -735,6 +837,7 @@ fn commit_to_one_below_tip_with_three_context_lines() -> anyhow::Result<()> {
                 context_lines,
             )?,
             context_lines,
+            DiffOptions::default(),
         )?;
 
         assert_eq!(
@@ -1089,6 +1192,7 @@ fn validate_no_change_on_noop() -> anyhow::Result<()> {
         None,
         specs.clone(),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(
         outcome.new_commit, None,
@@ -8,6 +8,7 @@ use crate::utils::{
     to_change_specs_whole_file, visualize_index, visualize_index_with_content, visualize_tree,
     worktree_changes_with_diffs, writable_scenario, writable_scenario_with_ssh_key, write_sequence,
 };
+use but_core::unified_diff::DiffOptions;
 use but_testsupport::{assure_stable_env, visualize_commit_graph};
 use but_workspace::DiffSpec;
 use but_workspace::commit_engine::{Destination, ReferenceFrame, StackSegmentId};
@@ -36,6 +37,7 @@ fn new_commits_to_tip_from_unborn_head() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     let new_commit_id = outcome.new_commit.expect("a new commit was created");
@@ -69,6 +71,7 @@ fn new_commits_to_tip_from_unborn_head() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     // The HEAD reference was updated.
     insta::assert_snapshot!(graph_commit_outcome(&repo, &outcome)?, @r"
@@ -107,6 +110,7 @@ fn new_commits_to_tip_from_unborn_head() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     // The HEAD reference was updated, along with all other tag-references that pointed to it.
@@ -132,6 +136,7 @@ fn new_commits_to_tip_from_unborn_head() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     assure_no_worktree_changes(&repo)?;
@@ -185,6 +190,7 @@ fn new_commits_to_tip_from_unborn_head() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     // Updated references are visible (but probably nobody needs them).
@@ -300,6 +306,7 @@ fn new_stack_receives_commit_and_adds_it_to_workspace_commit() -> anyhow::Result
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -375,6 +382,7 @@ fn first_partial_commit_to_tip_from_unborn_head() -> anyhow::Result<()> {
             hunk_headers: vec![hunk_header("-0,0", "+1,2")],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     insta::assert_snapshot!(visualize_tree(&repo, &outcome)?, @r#"
@@ -431,6 +439,7 @@ fn first_partial_commit_to_tip_from_unborn_head() -> anyhow::Result<()> {
             hunk_headers: vec![hunk_header("-0,0", "+4,1")],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     insta::assert_snapshot!(visualize_tree(&repo, &outcome)?, @r#"
     f9cc7d6
@@ -512,6 +521,7 @@ fn first_partial_commit_to_tip_from_unborn_head() -> anyhow::Result<()> {
             },
         ],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     let head_commit = outcome.new_commit.unwrap();
@@ -600,6 +610,7 @@ fn insert_commit_into_single_stack_with_signatures() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     // it rewrites the history to the top of the stack.
@@ -696,6 +707,7 @@ fn insert_commit_into_single_stack_with_signatures() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     let rewritten_head_id = repo.head_id()?;
     insta::assert_snapshot!(visualize_commit_graph(&repo, rewritten_head_id)?, @r"
@@ -756,6 +768,7 @@ fn branch_tip_below_non_merge_workspace_commit() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -809,6 +822,7 @@ fn deletions() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     insta::assert_snapshot!(visualize_tree(&repo, &outcome)?, @r#"
@@ -859,6 +873,7 @@ fn insert_commits_into_workspace() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -931,6 +946,7 @@ fn insert_commits_into_workspace_with_conflict() -> anyhow::Result<()> {
         None,
         to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )
     .expect("the rebase engine should communicate the merge-conflict failure");
     // The failing path is clearly communicated.
@@ -1053,6 +1069,7 @@ fn workspace_commit_with_merge_conflict() -> anyhow::Result<()> {
             None,
             to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
             CONTEXT_LINES,
+            DiffOptions::default(),
         )
         .expect("merge fails but we make it observable");
         insta::allow_duplicates! {
@@ -1135,6 +1152,7 @@ fn merge_commit_remains_unsigned_in_remerge() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -1223,6 +1241,7 @@ fn two_commits_three_buckets_disambiguate_insertion_position_to_one_below_top()
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -1281,6 +1300,7 @@ fn two_commits_three_buckets_disambiguate_insertion_position_to_top() -> anyhow:
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -1356,6 +1376,7 @@ fn commit_on_top_of_branch_in_workspace() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -1438,6 +1459,7 @@ fn commit_on_top_of_branch_in_workspace() -> anyhow::Result<()> {
             hunk_headers: vec![hunk_header("-22,5", "+0,0")],
         }],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, vec![]);
 
@@ -1508,6 +1530,7 @@ fn commit_on_top_of_branch_in_workspace() -> anyhow::Result<()> {
         None,
         vec![],
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     assert_eq!(outcome.rejected_specs, vec![], "nothing to reject");
 
@@ -1572,6 +1595,7 @@ fn amend_on_top_of_branch_in_workspace() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     write_vrbranches_to_refs(&vb, &repo)?;
@@ -1644,6 +1668,7 @@ fn amend_edit_message_only() -> anyhow::Result<()> {
         None,
         to_change_specs_all_hunks(&repo, but_core::diff::worktree_changes(&repo)?)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     assert!(
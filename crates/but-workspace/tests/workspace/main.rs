@@ -2,5 +2,6 @@ mod branch_details;
 mod commit_engine;
 mod head_info;
 mod ref_metadata;
+mod stash;
 mod tree_manipulation;
 mod utils;
@@ -183,6 +183,7 @@ mod virtual_branches_toml {
                     ),
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -199,6 +200,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -215,6 +217,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -231,6 +234,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -247,6 +251,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -263,6 +268,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -279,6 +285,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -295,6 +302,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -311,6 +319,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -327,6 +336,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -343,6 +353,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -359,6 +370,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -375,6 +387,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
             Branch {
                 ref_info: RefInfo {
@@ -391,6 +404,7 @@ mod virtual_branches_toml {
                     pull_request: None,
                     review_id: None,
                 },
+                stash: None,
             },
         ]
         ");
@@ -928,6 +942,115 @@ mod virtual_branches_toml {
     }
 }
 
+mod git_refs {
+    use crate::ref_metadata::roundtrip_journey;
+    use but_core::RefMetadata;
+    use but_core::ref_metadata::ValueInfo;
+    use but_testsupport::gix_testtools::tempfile::{TempDir, tempdir};
+    use but_workspace::{GitRefMetadata, METADATA_REF};
+    use std::path::PathBuf;
+
+    #[test]
+    fn migrate_from_toml_and_journey() -> anyhow::Result<()> {
+        let (repo, _tmp) = empty_repo()?;
+        let mut store =
+            GitRefMetadata::from_repo_or_migrate_toml(&repo, vb_fixture("virtual-branches-01"))?;
+        assert_eq!(store.iter().count(), 15, "everything was imported");
+        assert!(
+            repo.try_find_reference(METADATA_REF)?.is_some(),
+            "the migration is written immediately"
+        );
+
+        let reopened = GitRefMetadata::from_repo(&repo)?;
+        let ws = reopened.workspace("refs/heads/gitbutler/workspace".try_into()?)?;
+        assert!(!ws.is_default(), "value read back from Git");
+        assert_eq!(
+            *ws,
+            *store.workspace("refs/heads/gitbutler/workspace".try_into()?)?,
+            "the stored value round-trips"
+        );
+        drop(reopened);
+
+        roundtrip_journey(&mut store)?;
+        drop(store);
+
+        let store = GitRefMetadata::from_repo(&repo)?;
+        assert_eq!(store.iter().count(), 0, "on drop we write the changes");
+        Ok(())
+    }
+
+    #[test]
+    fn migration_is_skipped_if_metadata_exists() -> anyhow::Result<()> {
+        let (repo, _tmp) = empty_repo()?;
+        let mut store = GitRefMetadata::from_repo(&repo)?;
+        let mut branch = store.branch("refs/heads/feat".try_into()?)?;
+        assert!(branch.is_default(), "nothing is stored yet");
+        branch.description = Some("a description".into());
+        store.set_branch(&branch)?;
+        drop(store);
+
+        let store =
+            GitRefMetadata::from_repo_or_migrate_toml(&repo, vb_fixture("virtual-branches-01"))?;
+        assert_eq!(store.iter().count(), 1, "no migration took place");
+        let branch = store.branch("refs/heads/feat".try_into()?)?;
+        assert_eq!(branch.description.as_deref(), Some("a description"));
+        Ok(())
+    }
+
+    #[test]
+    fn references_nested_in_other_references_have_their_own_metadata() -> anyhow::Result<()> {
+        let (repo, _tmp) = empty_repo()?;
+        let mut store = GitRefMetadata::from_repo(&repo)?;
+        for (ref_name, description) in
+            [("refs/heads/foo", "outer"), ("refs/heads/foo/bar", "inner")]
+        {
+            let mut branch = store.branch(ref_name.try_into()?)?;
+            branch.description = Some(description.into());
+            store.set_branch(&branch)?;
+        }
+        store.write_if_changed()?;
+        drop(store);
+
+        let store = GitRefMetadata::from_repo(&repo)?;
+        assert_eq!(store.iter().count(), 2, "no metadata was lost");
+        for (ref_name, description) in
+            [("refs/heads/foo", "outer"), ("refs/heads/foo/bar", "inner")]
+        {
+            let branch = store.branch(ref_name.try_into()?)?;
+            assert_eq!(branch.description.as_deref(), Some(description));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_writes_are_detected() -> anyhow::Result<()> {
+        let (repo, _tmp) = empty_repo()?;
+        let mut first = GitRefMetadata::from_repo(&repo)?;
+        let mut second = GitRefMetadata::from_repo(&repo)?;
+        for store in [&mut first, &mut second] {
+            let mut branch = store.branch("refs/heads/feat".try_into()?)?;
+            branch.description = Some("a description".into());
+            store.set_branch(&branch)?;
+        }
+        first.write_if_changed()?;
+        assert!(
+            second.write_if_changed().is_err(),
+            "the reference changed since the second store read it"
+        );
+        Ok(())
+    }
+
+    fn vb_fixture(name: &str) -> PathBuf {
+        format!("tests/fixtures/{name}.toml").into()
+    }
+
+    fn empty_repo() -> anyhow::Result<(gix::Repository, TempDir)> {
+        let tmp = tempdir()?;
+        let repo = gix::init_bare(tmp.path())?;
+        Ok((repo, tmp))
+    }
+}
+
 /// Assure everything can round-trip and the data looks consistent, independently of the actual data,
 /// from a store that already contains data.
 fn roundtrip_journey(metadata: &mut impl RefMetadata) -> anyhow::Result<()> {
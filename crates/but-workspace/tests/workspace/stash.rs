@@ -0,0 +1,166 @@
+use crate::utils::{CONTEXT_LINES, to_change_specs_whole_file, writable_scenario};
+use but_core::RefMetadata;
+use but_testsupport::git_status;
+use but_workspace::GitRefMetadata;
+use but_workspace::commit_engine::RejectionReason;
+use but_workspace::stash::{self, ApplyMode, StashStatus};
+
+#[test]
+fn create_list_and_pop() -> anyhow::Result<()> {
+    let (repo, _tmp) = writable_scenario("two-commits-with-line-offset");
+    let workdir = repo.workdir().expect("non-bare");
+    std::fs::write(workdir.join("file"), "changed\n")?;
+    std::fs::write(workdir.join("new-file"), "new\n")?;
+    assert_eq!(git_status(&repo)?, " M file\n?? new-file\n");
+
+    let mut meta = GitRefMetadata::from_repo(&repo)?;
+    let specs = to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?);
+    let outcome = stash::create(
+        &repo,
+        "refs/heads/main".try_into()?,
+        specs,
+        CONTEXT_LINES,
+        &mut meta,
+    )?;
+    assert!(outcome.rejected_specs.is_empty());
+    let stash_commit = outcome.stash_commit.expect("all changes were stashed");
+    assert_eq!(git_status(&repo)?, "", "stashed changes leave the worktree");
+
+    let err = stash::create(
+        &repo,
+        "refs/heads/main".try_into()?,
+        Vec::new(),
+        CONTEXT_LINES,
+        &mut meta,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("already has a stash"),
+        "there can only be one stash per branch"
+    );
+
+    let stashes = stash::list(&repo, &meta)?;
+    assert_eq!(stashes.len(), 1);
+    assert_eq!(stashes[0].commit_id, stash_commit);
+    assert!(stashes[0].status.is_none(), "the stash sits on top of main");
+    assert_eq!(
+        stashes[0]
+            .changes
+            .iter()
+            .map(|c| c.path.to_string())
+            .collect::<Vec<_>>(),
+        ["file", "new-file"]
+    );
+
+    let outcome = stash::apply(
+        &repo,
+        "refs/heads/main".try_into()?,
+        ApplyMode::Pop,
+        &mut meta,
+    )?;
+    assert!(outcome.rejected_specs.is_empty());
+    assert!(outcome.removed, "popping removes the stash");
+    assert_eq!(git_status(&repo)?, " M file\n?? new-file\n");
+    assert_eq!(std::fs::read(workdir.join("file"))?, b"changed\n");
+    assert!(stash::list(&repo, &meta)?.is_empty());
+    assert!(
+        repo.try_find_reference("refs/gitbutler/stashes/main")?
+            .is_none(),
+        "the reference keeping the stash alive is gone as well"
+    );
+    Ok(())
+}
+
+#[test]
+fn conflicting_changes_are_rejected_and_keep_the_stash() -> anyhow::Result<()> {
+    let (repo, _tmp) = writable_scenario("two-commits-with-line-offset");
+    let workdir = repo.workdir().expect("non-bare");
+    std::fs::write(workdir.join("file"), "stashed\n")?;
+    std::fs::write(workdir.join("new-file"), "new\n")?;
+
+    let mut meta = GitRefMetadata::from_repo(&repo)?;
+    let specs = to_change_specs_whole_file(but_core::diff::worktree_changes(&repo)?);
+    stash::create(
+        &repo,
+        "refs/heads/main".try_into()?,
+        specs,
+        CONTEXT_LINES,
+        &mut meta,
+    )?;
+
+    std::fs::write(workdir.join("file"), "in the way\n")?;
+    let outcome = stash::apply(
+        &repo,
+        "refs/heads/main".try_into()?,
+        ApplyMode::Pop,
+        &mut meta,
+    )?;
+    assert_eq!(outcome.rejected_specs.len(), 1);
+    assert_eq!(
+        outcome.rejected_specs[0].0,
+        RejectionReason::CherryPickMergeConflict
+    );
+    assert_eq!(outcome.rejected_specs[0].1.path_bytes, "file");
+    assert!(
+        !outcome.removed,
+        "the stash is kept as not everything applied"
+    );
+    assert_eq!(
+        std::fs::read(workdir.join("file"))?,
+        b"in the way\n",
+        "conflicting worktree changes are left untouched"
+    );
+    assert_eq!(std::fs::read(workdir.join("new-file"))?, b"new\n");
+    assert_eq!(stash::list(&repo, &meta)?.len(), 1);
+
+    assert!(stash::remove(
+        &repo,
+        "refs/heads/main".try_into()?,
+        &mut meta
+    )?);
+    assert!(stash::list(&repo, &meta)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn missing_stash_commits_are_listed_and_can_be_removed() -> anyhow::Result<()> {
+    let (repo, _tmp) = writable_scenario("two-commits-with-line-offset");
+    let mut meta = GitRefMetadata::from_repo(&repo)?;
+    let missing_id = gix::ObjectId::from_hex(b"0123456789012345678901234567890123456789")?;
+    for ref_name in ["refs/heads/main", "refs/heads/other"] {
+        let mut branch = meta.branch(ref_name.try_into()?)?;
+        branch.stash = Some(missing_id);
+        meta.set_branch(&branch)?;
+    }
+
+    let stashes = stash::list(&repo, &meta)?;
+    assert_eq!(
+        stashes.len(),
+        2,
+        "a missing commit doesn't fail the listing"
+    );
+    assert!(stashes.iter().all(
+        |stash| matches!(stash.status, Some(StashStatus::Missing)) && stash.changes.is_empty()
+    ));
+
+    let err = stash::apply(
+        &repo,
+        "refs/heads/main".try_into()?,
+        ApplyMode::Apply,
+        &mut meta,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("doesn't exist anymore"), "{err}");
+    assert!(
+        meta.branch("refs/heads/main".try_into()?)?.stash.is_none(),
+        "applying clears the dangling stash"
+    );
+
+    assert!(stash::remove(
+        &repo,
+        "refs/heads/other".try_into()?,
+        &mut meta
+    )?);
+    assert!(stash::list(&repo, &meta)?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use but_core::commit::HeadersV2;
+use but_workspace::{DiffSpec, split_commit_in_stack};
+use gitbutler_command_context::CommandContext;
+use gitbutler_stack::{Stack, VirtualBranchesHandle};
+use gix::prelude::ObjectIdExt;
+use tempfile::TempDir;
+
+use crate::utils::{CONTEXT_LINES, diff_spec};
+
+#[test]
+fn split_into_three_commits() -> anyhow::Result<()> {
+    let (ctx, _tmp) = command_ctx()?;
+    let repo = ctx.gix_repo()?;
+    let (stack, source_id, descendant_id) = stack_and_commits(&ctx, &repo)?;
+    let source = but_core::Commit::from_id(source_id.attach(&repo))?;
+
+    let outcome = split_commit_in_stack(
+        &ctx,
+        stack.id,
+        source_id,
+        vec![
+            ("first".into(), vec![whole_file("file1")]),
+            ("second".into(), vec![whole_file("file2")]),
+            ("third".into(), vec![whole_file("file3")]),
+        ],
+        CONTEXT_LINES,
+    )?;
+
+    let stack = VirtualBranchesHandle::new(ctx.project().gb_dir()).get_stack(stack.id)?;
+    let new_descendant_id = stack.heads[1].head_oid(&repo)?;
+    let third_id = stack.heads[0].head_oid(&repo)?;
+    let third = but_core::Commit::from_id(third_id.attach(&repo))?;
+    let second = but_core::Commit::from_id(third.parents[0].attach(&repo))?;
+    let first = but_core::Commit::from_id(second.parents[0].attach(&repo))?;
+    assert_eq!(
+        first.parents.as_slice(),
+        source.parents.as_slice(),
+        "the new commits take the place of the source commit"
+    );
+    assert_eq!(
+        [
+            first.message.to_string(),
+            second.message.to_string(),
+            third.message.to_string()
+        ],
+        ["first", "second", "third"]
+    );
+
+    assert_eq!(
+        files_in(&repo, first.tree)?,
+        ["file", "file1"],
+        "each commit adds the changes of its group"
+    );
+    assert_eq!(files_in(&repo, second.tree)?, ["file", "file1", "file2"]);
+    assert_eq!(
+        third.tree, source.tree,
+        "together, the new commits contain all changes of the source commit"
+    );
+
+    let change_id = |commit: &but_core::Commit<'_>| commit.headers().map(|hdr| hdr.change_id);
+    assert_eq!(
+        change_id(&first),
+        change_id(&source),
+        "the first commit keeps the change-id"
+    );
+    assert_ne!(change_id(&second), change_id(&source));
+    assert_ne!(change_id(&third), change_id(&source));
+    assert_ne!(
+        change_id(&second),
+        change_id(&third),
+        "all other commits get their own change-id"
+    );
+
+    let descendant = repo.find_commit(new_descendant_id)?;
+    assert_ne!(
+        new_descendant_id, descendant_id,
+        "the descendant was rebased"
+    );
+    assert_eq!(
+        descendant
+            .parent_ids()
+            .map(|id| id.detach())
+            .collect::<Vec<_>>(),
+        [third_id],
+        "the descendant is on top of the last of the new commits"
+    );
+    assert_eq!(
+        files_in(&repo, descendant.tree_id()?.detach())?,
+        ["file", "file1", "file2", "file3", "file4"]
+    );
+
+    assert!(
+        outcome
+            .replaced_commits
+            .contains(&(source_id, first.id.detach())),
+        "the source commit is replaced by the first new commit"
+    );
+    assert!(
+        outcome
+            .replaced_commits
+            .contains(&(descendant_id, new_descendant_id))
+    );
+    Ok(())
+}
+
+#[test]
+fn unmentioned_changes_go_into_the_first_commit() -> anyhow::Result<()> {
+    let (ctx, _tmp) = command_ctx()?;
+    let repo = ctx.gix_repo()?;
+    let (stack, source_id, _descendant_id) = stack_and_commits(&ctx, &repo)?;
+
+    split_commit_in_stack(
+        &ctx,
+        stack.id,
+        source_id,
+        vec![
+            ("first".into(), vec![whole_file("file2")]),
+            ("second".into(), vec![whole_file("file3")]),
+        ],
+        CONTEXT_LINES,
+    )?;
+
+    let stack = VirtualBranchesHandle::new(ctx.project().gb_dir()).get_stack(stack.id)?;
+    let second = repo.find_commit(stack.heads[0].head_oid(&repo)?)?;
+    let first = repo.find_commit(second.parent_ids().next().expect("single parent"))?;
+    assert_eq!(
+        files_in(&repo, first.tree_id()?.detach())?,
+        ["file", "file1", "file2"]
+    );
+    assert_eq!(
+        files_in(&repo, second.tree_id()?.detach())?,
+        ["file", "file1", "file2", "file3"]
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_groups() -> anyhow::Result<()> {
+    let (ctx, _tmp) = command_ctx()?;
+    let repo = ctx.gix_repo()?;
+    let (stack, source_id, _descendant_id) = stack_and_commits(&ctx, &repo)?;
+
+    let err = split_commit_in_stack(
+        &ctx,
+        stack.id,
+        source_id,
+        vec![("only".into(), vec![whole_file("file1")])],
+        CONTEXT_LINES,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "A commit must be split into at least two commits"
+    );
+
+    let err = split_commit_in_stack(
+        &ctx,
+        stack.id,
+        source_id,
+        vec![
+            ("first".into(), vec![whole_file("file1")]),
+            ("empty".into(), vec![]),
+        ],
+        CONTEXT_LINES,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Each commit to split into must receive at least one change"
+    );
+
+    let err = split_commit_in_stack(
+        &ctx,
+        stack.id,
+        source_id,
+        vec![
+            ("first".into(), vec![whole_file("file1")]),
+            (
+                "second".into(),
+                vec![whole_file("file2"), whole_file("file1")],
+            ),
+        ],
+        CONTEXT_LINES,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Each change must only be part of one commit to split into"
+    );
+
+    assert_eq!(
+        VirtualBranchesHandle::new(ctx.project().gb_dir())
+            .get_stack(stack.id)?
+            .heads[0]
+            .head_oid(&repo)?,
+        source_id,
+        "nothing changed"
+    );
+    Ok(())
+}
+
+#[test]
+fn merge_and_conflicted_commits_are_rejected() -> anyhow::Result<()> {
+    let (ctx, _tmp) = command_ctx()?;
+    let repo = ctx.gix_repo()?;
+    let (stack, source_id, descendant_id) = stack_and_commits(&ctx, &repo)?;
+    let source = but_core::Commit::from_id(source_id.attach(&repo))?;
+    let groups = || {
+        vec![
+            ("first".into(), vec![whole_file("file1")]),
+            ("second".into(), vec![whole_file("file2")]),
+        ]
+    };
+
+    let mut merge = (*source).clone();
+    merge.parents = vec![source.parents[0], descendant_id].into();
+    let merge_id = repo.write_object(&merge)?.detach();
+    let err = split_commit_in_stack(&ctx, stack.id, merge_id, groups(), CONTEXT_LINES).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Can only split commits with exactly one parent"
+    );
+
+    let mut conflicted = (*source).clone();
+    HeadersV2 {
+        change_id: "conflicted".into(),
+        conflicted: Some(1),
+    }
+    .set_in_commit(&mut conflicted);
+    let conflicted_id = repo.write_object(&conflicted)?.detach();
+    let err =
+        split_commit_in_stack(&ctx, stack.id, conflicted_id, groups(), CONTEXT_LINES).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Cannot split conflicted commit {conflicted_id}")
+    );
+    Ok(())
+}
+
+fn command_ctx() -> anyhow::Result<(CommandContext, TempDir)> {
+    gitbutler_testsupport::writable::fixture("split-commit.sh", "split")
+}
+
+/// Return the only stack along with the commit to split and its descendant.
+fn stack_and_commits(
+    ctx: &CommandContext,
+    repo: &gix::Repository,
+) -> anyhow::Result<(Stack, gix::ObjectId, gix::ObjectId)> {
+    let stack = VirtualBranchesHandle::new(ctx.project().gb_dir())
+        .list_stacks_in_workspace()?
+        .pop()
+        .expect("the fixture has one stack");
+    let source_id = stack.heads[0].head_oid(repo)?;
+    let descendant_id = stack.heads[1].head_oid(repo)?;
+    Ok((stack, source_id, descendant_id))
+}
+
+fn whole_file(path: &str) -> DiffSpec {
+    diff_spec(None, path, [])
+}
+
+/// Return the paths of all files in the tree with `tree_id`, without descending into directories.
+fn files_in(repo: &gix::Repository, tree_id: gix::ObjectId) -> anyhow::Result<Vec<String>> {
+    Ok(repo
+        .find_tree(tree_id)?
+        .iter()
+        .map(|entry| entry.map(|entry| entry.filename().to_string()))
+        .collect::<Result<_, _>>()?)
+}
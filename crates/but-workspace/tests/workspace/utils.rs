@@ -1,5 +1,5 @@
 use bstr::ByteSlice;
-use but_core::unified_diff::DiffHunk;
+use but_core::unified_diff::{DiffHunk, DiffOptions};
 use but_core::{TreeChange, TreeStatus, UnifiedDiff};
 use but_testsupport::gix_testtools;
 use but_testsupport::gix_testtools::{Creation, tempfile};
@@ -267,6 +267,7 @@ pub fn commit_whole_files_and_all_hunks_from_workspace(
         None,
         to_change_specs_whole_file(worktree_changes.clone()),
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
     let all_hunks_output = but_workspace::commit_engine::create_commit(
         repo,
@@ -274,6 +275,7 @@ pub fn commit_whole_files_and_all_hunks_from_workspace(
         None,
         to_change_specs_all_hunks(repo, worktree_changes)?,
         CONTEXT_LINES,
+        DiffOptions::default(),
     )?;
 
     if whole_file_output.new_commit.is_some() && all_hunks_output.new_commit.is_some() {
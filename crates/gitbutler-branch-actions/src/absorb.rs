@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use but_core::unified_diff::DiffOptions;
+use but_core::UnifiedDiff;
+use but_hunk_dependency::ui::{
+    hunk_dependencies_for_workspace_changes_by_worktree_dir, Engine, HunkLock,
+};
+use but_rebase::RebaseStep;
+use but_workspace::commit_engine::{self, RejectionReason};
+use but_workspace::stack_ext::StackExt;
+use but_workspace::{DiffSpec, HunkHeader};
+use gitbutler_command_context::CommandContext;
+use gitbutler_oxidize::ObjectIdExt;
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_stack::StackId;
+#[allow(deprecated)]
+use gitbutler_workspace::{
+    branch_trees::{update_uncommited_changes, WorkspaceState},
+    checkout_branch_trees, compute_updated_branch_head_for_commits,
+};
+use serde::Serialize;
+
+use crate::VirtualBranchesExt;
+
+/// The result of absorbing uncommitted changes into the commits that introduced the lines they touch.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsorbOutcome {
+    /// The commits that were amended, in no particular order.
+    pub absorbed: Vec<AbsorbedCommit>,
+    /// The hunks that were left in the worktree, along with the reason for it.
+    pub remaining: Vec<RemainingHunk>,
+}
+
+/// A commit that was amended with worktree hunks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbsorbedCommit {
+    /// The stack that contains the commit.
+    pub stack_id: StackId,
+    /// The commit before it was amended.
+    #[serde(with = "gitbutler_serde::object_id")]
+    pub old_commit_id: gix::ObjectId,
+    /// The commit after it was amended and its stack was rebased.
+    #[serde(with = "gitbutler_serde::object_id")]
+    pub new_commit_id: gix::ObjectId,
+    /// The worktree hunks that were absorbed into the commit.
+    pub changes: Vec<DiffSpec>,
+}
+
+/// A worktree hunk that wasn't absorbed into any commit.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemainingHunk {
+    /// The path of the file the hunk is in, as seen in the worktree.
+    #[serde(serialize_with = "gitbutler_serde::bstring_lossy::serialize")]
+    pub path: bstr::BString,
+    /// The hunk itself, computed without context lines.
+    pub hunk: HunkHeader,
+    /// Why the hunk wasn't absorbed.
+    pub reason: AbsorbRejection,
+}
+
+/// The reason for a worktree hunk not being absorbed into a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", content = "subject")]
+pub enum AbsorbRejection {
+    /// The hunk doesn't touch any lines that were introduced by commits in the workspace.
+    Unlocked,
+    /// The hunk touches lines that were introduced by commits in more than one stack, so there is no single commit to amend.
+    Ambiguous,
+    /// Amending the commit with the hunk failed for the given reason.
+    Rejected(RejectionReason),
+}
+
+/// Amend every uncommitted hunk into its *IntroducingCommit*, the commit closest to the stack tip that introduced
+/// lines the hunk touches, and rebase each affected stack once.
+///
+/// Hunks that don't touch any committed lines, or that touch lines of multiple stacks, are left in the worktree
+/// and reported as [remaining](AbsorbOutcome::remaining).
+pub(crate) fn absorb(
+    ctx: &CommandContext,
+    perm: &mut WorktreeWritePermission,
+) -> Result<AbsorbOutcome> {
+    let old_workspace = WorkspaceState::create(ctx, perm.read_permission())?;
+    let vb_state = ctx.project().virtual_branches();
+    let repo = ctx.gix_repo()?;
+    let dependencies = hunk_dependencies_for_workspace_changes_by_worktree_dir(
+        ctx,
+        &ctx.project().path,
+        &ctx.project().gb_dir(),
+        Engine::default(),
+    )?;
+
+    let mut remaining = Vec::new();
+    let mut specs_by_commit = BTreeMap::<(StackId, gix::ObjectId), Vec<DiffSpec>>::new();
+    let mut steps_by_stack = BTreeMap::new();
+    for change in but_core::diff::worktree_changes(&repo)?.changes {
+        let UnifiedDiff::Patch { hunks, .. } = change.unified_diff(&repo, 0)? else {
+            continue;
+        };
+        let path = change.path.to_string();
+        for hunk in hunks {
+            let hunk = HunkHeader::from(hunk);
+            let locks = dependencies
+                .diffs
+                .iter()
+                .find(|(dep_path, dep_hunk, _)| {
+                    *dep_path == path
+                        && dep_hunk.old_start == hunk.old_start
+                        && dep_hunk.new_start == hunk.new_start
+                })
+                .map(|(_, _, locks)| locks.as_slice())
+                .unwrap_or_default();
+            let Some(HunkLock {
+                stack_id,
+                commit_id,
+            }) = introducing_commit(ctx, &repo, &mut steps_by_stack, locks)?
+            else {
+                remaining.push(RemainingHunk {
+                    path: change.path.clone(),
+                    hunk,
+                    reason: if locks.is_empty() {
+                        AbsorbRejection::Unlocked
+                    } else {
+                        AbsorbRejection::Ambiguous
+                    },
+                });
+                continue;
+            };
+            let specs = specs_by_commit.entry((stack_id, commit_id)).or_default();
+            match specs.iter_mut().find(|spec| spec.path_bytes == change.path) {
+                Some(spec) => spec.hunk_headers.push(hunk),
+                None => specs.push(DiffSpec {
+                    previous_path_bytes: change.previous_path().map(ToOwned::to_owned),
+                    path_bytes: change.path.clone(),
+                    hunk_headers: vec![hunk],
+                }),
+            }
+        }
+    }
+
+    let mut amended = Vec::new();
+    for ((stack_id, commit_id), mut specs) in specs_by_commit {
+        let outcome = commit_engine::create_commit(
+            &repo,
+            commit_engine::Destination::AmendCommit {
+                commit_id,
+                new_message: None,
+            },
+            None,
+            specs.clone(),
+            0, /* the hunks were computed without context lines */
+            DiffOptions::default(),
+        )?;
+        for (reason, spec) in outcome.rejected_specs {
+            specs.retain(|absorbed_spec| *absorbed_spec != spec);
+            for hunk in spec.hunk_headers {
+                remaining.push(RemainingHunk {
+                    path: spec.path_bytes.clone(),
+                    hunk,
+                    reason: AbsorbRejection::Rejected(reason),
+                });
+            }
+        }
+        if let Some(new_commit_id) = outcome.new_commit {
+            amended.push((stack_id, commit_id, new_commit_id, specs));
+        }
+    }
+
+    let mut absorbed = Vec::new();
+    for (stack_id, steps) in steps_by_stack {
+        let amended_in_stack: Vec<_> = amended
+            .iter()
+            .filter(|(amended_stack_id, ..)| *amended_stack_id == stack_id)
+            .collect();
+        if amended_in_stack.is_empty() {
+            continue;
+        }
+        let steps = steps
+            .into_iter()
+            .map(|step| match step {
+                RebaseStep::Pick {
+                    commit_id,
+                    new_message,
+                } => RebaseStep::Pick {
+                    commit_id: amended_in_stack
+                        .iter()
+                        .find(|(_, old_commit_id, ..)| *old_commit_id == commit_id)
+                        .map_or(commit_id, |(_, _, new_commit_id, _)| *new_commit_id),
+                    new_message,
+                },
+                other => other,
+            })
+            .collect::<Vec<_>>();
+
+        let mut stack = vb_state.get_stack(stack_id)?;
+        let old_head = stack.head_oid(&repo)?;
+        let mut rebase = but_rebase::Rebase::new(&repo, stack.merge_base(ctx)?, None)?;
+        rebase.rebase_noops(false);
+        rebase.steps(steps)?;
+        let output = rebase.rebase()?;
+        let new_head = output.top_commit.to_git2();
+
+        let (new_head_oid, new_tree_oid) = if ctx.app_settings().feature_flags.v3 {
+            (new_head, None)
+        } else {
+            #[allow(deprecated)]
+            let res = compute_updated_branch_head_for_commits(
+                ctx.repo(),
+                &repo,
+                old_head.to_git2(),
+                stack.tree(ctx)?,
+                new_head,
+            )?;
+            (res.head, Some(res.tree))
+        };
+        stack.set_stack_head_and_heads_from_rebase_output(
+            ctx,
+            new_head_oid,
+            new_tree_oid,
+            output.references,
+        )?;
+
+        for (_, old_commit_id, amended_commit_id, specs) in amended_in_stack {
+            let new_commit_id = output
+                .commit_mapping
+                .iter()
+                .find(|(_, old, _)| old == amended_commit_id)
+                .map_or(*amended_commit_id, |(_, _, new)| *new);
+            absorbed.push(AbsorbedCommit {
+                stack_id,
+                old_commit_id: *old_commit_id,
+                new_commit_id,
+                changes: specs.clone(),
+            });
+        }
+    }
+
+    if !absorbed.is_empty() {
+        let new_workspace = WorkspaceState::create(ctx, perm.read_permission())?;
+        if ctx.app_settings().feature_flags.v3 {
+            update_uncommited_changes(ctx, old_workspace, new_workspace, perm)?;
+        } else {
+            #[allow(deprecated)]
+            checkout_branch_trees(ctx, perm)?;
+        }
+        crate::integration::update_workspace_commit(&vb_state, ctx)
+            .context("failed to update gitbutler workspace")?;
+    }
+
+    Ok(AbsorbOutcome {
+        absorbed,
+        remaining,
+    })
+}
+
+/// Return the commit the hunk with `locks` should be absorbed into, which is the one closest to the tip of its stack.
+/// Return `None` if there are no `locks`, or if they are spread over multiple stacks.
+///
+/// `steps_by_stack` caches the rebase steps of each stack, from base to tip, and is filled as stacks are encountered.
+fn introducing_commit(
+    ctx: &CommandContext,
+    repo: &gix::Repository,
+    steps_by_stack: &mut BTreeMap<StackId, Vec<RebaseStep>>,
+    locks: &[HunkLock],
+) -> Result<Option<HunkLock>> {
+    let Some(first) = locks.first() else {
+        return Ok(None);
+    };
+    if locks.iter().any(|lock| lock.stack_id != first.stack_id) {
+        return Ok(None);
+    }
+    let steps = match steps_by_stack.entry(first.stack_id) {
+        std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+        std::collections::btree_map::Entry::Vacant(entry) => {
+            let stack = ctx.project().virtual_branches().get_stack(first.stack_id)?;
+            entry.insert(stack.as_rebase_steps(ctx, repo)?)
+        }
+    };
+    Ok(locks
+        .iter()
+        .max_by_key(|lock| {
+            steps.iter().position(|step| {
+                matches!(step, RebaseStep::Pick { commit_id, .. } if *commit_id == lock.commit_id)
+            })
+        })
+        .copied())
+}
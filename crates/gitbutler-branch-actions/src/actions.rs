@@ -1,12 +1,14 @@
 use super::r#virtual as vbranch;
+use crate::absorb::{self, AbsorbOutcome};
 use crate::branch_upstream_integration;
 use crate::branch_upstream_integration::IntegrationStrategy;
+use crate::move_branch::{self, BranchDestination};
 use crate::move_commits;
 use crate::r#virtual::StackListResult;
 use crate::reorder::{self, StackOrder};
 use crate::upstream_integration::{
-    self, BaseBranchResolution, BaseBranchResolutionApproach, IntegrationOutcome, Resolution,
-    StackStatuses, UpstreamIntegrationContext,
+    self, BaseBranchResolution, BaseBranchResolutionApproach, IntegrationOutcome,
+    IntegrationPreview, Resolution, StackStatuses, UpstreamIntegrationContext,
 };
 use crate::VirtualBranchHunkRangeMap;
 use crate::{
@@ -19,6 +21,7 @@ use crate::{
     VirtualBranchesExt,
 };
 use anyhow::{Context, Result};
+use but_core::unified_diff::DiffOptions;
 use but_workspace::{commit_engine, stack_heads_info, ui, DiffSpec};
 use gitbutler_branch::{BranchCreateRequest, BranchUpdateRequest};
 use gitbutler_command_context::CommandContext;
@@ -179,6 +182,24 @@ pub fn set_target_push_remote(ctx: &CommandContext, push_remote: &str) -> Result
     base::set_target_push_remote(ctx, push_remote)
 }
 
+/// Make the stack with `stack_id` integrate with `target_branch` instead of the default target,
+/// or with the default target again if `None`.
+pub fn set_stack_target(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    target_branch: Option<&RemoteRefname>,
+) -> Result<()> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx)
+        .context("Setting a stack target requires open workspace mode")?;
+    let mut guard = ctx.project().exclusive_worktree_access();
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::GenericBranchUpdate),
+        guard.write_permission(),
+    );
+    base::set_stack_target(ctx, stack_id, target_branch)
+}
+
 pub fn push_base_branch(ctx: &CommandContext, with_force: bool) -> Result<()> {
     base::push(ctx, with_force)
 }
@@ -320,6 +341,18 @@ pub fn amend(
     amend_with_commit_engine(ctx, stack_id, commit_oid, worktree_changes)
 }
 
+/// Amend all uncommitted hunks into the commits that introduced the lines they touch, leaving all other hunks in the worktree.
+pub fn absorb(ctx: &CommandContext) -> Result<AbsorbOutcome> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Absorbing changes requires open workspace mode")?;
+    let mut guard = ctx.project().exclusive_worktree_access();
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::AmendCommit),
+        guard.write_permission(),
+    );
+    absorb::absorb(ctx, guard.write_permission())
+}
+
 /// This is backported version of amending using the new commit engine, in the old API
 fn amend_with_commit_engine(
     ctx: &CommandContext,
@@ -340,6 +373,7 @@ fn amend_with_commit_engine(
         None,
         worktree_changes,
         3, // for the old API this is hardcoded
+        DiffOptions::default(),
         guard.write_permission(),
     )?;
     let new_commit = outcome.new_commit.ok_or(anyhow::anyhow!(
@@ -525,6 +559,28 @@ pub fn move_commit(
     )
 }
 
+pub fn move_branch(
+    ctx: &CommandContext,
+    source_stack_id: StackId,
+    branch_name: &str,
+    destination: BranchDestination,
+) -> Result<StackId> {
+    ctx.verify()?;
+    assure_open_workspace_mode(ctx).context("Moving a branch requires open workspace mode")?;
+    let mut guard = ctx.project().exclusive_worktree_access();
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::MoveBranch),
+        guard.write_permission(),
+    );
+    move_branch::move_branch(
+        ctx,
+        source_stack_id,
+        branch_name,
+        destination,
+        guard.write_permission(),
+    )
+}
+
 #[instrument(level = tracing::Level::DEBUG, skip(ctx), err(Debug))]
 pub fn create_virtual_branch_from_branch(
     ctx: &CommandContext,
@@ -574,17 +630,28 @@ pub fn upstream_integration_statuses(
     upstream_integration::upstream_integration_statuses(&context)
 }
 
-pub fn integrate_upstream(
+/// Return what [`integrate_upstream()`] would do with `resolutions`, without changing anything.
+pub fn preview_upstream_integration(
     ctx: &CommandContext,
     resolutions: &[Resolution],
     base_branch_resolution: Option<BaseBranchResolution>,
-) -> Result<IntegrationOutcome> {
+) -> Result<IntegrationPreview> {
     let mut guard = ctx.project().exclusive_worktree_access();
 
-    let _ = ctx.create_snapshot(
-        SnapshotDetails::new(OperationKind::UpdateWorkspaceBase),
+    upstream_integration::preview_upstream_integration(
+        ctx,
+        resolutions,
+        base_branch_resolution,
         guard.write_permission(),
-    );
+    )
+}
+
+pub fn integrate_upstream(
+    ctx: &CommandContext,
+    resolutions: &[Resolution],
+    base_branch_resolution: Option<BaseBranchResolution>,
+) -> Result<IntegrationOutcome> {
+    let mut guard = ctx.project().exclusive_worktree_access();
 
     upstream_integration::integrate_upstream(
         ctx,
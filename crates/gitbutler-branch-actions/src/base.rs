@@ -10,7 +10,7 @@ use anyhow::{anyhow, Context, Result};
 use gitbutler_branch::GITBUTLER_WORKSPACE_REFERENCE;
 use gitbutler_command_context::CommandContext;
 use gitbutler_error::error::Marker;
-use gitbutler_oxidize::ObjectIdExt;
+use gitbutler_oxidize::{ObjectIdExt, RepoExt};
 use gitbutler_project::FetchResult;
 use gitbutler_reference::{Refname, RemoteRefname};
 use gitbutler_repo::{
@@ -18,7 +18,7 @@ use gitbutler_repo::{
     RepositoryExt,
 };
 use gitbutler_repo_actions::RepoActionsExt;
-use gitbutler_stack::{BranchOwnershipClaims, Stack, Target, VirtualBranchesHandle};
+use gitbutler_stack::{BranchOwnershipClaims, Stack, StackId, Target, VirtualBranchesHandle};
 use serde::Serialize;
 use tracing::instrument;
 
@@ -253,6 +253,66 @@ pub(crate) fn set_target_push_remote(ctx: &CommandContext, push_remote_name: &st
     Ok(())
 }
 
+/// Make the stack with `stack_id` integrate with `target_branch_ref`, like a release branch, instead of the
+/// default target. If `None` or the branch of the default target is given, the stack uses the default target again.
+pub(crate) fn set_stack_target(
+    ctx: &CommandContext,
+    stack_id: StackId,
+    target_branch_ref: Option<&RemoteRefname>,
+) -> Result<()> {
+    let vb_state = ctx.project().virtual_branches();
+    let stack = vb_state.get_stack_in_workspace(stack_id)?;
+    let default_target = vb_state.get_default_target()?;
+    let Some(target_branch_ref) =
+        target_branch_ref.filter(|target_branch_ref| **target_branch_ref != default_target.branch)
+    else {
+        vb_state.remove_stack_target(stack_id)?;
+        update_workspace_commit(&vb_state, ctx)?;
+        return Ok(());
+    };
+
+    let repo = ctx.repo();
+    let target_branch = repo
+        .maybe_find_branch_by_refname(&target_branch_ref.clone().into())?
+        .ok_or(anyhow!("remote branch '{}' not found", target_branch_ref))?;
+    let remote = repo
+        .find_remote(target_branch_ref.remote())
+        .context(format!(
+            "failed to find remote for branch {}",
+            target_branch_ref
+        ))?;
+    let remote_url = remote.url().context(format!(
+        "failed to get remote url for {}",
+        target_branch_ref.remote()
+    ))?;
+    let target_branch_head = target_branch.get().peel_to_commit().context(format!(
+        "failed to peel branch {} to commit",
+        target_branch_ref
+    ))?;
+
+    // Just like the default target, it starts out at the merge-base so upstream changes can be integrated later.
+    let stack_head = stack.head_oid(&repo.to_gix()?)?.to_git2();
+    let target_commit_oid = repo
+        .merge_base(stack_head, target_branch_head.id())
+        .context(format!(
+            "Failed to calculate merge base between {} and {}",
+            stack_head,
+            target_branch_head.id()
+        ))?;
+
+    vb_state.set_stack_target(
+        stack_id,
+        Target {
+            branch: target_branch_ref.clone(),
+            remote_url: remote_url.to_string(),
+            sha: target_commit_oid,
+            push_remote_name: None,
+        },
+    )?;
+    update_workspace_commit(&vb_state, ctx)?;
+    Ok(())
+}
+
 fn set_exclude_decoration(ctx: &CommandContext) -> Result<()> {
     let repo = ctx.repo();
     let mut config = repo.config()?;
@@ -37,6 +37,9 @@ pub struct VirtualBranchCommit {
     pub change_id: Option<String>,
     pub is_signed: bool,
     pub conflicted: bool,
+    /// If the conflicts of this commit were resolved automatically with recorded resolutions,
+    /// so it should be reviewed.
+    pub has_recorded_resolution: bool,
     /// The id of the remote commit from which this one was copied, as identified by
     /// having equal author, committer, and commit message.
     /// This is used by the frontend similar to the `change_id` to group matching commits.
@@ -122,6 +125,7 @@ pub(crate) fn commit_to_vbranch_commit(
         change_id: commit.change_id(),
         is_signed: commit.is_signed(),
         conflicted: commit.is_conflicted(),
+        has_recorded_resolution: commit.has_recorded_resolution(),
         copied_from_remote_id,
         remote_commit_id,
         conflicted_files,
@@ -3,18 +3,22 @@ mod actions;
 // This is our API
 #[allow(deprecated)]
 pub use actions::{
-    amend, can_apply_remote_branch, create_commit, create_virtual_branch,
+    absorb, amend, can_apply_remote_branch, create_commit, create_virtual_branch,
     create_virtual_branch_from_branch, delete_local_branch, fetch_from_remotes, find_commit,
     find_git_branches, get_uncommited_files, get_uncommited_files_reusable, insert_blank_commit,
     integrate_upstream, integrate_upstream_commits, list_commit_files, list_virtual_branches,
-    list_virtual_branches_cached, move_commit, move_commit_file, push_base_branch, reorder_stack,
-    reset_files, reset_virtual_branch, resolve_upstream_integration, set_base_branch,
+    list_virtual_branches_cached, move_branch, move_commit, move_commit_file,
+    preview_upstream_integration, push_base_branch, reorder_stack, reset_files,
+    reset_virtual_branch, resolve_upstream_integration, set_base_branch, set_stack_target,
     set_target_push_remote, squash_commits, unapply_lines, unapply_ownership, unapply_stack,
     undo_commit, update_branch_order, update_commit_message, update_virtual_branch,
     upstream_integration_statuses,
 };
 mod squash;
 
+mod absorb;
+pub use absorb::{AbsorbOutcome, AbsorbRejection, AbsorbedCommit, RemainingHunk};
+
 mod r#virtual;
 pub use r#virtual::{BranchStatus, VirtualBranch, VirtualBranchHunksByPathMap, VirtualBranches};
 /// Avoid using these!
@@ -46,6 +50,8 @@ mod remote;
 pub use remote::{RemoteBranchData, RemoteCommit};
 
 pub mod branch_upstream_integration;
+mod move_branch;
+pub use move_branch::BranchDestination;
 mod move_commits;
 pub mod reorder;
 pub use reorder::{SeriesOrder, StackOrder};
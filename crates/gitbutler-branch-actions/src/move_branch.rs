@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use but_rebase::{RebaseOutput, RebaseStep};
+use gitbutler_command_context::CommandContext;
+use gitbutler_oxidize::{ObjectIdExt, OidExt};
+use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_stack::{Stack, StackId, VirtualBranchesHandle};
+#[allow(deprecated)]
+use gitbutler_workspace::{
+    branch_trees::{update_uncommited_changes, WorkspaceState},
+    checkout_branch_trees, compute_updated_branch_head_for_commits,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::reorder::commits_order;
+use crate::VirtualBranchesExt;
+
+/// Where a branch should be moved to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum BranchDestination {
+    /// Insert the branch into the existing stack with `stack_id`.
+    #[serde(rename_all = "camelCase")]
+    Stack {
+        /// The stack to insert the branch into.
+        stack_id: StackId,
+        /// The name of the branch in the destination stack that the moved branch should be placed on top of,
+        /// or `None` to place it at the bottom of the stack.
+        on_top_of: Option<String>,
+    },
+    /// Make the branch the only branch of a new stack.
+    NewStack,
+}
+
+/// Move the branch `branch_name` with all of its commits out of the stack with `source_stack_id` and into `destination`.
+/// Pull-request and review information stays attached to the branch.
+///
+/// All affected stacks are rebased first, and only if that succeeds their new state is written. If the source stack
+/// is left without branches, it is removed.
+///
+/// Returns the id of the stack that now contains the branch.
+pub(crate) fn move_branch(
+    ctx: &CommandContext,
+    source_stack_id: StackId,
+    branch_name: &str,
+    destination: BranchDestination,
+    perm: &mut WorktreeWritePermission,
+) -> Result<StackId> {
+    let old_workspace = WorkspaceState::create(ctx, perm.read_permission())?;
+    let vb_state = ctx.project().virtual_branches();
+    let gix_repo = ctx.gix_repo()?;
+
+    let mut source_stack = vb_state.get_stack_in_workspace(source_stack_id)?;
+    let source_merge_base = source_stack.merge_base(ctx)?;
+    let source_old_head = source_stack.head_oid(&gix_repo)?;
+    let source_old_tree = source_stack.tree(ctx)?;
+    let mut commits_by_branch: HashMap<String, Vec<git2::Oid>> = commits_order(ctx, &source_stack)?
+        .series
+        .into_iter()
+        .map(|series| (series.name, series.commit_ids))
+        .collect();
+
+    let branch_idx = source_stack
+        .heads
+        .iter()
+        .position(|head| head.name() == branch_name && !head.archived)
+        .with_context(|| format!("Branch '{branch_name}' isn't part of the source stack"))?;
+    let branch = source_stack.heads.remove(branch_idx);
+    let source_is_empty = source_stack.heads.iter().all(|head| head.archived);
+
+    let mut destination_stack = match &destination {
+        BranchDestination::Stack {
+            stack_id,
+            on_top_of,
+        } => {
+            if *stack_id == source_stack_id {
+                bail!("Branches can't be moved within the same stack");
+            }
+            let mut destination_stack = vb_state.get_stack_in_workspace(*stack_id)?;
+            commits_by_branch.extend(
+                commits_order(ctx, &destination_stack)?
+                    .series
+                    .into_iter()
+                    .map(|series| (series.name, series.commit_ids)),
+            );
+            let insert_idx = match on_top_of {
+                Some(name) => {
+                    destination_stack
+                        .heads
+                        .iter()
+                        .position(|head| head.name() == name && !head.archived)
+                        .with_context(|| {
+                            format!("Branch '{name}' isn't part of the destination stack")
+                        })?
+                        + 1
+                }
+                None => destination_stack
+                    .heads
+                    .iter()
+                    .position(|head| !head.archived)
+                    .unwrap_or(destination_stack.heads.len()),
+            };
+            let merge_base = destination_stack.merge_base(ctx)?;
+            let old_head = destination_stack.head_oid(&gix_repo)?;
+            let old_tree = destination_stack.tree(ctx)?;
+            destination_stack.heads.insert(insert_idx, branch);
+            DestinationStack {
+                merge_base,
+                old_head_and_tree: Some((old_head, old_tree)),
+                stack: destination_stack,
+            }
+        }
+        BranchDestination::NewStack => {
+            if source_is_empty {
+                bail!("Branch '{branch_name}' already is the only branch of its stack");
+            }
+            let mut stack = Stack::new_with_just_heads(
+                vec![branch],
+                gitbutler_time::time::now_ms(),
+                vb_state.next_order_index()?,
+                true,
+            );
+            stack.name = branch_name.to_owned();
+            DestinationStack {
+                merge_base: source_merge_base,
+                old_head_and_tree: None,
+                stack,
+            }
+        }
+    };
+
+    // Rebase everything before persisting anything so a failure leaves all stacks untouched.
+    let source_output = if source_is_empty {
+        None
+    } else {
+        Some(rebase_heads(
+            &gix_repo,
+            &source_stack,
+            source_merge_base,
+            &commits_by_branch,
+        )?)
+    };
+    let destination_output = rebase_heads(
+        &gix_repo,
+        &destination_stack.stack,
+        destination_stack.merge_base,
+        &commits_by_branch,
+    )?;
+
+    if let BranchDestination::NewStack = destination {
+        if let Some(target) = vb_state.maybe_get_stack_target(source_stack_id)? {
+            vb_state.set_stack_target(destination_stack.stack.id, target)?;
+        }
+    }
+    match source_output {
+        Some(output) => update_stack(
+            ctx,
+            &vb_state,
+            &gix_repo,
+            &mut source_stack,
+            Some((source_old_head, source_old_tree)),
+            output,
+        )?,
+        None => vb_state.delete_branch_entry(&source_stack_id)?,
+    }
+    update_stack(
+        ctx,
+        &vb_state,
+        &gix_repo,
+        &mut destination_stack.stack,
+        destination_stack.old_head_and_tree,
+        destination_output,
+    )?;
+
+    let new_workspace = WorkspaceState::create(ctx, perm.read_permission())?;
+    if ctx.app_settings().feature_flags.v3 {
+        update_uncommited_changes(ctx, old_workspace, new_workspace, perm)?;
+    } else {
+        #[allow(deprecated)]
+        checkout_branch_trees(ctx, perm)?;
+    }
+    crate::integration::update_workspace_commit(&vb_state, ctx)
+        .context("failed to update gitbutler workspace")?;
+
+    Ok(destination_stack.stack.id)
+}
+
+/// The stack that receives the moved branch, along with the information needed to rebase it.
+struct DestinationStack {
+    stack: Stack,
+    merge_base: gix::ObjectId,
+    /// The head and tree before the move, or `None` if the stack is new.
+    old_head_and_tree: Option<(gix::ObjectId, git2::Oid)>,
+}
+
+/// Rebase the commits of all non-archived heads of `stack` onto `merge_base`, in the order of the heads.
+fn rebase_heads(
+    gix_repo: &gix::Repository,
+    stack: &Stack,
+    merge_base: gix::ObjectId,
+    commits_by_branch: &HashMap<String, Vec<git2::Oid>>,
+) -> Result<RebaseOutput> {
+    let mut steps: Vec<RebaseStep> = Vec::new();
+    for head in stack.heads.iter().filter(|head| !head.archived) {
+        for oid in commits_by_branch
+            .get(head.name())
+            .into_iter()
+            .flatten()
+            .rev()
+        {
+            steps.push(RebaseStep::Pick {
+                commit_id: oid.to_gix(),
+                new_message: None,
+            });
+        }
+        steps.push(RebaseStep::Reference(but_core::Reference::Virtual(
+            head.name().clone(),
+        )));
+    }
+    let mut builder = but_rebase::Rebase::new(gix_repo, merge_base, None)?;
+    let builder = builder.steps(steps)?;
+    builder.rebase_noops(false);
+    builder.rebase()
+}
+
+/// Point the heads of `stack` to the rebased commits in `output` and persist it.
+fn update_stack(
+    ctx: &CommandContext,
+    vb_state: &VirtualBranchesHandle,
+    gix_repo: &gix::Repository,
+    stack: &mut Stack,
+    old_head_and_tree: Option<(gix::ObjectId, git2::Oid)>,
+    output: RebaseOutput,
+) -> Result<()> {
+    let new_head = output.top_commit.to_git2();
+    let (new_head_oid, new_tree_oid) = if ctx.app_settings().feature_flags.v3 {
+        (new_head, None)
+    } else if let Some((old_head, old_tree)) = old_head_and_tree {
+        #[allow(deprecated)]
+        let res = compute_updated_branch_head_for_commits(
+            ctx.repo(),
+            gix_repo,
+            old_head.to_git2(),
+            old_tree,
+            new_head,
+        )?;
+        (res.head, Some(res.tree))
+    } else {
+        let tree = gix_repo.find_commit(output.top_commit)?.tree_id()?;
+        (new_head, Some(tree.to_git2()))
+    };
+
+    stack.set_heads_from_rebase_output(ctx, output.references)?;
+    stack.set_stack_head(vb_state, gix_repo, new_head_oid, new_tree_oid)
+}
@@ -86,9 +86,12 @@ pub fn reorder_stack(
     };
 
     // Ensure the stack head is set to the new oid after rebasing
-    stack.set_stack_head(&state, &gix_repo, new_head_oid, new_tree_oid)?;
-
-    stack.set_heads_from_rebase_output(ctx, output.references.clone())?;
+    stack.set_stack_head_and_heads_from_rebase_output(
+        ctx,
+        new_head_oid,
+        new_tree_oid,
+        output.references.clone(),
+    )?;
 
     let new_workspace = WorkspaceState::create(ctx, perm.read_permission())?;
     if ctx.app_settings().feature_flags.v3 {
@@ -168,22 +168,17 @@ pub fn push_stack(ctx: &CommandContext, stack_id: StackId, with_force: bool) ->
     let stack = state.get_stack(stack_id)?;
 
     let repo = ctx.repo();
-    let default_target = state.get_default_target()?;
-    let merge_base = repo.find_commit(repo.merge_base(
-        stack.head_oid(&repo.to_gix()?)?.to_git2(),
-        default_target.sha,
-    )?)?;
+    let target = state.get_stack_target(stack_id)?;
+    let merge_base =
+        repo.find_commit(repo.merge_base(stack.head_oid(&repo.to_gix()?)?.to_git2(), target.sha)?)?;
     // let merge_base: CommitOrChangeId = merge_base.into();
 
     // First fetch, because we dont want to push integrated series
-    ctx.fetch(
-        &default_target.push_remote_name(),
-        Some("push_stack".into()),
-    )?;
+    ctx.fetch(&target.push_remote_name(), Some("push_stack".into()))?;
     let gix_repo = ctx.gix_repo_for_merging_non_persisting()?;
     let cache = gix_repo.commit_graph_if_enabled()?;
     let mut graph = gix_repo.revision_graph(cache.as_ref());
-    let mut check_commit = IsCommitIntegrated::new(ctx, &default_target, &gix_repo, &mut graph)?;
+    let mut check_commit = IsCommitIntegrated::new(ctx, &target, &gix_repo, &mut graph)?;
     let stack_branches = stack.branches();
     for branch in stack_branches {
         if branch.archived {
@@ -230,7 +225,7 @@ pub(crate) fn branch_integrated(
 pub(crate) fn stack_series(
     ctx: &CommandContext,
     stack: &mut Stack,
-    default_target: &Target,
+    target: &Target,
     check_commit: &mut IsCommitIntegrated,
     stack_dependencies: StackDependencies,
 ) -> (Vec<Result<PatchSeries, serde_error::Error>>, bool) {
@@ -241,7 +236,7 @@ pub(crate) fn stack_series(
             ctx,
             stack_branch,
             stack,
-            default_target,
+            target,
             check_commit,
             &stack_dependencies,
             &api_series
@@ -271,7 +266,7 @@ fn stack_branch_to_api_branch(
     ctx: &CommandContext,
     stack_branch: StackBranch,
     stack: &Stack,
-    default_target: &Target,
+    target: &Target,
     check_commit: &mut IsCommitIntegrated,
     stack_dependencies: &StackDependencies,
     parent_series: &[&PatchSeries],
@@ -279,7 +274,7 @@ fn stack_branch_to_api_branch(
     let mut requires_force = false;
     let repo = ctx.repo();
     let branch_commits = stack_branch.commits(ctx, stack)?;
-    let remote = default_target.push_remote_name();
+    let remote = target.push_remote_name();
     let upstream_reference = if stack_branch.pushed(remote.as_str(), repo) {
         Some(stack_branch.remote_reference(remote.as_str()))
     } else {
@@ -57,9 +57,7 @@ pub(crate) fn undo_commit(
     }
 
     let new_head = output.top_commit.to_git2();
-    stack.set_stack_head(&vb_state, &repo, new_head, None)?;
-
-    stack.set_heads_from_rebase_output(ctx, output.references)?;
+    stack.set_stack_head_and_heads_from_rebase_output(ctx, new_head, None, output.references)?;
 
     crate::integration::update_workspace_commit(&vb_state, ctx)
         .context("failed to update gitbutler workspace")?;
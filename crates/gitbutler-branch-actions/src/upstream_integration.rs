@@ -1,19 +1,29 @@
 use crate::stack::branch_integrated;
 use crate::{r#virtual::IsCommitIntegrated, BranchManagerExt, VirtualBranchesExt as _};
 use anyhow::{anyhow, bail, Context, Result};
+use bstr::ByteSlice;
 use but_core::Reference;
-use but_rebase::{RebaseOutput, RebaseStep};
+use but_rebase::{resolution::RecordedResolutions, RebaseOutput, RebaseStep};
 use but_workspace::stack_ext::StackExt;
-use gitbutler_cherry_pick::RepositoryExt;
+use gitbutler_cherry_pick::{ConflictedTreeKey, RepositoryExt};
 use gitbutler_command_context::CommandContext;
 use gitbutler_commit::commit_ext::CommitExt as _;
+use gitbutler_oplog::{
+    entry::{OperationKind, SnapshotDetails, Trailer},
+    OplogExt,
+};
 use gitbutler_oxidize::{
     git2_to_gix_object_id, gix_to_git2_oid, GixRepositoryExt, ObjectIdExt, OidExt, RepoExt,
 };
 use gitbutler_project::access::WorktreeWritePermission;
+use gitbutler_reference::RemoteRefname;
 use gitbutler_repo::logging::RepositoryExt as _;
 use gitbutler_repo::RepositoryExt as _;
-use gitbutler_repo::{logging::LogUntil, rebase::gitbutler_merge_commits};
+use gitbutler_repo::{
+    logging::LogUntil,
+    rebase::{gitbutler_merge_commits, ConflictEntries},
+};
+use gitbutler_repo_actions::RepoActionsExt as _;
 use gitbutler_serde::BStringForFrontend;
 
 use gitbutler_stack::{Stack, StackId, Target, VirtualBranchesHandle};
@@ -21,7 +31,9 @@ use gitbutler_workspace::branch_trees::{update_uncommited_changes, WorkspaceStat
 #[allow(deprecated)]
 use gitbutler_workspace::{checkout_branch_trees, compute_updated_branch_head};
 use gix::merge::tree::TreatAsUnresolved;
+use gix::refs::transaction::{Change, PreviousValue, RefEdit, RefLog};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, PartialEq, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -100,6 +112,55 @@ pub struct IntegrationOutcome {
     archived_branches: Vec<String>,
     /// This is the list of review ids that have been closed as a result of the upstream integration
     review_ids_to_close: Vec<String>,
+    /// This is the list of branch names that have been deleted on the push remote as a result of the upstream integration
+    deleted_remote_branches: Vec<String>,
+    /// This is the list of branch names that were not deleted on the push remote even though they are integrated,
+    /// as they changed there since they were last fetched, or couldn't be deleted.
+    kept_remote_branches: Vec<String>,
+}
+
+/// What integrating upstream with a set of [resolutions](Resolution) would result in.
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrationPreview {
+    /// The paths of uncommitted changes that would conflict with the new target.
+    pub worktree_conflicts: Vec<BStringForFrontend>,
+    /// The outcome for each stack in the workspace.
+    pub stacks: Vec<(StackId, StackIntegrationPreview)>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(tag = "type", content = "subject", rename_all = "camelCase")]
+pub enum StackIntegrationPreview {
+    /// The stack would be updated to contain the given branches.
+    Updated {
+        branches: Vec<BranchIntegrationPreview>,
+    },
+    /// The stack would be unapplied.
+    Unapplied,
+    /// The stack would be deleted.
+    Deleted,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchIntegrationPreview {
+    pub name: String,
+    /// The commits the branch would have, from top to bottom.
+    pub commits: Vec<CommitIntegrationPreview>,
+    /// If all commits of the branch are integrated, so the branch would be archived.
+    pub archived: bool,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitIntegrationPreview {
+    /// The commit this one would be created from, or `None` if it's the merge commit that integrates the new target.
+    #[serde(with = "gitbutler_serde::oid_opt")]
+    pub original_id: Option<git2::Oid>,
+    pub message: BStringForFrontend,
+    /// The paths that would be conflicted in this commit, if any.
+    pub conflicted_paths: Vec<BStringForFrontend>,
 }
 
 impl StackStatus {
@@ -154,6 +215,9 @@ pub struct Resolution {
     pub branch_id: StackId,
     pub approach: ResolutionApproach,
     pub delete_integrated_branches: bool,
+    /// Also delete integrated branches that GitButler created on the push remote, along with their local references.
+    #[serde(default)]
+    pub delete_integrated_remote_branches: bool,
 }
 
 enum IntegrationResult {
@@ -173,6 +237,9 @@ pub struct UpstreamIntegrationContext<'a> {
     stacks_in_workspace: Vec<Stack>,
     new_target: git2::Commit<'a>,
     target: Target,
+    /// The targets of stacks in the workspace that don't integrate with the default `target`,
+    /// along with the commit their target branch is now at.
+    stack_targets: HashMap<StackId, (Target, git2::Commit<'a>)>,
     ctx: &'a CommandContext,
     gix_repo: &'a gix::Repository,
 }
@@ -187,27 +254,51 @@ impl<'a> UpstreamIntegrationContext<'a> {
         let virtual_branches_handle = ctx.project().virtual_branches();
         let target = virtual_branches_handle.get_default_target()?;
         let repo = ctx.repo();
-        let target_branch = repo
-            .maybe_find_branch_by_refname(&target.branch.clone().into())?
-            .ok_or(anyhow!("Branch not found"))?;
 
-        let new_target = target_commit_oid.map_or_else(
-            || target_branch.get().peel_to_commit(),
-            |oid| repo.find_commit(oid),
-        )?;
+        let new_target = match target_commit_oid {
+            Some(oid) => repo.find_commit(oid)?,
+            None => target_branch_head(repo, &target)?,
+        };
 
         let stacks_in_workspace = virtual_branches_handle.list_stacks_in_workspace()?;
+        let mut stack_targets = HashMap::new();
+        for stack in &stacks_in_workspace {
+            if let Some(stack_target) = virtual_branches_handle.maybe_get_stack_target(stack.id)? {
+                let new_stack_target = target_branch_head(repo, &stack_target)?;
+                stack_targets.insert(stack.id, (stack_target, new_stack_target));
+            }
+        }
 
         Ok(Self {
             _permission: Some(permission),
             repo,
             new_target,
             target: target.clone(),
+            stack_targets,
             stacks_in_workspace,
             ctx,
             gix_repo,
         })
     }
+
+    /// Return the target of the stack with `stack_id` and the commit its target branch is now at.
+    fn target_of(&self, stack_id: StackId) -> (&Target, &git2::Commit<'a>) {
+        self.stack_targets
+            .get(&stack_id)
+            .map_or((&self.target, &self.new_target), |(target, new_target)| {
+                (target, new_target)
+            })
+    }
+}
+
+fn target_branch_head<'repo>(
+    repo: &'repo git2::Repository,
+    target: &Target,
+) -> Result<git2::Commit<'repo>> {
+    let target_branch = repo
+        .maybe_find_branch_by_refname(&target.branch.clone().into())?
+        .ok_or(anyhow!("Branch not found"))?;
+    Ok(target_branch.get().peel_to_commit()?)
 }
 
 /// Returns the status of a stack
@@ -300,6 +391,9 @@ fn get_stack_status(
             .collect();
         let mut rebase = but_rebase::Rebase::new(gix_repo, Some(rebase_base.to_gix()), None)?;
         rebase.rebase_noops(false);
+        rebase.recorded_resolutions(RecordedResolutions::at(
+            ctx.project().recorded_resolutions_dir(),
+        ));
         rebase.steps(steps)?;
         let output = rebase.rebase()?;
         let new_head_oid = output.top_commit.to_git2();
@@ -367,21 +461,70 @@ pub fn upstream_integration_statuses(
         repo,
         new_target,
         target,
+        stack_targets,
         stacks_in_workspace,
         ..
     } = context;
-    let old_target = repo.find_commit(target.sha)?;
 
     let gix_repo = gitbutler_command_context::gix_repo_for_merging(repo.path())?;
     let gix_repo_in_memory = gix_repo.clone().with_object_memory();
 
-    if new_target.id() == old_target.id() {
+    let default_target_is_up_to_date = new_target.id() == target.sha;
+    if default_target_is_up_to_date
+        && stack_targets
+            .values()
+            .all(|(stack_target, new_stack_target)| new_stack_target.id() == stack_target.sha)
+    {
         return Ok(StackStatuses::UpToDate);
     };
 
+    let worktree_conflicts = if default_target_is_up_to_date {
+        Vec::new()
+    } else {
+        worktree_conflicts_with_new_target(context, &gix_repo)?
+    };
+
+    let statuses = stacks_in_workspace
+        .iter()
+        .map(|stack| {
+            let (stack_target, new_stack_target) = context.target_of(stack.id);
+            Ok((
+                stack.id,
+                get_stack_status(
+                    repo,
+                    &gix_repo_in_memory,
+                    stack_target.clone(),
+                    git2_to_gix_object_id(new_stack_target.id()),
+                    stack,
+                    context.ctx,
+                )?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(StackStatuses::UpdatesRequired {
+        worktree_conflicts,
+        statuses,
+    })
+}
+
+/// Return the paths of uncommitted changes that would conflict with the new commits of the default target.
+fn worktree_conflicts_with_new_target(
+    context: &UpstreamIntegrationContext,
+    gix_repo: &gix::Repository,
+) -> Result<Vec<BStringForFrontend>> {
+    let UpstreamIntegrationContext {
+        new_target,
+        stack_targets,
+        stacks_in_workspace,
+        ..
+    } = context;
+
+    // Stacks with their own target don't have to be based on the default target at all.
     let heads = stacks_in_workspace
         .iter()
-        .map(|stack| stack.head_oid(&gix_repo))
+        .filter(|stack| !stack_targets.contains_key(&stack.id))
+        .map(|stack| stack.head_oid(gix_repo))
         .chain(Some(Ok(new_target.id().to_gix())))
         .collect::<Result<Vec<_>>>()?;
 
@@ -435,29 +578,229 @@ pub fn upstream_integration_statuses(
         .map(|c| c.ours.location().into())
         .collect::<Vec<BStringForFrontend>>();
 
-    let statuses = stacks_in_workspace
+    Ok(worktree_conflicts)
+}
+
+/// Simulate [`integrate_upstream()`] with `resolutions` in memory and return what the stacks would look like.
+/// Nothing is written to disk, and no reference is changed.
+pub(crate) fn preview_upstream_integration(
+    ctx: &CommandContext,
+    resolutions: &[Resolution],
+    base_branch_resolution: Option<BaseBranchResolution>,
+    permission: &mut WorktreeWritePermission,
+) -> Result<IntegrationPreview> {
+    let (target_commit_oid, base_branch_resolution_approach) = base_branch_resolution
+        .map(|r| (Some(r.target_commit_oid), Some(r.approach)))
+        .unwrap_or((None, None));
+
+    let gix_repo = ctx.gix_repo()?;
+    let context = &UpstreamIntegrationContext::open(ctx, target_commit_oid, permission, &gix_repo)?;
+    let UpstreamIntegrationContext {
+        repo,
+        new_target,
+        target,
+        stacks_in_workspace,
+        ..
+    } = context;
+
+    let merge_repo = gitbutler_command_context::gix_repo_for_merging(repo.path())?;
+    let gix_repo_in_memory = merge_repo.clone().with_object_memory();
+
+    let worktree_conflicts = if new_target.id() == target.sha {
+        Vec::new()
+    } else {
+        worktree_conflicts_with_new_target(context, &merge_repo)?
+    };
+
+    let stacks = resolutions
         .iter()
-        .map(|stack| {
-            Ok((
-                stack.id,
-                get_stack_status(
-                    repo,
+        .map(|resolution| {
+            let Some(stack) = stacks_in_workspace
+                .iter()
+                .find(|stack| stack.id == resolution.branch_id)
+            else {
+                bail!("Failed to find virtual branch");
+            };
+            let preview = match resolution.approach {
+                ResolutionApproach::Unapply => StackIntegrationPreview::Unapplied,
+                ResolutionApproach::Delete => StackIntegrationPreview::Deleted,
+                ResolutionApproach::Merge => preview_merge(context, &gix_repo_in_memory, stack)?,
+                ResolutionApproach::Rebase => preview_rebase(
+                    context,
                     &gix_repo_in_memory,
-                    target.clone(),
-                    git2_to_gix_object_id(new_target.id()),
                     stack,
-                    context.ctx,
+                    base_branch_resolution_approach.as_ref(),
                 )?,
-            ))
+            };
+            Ok((stack.id, preview))
         })
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(StackStatuses::UpdatesRequired {
+    Ok(IntegrationPreview {
         worktree_conflicts,
-        statuses,
+        stacks,
+    })
+}
+
+/// Rebase `stack` like [`compute_resolutions()`] would, but write all objects into `gix_repo_in_memory`.
+fn preview_rebase(
+    context: &UpstreamIntegrationContext,
+    gix_repo_in_memory: &gix::Repository,
+    stack: &Stack,
+    base_branch_resolution_approach: Option<&BaseBranchResolutionApproach>,
+) -> Result<StackIntegrationPreview> {
+    let IntegrationRebase {
+        onto,
+        steps,
+        for_archival,
+    } = integration_rebase(context, stack, base_branch_resolution_approach)?;
+
+    let mut rebase = but_rebase::Rebase::new(gix_repo_in_memory, Some(onto.to_gix()), None)?;
+    rebase.rebase_noops(false);
+    rebase.recorded_resolutions(RecordedResolutions::at(
+        context.ctx.project().recorded_resolutions_dir(),
+    ));
+    rebase.steps(steps.clone())?;
+    let output = rebase.rebase()?;
+
+    let branches = as_buckets(steps)
+        .into_iter()
+        .rev()
+        .map(|(reference, picks)| {
+            let commits = picks
+                .iter()
+                .rev()
+                .filter_map(RebaseStep::commit_id)
+                .map(|original_id| {
+                    let original_id = original_id.to_owned();
+                    let new_id = output
+                        .commit_mapping
+                        .iter()
+                        .find_map(|(_base, old, new)| (*old == original_id).then_some(*new))
+                        .context("Every picked commit is rebased")?;
+                    preview_commit(gix_repo_in_memory, new_id, Some(original_id.to_git2()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(BranchIntegrationPreview {
+                archived: for_archival.contains(&reference),
+                name: reference_name(&reference),
+                commits,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(StackIntegrationPreview::Updated { branches })
+}
+
+/// Merge the new target into `stack` like [`compute_resolutions()`] would, but only in memory.
+fn preview_merge(
+    context: &UpstreamIntegrationContext,
+    gix_repo_in_memory: &gix::Repository,
+    stack: &Stack,
+) -> Result<StackIntegrationPreview> {
+    let (target, new_target) = context.target_of(stack.id);
+    let head = stack.head_oid(gix_repo_in_memory)?;
+    let new_target_id = new_target.id().to_gix();
+    let merge_base = gix_repo_in_memory.merge_base(head, new_target_id)?.detach();
+    let tree_of = |id: gix::ObjectId| -> Result<gix::ObjectId> {
+        Ok(but_core::Commit::from_id(id.attach(gix_repo_in_memory))?
+            .tree_id_or_auto_resolution()?
+            .detach())
+    };
+    let merge = gix_repo_in_memory.merge_trees(
+        tree_of(merge_base)?,
+        tree_of(new_target_id)?,
+        tree_of(head)?,
+        gix_repo_in_memory.default_merge_labels(),
+        gix_repo_in_memory.merge_options_force_ours()?,
+    )?;
+    let mut conflicted_paths = Vec::new();
+    for conflict in merge
+        .conflicts
+        .iter()
+        .filter(|c| c.is_unresolved(TreatAsUnresolved::forced_resolution()))
+    {
+        let (ours, theirs) = conflict.changes_in_resolution();
+        for path in [ours.location(), theirs.location()] {
+            let path = BStringForFrontend::from(path);
+            if !conflicted_paths.contains(&path) {
+                conflicted_paths.push(path);
+            }
+        }
+    }
+
+    let top_branch = stack.heads.last().context("top branch not found")?;
+    let merge_commit = CommitIntegrationPreview {
+        original_id: None,
+        message: format!(
+            "Merge `{}` into `{}`",
+            target.branch.fullname(),
+            top_branch.name()
+        )
+        .into(),
+        conflicted_paths,
+    };
+
+    let mut branches = as_buckets(stack.as_rebase_steps(context.ctx, gix_repo_in_memory)?)
+        .into_iter()
+        .rev()
+        .map(|(reference, picks)| {
+            let commits = picks
+                .iter()
+                .rev()
+                .filter_map(RebaseStep::commit_id)
+                .map(|id| {
+                    let id = id.to_owned();
+                    preview_commit(gix_repo_in_memory, id, Some(id.to_git2()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(BranchIntegrationPreview {
+                name: reference_name(&reference),
+                commits,
+                archived: false,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(top) = branches.first_mut() {
+        top.commits.insert(0, merge_commit);
+    }
+    Ok(StackIntegrationPreview::Updated { branches })
+}
+
+fn preview_commit(
+    gix_repo: &gix::Repository,
+    id: gix::ObjectId,
+    original_id: Option<git2::Oid>,
+) -> Result<CommitIntegrationPreview> {
+    let commit = but_core::Commit::from_id(id.attach(gix_repo))?;
+    let conflicted_paths = if commit.is_conflicted() {
+        let conflict_files = gix_repo
+            .find_tree(commit.tree)?
+            .find_entry(&*ConflictedTreeKey::ConflictFiles)
+            .context("conflict files not found")?
+            .object()?;
+        toml::from_str::<ConflictEntries>(&conflict_files.data.to_str_lossy())
+            .unwrap_or_default()
+            .paths()
+            .into_iter()
+            .map(|path| gix::path::into_bstr(path).into_owned().into())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(CommitIntegrationPreview {
+        original_id,
+        message: commit.message.clone().into(),
+        conflicted_paths,
     })
 }
 
+fn reference_name(reference: &Reference) -> String {
+    match reference {
+        Reference::Git(name) => name.shorten().to_string(),
+        Reference::Virtual(name) => name.clone(),
+    }
+}
+
 pub(crate) fn integrate_upstream(
     ctx: &CommandContext,
     resolutions: &[Resolution],
@@ -471,9 +814,11 @@ pub(crate) fn integrate_upstream(
         .unwrap_or((None, None));
 
     let gix_repo = ctx.gix_repo()?;
-    let context = UpstreamIntegrationContext::open(ctx, target_commit_oid, permission, &gix_repo)?;
+    let mut context =
+        UpstreamIntegrationContext::open(ctx, target_commit_oid, permission, &gix_repo)?;
     let virtual_branches_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
     let default_target = virtual_branches_state.get_default_target()?;
+    let push_remote = default_target.push_remote_name();
 
     let mut newly_archived_branches = vec![];
     let mut to_be_closed_review_ids = vec![];
@@ -512,6 +857,23 @@ pub(crate) fn integrate_upstream(
 
     let integration_results =
         compute_resolutions(&context, resolutions, base_branch_resolution_approach)?;
+    let remote_branches_to_delete =
+        integrated_remote_branches(&context, &push_remote, resolutions, &integration_results)?;
+
+    // Record the local references we are about to delete so restoring the snapshot brings them back.
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::UpdateWorkspaceBase).with_trailers(
+            remote_branches_to_delete
+                .iter()
+                .flat_map(|branch| &branch.local_refs)
+                .map(|(name, id)| Trailer::deleted_reference(name.as_ref(), *id))
+                .collect(),
+        ),
+        context
+            ._permission
+            .as_deref_mut()
+            .expect("Permission provided above"),
+    );
 
     {
         // We preform the updates in stages. If deleting or unapplying fails, we
@@ -577,6 +939,16 @@ pub(crate) fn integrate_upstream(
             }
             stack.set_stack_head(&virtual_branches_state, &gix_repo, *head, *tree)?;
 
+            if let Some((stack_target, new_stack_target)) = context.stack_targets.get(branch_id) {
+                virtual_branches_state.set_stack_target(
+                    *branch_id,
+                    Target {
+                        sha: new_stack_target.id(),
+                        ..stack_target.clone()
+                    },
+                )?;
+            }
+
             let delete_local_refs = resolutions
                 .iter()
                 .find(|r| r.branch_id == *branch_id)
@@ -618,9 +990,186 @@ pub(crate) fn integrate_upstream(
         crate::integration::update_workspace_commit(&virtual_branches_state, ctx)?;
     }
 
+    let (deleted_remote_branches, kept_remote_branches) =
+        delete_integrated_remote_branches(ctx, &gix_repo, &push_remote, remote_branches_to_delete);
+
     Ok(IntegrationOutcome {
         archived_branches: newly_archived_branches,
         review_ids_to_close: to_be_closed_review_ids,
+        deleted_remote_branches,
+        kept_remote_branches,
+    })
+}
+
+/// An integrated branch that GitButler created, and which still exists on the push remote.
+struct IntegratedRemoteBranch {
+    name: String,
+    /// The remote tracking reference and the local branch, along with the commit they point to.
+    local_refs: Vec<(gix::refs::FullName, gix::ObjectId)>,
+}
+
+/// Find the branches to delete on `remote` for all stacks whose resolution asks for it.
+/// These are the branches that are archived or will be archived according to `integration_results`,
+/// or all branches of stacks that will be deleted.
+/// Branches the stacks were created from are kept, as GitButler didn't create them.
+fn integrated_remote_branches(
+    context: &UpstreamIntegrationContext,
+    remote: &str,
+    resolutions: &[Resolution],
+    integration_results: &[(StackId, IntegrationResult)],
+) -> Result<Vec<IntegratedRemoteBranch>> {
+    let UpstreamIntegrationContext {
+        gix_repo,
+        stacks_in_workspace,
+        ..
+    } = context;
+
+    let mut out = Vec::new();
+    for (stack_id, integration_result) in integration_results {
+        let wants_deletion = resolutions
+            .iter()
+            .find(|r| r.branch_id == *stack_id)
+            .is_some_and(|r| r.delete_integrated_remote_branches);
+        if !wants_deletion {
+            continue;
+        }
+        let Some(stack) = stacks_in_workspace.iter().find(|s| s.id == *stack_id) else {
+            continue;
+        };
+
+        for head in &stack.heads {
+            let full_name = head.full_name()?;
+            let is_integrated = match integration_result {
+                IntegrationResult::DeleteBranch => true,
+                IntegrationResult::UpdatedObjects { for_archival, .. } => {
+                    head.archived
+                        || for_archival.iter().any(|reference| match reference {
+                            Reference::Git(r) => r == &full_name,
+                            Reference::Virtual(r) => r == head.name(),
+                        })
+                }
+                IntegrationResult::UnapplyBranch => false,
+            };
+            // Only delete branches we created, and not the one the stack was created from.
+            let is_source_branch = stack
+                .source_refname
+                .as_ref()
+                .and_then(|source| source.branch())
+                .is_some_and(|source_branch| source_branch == head.name());
+            if !is_integrated || is_source_branch {
+                continue;
+            }
+
+            let Some(mut remote_ref) =
+                gix_repo.try_find_reference(head.remote_reference(remote).as_str())?
+            else {
+                continue;
+            };
+            let mut local_refs = vec![(
+                remote_ref.name().to_owned(),
+                remote_ref.peel_to_id_in_place()?.detach(),
+            )];
+            if let Some(mut local_ref) = gix_repo.try_find_reference(full_name.as_bstr())? {
+                local_refs.push((full_name, local_ref.peel_to_id_in_place()?.detach()));
+            }
+            out.push(IntegratedRemoteBranch {
+                name: head.name().to_owned(),
+                local_refs,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Delete `branches` on `remote` along with their local references, and return the names of the deleted branches
+/// along with the names of the branches that were kept.
+///
+/// Branches are only deleted if they still point to the commit they pointed to when they were last fetched,
+/// and kept otherwise. Failures are only logged as the integration itself is complete at this point.
+fn delete_integrated_remote_branches(
+    ctx: &CommandContext,
+    repo: &gix::Repository,
+    remote: &str,
+    branches: Vec<IntegratedRemoteBranch>,
+) -> (Vec<String>, Vec<String>) {
+    let (mut deleted_branches, mut kept_branches) = (Vec::new(), Vec::new());
+    if branches.is_empty() {
+        return (deleted_branches, kept_branches);
+    }
+    // Refresh the remote tracking branches, as deleting a branch someone pushed to since would lose their commits.
+    if let Err(err) = ctx.fetch(remote, Some("delete_integrated_remote_branches".into())) {
+        tracing::warn!("Could not fetch '{remote}' to delete integrated branches: {err}");
+        kept_branches.extend(branches.into_iter().map(|branch| branch.name));
+        return (deleted_branches, kept_branches);
+    }
+
+    for IntegratedRemoteBranch { name, local_refs } in branches {
+        let (remote_ref_name, expected_id) = &local_refs[0];
+        let remote_id = match current_id(repo, remote_ref_name) {
+            Ok(remote_id) => remote_id,
+            Err(err) => {
+                tracing::warn!("Could not read remote tracking branch of '{name}': {err}");
+                kept_branches.push(name);
+                continue;
+            }
+        };
+        match remote_id {
+            Some(id) if id != *expected_id => {
+                tracing::warn!(
+                    "Keeping integrated branch '{name}' on '{remote}' as it changed to {id} since it was last fetched"
+                );
+                kept_branches.push(name);
+                continue;
+            }
+            Some(_) => {
+                // The lease makes the push fail if the branch changed after the fetch.
+                if let Err(err) = ctx.push(
+                    git2::Oid::zero(),
+                    &RemoteRefname::new(remote, &name),
+                    true,
+                    Some(format!(":refs/heads/{name}")),
+                    None,
+                ) {
+                    tracing::warn!(
+                        "Could not delete integrated branch '{name}' on '{remote}': {err}"
+                    );
+                    kept_branches.push(name);
+                    continue;
+                }
+            }
+            // The branch is already gone on the remote, and so is its remote tracking branch.
+            None => {}
+        }
+
+        // Skip the remote tracking branch if the fetch removed it already.
+        for (ref_name, id) in local_refs
+            .into_iter()
+            .skip(usize::from(remote_id.is_none()))
+        {
+            let delete = RefEdit {
+                change: Change::Delete {
+                    expected: PreviousValue::ExistingMustMatch(id.into()),
+                    log: RefLog::AndReference,
+                },
+                name: ref_name,
+                deref: false,
+            };
+            if let Err(err) = repo.edit_reference(delete) {
+                tracing::warn!(
+                    "Could not delete local reference of integrated branch '{name}': {err}"
+                );
+            }
+        }
+        deleted_branches.push(name);
+    }
+    (deleted_branches, kept_branches)
+}
+
+/// Return the id `name` points to, or `None` if it doesn't exist.
+fn current_id(repo: &gix::Repository, name: &gix::refs::FullName) -> Result<Option<gix::ObjectId>> {
+    Ok(match repo.try_find_reference(name.as_bstr())? {
+        Some(mut reference) => Some(reference.peel_to_id_in_place()?.detach()),
+        None => None,
     })
 }
 
@@ -664,6 +1213,9 @@ pub(crate) fn resolve_upstream_integration(
                 but_rebase::Rebase::new(&gix_repo, Some(new_target_id.to_gix()), None)?;
             rebase.steps(steps)?;
             rebase.rebase_noops(false);
+            rebase.recorded_resolutions(RecordedResolutions::at(
+                ctx.project().recorded_resolutions_dir(),
+            ));
             let outcome = rebase.rebase()?;
             let new_head = outcome.top_commit.to_git2();
 
@@ -679,8 +1231,6 @@ fn compute_resolutions(
 ) -> Result<Vec<(StackId, IntegrationResult)>> {
     let UpstreamIntegrationContext {
         repo,
-        new_target,
-        target,
         stacks_in_workspace,
         ctx,
         ..
@@ -695,6 +1245,7 @@ fn compute_resolutions(
             else {
                 bail!("Failed to find virtual branch");
             };
+            let (target, new_target) = context.target_of(branch_stack.id);
 
             match resolution.approach {
                 ResolutionApproach::Unapply => {
@@ -748,75 +1299,22 @@ fn compute_resolutions(
                     ))
                 }
                 ResolutionApproach::Rebase => {
-                    let gix_repo = gitbutler_command_context::gix_repo_for_merging(repo.path())?;
-                    let cache = gix_repo.commit_graph_if_enabled()?;
-                    let mut graph = gix_repo.revision_graph(cache.as_ref());
-                    let upstream_commit_oids =
-                        repo.l(new_target.id(), LogUntil::Commit(target.sha), true)?;
-                    let mut check_commit = IsCommitIntegrated::new_basic(
-                        &gix_repo,
-                        repo,
-                        &mut graph,
-                        git2_to_gix_object_id(target.sha),
-                        git2_to_gix_object_id(new_target.tree_id()),
-                        upstream_commit_oids,
-                    );
-
-                    // Rebase the commits, then try rebasing the tree. If
-                    // the tree ends up conflicted, commit the tree.
-
-                    // If the base branch needs to resolve its divergence
-                    // pick only the commits that are ahead of the old target head
-                    let lower_bound = if base_branch_resolution_approach.is_some() {
-                        target.sha
-                    } else {
-                        new_target.id()
-                    };
-
-                    let all_steps = branch_stack.as_rebase_steps(context.ctx, context.gix_repo)?;
-                    let branches_before = as_buckets(all_steps.clone());
-                    // Filter out any integrated commits
-                    let steps = all_steps
-                        .into_iter()
-                        .filter_map(|s| match s {
-                            RebaseStep::Pick {
-                                commit_id,
-                                new_message: _,
-                            } => {
-                                let commit = repo.find_commit(commit_id.to_git2()).ok()?;
-                                let is_integrated = check_commit.is_integrated(&commit).ok()?;
-                                if is_integrated {
-                                    None
-                                } else {
-                                    Some(s)
-                                }
-                            }
-                            _ => Some(s),
-                        })
-                        .collect::<Vec<_>>();
-
-                    let branches_after = as_buckets(steps.clone());
-
-                    // Branches that used to have commits but now don't are marked for archival
-                    let mut for_archival = vec![];
-                    for (ref_before, steps_before) in branches_before {
-                        if let Some((_, steps_after)) = branches_after
-                            .iter()
-                            .find(|(ref_after, _)| ref_after == &ref_before)
-                        {
-                            // if there were steps before and now there are none, this should be marked for archival
-                            if !steps_before.is_empty() && steps_after.is_empty() {
-                                for_archival.push(ref_before);
-                            }
-                        }
-                    }
-
-                    let mut rebase = but_rebase::Rebase::new(
-                        context.gix_repo,
-                        Some(lower_bound.to_gix()),
-                        None,
+                    let IntegrationRebase {
+                        onto,
+                        steps,
+                        for_archival,
+                    } = integration_rebase(
+                        context,
+                        branch_stack,
+                        base_branch_resolution_approach.as_ref(),
                     )?;
+
+                    let mut rebase =
+                        but_rebase::Rebase::new(context.gix_repo, Some(onto.to_gix()), None)?;
                     rebase.rebase_noops(false);
+                    rebase.recorded_resolutions(RecordedResolutions::at(
+                        context.ctx.project().recorded_resolutions_dir(),
+                    ));
                     rebase.steps(steps)?;
                     let output = rebase.rebase()?;
                     let new_head = output.top_commit.to_git2();
@@ -828,7 +1326,7 @@ fn compute_resolutions(
                         #[allow(deprecated)]
                         let res = compute_updated_branch_head(
                             repo,
-                            &gix_repo,
+                            context.gix_repo,
                             branch_stack,
                             new_head,
                             ctx,
@@ -853,6 +1351,96 @@ fn compute_resolutions(
     Ok(results)
 }
 
+/// The steps to rebase a stack onto its new target with.
+struct IntegrationRebase {
+    /// The commit to rebase onto.
+    onto: git2::Oid,
+    /// The steps to rebase, without the commits that are integrated already.
+    steps: Vec<RebaseStep>,
+    /// The references which don't have commits anymore once integrated commits are dropped.
+    for_archival: Vec<Reference>,
+}
+
+/// Compute how to rebase `branch_stack` onto its new target, dropping all commits that are integrated.
+fn integration_rebase(
+    context: &UpstreamIntegrationContext,
+    branch_stack: &Stack,
+    base_branch_resolution_approach: Option<&BaseBranchResolutionApproach>,
+) -> Result<IntegrationRebase> {
+    let repo = context.repo;
+    let (target, new_target) = context.target_of(branch_stack.id);
+    let gix_repo = gitbutler_command_context::gix_repo_for_merging(repo.path())?;
+    let cache = gix_repo.commit_graph_if_enabled()?;
+    let mut graph = gix_repo.revision_graph(cache.as_ref());
+    let upstream_commit_oids = repo.l(new_target.id(), LogUntil::Commit(target.sha), true)?;
+    let mut check_commit = IsCommitIntegrated::new_basic(
+        &gix_repo,
+        repo,
+        &mut graph,
+        git2_to_gix_object_id(target.sha),
+        git2_to_gix_object_id(new_target.tree_id()),
+        upstream_commit_oids,
+    );
+
+    // Rebase the commits, then try rebasing the tree. If
+    // the tree ends up conflicted, commit the tree.
+
+    // If the base branch needs to resolve its divergence
+    // pick only the commits that are ahead of the old target head.
+    // This never applies to stacks with their own target.
+    let onto = if base_branch_resolution_approach.is_some()
+        && !context.stack_targets.contains_key(&branch_stack.id)
+    {
+        target.sha
+    } else {
+        new_target.id()
+    };
+
+    let all_steps = branch_stack.as_rebase_steps(context.ctx, context.gix_repo)?;
+    let branches_before = as_buckets(all_steps.clone());
+    // Filter out any integrated commits
+    let steps = all_steps
+        .into_iter()
+        .filter_map(|s| match s {
+            RebaseStep::Pick {
+                commit_id,
+                new_message: _,
+            } => {
+                let commit = repo.find_commit(commit_id.to_git2()).ok()?;
+                let is_integrated = check_commit.is_integrated(&commit).ok()?;
+                if is_integrated {
+                    None
+                } else {
+                    Some(s)
+                }
+            }
+            _ => Some(s),
+        })
+        .collect::<Vec<_>>();
+
+    let branches_after = as_buckets(steps.clone());
+
+    // Branches that used to have commits but now don't are marked for archival
+    let mut for_archival = vec![];
+    for (ref_before, steps_before) in branches_before {
+        if let Some((_, steps_after)) = branches_after
+            .iter()
+            .find(|(ref_after, _)| ref_after == &ref_before)
+        {
+            // if there were steps before and now there are none, this should be marked for archival
+            if !steps_before.is_empty() && steps_after.is_empty() {
+                for_archival.push(ref_before);
+            }
+        }
+    }
+
+    Ok(IntegrationRebase {
+        onto,
+        steps,
+        for_archival,
+    })
+}
+
 pub(crate) fn as_buckets(steps: Vec<RebaseStep>) -> Vec<(but_core::Reference, Vec<RebaseStep>)> {
     let mut buckets = vec![];
     let mut current_steps = vec![];
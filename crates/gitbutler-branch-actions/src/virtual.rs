@@ -327,6 +327,7 @@ pub fn list_virtual_branches_cached(
     let default_target = vb_state
         .get_default_target()
         .context("failed to get default target")?;
+    let stack_targets = vb_state.list_stack_targets()?;
 
     let status = get_applied_status_cached(ctx, Some(perm), worktree_changes)?;
     let max_selected_for_changes = status
@@ -349,18 +350,18 @@ pub fn list_virtual_branches_cached(
             None => None,
         };
 
+        let target = stack_targets.get(&branch.id).unwrap_or(&default_target);
         // find all commits on head that are not on target.sha
         let commits = repo.log(
             branch.head_oid(&gix_repo)?.to_git2(),
-            LogUntil::Commit(default_target.sha),
+            LogUntil::Commit(target.sha),
             false,
         )?;
-        let mut check_commit =
-            IsCommitIntegrated::new(ctx, &default_target, &gix_repo, &mut graph)?;
+        let mut check_commit = IsCommitIntegrated::new(ctx, target, &gix_repo, &mut graph)?;
 
         let merge_base = gix_repo
             .merge_base_with_graph(
-                default_target.sha.to_gix(),
+                target.sha.to_gix(),
                 branch.head_oid(&gix_repo)?,
                 check_commit.graph,
             )
@@ -404,7 +405,7 @@ pub fn list_virtual_branches_cached(
         let (series, force) = stack_series(
             ctx,
             &mut branch,
-            &default_target,
+            target,
             &mut check_commit,
             stack_dependencies,
         );
@@ -1318,8 +1319,7 @@ pub(crate) fn update_commit_message(
     let output = rebase.rebase()?;
 
     let new_head = output.top_commit.to_git2();
-    stack.set_stack_head(&vb_state, &gix_repo, new_head, None)?;
-    stack.set_heads_from_rebase_output(ctx, output.references)?;
+    stack.set_stack_head_and_heads_from_rebase_output(ctx, new_head, None, output.references)?;
 
     crate::integration::update_workspace_commit(&vb_state, ctx)
         .context("failed to update gitbutler workspace")?;
@@ -1,6 +1,8 @@
+use bstr::ByteSlice;
 use but_workspace::{DiffSpec, HunkHeader};
 use gitbutler_branch::BranchCreateRequest;
-use gitbutler_branch_actions::list_commit_files;
+use gitbutler_branch_actions::{list_commit_files, AbsorbRejection};
+use gitbutler_oxidize::{ObjectIdExt, OidExt};
 
 use super::*;
 
@@ -207,6 +209,238 @@ fn locked_hunk() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn absorb_locked_hunks_only() -> anyhow::Result<()> {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    // create commit
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    let commit_oid =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None).unwrap();
+
+    // change the committed line, and add a file that isn't known to any commit
+    fs::write(repo.path().join("file.txt"), "more content").unwrap();
+    fs::write(repo.path().join("file2.txt"), "content2").unwrap();
+    let outcome = gitbutler_branch_actions::absorb(ctx)?;
+
+    assert_eq!(outcome.absorbed.len(), 1);
+    assert_eq!(outcome.absorbed[0].stack_id, stack_entry.id);
+    assert_eq!(outcome.absorbed[0].old_commit_id, commit_oid.to_gix());
+    assert_eq!(outcome.remaining.len(), 1);
+    assert_eq!(outcome.remaining[0].path, "file2.txt");
+    assert_eq!(outcome.remaining[0].reason, AbsorbRejection::Unlocked);
+
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    assert_eq!(branch.series[0].clone()?.patches.len(), 1);
+    assert_eq!(
+        list_commit_files(ctx, branch.series[0].clone()?.patches[0].id)?[0].hunks[0].diff_lines,
+        "@@ -0,0 +1 @@\n+more content\n\\ No newline at end of file\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn absorb_into_commit_closest_to_tip() -> anyhow::Result<()> {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repo.path().join("file.txt"), "1\n2\n3\n4\n").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None).unwrap();
+    fs::write(repo.path().join("file.txt"), "1\n2\nthree\n4\n").unwrap();
+    let commit_two_oid =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None).unwrap();
+
+    // a single hunk that touches lines of both commits
+    fs::write(repo.path().join("file.txt"), "1\nTWO\nTHREE\n4\n").unwrap();
+    let outcome = gitbutler_branch_actions::absorb(ctx)?;
+
+    assert_eq!(outcome.remaining.len(), 0);
+    assert_eq!(outcome.absorbed.len(), 1);
+    assert_eq!(
+        outcome.absorbed[0].old_commit_id,
+        commit_two_oid.to_gix(),
+        "the commit closest to the tip of the stack is amended"
+    );
+
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    assert_eq!(branch.files.len(), 0);
+    let patches = branch.series[0].clone()?.patches;
+    assert_eq!(patches.len(), 2);
+    assert!(patches
+        .iter()
+        .any(|patch| patch.id.to_gix() == outcome.absorbed[0].new_commit_id));
+    let diff = list_commit_files(ctx, outcome.absorbed[0].new_commit_id.to_git2())?[0].hunks[0]
+        .diff_lines
+        .to_str_lossy()
+        .into_owned();
+    assert!(diff.contains("-2\n-3\n+TWO\n+THREE\n"), "{diff}");
+    Ok(())
+}
+
+#[test]
+fn absorb_into_multiple_commits_of_a_stack() -> anyhow::Result<()> {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    let commit_one_oid =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit one", None).unwrap();
+    fs::write(repo.path().join("file2.txt"), "content2").unwrap();
+    let commit_two_oid =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit two", None).unwrap();
+
+    fs::write(repo.path().join("file.txt"), "more content").unwrap();
+    fs::write(repo.path().join("file2.txt"), "more content2").unwrap();
+    let outcome = gitbutler_branch_actions::absorb(ctx)?;
+
+    assert_eq!(outcome.remaining.len(), 0);
+    assert_eq!(outcome.absorbed.len(), 2);
+    assert!(outcome
+        .absorbed
+        .iter()
+        .all(|absorbed| absorbed.stack_id == stack_entry.id));
+    let absorbed_into = |old_commit_id: git2::Oid| {
+        outcome
+            .absorbed
+            .iter()
+            .find(|absorbed| absorbed.old_commit_id == old_commit_id.to_gix())
+            .expect("each commit was amended")
+            .new_commit_id
+    };
+    let new_commit_one_oid = absorbed_into(commit_one_oid);
+    let new_commit_two_oid = absorbed_into(commit_two_oid);
+
+    let new_commit_two = repo.find_commit(new_commit_two_oid.to_git2())?;
+    assert_eq!(
+        new_commit_two.parent_id(0)?,
+        new_commit_one_oid.to_git2(),
+        "the stack was rebased so the amended commits are on top of each other"
+    );
+
+    let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap();
+    assert_eq!(branch.files.len(), 0);
+    assert_eq!(branch.series[0].clone()?.patches.len(), 2);
+    assert_eq!(
+        list_commit_files(ctx, new_commit_one_oid.to_git2())?[0].hunks[0].diff_lines,
+        "@@ -0,0 +1 @@\n+more content\n\\ No newline at end of file\n"
+    );
+    assert_eq!(
+        list_commit_files(ctx, new_commit_two_oid.to_git2())?[0].hunks[0].diff_lines,
+        "@@ -0,0 +1 @@\n+more content2\n\\ No newline at end of file\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn absorb_leaves_hunks_locked_to_multiple_stacks() -> anyhow::Result<()> {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    let mut lines = repo.gen_file("file.txt", 10);
+    repo.commit_all("initial commit");
+    repo.push();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry_1 =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    lines[1] = "change 1".to_string();
+    repo.write_file("file.txt", &lines);
+    gitbutler_branch_actions::create_commit(ctx, stack_entry_1.id, "commit to branch 1", None)
+        .unwrap();
+
+    let stack_entry_2 = gitbutler_branch_actions::create_virtual_branch(
+        ctx,
+        &BranchCreateRequest {
+            selected_for_changes: Some(true),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    lines[8] = "change 2".to_string();
+    repo.write_file("file.txt", &lines);
+    gitbutler_branch_actions::create_commit(ctx, stack_entry_2.id, "commit to branch 2", None)
+        .unwrap();
+
+    // a single hunk that touches the lines of both stacks
+    for line in &mut lines[1..=8] {
+        *line = format!("{line} again");
+    }
+    repo.write_file("file.txt", &lines);
+    let outcome = gitbutler_branch_actions::absorb(ctx)?;
+
+    assert_eq!(outcome.absorbed.len(), 0);
+    assert_eq!(outcome.remaining.len(), 1);
+    assert_eq!(outcome.remaining[0].path, "file.txt");
+    assert_eq!(outcome.remaining[0].reason, AbsorbRejection::Ambiguous);
+
+    for stack_id in [stack_entry_1.id, stack_entry_2.id] {
+        let branch = gitbutler_branch_actions::list_virtual_branches(ctx)
+            .unwrap()
+            .branches
+            .into_iter()
+            .find(|b| b.id == stack_id)
+            .unwrap();
+        assert_eq!(
+            branch.series[0].clone()?.patches.len(),
+            1,
+            "no commit was amended"
+        );
+    }
+    Ok(())
+}
+
 #[test]
 fn non_existing_ownership() {
     let Test { repo, ctx, .. } = &Test::default();
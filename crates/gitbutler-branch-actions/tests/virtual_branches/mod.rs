@@ -67,6 +67,7 @@ mod insert_blank_commit;
 mod list;
 mod list_details;
 mod locking;
+mod move_branch;
 mod move_commit_file;
 mod move_commit_to_vbranch;
 mod oplog;
@@ -76,6 +77,7 @@ mod save_and_unapply_virtual_branch;
 mod selected_for_changes;
 mod set_base_branch;
 mod squash;
+mod stack_target;
 mod unapply_ownership;
 mod unapply_without_saving_virtual_branch;
 mod undo_commit;
@@ -0,0 +1,123 @@
+use gitbutler_branch_actions::{stack::CreateSeriesRequest, BranchDestination, VirtualBranch};
+
+use super::Test;
+
+fn patches_by_series(branch: &VirtualBranch) -> Vec<(String, usize)> {
+    branch
+        .series
+        .iter()
+        .map(|series| {
+            let series = series.clone().unwrap();
+            (series.name, series.patches.len())
+        })
+        .collect()
+}
+
+#[test]
+fn into_new_stack_and_back() {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    std::fs::write(repo.path().join("file.txt"), "content").unwrap();
+    let source_stack_id = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches[0]
+        .id;
+    gitbutler_branch_actions::create_commit(ctx, source_stack_id, "bottom commit", None).unwrap();
+
+    gitbutler_branch_actions::stack::create_branch(
+        ctx,
+        source_stack_id,
+        CreateSeriesRequest {
+            name: "top-series".into(),
+            description: None,
+            target_patch: None,
+            preceding_head: None,
+        },
+    )
+    .unwrap();
+    std::fs::write(repo.path().join("other-file.txt"), "content").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, source_stack_id, "top commit", None).unwrap();
+
+    let new_stack_id = gitbutler_branch_actions::move_branch(
+        ctx,
+        source_stack_id,
+        "top-series",
+        BranchDestination::NewStack,
+    )
+    .unwrap();
+    assert_ne!(new_stack_id, source_stack_id);
+
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches;
+    assert_eq!(branches.len(), 2);
+    let source = branches.iter().find(|b| b.id == source_stack_id).unwrap();
+    let bottom_series_name = source.series[0].clone().unwrap().name;
+    assert_eq!(patches_by_series(source), [(bottom_series_name.clone(), 1)]);
+    let new = branches.iter().find(|b| b.id == new_stack_id).unwrap();
+    assert_eq!(patches_by_series(new), [("top-series".to_owned(), 1)]);
+
+    let destination_stack_id = gitbutler_branch_actions::move_branch(
+        ctx,
+        new_stack_id,
+        "top-series",
+        BranchDestination::Stack {
+            stack_id: source_stack_id,
+            on_top_of: Some(bottom_series_name.clone()),
+        },
+    )
+    .unwrap();
+    assert_eq!(destination_stack_id, source_stack_id);
+
+    let branches = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches;
+    assert_eq!(branches.len(), 1, "the emptied stack is removed");
+    assert_eq!(
+        patches_by_series(&branches[0]),
+        [("top-series".to_owned(), 1), (bottom_series_name, 1)]
+    );
+    assert_eq!(branches[0].files.len(), 0);
+}
+
+#[test]
+fn only_branch_cannot_become_new_stack() {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    std::fs::write(repo.path().join("file.txt"), "content").unwrap();
+    let stack = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .remove(0);
+    gitbutler_branch_actions::create_commit(ctx, stack.id, "commit", None).unwrap();
+
+    let branch_name = stack.series[0].clone().unwrap().name;
+    let err = gitbutler_branch_actions::move_branch(
+        ctx,
+        stack.id,
+        &branch_name,
+        BranchDestination::NewStack,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Branch '{}' already is the only branch of its stack",
+            stack.name
+        )
+    );
+}
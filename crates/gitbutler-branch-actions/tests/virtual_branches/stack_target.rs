@@ -0,0 +1,233 @@
+use gitbutler_branch_actions::upstream_integration::{StackIntegrationPreview, StackStatuses};
+use gitbutler_reference::LocalRefname;
+use gitbutler_stack::VirtualBranchesHandle;
+
+use super::*;
+
+/// Create `release` with a commit on top of `master` on the remote, leaving `master` checked out.
+fn setup_release_branch(repo: &TestProject) -> git2::Oid {
+    let release: LocalRefname = "refs/heads/release".parse().unwrap();
+    repo.checkout(&release);
+    fs::write(repo.path().join("release.txt"), "release fix").unwrap();
+    let release_head = repo.commit_all("release fix");
+    repo.push_branch(&release);
+    repo.checkout(&"refs/heads/master".parse().unwrap());
+    repo.fetch();
+    release_head
+}
+
+#[test]
+fn set_and_reset() {
+    let Test {
+        repo, ctx, project, ..
+    } = &Test::default();
+    setup_release_branch(repo);
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+    let base_sha = gitbutler_branch_actions::base::get_base_branch_data(ctx)
+        .unwrap()
+        .base_sha;
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+
+    gitbutler_branch_actions::set_stack_target(
+        ctx,
+        stack_entry.id,
+        Some(&"refs/remotes/origin/release".parse().unwrap()),
+    )
+    .unwrap();
+
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let target = vb_state
+        .maybe_get_stack_target(stack_entry.id)
+        .unwrap()
+        .expect("stack has its own target");
+    assert_eq!(target.branch.to_string(), "refs/remotes/origin/release");
+    assert_eq!(
+        target.sha, base_sha,
+        "the stack isn't based on the release commit yet"
+    );
+    assert_eq!(
+        vb_state.get_default_target().unwrap().sha,
+        base_sha,
+        "the default target is unaffected"
+    );
+
+    gitbutler_branch_actions::set_stack_target(
+        ctx,
+        stack_entry.id,
+        Some(&"refs/remotes/origin/master".parse().unwrap()),
+    )
+    .unwrap();
+    assert!(
+        vb_state
+            .maybe_get_stack_target(stack_entry.id)
+            .unwrap()
+            .is_none(),
+        "targeting the default branch is the same as having no target"
+    );
+}
+
+#[test]
+fn integrate_upstream_of_stack_target() {
+    let Test {
+        repo, ctx, project, ..
+    } = &Test::default();
+    let release_head = setup_release_branch(repo);
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+    let base_sha = gitbutler_branch_actions::base::get_base_branch_data(ctx)
+        .unwrap()
+        .base_sha;
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None).unwrap();
+
+    assert!(
+        matches!(
+            gitbutler_branch_actions::upstream_integration_statuses(ctx, None).unwrap(),
+            StackStatuses::UpToDate
+        ),
+        "the default target didn't move"
+    );
+
+    gitbutler_branch_actions::set_stack_target(
+        ctx,
+        stack_entry.id,
+        Some(&"refs/remotes/origin/release".parse().unwrap()),
+    )
+    .unwrap();
+
+    let StackStatuses::UpdatesRequired {
+        worktree_conflicts,
+        statuses,
+    } = gitbutler_branch_actions::upstream_integration_statuses(ctx, None).unwrap()
+    else {
+        panic!("the stack target has a new commit");
+    };
+    assert!(worktree_conflicts.is_empty());
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses[0].0, stack_entry.id);
+
+    gitbutler_branch_actions::integrate_upstream(
+        ctx,
+        &[gitbutler_branch_actions::upstream_integration::Resolution {
+            branch_id: stack_entry.id,
+            approach: gitbutler_branch_actions::upstream_integration::ResolutionApproach::Rebase,
+            delete_integrated_branches: false,
+            delete_integrated_remote_branches: false,
+        }],
+        None,
+    )
+    .unwrap();
+
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    assert_eq!(
+        vb_state.get_stack_target(stack_entry.id).unwrap().sha,
+        release_head,
+        "the stack target was updated"
+    );
+    assert_eq!(
+        vb_state.get_default_target().unwrap().sha,
+        base_sha,
+        "the default target didn't move"
+    );
+
+    let list_result = gitbutler_branch_actions::list_virtual_branches(ctx).unwrap();
+    let branch = &list_result.branches[0];
+    assert_eq!(branch.series[0].clone().unwrap().patches.len(), 1);
+    let head = repo.find_commit(branch.head).unwrap();
+    assert_eq!(head.parent_id(0).unwrap(), release_head);
+}
+
+#[test]
+fn preview_integration_of_stack_target() {
+    let Test {
+        repo, ctx, project, ..
+    } = &Test::default();
+    let release: LocalRefname = "refs/heads/release".parse().unwrap();
+    repo.checkout(&release);
+    fs::write(repo.path().join("file.txt"), "release").unwrap();
+    repo.commit_all("release fix");
+    repo.push_branch(&release);
+    repo.checkout(&"refs/heads/master".parse().unwrap());
+    repo.fetch();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    let commit_id =
+        gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None).unwrap();
+    gitbutler_branch_actions::set_stack_target(
+        ctx,
+        stack_entry.id,
+        Some(&"refs/remotes/origin/release".parse().unwrap()),
+    )
+    .unwrap();
+
+    let vb_state = VirtualBranchesHandle::new(project.gb_dir());
+    let target_before = vb_state.get_stack_target(stack_entry.id).unwrap().sha;
+    let preview = gitbutler_branch_actions::preview_upstream_integration(
+        ctx,
+        &[gitbutler_branch_actions::upstream_integration::Resolution {
+            branch_id: stack_entry.id,
+            approach: gitbutler_branch_actions::upstream_integration::ResolutionApproach::Rebase,
+            delete_integrated_branches: false,
+            delete_integrated_remote_branches: false,
+        }],
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(preview.stacks.len(), 1);
+    let (stack_id, StackIntegrationPreview::Updated { branches }) = &preview.stacks[0] else {
+        panic!("the stack is rebased");
+    };
+    assert_eq!(*stack_id, stack_entry.id);
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].commits.len(), 1);
+    let commit = &branches[0].commits[0];
+    assert_eq!(commit.original_id, Some(commit_id));
+    assert_eq!(
+        commit
+            .conflicted_paths
+            .iter()
+            .map(|path| path.to_string())
+            .collect::<Vec<_>>(),
+        ["file.txt"],
+        "both sides added the file with different content"
+    );
+
+    assert_eq!(
+        vb_state.get_stack_target(stack_entry.id).unwrap().sha,
+        target_before,
+        "the stack target didn't change"
+    );
+    let list_result = gitbutler_branch_actions::list_virtual_branches(ctx).unwrap();
+    assert_eq!(
+        list_result.branches[0].head, commit_id,
+        "the stack wasn't rebased"
+    );
+}
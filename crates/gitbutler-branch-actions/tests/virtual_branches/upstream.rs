@@ -130,3 +130,179 @@ fn detect_integrated_commits() {
         assert!(branches[0].series[0].clone().unwrap().patches[2].is_integrated);
     }
 }
+
+#[test]
+fn delete_integrated_remote_branches() {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None).unwrap();
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false).unwrap();
+
+    let branch_name = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap()
+        .series[0]
+        .as_ref()
+        .unwrap()
+        .name
+        .clone();
+    let remote_branch = format!("refs/heads/{branch_name}");
+    let remote_branch_names = |repo: &TestProject| {
+        repo.remote_references()
+            .iter()
+            .filter_map(|r| r.name().map(ToOwned::to_owned))
+            .collect::<Vec<_>>()
+    };
+    assert!(
+        remote_branch_names(repo).contains(&remote_branch),
+        "the branch was pushed"
+    );
+
+    repo.merge(
+        &format!("refs/remotes/origin/{branch_name}")
+            .parse()
+            .unwrap(),
+    )
+    .unwrap();
+    repo.fetch();
+
+    gitbutler_branch_actions::integrate_upstream(
+        ctx,
+        &[gitbutler_branch_actions::upstream_integration::Resolution {
+            branch_id: stack_entry.id,
+            approach: gitbutler_branch_actions::upstream_integration::ResolutionApproach::Rebase,
+            delete_integrated_branches: true,
+            delete_integrated_remote_branches: true,
+        }],
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        remote_branch_names(repo),
+        ["refs/heads/master"],
+        "the integrated branch was deleted on the remote"
+    );
+    let local_refs: Vec<_> = repo
+        .references()
+        .iter()
+        .filter_map(|r| r.name().map(ToOwned::to_owned))
+        .collect();
+    assert!(
+        !local_refs.contains(&format!("refs/remotes/origin/{branch_name}")),
+        "the remote tracking branch is gone"
+    );
+    assert!(
+        !local_refs.contains(&remote_branch),
+        "the local branch is gone as well"
+    );
+}
+
+#[test]
+fn keep_integrated_remote_branches_that_changed_on_the_remote() {
+    let Test { repo, ctx, .. } = &Test::default();
+
+    gitbutler_branch_actions::set_base_branch(
+        ctx,
+        &"refs/remotes/origin/master".parse().unwrap(),
+        false,
+    )
+    .unwrap();
+
+    let stack_entry =
+        gitbutler_branch_actions::create_virtual_branch(ctx, &BranchCreateRequest::default())
+            .unwrap();
+    fs::write(repo.path().join("file.txt"), "content").unwrap();
+    gitbutler_branch_actions::create_commit(ctx, stack_entry.id, "commit", None).unwrap();
+    gitbutler_branch_actions::stack::push_stack(ctx, stack_entry.id, false).unwrap();
+
+    let branch_name = gitbutler_branch_actions::list_virtual_branches(ctx)
+        .unwrap()
+        .branches
+        .into_iter()
+        .find(|b| b.id == stack_entry.id)
+        .unwrap()
+        .series[0]
+        .as_ref()
+        .unwrap()
+        .name
+        .clone();
+    let remote_branch = format!("refs/heads/{branch_name}");
+
+    repo.merge(
+        &format!("refs/remotes/origin/{branch_name}")
+            .parse()
+            .unwrap(),
+    )
+    .unwrap();
+    repo.fetch();
+
+    // A collaborator pushes to the branch after we last fetched.
+    let remote_repo = git2::Repository::open(
+        repo.local_repo
+            .find_remote("origin")
+            .unwrap()
+            .url()
+            .unwrap(),
+    )
+    .unwrap();
+    let tip = remote_repo
+        .find_reference(&remote_branch)
+        .unwrap()
+        .peel_to_commit()
+        .unwrap();
+    let signature = git2::Signature::now("collaborator", "collaborator@example.com").unwrap();
+    let collaborator_commit = remote_repo
+        .commit(
+            Some(&remote_branch),
+            &signature,
+            &signature,
+            "collaborator commit",
+            &tip.tree().unwrap(),
+            &[&tip],
+        )
+        .unwrap();
+
+    gitbutler_branch_actions::integrate_upstream(
+        ctx,
+        &[gitbutler_branch_actions::upstream_integration::Resolution {
+            branch_id: stack_entry.id,
+            approach: gitbutler_branch_actions::upstream_integration::ResolutionApproach::Rebase,
+            delete_integrated_branches: true,
+            delete_integrated_remote_branches: true,
+        }],
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        remote_repo
+            .find_reference(&remote_branch)
+            .unwrap()
+            .target(),
+        Some(collaborator_commit),
+        "the branch changed since the last fetch, so it's kept along with the commit of the collaborator"
+    );
+    assert_eq!(
+        repo.local_repo
+            .find_reference(&format!("refs/remotes/origin/{branch_name}"))
+            .unwrap()
+            .target(),
+        Some(collaborator_commit),
+        "the remote tracking branch is kept and up to date"
+    );
+}
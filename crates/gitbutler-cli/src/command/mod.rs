@@ -2,6 +2,9 @@ pub mod prepare;
 pub mod project;
 pub mod vbranch;
 
+/// How long to wait for other processes, like the GitButler app, to release the workspace.
+const WORKTREE_ACCESS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
 fn debug_print(this: impl std::fmt::Debug) -> anyhow::Result<()> {
     println!("{:#?}", this);
     Ok(())
@@ -60,6 +63,7 @@ pub mod workspace {
                 branch_id: b.id,
                 approach,
                 delete_integrated_branches: false,
+                delete_integrated_remote_branches: false,
             })
             .collect();
         gitbutler_branch_actions::integrate_upstream(&ctx, &resolutions, None)?;
@@ -8,7 +8,7 @@ use gitbutler_project::Project;
 use gitbutler_reference::{LocalRefname, Refname};
 use gitbutler_stack::{Stack, VirtualBranchesHandle};
 
-use crate::command::debug_print;
+use crate::command::{debug_print, WORKTREE_ACCESS_TIMEOUT};
 
 pub fn list_commit_files(project: Project, commit_id_hex: String) -> Result<()> {
     let ctx = CommandContext::open(&project, AppSettings::default())?;
@@ -80,7 +80,7 @@ pub fn apply(project: Project, branch_name: String, from_branch: bool) -> Result
 fn apply_by_name(project: Project, branch_name: String) -> Result<()> {
     let stack = stack_by_name(&project, &branch_name)?;
     let ctx = CommandContext::open(&project, AppSettings::default())?;
-    let mut guard = project.exclusive_worktree_access();
+    let mut guard = project.try_exclusive_worktree_access(WORKTREE_ACCESS_TIMEOUT)?;
     debug_print(
         ctx.branch_manager().create_virtual_branch_from_branch(
             stack
@@ -106,7 +106,7 @@ fn apply_from_branch(project: Project, branch_name: String) -> Result<()> {
 
     let ctx = CommandContext::open(&project, AppSettings::default())?;
 
-    let mut guard = project.exclusive_worktree_access();
+    let mut guard = project.try_exclusive_worktree_access(WORKTREE_ACCESS_TIMEOUT)?;
     debug_print(ctx.branch_manager().create_virtual_branch_from_branch(
         &target,
         None,
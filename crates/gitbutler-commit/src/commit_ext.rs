@@ -1,4 +1,5 @@
 use bstr::BStr;
+use but_core::commit::HEADERS_RECORDED_RESOLUTION_FIELD;
 
 use crate::commit_headers::HasCommitHeaders;
 
@@ -11,6 +12,9 @@ pub trait CommitExt {
     fn change_id(&self) -> Option<String>;
     fn is_signed(&self) -> bool;
     fn is_conflicted(&self) -> bool;
+    /// Return `true` if conflicts of this commit were resolved automatically by reusing recorded resolutions,
+    /// so it should be reviewed.
+    fn has_recorded_resolution(&self) -> bool;
 }
 
 impl CommitExt for git2::Commit<'_> {
@@ -30,6 +34,11 @@ impl CommitExt for git2::Commit<'_> {
             .and_then(|headers| headers.conflicted.map(|conflicted| conflicted > 0))
             .unwrap_or(false)
     }
+
+    fn has_recorded_resolution(&self) -> bool {
+        self.header_field_bytes(HEADERS_RECORDED_RESOLUTION_FIELD)
+            .is_ok()
+    }
 }
 
 impl CommitExt for gix::Commit<'_> {
@@ -52,6 +61,15 @@ impl CommitExt for gix::Commit<'_> {
             .and_then(|headers| headers.conflicted.map(|conflicted| conflicted > 0))
             .unwrap_or(false)
     }
+
+    fn has_recorded_resolution(&self) -> bool {
+        self.decode().is_ok_and(|decoded| {
+            decoded
+                .extra_headers()
+                .find(HEADERS_RECORDED_RESOLUTION_FIELD)
+                .is_some()
+        })
+    }
 }
 
 fn contains<'a, I>(iter: I, item: &git2::Commit<'a>) -> bool
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use gitbutler_branch_actions::RemoteBranchFile;
 use gitbutler_command_context::CommandContext;
 use gitbutler_operating_modes::{assure_edit_mode, assure_open_workspace_mode, EditModeMetadata};
@@ -8,7 +8,7 @@ use gitbutler_oplog::{
 };
 use gitbutler_reference::ReferenceName;
 
-use crate::ConflictEntryPresence;
+use crate::{ConflictEntryPresence, ConflictedCommit};
 
 pub fn enter_edit_mode(
     ctx: &CommandContext,
@@ -71,3 +71,43 @@ pub fn starting_index_state(
 
     crate::starting_index_state(ctx, guard.read_permission())
 }
+
+pub fn list_conflicted_commits(ctx: &CommandContext) -> Result<Vec<ConflictedCommit>> {
+    let guard = ctx.project().exclusive_worktree_access();
+
+    crate::conflicted_commits(ctx, guard.read_permission())
+}
+
+pub fn start_conflict_resolution(ctx: &CommandContext) -> Result<EditModeMetadata> {
+    let mut guard = ctx.project().exclusive_worktree_access();
+
+    assure_open_workspace_mode(ctx)
+        .context("Resolving conflicts may only be started when the workspace is open")?;
+
+    let snapshot = ctx
+        .prepare_snapshot(guard.read_permission())
+        .context("Failed to prepare snapshot")?;
+
+    let Some(edit_mode_metadata) =
+        crate::enter_next_conflicted_commit(ctx, guard.write_permission())?
+    else {
+        bail!("There are no conflicted commits to resolve");
+    };
+
+    let _ = ctx.commit_snapshot(
+        snapshot,
+        SnapshotDetails::new(OperationKind::EnterEditMode),
+        guard.write_permission(),
+    );
+
+    Ok(edit_mode_metadata)
+}
+
+pub fn continue_conflict_resolution(ctx: &CommandContext) -> Result<Option<EditModeMetadata>> {
+    let mut guard = ctx.project().exclusive_worktree_access();
+
+    assure_edit_mode(ctx)
+        .context("Conflict resolution may only be continued while in edit mode")?;
+
+    crate::continue_conflict_resolution(ctx, guard.write_permission())
+}
@@ -4,6 +4,7 @@ use std::str::FromStr;
 
 use anyhow::{bail, Context, Result};
 use bstr::ByteSlice;
+use but_rebase::resolution::RecordedResolutions;
 use but_workspace::stack_ext::StackExt;
 use git2::build::CheckoutBuilder;
 use gitbutler_branch_actions::internal::list_virtual_branches;
@@ -26,7 +27,7 @@ use gitbutler_project::access::{WorktreeReadPermission, WorktreeWritePermission}
 use gitbutler_reference::{ReferenceName, Refname};
 use gitbutler_repo::RepositoryExt;
 use gitbutler_repo::{signature, SignaturePurpose};
-use gitbutler_stack::{Stack, VirtualBranchesHandle};
+use gitbutler_stack::{Stack, StackId, VirtualBranchesHandle};
 use gitbutler_workspace::branch_trees::{update_uncommited_changes_with_tree, WorkspaceState};
 #[allow(deprecated)]
 use gitbutler_workspace::{checkout_branch_trees, compute_updated_branch_head};
@@ -40,48 +41,58 @@ const UNCOMMITED_CHANGES_REF: &str = "refs/heads/gitbutler/edit-uncommited-chang
 /// if `commit` is conflicted. That tree is turned into an index that records the conflicts that occurred
 /// during the merge.
 fn get_commit_index(repository: &git2::Repository, commit: &git2::Commit) -> Result<git2::Index> {
-    let commit_tree = commit.tree().context("Failed to get commit's tree")?;
     // Checkout the commit as unstaged changes
     if commit.is_conflicted() {
-        let base = commit_tree
-            .get_name(".conflict-base-0")
-            .context("Failed to get base")?
-            .id();
-        let ours = commit_tree
-            .get_name(".conflict-side-0")
-            .context("Failed to get base")?
-            .id();
-        let theirs = commit_tree
-            .get_name(".conflict-side-1")
-            .context("Failed to get base")?
-            .id();
-
-        let gix_repo = gix_repo_for_merging(repository.path())?;
-        // Merge without favoring a side this time to get a tree containing the actual conflicts.
-        let mut merge_result = gix_repo.merge_trees(
-            git2_to_gix_object_id(base),
-            git2_to_gix_object_id(ours),
-            git2_to_gix_object_id(theirs),
-            gix_repo.default_merge_labels(),
-            gix_repo.tree_merge_options()?,
-        )?;
-        let merged_tree_id = merge_result.tree.write()?;
-        let mut index = gix_repo.index_from_tree(&merged_tree_id)?;
-        if !merge_result.index_changed_after_applying_conflicts(
-            &mut index,
-            gix::merge::tree::TreatAsUnresolved::git(),
-            gix::merge::tree::apply_index_entries::RemovalMode::Mark,
-        ) {
-            tracing::warn!("There must be an issue with conflict-commit creation as re-merging the conflicting trees didn't yield a conflicting index.");
-        }
-        gix_to_git2_index(&index)
+        gix_to_git2_index(&get_conflicted_commit_index(repository, commit)?)
     } else {
+        let commit_tree = commit.tree().context("Failed to get commit's tree")?;
         let mut index = git2::Index::new()?;
         index.read_tree(&commit_tree)?;
         Ok(index)
     }
 }
 
+/// Re-merge the base and both sides of the conflicted `commit` without favoring a side, and return
+/// the resulting index which records the conflicts as stages. It's backed by the index file of `repository`.
+fn get_conflicted_commit_index(
+    repository: &git2::Repository,
+    commit: &git2::Commit,
+) -> Result<gix::index::File> {
+    let commit_tree = commit.tree().context("Failed to get commit's tree")?;
+    let base = commit_tree
+        .get_name(".conflict-base-0")
+        .context("Failed to get base")?
+        .id();
+    let ours = commit_tree
+        .get_name(".conflict-side-0")
+        .context("Failed to get base")?
+        .id();
+    let theirs = commit_tree
+        .get_name(".conflict-side-1")
+        .context("Failed to get base")?
+        .id();
+
+    let gix_repo = gix_repo_for_merging(repository.path())?;
+    // Merge without favoring a side this time to get a tree containing the actual conflicts.
+    let mut merge_result = gix_repo.merge_trees(
+        git2_to_gix_object_id(base),
+        git2_to_gix_object_id(ours),
+        git2_to_gix_object_id(theirs),
+        gix_repo.default_merge_labels(),
+        gix_repo.tree_merge_options()?,
+    )?;
+    let merged_tree_id = merge_result.tree.write()?;
+    let mut index = gix_repo.index_from_tree(&merged_tree_id)?;
+    if !merge_result.index_changed_after_applying_conflicts(
+        &mut index,
+        gix::merge::tree::TreatAsUnresolved::git(),
+        gix::merge::tree::apply_index_entries::RemovalMode::Mark,
+    ) {
+        tracing::warn!("There must be an issue with conflict-commit creation as re-merging the conflicting trees didn't yield a conflicting index.");
+    }
+    Ok(index)
+}
+
 /// Returns a commit to be the HEAD of `gitbutler/edit`
 ///
 /// This should a commit who's tree is what the commit getting edited
@@ -169,6 +180,22 @@ fn checkout_edit_branch(ctx: &CommandContext, commit: git2::Commit) -> Result<()
         ),
     )?;
 
+    // Also record the conflicts in the index so external merge tools can pick them up.
+    if commit.is_conflicted() {
+        get_conflicted_commit_index(repository, &commit)?.write(Default::default())?;
+    }
+
+    Ok(())
+}
+
+/// Reset the index to the tree of `HEAD` if it still records conflicts from edit mode.
+fn clear_index_conflicts(repository: &git2::Repository) -> Result<()> {
+    let mut index = repository.index()?;
+    index.read(true)?;
+    if index.has_conflicts() {
+        index.read_tree(&repository.head()?.peel_to_tree()?)?;
+        index.write()?;
+    }
     Ok(())
 }
 
@@ -238,6 +265,7 @@ pub(crate) fn abort_and_return_to_workspace(
         uncommited_changes.as_object(),
         Some(CheckoutBuilder::new().force().remove_untracked(true)),
     )?;
+    clear_index_conflicts(repository)?;
 
     Ok(())
 }
@@ -289,6 +317,18 @@ pub(crate) fn save_and_return_to_workspace(
         .context("Failed to commit new commit")?;
 
     let gix_repo = repository.to_gix()?;
+    let recorded_resolutions = RecordedResolutions::at(ctx.project().recorded_resolutions_dir());
+    if commit.is_conflicted() {
+        // Remember how the conflicts were resolved to resolve them automatically if they occur again.
+        let conflicted_commit =
+            but_core::Commit::from_id(gix::Id::from_id(commit.id().to_gix(), &gix_repo))?;
+        if let Err(err) = recorded_resolutions.record(&conflicted_commit, tree.id().to_gix()) {
+            tracing::warn!(
+                "Failed to record conflict resolutions of {}: {err:#}",
+                commit.id()
+            );
+        }
+    }
 
     let mut steps = stack.as_rebase_steps(ctx, &gix_repo)?;
     // swap out the old commit with the new, updated one
@@ -302,6 +342,7 @@ pub(crate) fn save_and_return_to_workspace(
     let merge_base = stack.merge_base(ctx)?;
     let mut rebase = but_rebase::Rebase::new(&gix_repo, Some(merge_base), None)?;
     rebase.rebase_noops(false);
+    rebase.recorded_resolutions(recorded_resolutions);
     rebase.steps(steps)?;
     let output = rebase.rebase()?;
     let new_branch_head = output.top_commit.to_git2();
@@ -315,8 +356,12 @@ pub(crate) fn save_and_return_to_workspace(
         (res.head, Some(res.tree))
     };
 
-    stack.set_stack_head(&vb_state, &gix_repo, new_branch_head, new_branch_tree)?;
-    stack.set_heads_from_rebase_output(ctx, output.references)?;
+    stack.set_stack_head_and_heads_from_rebase_output(
+        ctx,
+        new_branch_head,
+        new_branch_tree,
+        output.references,
+    )?;
 
     // Switch branch to gitbutler/workspace
     repository
@@ -340,6 +385,8 @@ pub(crate) fn save_and_return_to_workspace(
         checkout_branch_trees(ctx, perm)?;
     }
     update_workspace_commit(&vb_state, ctx)?;
+    // The worktree was committed as is, so conflicts recorded when entering edit mode are no longer relevant.
+    clear_index_conflicts(repository)?;
     list_virtual_branches(ctx, perm)?;
 
     Ok(())
@@ -406,3 +453,89 @@ pub(crate) fn starting_index_state(
 
     Ok(diff_files)
 }
+
+/// A commit in the workspace that was left conflicted by a rebase.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictedCommit {
+    /// The stack that contains the commit.
+    pub stack_id: StackId,
+    /// The sha of the conflicted commit.
+    #[serde(with = "gitbutler_serde::oid")]
+    pub commit_oid: git2::Oid,
+    /// The ref of the vbranch which owns this commit, suitable for entering edit mode.
+    pub branch_reference: ReferenceName,
+}
+
+/// Lists all conflicted commits in the workspace in the order in which they should be resolved,
+/// i.e. stack by stack, and from the bottom to the top of each stack.
+pub(crate) fn conflicted_commits(
+    ctx: &CommandContext,
+    _perm: &WorktreeReadPermission,
+) -> Result<Vec<ConflictedCommit>> {
+    let repository = ctx.repo();
+    let gix_repo = repository.to_gix()?;
+    let vb_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
+    let mut stacks = vb_state.list_stacks_in_workspace()?;
+    stacks.sort_by_key(|stack| stack.order);
+
+    let mut conflicted_commits = vec![];
+    for stack in stacks {
+        let branch_reference: ReferenceName = stack.refname()?.to_string().into();
+        for step in stack.as_rebase_steps(ctx, &gix_repo)? {
+            let but_rebase::RebaseStep::Pick { commit_id, .. } = step else {
+                continue;
+            };
+            if repository.find_commit(commit_id.to_git2())?.is_conflicted() {
+                conflicted_commits.push(ConflictedCommit {
+                    stack_id: stack.id,
+                    commit_oid: commit_id.to_git2(),
+                    branch_reference: branch_reference.clone(),
+                });
+            }
+        }
+    }
+    Ok(conflicted_commits)
+}
+
+/// Enter edit mode for the first of the conflicted commits in the workspace, or return `None` if there are none.
+pub(crate) fn enter_next_conflicted_commit(
+    ctx: &CommandContext,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Option<EditModeMetadata>> {
+    let Some(next) = conflicted_commits(ctx, perm.read_permission())?
+        .into_iter()
+        .next()
+    else {
+        return Ok(None);
+    };
+    let repository = ctx.repo();
+    let commit = repository
+        .find_commit(next.commit_oid)
+        .context("Failed to find commit")?;
+    let branch = repository
+        .find_reference(&next.branch_reference)
+        .context("Failed to find branch reference")?;
+    enter_edit_mode(ctx, commit, &branch, perm).map(Some)
+}
+
+/// Save the resolution of the commit that is currently edited, rebase its descendants, and move on to the next
+/// conflicted commit in the workspace, similar to `git rebase --continue`.
+///
+/// Returns the metadata of the next commit to resolve, or `None` if no conflicted commit is left
+/// and the workspace is open again.
+///
+/// Fails if the index still records conflicts, which is how external merge tools signal that they are not done yet.
+pub(crate) fn continue_conflict_resolution(
+    ctx: &CommandContext,
+    perm: &mut WorktreeWritePermission,
+) -> Result<Option<EditModeMetadata>> {
+    let mut index = ctx.repo().index()?;
+    index.read(true)?;
+    if index.has_conflicts() {
+        bail!("There are still unresolved conflicts in the index. Resolve them and mark them as resolved before continuing")
+    }
+
+    save_and_return_to_workspace(ctx, perm)?;
+    enter_next_conflicted_commit(ctx, perm)
+}
@@ -1,7 +1,10 @@
 use anyhow::Result;
 use git2::build::CheckoutBuilder;
 use gitbutler_command_context::CommandContext;
-use gitbutler_edit_mode::commands::{enter_edit_mode, save_and_return_to_workspace};
+use gitbutler_edit_mode::commands::{
+    abort_and_return_to_workspace, continue_conflict_resolution, enter_edit_mode,
+    list_conflicted_commits, save_and_return_to_workspace, start_conflict_resolution,
+};
 use tempfile::TempDir;
 
 fn command_ctx(folder: &str) -> Result<(CommandContext, TempDir)> {
@@ -62,3 +65,217 @@ fn conficted_entries_get_written_when_leaving_edit_mode() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn conflict_resolution_needs_conflicted_commits() -> Result<()> {
+    let (ctx, _tempdir) = command_ctx("conficted_entries_get_written_when_leaving_edit_mode")?;
+
+    assert_eq!(list_conflicted_commits(&ctx)?, vec![]);
+    assert_eq!(
+        start_conflict_resolution(&ctx).unwrap_err().to_string(),
+        "There are no conflicted commits to resolve"
+    );
+    assert!(
+        gitbutler_operating_modes::in_open_workspace_mode(&ctx),
+        "the workspace stays open"
+    );
+
+    Ok(())
+}
+
+/// Return the paths of all conflicts in the index of `repository` along with the stages they have.
+fn index_conflicts(repository: &git2::Repository) -> Result<Vec<(String, [bool; 3])>> {
+    let mut index = repository.index()?;
+    index.read(true)?;
+    let mut conflicts = index
+        .conflicts()?
+        .map(|conflict| {
+            let conflict = conflict?;
+            let path = conflict
+                .ancestor
+                .as_ref()
+                .or(conflict.our.as_ref())
+                .or(conflict.their.as_ref())
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                .unwrap_or_default();
+            Ok((
+                path,
+                [
+                    conflict.ancestor.is_some(),
+                    conflict.our.is_some(),
+                    conflict.their.is_some(),
+                ],
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    conflicts.sort();
+    Ok(conflicts)
+}
+
+// Fixture:
+// * xxx (HEAD -> gitbutler/workspace) GitButler Workspace Commit
+// * xxx second (conflicted)
+// * xxx first (conflicted)
+// * xxx (origin/main) upstream
+// * xxx (main) add one and two
+// * xxx init
+// Where "first" conflicts with "upstream" in "one", and "second" in "two".
+#[test]
+fn conflict_resolution_walks_through_conflicted_commits() -> Result<()> {
+    let (ctx, _tempdir) = command_ctx("conflicted-commits")?;
+    let repository = ctx.repo();
+
+    let conflicted_commits = list_conflicted_commits(&ctx)?;
+    let messages = conflicted_commits
+        .iter()
+        .map(|c| {
+            Ok(repository
+                .find_commit(c.commit_oid)?
+                .summary()
+                .map(ToOwned::to_owned))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(
+        messages,
+        [Some("first".to_owned()), Some("second".to_owned())],
+        "conflicted commits are listed from the bottom to the top of the stack"
+    );
+    assert!(conflicted_commits
+        .iter()
+        .all(|c| c.branch_reference == conflicted_commits[0].branch_reference));
+
+    let metadata = start_conflict_resolution(&ctx)?;
+    assert_eq!(metadata.commit_oid, conflicted_commits[0].commit_oid);
+    assert!(gitbutler_operating_modes::in_edit_mode(&ctx));
+    assert_eq!(
+        index_conflicts(repository)?,
+        [("one".to_owned(), [true, true, true])],
+        "the conflict is recorded with all three stages for use by merge tools"
+    );
+
+    assert_eq!(
+        continue_conflict_resolution(&ctx).unwrap_err().to_string(),
+        "There are still unresolved conflicts in the index. Resolve them and mark them as resolved before continuing"
+    );
+    assert!(
+        gitbutler_operating_modes::in_edit_mode(&ctx),
+        "nothing changes while conflicts remain"
+    );
+
+    let workdir = repository.workdir().expect("non-bare");
+    std::fs::write(workdir.join("one"), "resolved-1\n")?;
+    let mut index = repository.index()?;
+    index.add_path("one".as_ref())?;
+    index.write()?;
+
+    let next = continue_conflict_resolution(&ctx)?.expect("the second commit is still conflicted");
+    let next_commit = repository.find_commit(next.commit_oid)?;
+    assert_eq!(next_commit.summary(), Some("second"));
+    assert_ne!(
+        next.commit_oid, conflicted_commits[1].commit_oid,
+        "the second commit was rebased onto the resolved first commit"
+    );
+    assert!(gitbutler_operating_modes::in_edit_mode(&ctx));
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("one"))?,
+        "resolved-1\n",
+        "the resolution of the first commit is part of the second one"
+    );
+    assert_eq!(
+        index_conflicts(repository)?,
+        [("two".to_owned(), [true, true, true])]
+    );
+
+    std::fs::write(workdir.join("two"), "resolved-2\n")?;
+    let mut index = repository.index()?;
+    index.add_path("two".as_ref())?;
+    index.write()?;
+
+    assert_eq!(
+        continue_conflict_resolution(&ctx)?,
+        None,
+        "there is nothing left to resolve"
+    );
+    assert!(gitbutler_operating_modes::in_open_workspace_mode(&ctx));
+    assert_eq!(list_conflicted_commits(&ctx)?, vec![]);
+    assert_eq!(index_conflicts(repository)?, vec![]);
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("one"))?,
+        "resolved-1\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("two"))?,
+        "resolved-2\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn entering_edit_mode_records_conflicts_of_conflicted_commits_in_index() -> Result<()> {
+    let (ctx, _tempdir) = command_ctx("conflicted-commits")?;
+    let repository = ctx.repo();
+
+    let second = list_conflicted_commits(&ctx)?
+        .pop()
+        .expect("two conflicted commits");
+    enter_edit_mode(&ctx, second.commit_oid, second.branch_reference)?;
+    assert_eq!(
+        index_conflicts(repository)?,
+        [("two".to_owned(), [true, true, true])],
+        "entering edit mode directly also records the conflicts"
+    );
+
+    abort_and_return_to_workspace(&ctx)?;
+    assert!(gitbutler_operating_modes::in_open_workspace_mode(&ctx));
+    assert_eq!(
+        index_conflicts(repository)?,
+        vec![],
+        "the conflicts are gone once the workspace is open again"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn saving_conflicted_commit_clears_conflicts_in_index() -> Result<()> {
+    let (ctx, _tempdir) = command_ctx("conflicted-commits")?;
+    let repository = ctx.repo();
+
+    let second = list_conflicted_commits(&ctx)?
+        .pop()
+        .expect("two conflicted commits");
+    enter_edit_mode(&ctx, second.commit_oid, second.branch_reference)?;
+    assert_eq!(
+        index_conflicts(repository)?,
+        [("two".to_owned(), [true, true, true])]
+    );
+
+    let workdir = repository.workdir().expect("non-bare");
+    std::fs::write(workdir.join("two"), "resolved-2\n")?;
+    save_and_return_to_workspace(&ctx)?;
+    assert!(gitbutler_operating_modes::in_open_workspace_mode(&ctx));
+    assert_eq!(
+        index_conflicts(repository)?,
+        vec![],
+        "the worktree was saved as is, so the conflicts don't linger in the index of the workspace"
+    );
+    assert_eq!(
+        std::fs::read_to_string(workdir.join("two"))?,
+        "resolved-2\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn entering_edit_mode_keeps_index_of_unconflicted_commits_free_of_conflicts() -> Result<()> {
+    let (ctx, _tempdir) = command_ctx("conficted_entries_get_written_when_leaving_edit_mode")?;
+    let repository = ctx.repo();
+
+    let foobar = repository.head()?.peel_to_commit()?.parent(0)?;
+    enter_edit_mode(&ctx, foobar.id(), "refs/gitbutler/branchy".into())?;
+    assert_eq!(index_conflicts(repository)?, vec![]);
+
+    Ok(())
+}
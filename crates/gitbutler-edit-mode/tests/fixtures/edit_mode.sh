@@ -37,4 +37,41 @@ git clone repo conficted_entries_get_written_when_leaving_edit_mode
   echo b > file
   $CLI branches create --set-default branchy
   $CLI branches commit  branchy --message foobar
-)
\ No newline at end of file
+)
+# Setup:
+# * (HEAD -> gitbutler/workspace) GitButler Workspace Commit
+# * second (conflicted)
+# * first (conflicted)
+# * (origin/main) upstream
+# * (main) add one and two
+# * init
+# Where "first" and "second" change "one" and "two" respectively, just like "upstream" does, and
+# are left conflicted after rebasing them onto it.
+git clone repo conflicted-commits-remote
+(cd conflicted-commits-remote
+  git config user.name "Author"
+  git config user.email "author@example.com"
+  echo base-1 > one
+  echo base-2 > two
+  git add . && git commit -m "add one and two"
+)
+git clone conflicted-commits-remote conflicted-commits
+(cd conflicted-commits
+  git config user.name "Author"
+  git config user.email "author@example.com"
+  $CLI project add --switch-to-workspace "$(git rev-parse --symbolic-full-name origin/main)"
+  $CLI branches create --set-default branchy
+  echo ours-1 > one
+  $CLI branches commit branchy --message first
+  echo ours-2 > two
+  $CLI branches commit branchy --message second
+)
+(cd conflicted-commits-remote
+  echo theirs-1 > one
+  echo theirs-2 > two
+  git commit -am "upstream"
+)
+(cd conflicted-commits
+  git fetch origin
+  $CLI integrate-upstream rebase
+)
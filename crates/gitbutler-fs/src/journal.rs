@@ -0,0 +1,361 @@
+//! A write-ahead journal to make mutations that touch multiple files and references all-or-nothing.
+//!
+//! All changes are recorded in a directory next to the state files, along with the previous state of everything
+//! they touch, before the first of them is applied. If the process dies while applying them, [`recover()`] finds
+//! the journal the next time and rolls it forward, or back if that isn't possible.
+//!
+//! As a journal is applied without further locking, it must only be used while holding exclusive access
+//! to the workspace, which also is when [`recover()`] should be called.
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use gix::refs::transaction::PreviousValue;
+use serde::{Deserialize, Serialize};
+
+/// The name of the directory in which a journal is prepared, and which is removed if it is found by [`recover()`].
+const INCOMPLETE_DIR: &str = "journal.incomplete";
+/// The name of the directory of a complete journal, which is rolled forward if it is found by [`recover()`].
+const COMPLETE_DIR: &str = "journal";
+/// The name of the file that lists all operations of a journal.
+const MANIFEST_FILE: &str = "journal.toml";
+
+/// Collects changes to files, like `virtual_branches.toml` or the index, and references, to apply them
+/// all at once with [`commit()`](Self::commit()).
+#[derive(Debug)]
+pub struct Journal {
+    /// The directory in which the journal is stored while it's applied.
+    gb_dir: PathBuf,
+    changes: Vec<Change>,
+}
+
+#[derive(Debug)]
+enum Change {
+    File {
+        path: PathBuf,
+        contents: Option<Vec<u8>>,
+    },
+    Reference {
+        name: String,
+        id: Option<gix::ObjectId>,
+    },
+}
+
+/// What [`recover()`] did with the journal it found.
+#[derive(Debug)]
+pub enum Recovery {
+    /// The journal was incomplete, so none of its changes were applied yet and it was removed.
+    Discarded,
+    /// All changes of the journal were applied.
+    RolledForward,
+    /// The changes couldn't be applied for the given reason, so everything the journal touched was restored
+    /// to its previous state.
+    RolledBack(anyhow::Error),
+}
+
+/// The on-disk representation of a journal.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    operations: Vec<Operation>,
+}
+
+/// A single change along with the state it replaces.
+///
+/// File contents are stored in files next to the manifest, while `None` means the file doesn't exist.
+/// Reference targets are stored as hex ids, while `None` means the reference doesn't exist.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum Operation {
+    File {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        old: Option<String>,
+    },
+    Reference {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        old: Option<String>,
+    },
+}
+
+/// Lifecycle
+impl Journal {
+    /// Create a new journal which will be stored in `gb_dir` while it's applied.
+    pub fn new(gb_dir: impl Into<PathBuf>) -> Self {
+        Journal {
+            gb_dir: gb_dir.into(),
+            changes: Vec::new(),
+        }
+    }
+}
+
+/// Recording changes
+impl Journal {
+    /// Write `contents` to the file at `path` once the journal is committed.
+    pub fn write_file(
+        &mut self,
+        path: impl Into<PathBuf>,
+        contents: impl Into<Vec<u8>>,
+    ) -> &mut Self {
+        self.changes.push(Change::File {
+            path: path.into(),
+            contents: Some(contents.into()),
+        });
+        self
+    }
+
+    /// Remove the file at `path`, if it exists, once the journal is committed.
+    pub fn remove_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.changes.push(Change::File {
+            path: path.into(),
+            contents: None,
+        });
+        self
+    }
+
+    /// Point the reference with the full `name` to `id` once the journal is committed, or delete it if `id` is `None`.
+    pub fn update_reference(
+        &mut self,
+        name: impl Into<String>,
+        id: impl Into<Option<gix::ObjectId>>,
+    ) -> &mut Self {
+        self.changes.push(Change::Reference {
+            name: name.into(),
+            id: id.into(),
+        });
+        self
+    }
+
+    /// Return `true` if no change was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Applying changes
+impl Journal {
+    /// Persist all changes along with the state they replace, then apply them in the order they were recorded
+    /// to files and to the references of `repo`.
+    ///
+    /// If a change can't be applied, all previous changes are undone before the error is returned.
+    /// A journal left behind by a previous process is recovered first.
+    pub fn commit(self, repo: &gix::Repository) -> Result<()> {
+        self.commit_inner(Some(repo))
+    }
+
+    /// Like [`commit()`](Self::commit()), but for journals that only change files and thus need no repository.
+    pub fn commit_files(self) -> Result<()> {
+        if self
+            .changes
+            .iter()
+            .any(|change| matches!(change, Change::Reference { .. }))
+        {
+            bail!("A journal with reference changes can only be committed with a repository");
+        }
+        self.commit_inner(None)
+    }
+
+    fn commit_inner(self, repo: Option<&gix::Repository>) -> Result<()> {
+        if self.changes.is_empty() {
+            return Ok(());
+        }
+        recover_inner(&self.gb_dir, repo)?;
+
+        let incomplete_dir = self.gb_dir.join(INCOMPLETE_DIR);
+        std::fs::create_dir_all(&incomplete_dir)?;
+        let mut manifest = Manifest::default();
+        for (idx, change) in self.changes.into_iter().enumerate() {
+            manifest.operations.push(match change {
+                Change::File { path, contents } => {
+                    let old = match std::fs::read(&path) {
+                        Ok(old) => Some(stage(&incomplete_dir, format!("{idx}.old"), &old)?),
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                        Err(err) => {
+                            return Err(err)
+                                .with_context(|| format!("Failed to read '{}'", path.display()))
+                        }
+                    };
+                    let new = contents
+                        .map(|new| stage(&incomplete_dir, format!("{idx}.new"), &new))
+                        .transpose()?;
+                    Operation::File { path, new, old }
+                }
+                Change::Reference { name, id } => {
+                    let old = repo
+                        .context("BUG: reference changes are only recorded with a repository")?
+                        .try_find_reference(name.as_str())?
+                        .and_then(|reference| reference.target().try_id().map(ToOwned::to_owned));
+                    Operation::Reference {
+                        name,
+                        new: id.map(|id| id.to_string()),
+                        old: old.map(|id| id.to_string()),
+                    }
+                }
+            });
+        }
+        stage(
+            &incomplete_dir,
+            MANIFEST_FILE.into(),
+            toml::to_string(&manifest)?.as_bytes(),
+        )?;
+        sync_dir(&incomplete_dir)?;
+
+        // From here on, the journal will be rolled forward if we are interrupted.
+        let complete_dir = self.gb_dir.join(COMPLETE_DIR);
+        std::fs::rename(&incomplete_dir, &complete_dir)?;
+        sync_dir(&self.gb_dir)?;
+
+        if let Err(err) = apply(&complete_dir, &manifest, repo, Direction::Forward) {
+            apply(&complete_dir, &manifest, repo, Direction::Backward)
+                .context("Failed to roll back journal, it will be rolled forward once more on the next attempt")?;
+            std::fs::remove_dir_all(&complete_dir)?;
+            return Err(err.context("Failed to apply journal, all its changes were rolled back"));
+        }
+        std::fs::remove_dir_all(&complete_dir)?;
+        Ok(())
+    }
+}
+
+/// Return `true` if there is a journal in `gb_dir` that needs to be [recovered](recover()).
+pub fn is_pending(gb_dir: &Path) -> bool {
+    gb_dir.join(INCOMPLETE_DIR).is_dir() || gb_dir.join(COMPLETE_DIR).is_dir()
+}
+
+/// Finish what a process that died while committing a journal in `gb_dir` started, applying it to files and
+/// the references of `repo`. Return `None` if there was nothing to do.
+///
+/// If the journal can neither be rolled forward nor back, it's kept for another attempt and an error is returned.
+pub fn recover(gb_dir: &Path, repo: &gix::Repository) -> Result<Option<Recovery>> {
+    recover_inner(gb_dir, Some(repo))
+}
+
+fn recover_inner(gb_dir: &Path, repo: Option<&gix::Repository>) -> Result<Option<Recovery>> {
+    let incomplete_dir = gb_dir.join(INCOMPLETE_DIR);
+    if incomplete_dir.is_dir() {
+        std::fs::remove_dir_all(&incomplete_dir)?;
+        return Ok(Some(Recovery::Discarded));
+    }
+
+    let complete_dir = gb_dir.join(COMPLETE_DIR);
+    if !complete_dir.is_dir() {
+        return Ok(None);
+    }
+    let manifest_path = complete_dir.join(MANIFEST_FILE);
+    let manifest: Manifest = toml::from_str(
+        &std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read journal at '{}'", manifest_path.display()))?,
+    )
+    .with_context(|| format!("Failed to parse journal at '{}'", manifest_path.display()))?;
+    let recovery = match apply(&complete_dir, &manifest, repo, Direction::Forward) {
+        Ok(()) => Recovery::RolledForward,
+        Err(err) => {
+            apply(&complete_dir, &manifest, repo, Direction::Backward).with_context(|| {
+                format!(
+                    "Failed to roll journal at '{}' forward or back",
+                    complete_dir.display()
+                )
+            })?;
+            Recovery::RolledBack(err)
+        }
+    };
+    std::fs::remove_dir_all(&complete_dir)?;
+    Ok(Some(recovery))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    /// Apply the new state of each operation, in order.
+    Forward,
+    /// Restore the old state of each operation, in reverse order.
+    Backward,
+}
+
+/// Apply all operations of `manifest` in `direction`, reading staged file contents from `dir`.
+/// Reference changes fail without `repo`.
+///
+/// Each operation sets its final state, so applying it more than once is harmless.
+fn apply(
+    dir: &Path,
+    manifest: &Manifest,
+    repo: Option<&gix::Repository>,
+    direction: Direction,
+) -> Result<()> {
+    let mut operations: Vec<_> = manifest.operations.iter().collect();
+    if let Direction::Backward = direction {
+        operations.reverse();
+    }
+    for operation in operations {
+        match operation {
+            Operation::File { path, new, old } => {
+                let staged = match direction {
+                    Direction::Forward => new,
+                    Direction::Backward => old,
+                };
+                match staged {
+                    Some(staged) => {
+                        crate::create_dirs_then_write(path, std::fs::read(dir.join(staged))?)
+                            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+                        // The journal is removed once applied, so its changes must be on disk by then.
+                        File::open(path)?.sync_all()?;
+                    }
+                    // Leading components may not be directories when rolling back, so only check for existence.
+                    None if path.symlink_metadata().is_ok() => std::fs::remove_file(path)
+                        .with_context(|| format!("Failed to remove '{}'", path.display()))?,
+                    None => {}
+                }
+            }
+            Operation::Reference { name, new, old } => {
+                let Some(repo) = repo else {
+                    bail!("Reference '{name}' can only be changed with a repository");
+                };
+                let id = match direction {
+                    Direction::Forward => new,
+                    Direction::Backward => old,
+                };
+                match id {
+                    Some(id) => {
+                        repo.reference(
+                            name.as_str(),
+                            gix::ObjectId::from_hex(id.as_bytes())?,
+                            PreviousValue::Any,
+                            "GitButler: apply journal",
+                        )
+                        .with_context(|| format!("Failed to update reference '{name}'"))?;
+                    }
+                    None => {
+                        if let Some(reference) = repo.try_find_reference(name.as_str())? {
+                            reference
+                                .delete()
+                                .with_context(|| format!("Failed to delete reference '{name}'"))?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Durably write `contents` to the file `name` in `dir`, and return `name`.
+fn stage(dir: &Path, name: String, contents: &[u8]) -> Result<String> {
+    let mut file = File::create(dir.join(&name))?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(name)
+}
+
+/// Make sure renames and newly created files in `dir` are persisted.
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
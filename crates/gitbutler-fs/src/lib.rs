@@ -13,6 +13,8 @@ use gix::{
 use serde::de::DeserializeOwned;
 use walkdir::WalkDir;
 
+pub mod journal;
+
 // Returns an ordered list of relative paths for files inside a directory recursively.
 pub fn list_files<P: AsRef<Path>>(
     dir_path: P,
@@ -0,0 +1,145 @@
+use gitbutler_fs::journal::{self, Journal, Recovery};
+
+fn repo_with_gb_dir() -> (tempfile::TempDir, gix::Repository, std::path::PathBuf) {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = gix::init(tmp.path()).unwrap();
+    let gb_dir = repo.git_dir().join("gitbutler");
+    std::fs::create_dir_all(&gb_dir).unwrap();
+    (tmp, repo, gb_dir)
+}
+
+fn some_id(repo: &gix::Repository) -> gix::ObjectId {
+    repo.write_blob("content").unwrap().detach()
+}
+
+#[test]
+fn commit_applies_all_changes() -> anyhow::Result<()> {
+    let (_tmp, repo, gb_dir) = repo_with_gb_dir();
+    let state = gb_dir.join("state.toml");
+    let obsolete = gb_dir.join("obsolete.toml");
+    std::fs::write(&state, "old")?;
+    std::fs::write(&obsolete, "obsolete")?;
+    let id = some_id(&repo);
+
+    let mut journal = Journal::new(&gb_dir);
+    journal
+        .write_file(&state, "new")
+        .remove_file(&obsolete)
+        .update_reference("refs/heads/journaled", id);
+    journal.commit(&repo)?;
+
+    assert_eq!(std::fs::read_to_string(&state)?, "new");
+    assert!(!obsolete.exists());
+    assert_eq!(repo.find_reference("refs/heads/journaled")?.id(), id);
+    assert!(
+        !journal::is_pending(&gb_dir),
+        "the journal is removed once applied"
+    );
+    Ok(())
+}
+
+#[test]
+fn commit_files_needs_no_repository() -> anyhow::Result<()> {
+    let (_tmp, repo, gb_dir) = repo_with_gb_dir();
+    let state = gb_dir.join("state.toml");
+
+    let mut journal = Journal::new(&gb_dir);
+    journal.write_file(&state, "new");
+    journal.commit_files()?;
+    assert_eq!(std::fs::read_to_string(&state)?, "new");
+
+    let mut journal = Journal::new(&gb_dir);
+    journal
+        .remove_file(&state)
+        .update_reference("refs/heads/journaled", some_id(&repo));
+    assert!(
+        journal.commit_files().is_err(),
+        "references can't be changed without repository"
+    );
+    assert!(state.exists(), "nothing was applied");
+    assert!(!journal::is_pending(&gb_dir));
+    Ok(())
+}
+
+#[test]
+fn commit_rolls_back_if_a_change_fails() -> anyhow::Result<()> {
+    let (_tmp, repo, gb_dir) = repo_with_gb_dir();
+    let state = gb_dir.join("state.toml");
+    std::fs::write(&state, "old")?;
+    let blocker = gb_dir.join("blocker");
+
+    let mut journal = Journal::new(&gb_dir);
+    journal
+        .write_file(&state, "new")
+        .update_reference("refs/heads/journaled", some_id(&repo))
+        .write_file(&blocker, "a file")
+        .write_file(
+            blocker.join("inner"),
+            "can't be written as its parent is a file",
+        );
+    let err = journal.commit(&repo).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Failed to apply journal, all its changes were rolled back"
+    );
+    assert_eq!(std::fs::read_to_string(&state)?, "old");
+    assert!(!blocker.exists());
+    assert!(repo.try_find_reference("refs/heads/journaled")?.is_none());
+    assert!(!journal::is_pending(&gb_dir));
+    Ok(())
+}
+
+#[test]
+fn recover_discards_incomplete_journals() -> anyhow::Result<()> {
+    let (_tmp, repo, gb_dir) = repo_with_gb_dir();
+    assert!(journal::recover(&gb_dir, &repo)?.is_none(), "nothing to do");
+
+    std::fs::create_dir(gb_dir.join("journal.incomplete"))?;
+    assert!(journal::is_pending(&gb_dir));
+    assert!(matches!(
+        journal::recover(&gb_dir, &repo)?,
+        Some(Recovery::Discarded)
+    ));
+    assert!(!journal::is_pending(&gb_dir));
+    Ok(())
+}
+
+#[test]
+fn recover_rolls_complete_journals_forward() -> anyhow::Result<()> {
+    let (_tmp, repo, gb_dir) = repo_with_gb_dir();
+    let state = gb_dir.join("state.toml");
+    std::fs::write(&state, "old")?;
+    let id = some_id(&repo);
+
+    // Pretend a process died right after the journal was completed.
+    let journal_dir = gb_dir.join("journal");
+    std::fs::create_dir(&journal_dir)?;
+    std::fs::write(journal_dir.join("0.new"), "new")?;
+    std::fs::write(journal_dir.join("0.old"), "old")?;
+    std::fs::write(
+        journal_dir.join("journal.toml"),
+        format!(
+            r#"[[operations]]
+kind = "file"
+path = {state:?}
+new = "0.new"
+old = "0.old"
+
+[[operations]]
+kind = "reference"
+name = "refs/heads/journaled"
+new = "{id}"
+"#
+        ),
+    )?;
+
+    assert!(matches!(
+        journal::recover(&gb_dir, &repo)?,
+        Some(Recovery::RolledForward)
+    ));
+    assert_eq!(std::fs::read_to_string(&state)?, "new");
+    assert_eq!(repo.find_reference("refs/heads/journaled")?.id(), id);
+    assert!(!journal::is_pending(&gb_dir));
+    Ok(())
+}
@@ -130,6 +130,8 @@ pub enum OperationKind {
     CreateCommit,
     CreateBranch,
     StashIntoBranch,
+    StashChanges,
+    ApplyStash,
     SetBaseBranch,
     MergeUpstream,
     UpdateWorkspaceBase,
@@ -153,6 +155,8 @@ pub enum OperationKind {
     SquashCommit,
     UpdateCommitMessage,
     MoveCommit,
+    MoveBranch,
+    SplitCommit,
     RestoreFromSnapshot,
     ReorderCommit,
     InsertBlankCommit,
@@ -214,6 +218,20 @@ pub struct Trailer {
     pub value: String,
 }
 
+impl Trailer {
+    /// The key of trailers recording a reference that was deleted by the operation as `<full-ref-name> <object-id>`,
+    /// so it can be recreated when the snapshot is restored.
+    pub const DELETED_REFERENCE: &'static str = "deleted_ref";
+
+    /// Record that the reference `name` that pointed to `id` was deleted.
+    pub fn deleted_reference(name: &gix::refs::FullNameRef, id: gix::ObjectId) -> Self {
+        Trailer {
+            key: Self::DELETED_REFERENCE.to_string(),
+            value: format!("{name} {id}", name = name.as_bstr()),
+        }
+    }
+}
+
 impl Display for Trailer {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let escaped_value = self.value.replace('\n', "\\n");
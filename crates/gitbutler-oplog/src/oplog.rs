@@ -17,6 +17,7 @@ use anyhow::{anyhow, bail, Context, Result};
 use git2::FileMode;
 use gitbutler_command_context::{CommandContext, RepositoryExtLite};
 use gitbutler_diff::{hunks_by_filepath, FileDiff};
+use gitbutler_fs::journal::Journal;
 use gitbutler_oxidize::ObjectIdExt as _;
 use gitbutler_oxidize::RepoExt;
 use gitbutler_oxidize::{
@@ -568,6 +569,30 @@ fn commit_snapshot(
     Ok(snapshot_commit_id)
 }
 
+/// Recreate the references that `details` recorded as deleted, unless they exist already.
+fn restore_deleted_references(repo: &gix::Repository, details: &SnapshotDetails) -> Result<()> {
+    for trailer in details
+        .trailers
+        .iter()
+        .filter(|trailer| trailer.key == Trailer::DELETED_REFERENCE)
+    {
+        let Some((name, id)) = trailer.value.split_once(' ') else {
+            continue;
+        };
+        let id = ObjectId::from_hex(id.as_bytes())?;
+        if repo.try_find_reference(name)?.is_some() || !repo.has_object(id) {
+            continue;
+        }
+        repo.reference(
+            name,
+            id,
+            gix::refs::transaction::PreviousValue::MustNotExist,
+            "GitButler: restore from snapshot",
+        )?;
+    }
+    Ok(())
+}
+
 fn restore_snapshot(
     ctx: &CommandContext,
     snapshot_commit_id: git2::Oid,
@@ -670,21 +695,58 @@ fn restore_snapshot(
     let mut checkout_builder = git2::build::CheckoutBuilder::new();
     checkout_builder.remove_untracked(true);
     checkout_builder.force();
+    // The index is written along with the state below.
+    checkout_builder.update_index(false);
     // Checkout the tree
     repo.checkout_tree(workdir_tree.as_object(), Some(&mut checkout_builder))?;
 
-    // Update virtual_branches.toml with the state from the snapshot
-    fs::write(
-        repo.path().join("gitbutler").join("virtual_branches.toml"),
-        vb_toml_blob.content(),
+    // Update the index and virtual_branches.toml with the state from the snapshot, along with the references
+    // to reflect its values, so all agree even if we are interrupted.
+    let mut index = Vec::new();
+    gix_repo
+        .index_from_tree(&workdir_tree.id().to_gix())?
+        .write_to(&mut index, Default::default())?;
+    let gb_dir = ctx.project().gb_dir();
+    let vb_toml_path = gb_dir.join("virtual_branches.toml");
+    let mut journal = Journal::new(&gb_dir);
+    journal
+        .write_file(gix_repo.index_path(), index.clone())
+        .write_file(&vb_toml_path, vb_toml_blob.content());
+    let restored_state: VirtualBranchesState = toml::from_str(
+        std::str::from_utf8(vb_toml_blob.content())
+            .context("virtual_branches.toml in snapshot isn't valid UTF-8")?,
     )?;
+    let restored_stacks = restored_state.list_stacks_in_workspace()?;
+    for stack in &restored_stacks {
+        for branch in &stack.heads {
+            if let Ok(Some((name, id))) = branch.reference_to_head_value() {
+                journal.update_reference(name, id);
+            }
+        }
+    }
+    if let Err(err) = journal.commit(&gix_repo) {
+        // The worktree is already checked out, so as before, references are updated on a best-effort basis.
+        tracing::warn!(
+            "failed to restore state and references together - updating references one by one: {err}"
+        );
+        let mut journal = Journal::new(&gb_dir);
+        journal
+            .write_file(gix_repo.index_path(), index)
+            .write_file(&vb_toml_path, vb_toml_blob.content());
+        journal.commit_files()?;
+        for stack in restored_stacks {
+            for branch in stack.heads {
+                branch.set_reference_to_head_value(&gix_repo).ok();
+            }
+        }
+    }
 
-    // Now that the toml file has been restored, update references to reflect the the values from virtual_branches.toml
-    let vb_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
-    let stacks = vb_state.list_stacks_in_workspace()?;
-    for stack in stacks {
-        for branch in stack.heads {
-            branch.set_reference_to_head_value(&gix_repo).ok();
+    let restored_details = snapshot_commit
+        .message()
+        .and_then(|msg| SnapshotDetails::from_str(msg).ok());
+    if let Some(details) = &restored_details {
+        if let Err(err) = restore_deleted_references(&gix_repo, details) {
+            tracing::warn!("failed to restore deleted references - ignoring: {err}")
         }
     }
 
@@ -698,9 +760,7 @@ fn restore_snapshot(
     let mut index = repo.index()?;
     index.read_tree(&index_tree)?;
 
-    let restored_operation = snapshot_commit
-        .message()
-        .and_then(|msg| SnapshotDetails::from_str(msg).ok())
+    let restored_operation = restored_details
         .map(|d| d.operation.to_string())
         .unwrap_or_default();
 
@@ -27,6 +27,18 @@ mod trailer {
         let result = Trailer::from_str(s);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn deleted_reference_roundtrip() -> anyhow::Result<()> {
+        let id = gix::ObjectId::from_hex(b"d2ac03c4dd8d8bfc0e6e5d6a4e6a7c1b3e4d7c5a")?;
+        let trailer = Trailer::deleted_reference("refs/remotes/origin/feat".try_into()?, id);
+        assert_eq!(
+            trailer.to_string(),
+            "deleted_ref: refs/remotes/origin/feat d2ac03c4dd8d8bfc0e6e5d6a4e6a7c1b3e4d7c5a"
+        );
+        assert_eq!(Trailer::from_str(&trailer.to_string())?, trailer);
+        Ok(())
+    }
 }
 
 mod version {
@@ -1,7 +1,9 @@
 use anyhow::{bail, Context};
 use parking_lot::RawRwLock;
-use std::path::PathBuf;
-use std::{collections::BTreeMap, sync::Arc};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use crate::{Project, ProjectId};
 
@@ -31,31 +33,172 @@ impl Project {
         Ok(lock)
     }
 
-    /// Return a guard for exclusive (read+write) worktree access, blocking while waiting for someone else,
-    /// in the same process only, to release it, or for all readers to disappear.
-    /// Locking is fair.
+    /// Return a guard for exclusive (read+write) worktree access, blocking while waiting for someone else
+    /// to release it, or for all readers to disappear.
+    /// Locking is fair within this process.
     ///
-    /// Note that this in-process locking works only under the assumption that no two instances of
-    /// GitButler are able to read or write the same repository.
+    /// Other processes, like the CLI, are kept out with an advisory lock under [`gb_dir()`](Self::gb_dir()),
+    /// and while waiting for them the process currently holding it is logged periodically.
+    /// Use [`Self::try_exclusive_worktree_access()`] to give up after a timeout instead.
     pub fn exclusive_worktree_access(&self) -> WriteWorkspaceGuard {
-        let mut map = WORKTREE_LOCKS.lock();
+        let locks = self.worktree_locks();
+        let inner = locks.in_process.write_arc();
         WriteWorkspaceGuard {
-            _inner: map.entry(self.id).or_default().write_arc(),
+            _inter_process: self.inter_process_lock_or_warn(&locks),
+            _inner: inner,
             perm: WorktreeWritePermission(()),
         }
     }
 
+    /// Like [`Self::exclusive_worktree_access()`], but fail if the access wasn't granted within `timeout`,
+    /// naming the process that holds it if it is another one.
+    pub fn try_exclusive_worktree_access(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<WriteWorkspaceGuard> {
+        let start = Instant::now();
+        let locks = self.worktree_locks();
+        let inner = locks.in_process.try_write_arc_for(timeout).with_context(|| {
+            format!("Timed out after {timeout:?} waiting for exclusive workspace access within this process")
+        })?;
+        Ok(WriteWorkspaceGuard {
+            _inter_process: self
+                .inter_process_lock(&locks, timeout.saturating_sub(start.elapsed()))?,
+            _inner: inner,
+            perm: WorktreeWritePermission(()),
+        })
+    }
+
     /// Return a guard for shared (read) worktree access, and block while waiting for writers to disappear.
     /// There can be multiple readers, but only a single writer. Waiting writers will be handled with priority,
     /// thus block readers to prevent writer starvation.
+    ///
+    /// Readers in different processes exclude each other, as the inter-process lock is always exclusive.
     pub fn shared_worktree_access(&self) -> WorkspaceReadGuard {
-        let mut map = WORKTREE_LOCKS.lock();
-        WorkspaceReadGuard(map.entry(self.id).or_default().read_arc())
+        let locks = self.worktree_locks();
+        let inner = locks.in_process.read_arc();
+        WorkspaceReadGuard {
+            _inter_process: self.inter_process_lock_or_warn(&locks),
+            _inner: inner,
+        }
+    }
+
+    /// Like [`Self::shared_worktree_access()`], but fail if the access wasn't granted within `timeout`,
+    /// naming the process that holds it if it is another one.
+    pub fn try_shared_worktree_access(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<WorkspaceReadGuard> {
+        let start = Instant::now();
+        let locks = self.worktree_locks();
+        let inner = locks.in_process.try_read_arc_for(timeout).with_context(|| {
+            format!("Timed out after {timeout:?} waiting for shared workspace access within this process")
+        })?;
+        Ok(WorkspaceReadGuard {
+            _inter_process: self
+                .inter_process_lock(&locks, timeout.saturating_sub(start.elapsed()))?,
+            _inner: inner,
+        })
+    }
+
+    /// Return the process that currently holds the inter-process workspace lock, if it is known.
+    ///
+    /// This is only for diagnostics, as the information is outdated as soon as it was read.
+    pub fn worktree_access_holder(&self) -> Option<LockHolder> {
+        LockHolder::read_from(&self.gb_dir().join(WORKTREE_LOCK_HOLDER))
+    }
+
+    fn worktree_locks(&self) -> Arc<WorktreeLocks> {
+        WORKTREE_LOCKS.lock().entry(self.id).or_default().clone()
+    }
+
+    /// Share the inter-process lock with all other guards of this process, or acquire it for them
+    /// within `timeout`.
+    fn inter_process_lock(
+        &self,
+        locks: &WorktreeLocks,
+        timeout: Duration,
+    ) -> anyhow::Result<Arc<InterProcessLock>> {
+        self.share_or_acquire_inter_process_lock(locks, Some(timeout))
+    }
+
+    /// Like [`Self::inter_process_lock()`], but wait forever and proceed without inter-process lock
+    /// if it can't be used, as the caller can't fail.
+    fn inter_process_lock_or_warn(&self, locks: &WorktreeLocks) -> Option<Arc<InterProcessLock>> {
+        match self.share_or_acquire_inter_process_lock(locks, None) {
+            Ok(lock) => Some(lock),
+            Err(err) => {
+                tracing::warn!(
+                    "Proceeding without inter-process lock on workspace of project '{}': {err:#}",
+                    self.title
+                );
+                None
+            }
+        }
+    }
+
+    /// Poll for the inter-process lock until it is shared by another guard of this process or acquired,
+    /// giving up after `timeout`, or waiting forever if it is `None`.
+    ///
+    /// The mutex in `locks` is only held for a single attempt at a time, so threads of this process waiting
+    /// for another process don't keep each other from observing their timeouts.
+    fn share_or_acquire_inter_process_lock(
+        &self,
+        locks: &WorktreeLocks,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Arc<InterProcessLock>> {
+        let gb_dir = self.gb_dir();
+        let start = Instant::now();
+        let mut last_report = start;
+        loop {
+            let shared = match timeout {
+                Some(timeout) => locks
+                    .inter_process
+                    .try_lock_for(timeout.saturating_sub(start.elapsed())),
+                None => Some(locks.inter_process.lock()),
+            };
+            if let Some(mut shared) = shared {
+                if let Some(lock) = shared.upgrade() {
+                    return Ok(lock);
+                }
+                if let Some(lock) = InterProcessLock::try_acquire(&gb_dir)? {
+                    let lock = Arc::new(lock);
+                    *shared = Arc::downgrade(&lock);
+                    return Ok(lock);
+                }
+            }
+
+            let holder = || {
+                self.worktree_access_holder()
+                    .map_or_else(|| "another process".to_owned(), |holder| holder.to_string())
+            };
+            match timeout {
+                Some(timeout) if start.elapsed() >= timeout => {
+                    bail!(
+                        "Timed out after {timeout:?} waiting for the workspace lock at '{}', which is held by {}",
+                        gb_dir.join(WORKTREE_LOCK).display(),
+                        holder()
+                    );
+                }
+                None if last_report.elapsed() >= REPORT_INTERVAL => {
+                    tracing::warn!(
+                        "Waited {:?} for the workspace lock at '{}', which is held by {}",
+                        start.elapsed(),
+                        gb_dir.join(WORKTREE_LOCK).display(),
+                        holder()
+                    );
+                    last_report = Instant::now();
+                }
+                _ => {}
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
     }
 }
 
 pub struct WriteWorkspaceGuard {
     _inner: parking_lot::ArcRwLockWriteGuard<RawRwLock, ()>,
+    _inter_process: Option<Arc<InterProcessLock>>,
     perm: WorktreeWritePermission,
 }
 
@@ -73,7 +216,10 @@ impl WriteWorkspaceGuard {
     }
 }
 
-pub struct WorkspaceReadGuard(#[allow(dead_code)] parking_lot::ArcRwLockReadGuard<RawRwLock, ()>);
+pub struct WorkspaceReadGuard {
+    _inner: parking_lot::ArcRwLockReadGuard<RawRwLock, ()>,
+    _inter_process: Option<Arc<InterProcessLock>>,
+}
 
 impl WorkspaceReadGuard {
     /// Signal that a read-permission is available - useful as API-marker to assure these
@@ -85,11 +231,11 @@ impl WorkspaceReadGuard {
 }
 
 /// A token to indicate read-only access was granted to the worktree, assuring there are no writers
-/// *within this process*.
+/// in this or any other process that respects the workspace lock.
 pub struct WorktreeReadPermission(());
 
 /// A token to indicate exclusive access was granted to the worktree, assuring there are no readers or other writers
-/// *within this process*.
+/// in this or any other process that respects the workspace lock.
 pub struct WorktreeWritePermission(());
 
 impl WorktreeWritePermission {
@@ -101,9 +247,106 @@ impl WorktreeWritePermission {
     }
 }
 
-static WORKTREE_LOCKS: parking_lot::Mutex<BTreeMap<ProjectId, Arc<parking_lot::RwLock<()>>>> =
+/// The name of the file in `gb_dir()` that is locked while a process accesses the workspace.
+const WORKTREE_LOCK: &str = "worktree.lock";
+/// The name of the file in `gb_dir()` that identifies the process holding [`WORKTREE_LOCK`].
+const WORKTREE_LOCK_HOLDER: &str = "worktree.lock.holder";
+/// How often to check if the inter-process lock was released.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How often to log the holder of the inter-process lock while waiting for it without timeout.
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct WorktreeLocks {
+    /// Orders readers and writers within this process.
+    in_process: Arc<parking_lot::RwLock<()>>,
+    /// The lock that keeps out other processes, shared by all guards of this process while any of them exists.
+    inter_process: parking_lot::Mutex<Weak<InterProcessLock>>,
+}
+
+static WORKTREE_LOCKS: parking_lot::Mutex<BTreeMap<ProjectId, Arc<WorktreeLocks>>> =
     parking_lot::Mutex::new(BTreeMap::new());
 
+/// An exclusive lock on the workspace across processes, which is released on drop.
+struct InterProcessLock {
+    lock: LockFile,
+    holder_path: PathBuf,
+}
+
+impl InterProcessLock {
+    /// Try once to lock the workspace lock file in `gb_dir`, and return `None` if another process holds it.
+    fn try_acquire(gb_dir: &Path) -> anyhow::Result<Option<Self>> {
+        std::fs::create_dir_all(gb_dir)?;
+        let path = gb_dir.join(WORKTREE_LOCK);
+        let holder_path = gb_dir.join(WORKTREE_LOCK_HOLDER);
+        let mut lock = LockFile::open(&path)
+            .with_context(|| format!("Failed to open lock file at '{}'", path.display()))?;
+        if !lock
+            .try_lock()
+            .with_context(|| format!("Failed to lock '{}'", path.display()))?
+        {
+            return Ok(None);
+        }
+        if let Err(err) = std::fs::write(&holder_path, LockHolder::current().to_file_contents()) {
+            tracing::warn!(
+                "Failed to record the holder of the workspace lock at '{}': {err}",
+                holder_path.display()
+            );
+        }
+        Ok(Some(InterProcessLock { lock, holder_path }))
+    }
+}
+
+impl Drop for InterProcessLock {
+    fn drop(&mut self) {
+        // Remove the holder while still owning the lock so the next holder's information isn't removed.
+        std::fs::remove_file(&self.holder_path).ok();
+        if let Err(err) = self.lock.unlock() {
+            tracing::warn!("Failed to release the workspace lock: {err}");
+        }
+    }
+}
+
+/// Identifies the process holding the inter-process workspace lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockHolder {
+    /// The id of the holding process.
+    pub pid: u32,
+    /// The command line of the holding process, with arguments separated by spaces.
+    pub command: String,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        LockHolder {
+            pid: std::process::id(),
+            command: std::env::args_os()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    fn read_from(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        let (pid, command) = contents.split_once('\n')?;
+        Some(LockHolder {
+            pid: pid.parse().ok()?,
+            command: command.trim_end().to_owned(),
+        })
+    }
+
+    fn to_file_contents(&self) -> String {
+        format!("{}\n{}\n", self.pid, self.command)
+    }
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "process {} ({})", self.pid, self.command)
+    }
+}
+
 /// A file-based lock that can indicate exclusive access.
 ///
 /// As opposed to its actual implementation, it will ignore failures due to lack of filesystem support.
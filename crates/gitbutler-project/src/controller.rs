@@ -14,12 +14,19 @@ pub struct Controller {
 
 impl Controller {
     /// Assure we can list projects, and if not possibly existing projects files will be renamed, and an error is produced early.
+    ///
+    /// Journals of operations that were interrupted in any of the listed projects are recovered as well.
     pub fn assure_app_can_startup_or_fix_it(
         &self,
         projects: Result<Vec<Project>>,
     ) -> Result<Vec<Project>> {
         match projects {
-            Ok(works) => Ok(works),
+            Ok(works) => {
+                for project in &works {
+                    recover_journal(project);
+                }
+                Ok(works)
+            }
             Err(probably_file_load_err) => {
                 let projects_path = self.local_data_dir.join("projects.json");
                 let max_attempts = 255;
@@ -276,3 +283,32 @@ impl Controller {
         self.local_data_dir.join("projects").join(id.to_string())
     }
 }
+
+/// Roll the journal of an operation in `project` that was interrupted forward or back, so its stacks
+/// and references agree again.
+///
+/// This is only logged on failure as the project may still be usable.
+fn recover_journal(project: &Project) {
+    let gb_dir = project.gb_dir();
+    if !gitbutler_fs::journal::is_pending(&gb_dir) {
+        return;
+    }
+    let res = (|| -> Result<_> {
+        // The journal may also belong to an operation that is still in progress in another process.
+        let _guard = project.try_exclusive_worktree_access(std::time::Duration::from_secs(10))?;
+        let repo = gix::open(&project.path)?;
+        gitbutler_fs::journal::recover(&gb_dir, &repo)
+    })();
+    match res {
+        Ok(None) => {}
+        Ok(Some(gitbutler_fs::journal::Recovery::RolledBack(err))) => {
+            tracing::warn!(project_id = %project.id, "Rolled back interrupted operation as it couldn't be completed: {err:#}");
+        }
+        Ok(Some(recovery)) => {
+            tracing::info!(project_id = %project.id, ?recovery, "Recovered interrupted operation");
+        }
+        Err(err) => {
+            tracing::error!(project_id = %project.id, "Failed to recover interrupted operation: {err:#}");
+        }
+    }
+}
@@ -70,6 +70,24 @@ pub struct CodePushState {
     pub timestamp: time::SystemTime,
 }
 
+/// Settings to tune how the file monitor of a project turns filesystem events into work.
+///
+/// Unset values fall back to defaults which work well for most repositories.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FileMonitorSettings {
+    /// The maximum amount of milliseconds to collect events for before processing them,
+    /// even if the filesystem doesn't become quiet.
+    pub debounce_timeout_ms: Option<u64>,
+    /// The amount of milliseconds between each update of the debouncer.
+    pub tick_rate_ms: Option<u64>,
+    /// The amount of ticks without new events after which pending events are processed.
+    pub flush_after_empty: Option<u32>,
+    /// If `true`, delay processing while the filesystem is flooded with events, for instance during builds,
+    /// and report large amounts of changed files as changes to their directories instead.
+    #[serde(default)]
+    pub adaptive: bool,
+}
+
 pub type ProjectId = Id<Project>;
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -98,6 +116,9 @@ pub struct Project {
     pub omit_certificate_check: Option<bool>,
     // The number of changed lines that will trigger a snapshot
     pub snapshot_lines_threshold: Option<usize>,
+    /// Control how filesystem changes in the worktree are observed.
+    #[serde(default)]
+    pub file_monitor: FileMonitorSettings,
 }
 
 /// Instantiation
@@ -149,6 +170,11 @@ impl Project {
         self.path.join(".git").join("gitbutler")
     }
 
+    /// Returns the path to the directory in which conflict resolutions are recorded for later reuse.
+    pub fn recorded_resolutions_dir(&self) -> PathBuf {
+        self.gb_dir().join("rerere")
+    }
+
     pub fn snapshot_lines_threshold(&self) -> usize {
         self.snapshot_lines_threshold.unwrap_or(20)
     }
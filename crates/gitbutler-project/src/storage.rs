@@ -3,7 +3,9 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::{ApiProject, AuthKey, CodePushState, FetchResult, Project, ProjectId};
+use crate::{
+    ApiProject, AuthKey, CodePushState, FetchResult, FileMonitorSettings, Project, ProjectId,
+};
 
 const PROJECTS_FILE: &str = "projects.json";
 
@@ -29,6 +31,7 @@ pub struct UpdateRequest {
     pub omit_certificate_check: Option<bool>,
     pub use_diff_context: Option<bool>,
     pub snapshot_lines_threshold: Option<usize>,
+    pub file_monitor: Option<FileMonitorSettings>,
 }
 
 fn default_false() -> bool {
@@ -133,6 +136,10 @@ impl Storage {
             project.snapshot_lines_threshold = Some(snapshot_lines_threshold);
         }
 
+        if let Some(file_monitor) = update_request.file_monitor {
+            project.file_monitor = file_monitor;
+        }
+
         self.inner
             .write(PROJECTS_FILE, &serde_json::to_string_pretty(&projects)?)?;
 
@@ -164,3 +164,95 @@ mod delete {
         assert!(!project.gb_dir().exists());
     }
 }
+
+mod access {
+    use super::*;
+    use gitbutler_project::access::{LockFile, LockHolder};
+    use std::time::Duration;
+
+    #[test]
+    fn worktree_access_is_shared_within_and_exclusive_across_processes() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path(), None, None).unwrap();
+
+        let first = project.shared_worktree_access();
+        let second = project
+            .try_shared_worktree_access(Duration::ZERO)
+            .expect("readers of the same process share the inter-process lock");
+        assert_eq!(
+            project.worktree_access_holder().map(|holder| holder.pid),
+            Some(std::process::id())
+        );
+        drop((first, second));
+        assert_eq!(project.worktree_access_holder(), None, "released on drop");
+
+        // Pretend another process holds the lock.
+        let mut foreign = LockFile::open(project.gb_dir().join("worktree.lock")).unwrap();
+        assert!(foreign.try_lock().unwrap());
+        std::fs::write(
+            project.gb_dir().join("worktree.lock.holder"),
+            "4242\nbut commit -m message\n",
+        )
+        .unwrap();
+        assert_eq!(
+            project.worktree_access_holder(),
+            Some(LockHolder {
+                pid: 4242,
+                command: "but commit -m message".into()
+            })
+        );
+        let err = project
+            .try_exclusive_worktree_access(Duration::from_millis(50))
+            .err()
+            .expect("the lock is held by someone else");
+        assert!(
+            err.to_string()
+                .ends_with("which is held by process 4242 (but commit -m message)"),
+            "{err}"
+        );
+
+        foreign.unlock().unwrap();
+        let mut guard = project
+            .try_exclusive_worktree_access(Duration::from_millis(50))
+            .unwrap();
+        let _perm = guard.write_permission();
+    }
+
+    #[test]
+    fn timeouts_are_honoured_while_another_thread_waits_for_another_process() {
+        let (controller, _tmp) = new();
+        let repository = gitbutler_testsupport::TestProject::default();
+        let project = controller.add(repository.path(), None, None).unwrap();
+
+        // Pretend another process holds the lock.
+        let mut foreign = LockFile::open(project.gb_dir().join("worktree.lock")).unwrap();
+        assert!(foreign.try_lock().unwrap());
+
+        let waiting_reader = std::thread::spawn({
+            let project = project.clone();
+            move || drop(project.shared_worktree_access())
+        });
+        // Give the reader time to start waiting for the other process.
+        std::thread::sleep(Duration::from_millis(100));
+
+        let start = std::time::Instant::now();
+        let err = project
+            .try_shared_worktree_access(Duration::from_millis(50))
+            .err()
+            .expect("the lock is still held by the other process");
+        assert!(
+            err.to_string().contains("waiting for the workspace lock"),
+            "{err}"
+        );
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "the timeout is honoured even though the in-process read lock was granted"
+        );
+
+        foreign.unlock().unwrap();
+        waiting_reader
+            .join()
+            .expect("the waiting reader gets the lock once it is released");
+    }
+}
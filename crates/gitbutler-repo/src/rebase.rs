@@ -1,4 +1,7 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use crate::RepositoryExt as _;
 use anyhow::{Context, Result};
@@ -212,6 +215,20 @@ impl ConflictEntries {
         set.len()
     }
 
+    /// Return all conflicting paths, sorted and without duplicates.
+    pub fn paths(&self) -> Vec<&Path> {
+        let mut paths = self
+            .ancestor_entries
+            .iter()
+            .chain(self.our_entries.iter())
+            .chain(self.their_entries.iter())
+            .map(PathBuf::as_path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
     /// Assure that the returned headers will always indicate a conflict.
     /// This is a fail-safe in case this instance has no paths stored as auto-resolution
     /// removed the path that would otherwise be conflicting.
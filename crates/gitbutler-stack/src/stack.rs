@@ -29,7 +29,7 @@ use crate::heads::remove_head;
 use crate::stack_branch::remote_reference;
 use crate::stack_branch::CommitOrChangeId;
 use crate::StackBranch;
-use crate::{ownership::BranchOwnershipClaims, VirtualBranchesHandle};
+use crate::{ownership::BranchOwnershipClaims, Target, VirtualBranchesHandle};
 
 pub type StackId = Id<Stack>;
 
@@ -313,15 +313,16 @@ impl Stack {
         Ok(commits)
     }
 
-    /// Returns the merge base of the stack head and the project's target branch.
-    /// The merge base is the common ancestor of the stack head and the project's target branch.
+    /// Returns the merge base of the stack head and the stack's target branch.
+    /// The merge base is the common ancestor of the stack head and the target branch of the stack,
+    /// which is the project's target branch unless the stack has a target of its own.
     ///
     /// # Errors
     /// - If a target is not set for the project
     /// - If the head commit of the stack is not found
     pub fn merge_base(&self, ctx: &CommandContext) -> Result<gix::ObjectId> {
         let virtual_branch_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
-        let target = virtual_branch_state.get_default_target()?;
+        let target = virtual_branch_state.get_stack_target(self.id)?;
         let gix_repo = ctx.gix_repo()?;
         let merge_base = gix_repo.merge_base(self.head_oid(&gix_repo)?, target.sha.to_gix())?;
         Ok(merge_base.detach())
@@ -457,7 +458,7 @@ impl Stack {
             new_head.head_oid(&gix_repo)?.to_git2(),
             ctx.repo(),
             self.head_oid(&gix_repo)?.to_git2(),
-            &state,
+            &state.get_stack_target(self.id)?,
         )?;
         let updated_heads = add_head(
             self.heads.clone(),
@@ -595,6 +596,39 @@ impl Stack {
         commit_id: git2::Oid,
         tree: Option<git2::Oid>,
     ) -> Result<()> {
+        let branch = self.update_stack_head(gix_repo, commit_id, tree)?;
+        match state {
+            Some(state) => state.set_stack_and_references(self.clone(), &[branch], gix_repo),
+            None => {
+                let (_, head) = get_head(&self.heads, &branch)?;
+                head.set_reference_to_head_value(gix_repo)
+            }
+        }
+    }
+
+    /// Like [`Self::set_stack_head()`] followed by [`Self::set_heads_from_rebase_output()`], but changes
+    /// `virtual_branches.toml` and the references of all affected branches at once, so they can't disagree after a crash.
+    pub fn set_stack_head_and_heads_from_rebase_output(
+        &mut self,
+        ctx: &CommandContext,
+        commit_id: git2::Oid,
+        tree: Option<git2::Oid>,
+        references: Vec<ReferenceSpec>,
+    ) -> Result<()> {
+        let gix_repo = ctx.gix_repo()?;
+        let mut branches = vec![self.update_stack_head(&gix_repo, commit_id, tree)?];
+        branches.extend(self.update_heads(heads_from_rebase_output(ctx, references)?)?);
+        branch_state(ctx).set_stack_and_references(self.clone(), &branches, &gix_repo)
+    }
+
+    /// Update the stack head as described in [`Self::set_stack_head()`], but without touching the reference
+    /// of the most recent branch or persisting anything. Return the name of that branch.
+    fn update_stack_head(
+        &mut self,
+        gix_repo: &gix::Repository,
+        commit_id: git2::Oid,
+        tree: Option<git2::Oid>,
+    ) -> Result<String> {
         self.ensure_initialized()?;
         self.updated_timestamp_ms = gitbutler_time::time::now_ms();
         #[allow(deprecated)] // this is the only place where this is allowed
@@ -610,11 +644,8 @@ impl Stack {
             .last_mut()
             .ok_or_else(|| anyhow!("Invalid state: no heads found"))?;
 
-        head.set_head(commit.id, gix_repo)?;
-        if let Some(state) = state {
-            state.set_stack(self.clone())?;
-        }
-        Ok(())
+        head.set_head_without_reference(commit.id);
+        Ok(head.name().clone())
     }
 
     /// Removes any heads that are refering to commits that are no longer between the stack head and the merge base
@@ -699,8 +730,13 @@ impl Stack {
         ctx: &CommandContext,
         new_heads: HashMap<String, Commit<'_>>,
     ) -> Result<()> {
-        let state = branch_state(ctx);
+        let branches = self.update_heads(new_heads)?;
+        branch_state(ctx).set_stack_and_references(self.clone(), &branches, &ctx.gix_repo()?)
+    }
 
+    /// Point the heads named in `new_heads` to their commits, without touching their references or persisting anything.
+    /// Return the names of all updated heads.
+    fn update_heads(&mut self, new_heads: HashMap<String, Commit<'_>>) -> Result<Vec<String>> {
         // same heads, just differente commits
         if self
             .heads
@@ -712,14 +748,14 @@ impl Stack {
         {
             return Err(anyhow!("The new head names do not match the current heads"));
         }
-        let gix_repo = ctx.gix_repo()?;
+        let mut updated = Vec::new();
         for head in &mut self.heads {
             if let Some(commit) = new_heads.get(head.name()) {
-                head.set_head(commit.clone(), &gix_repo)?;
+                head.set_head_without_reference(commit.clone());
+                updated.push(head.name().clone());
             }
         }
-        state.set_stack(self.clone())?;
-        Ok(())
+        Ok(updated)
     }
 
     /// Sets the stack heads according to the output from the rebase of a `but-rebase` rebase operation
@@ -728,12 +764,7 @@ impl Stack {
         ctx: &CommandContext,
         references: Vec<ReferenceSpec>,
     ) -> anyhow::Result<()> {
-        let mut new_heads: HashMap<String, Commit<'_>> = HashMap::new();
-        for spec in &references {
-            let commit = ctx.repo().find_commit(spec.commit_id.to_git2())?;
-            new_heads.insert(spec.reference.to_string(), commit);
-        }
-
+        let new_heads = heads_from_rebase_output(ctx, references)?;
         self.set_all_heads(ctx, new_heads)
     }
 
@@ -747,7 +778,7 @@ impl Stack {
 
         let stack_head = self.head; // Use the field directly because here the stack heads have not been migrated yet
         let virtual_branch_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
-        let target = virtual_branch_state.get_default_target()?;
+        let target = virtual_branch_state.get_stack_target(self.id)?;
         let merge_base = ctx.repo().merge_base(stack_head, target.sha)?;
 
         for head in self.heads.iter_mut() {
@@ -882,11 +913,9 @@ fn validate_target(
     reference: git2::Oid,
     repo: &git2::Repository,
     stack_head: git2::Oid,
-    state: &VirtualBranchesHandle,
+    stack_target: &Target,
 ) -> Result<()> {
-    let default_target = state.get_default_target()?;
-
-    let merge_base = repo.merge_base(stack_head, default_target.sha)?;
+    let merge_base = repo.merge_base(stack_head, stack_target.sha)?;
     let mut stack_commits = repo
         .log(stack_head, LogUntil::Commit(merge_base), false)?
         .iter()
@@ -925,6 +954,19 @@ fn branch_state(ctx: &CommandContext) -> VirtualBranchesHandle {
     VirtualBranchesHandle::new(ctx.project().gb_dir())
 }
 
+/// Map the names of the references in the output of a `but-rebase` rebase operation to their new commits.
+fn heads_from_rebase_output(
+    ctx: &CommandContext,
+    references: Vec<ReferenceSpec>,
+) -> Result<HashMap<String, Commit<'_>>> {
+    let mut new_heads: HashMap<String, Commit<'_>> = HashMap::new();
+    for spec in &references {
+        let commit = ctx.repo().find_commit(spec.commit_id.to_git2())?;
+        new_heads.insert(spec.reference.to_string(), commit);
+    }
+    Ok(new_heads)
+}
+
 fn patch_reference_exists(state: &VirtualBranchesHandle, name: &str) -> Result<bool> {
     Ok(state
         .list_stacks_in_workspace()?
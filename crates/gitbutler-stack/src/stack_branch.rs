@@ -40,6 +40,9 @@ pub struct StackBranch {
 
     #[serde(default)]
     pub review_id: Option<String>,
+    /// The commit holding uncommitted changes that were stashed on top of this branch, if there is one.
+    #[serde(default, with = "gitbutler_serde::object_id_opt")]
+    pub stash: Option<gix::ObjectId>,
 }
 
 /// A patch identifier which is either `CommitId` or a `ChangeId`.
@@ -95,6 +98,7 @@ impl StackBranch {
             pr_number: None,
             archived: false,
             review_id: None,
+            stash: None,
         };
         branch.set_real_reference(repo, &branch.head)?;
         Ok(branch)
@@ -113,6 +117,7 @@ impl StackBranch {
             pr_number,
             archived,
             review_id,
+            stash: None,
             head: CommitOrChangeId::CommitId(git2::Oid::zero().to_string()),
         }
     }
@@ -157,6 +162,15 @@ impl StackBranch {
         Ok(refname)
     }
 
+    /// Like [`Self::set_head()`], but only updates the cached data in this instance, leaving the git reference
+    /// to be updated along with the persisted state by [`VirtualBranchesHandle::set_stack_and_references()`].
+    pub(crate) fn set_head_without_reference<T>(&mut self, target: T)
+    where
+        T: Into<CommitOrChangeId>,
+    {
+        self.head = target.into();
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
@@ -279,6 +293,19 @@ impl StackBranch {
         Ok(())
     }
 
+    /// Return the full name of the reference of this branch along with the commit that the head property points to,
+    /// which is what [`Self::set_reference_to_head_value()`] would set the reference to.
+    /// Return `None` if the head is a legacy change id.
+    pub fn reference_to_head_value(&self) -> Result<Option<(String, gix::ObjectId)>> {
+        Ok(match &self.head {
+            CommitOrChangeId::CommitId(id) => Some((
+                qualified_reference_name(self.name()),
+                gix::ObjectId::from_str(id)?,
+            )),
+            CommitOrChangeId::ChangeId(_) => None,
+        })
+    }
+
     /// Updates the value on the struct to reflect the current value of the reference.
     /// Returns a boolean indicating whether the reference was updated.
     /// This should not really be needed since the head is always updated, but this function exists as a stopgap measure to be peformed before creating an oplog snapshot.
@@ -6,7 +6,7 @@ use std::{
 use anyhow::{anyhow, Result};
 use git2::Repository;
 use gitbutler_error::error::Code;
-use gitbutler_fs::read_toml_file_or_default;
+use gitbutler_fs::{journal::Journal, read_toml_file_or_default};
 use gitbutler_oxidize::{ObjectIdExt, OidExt as _, RepoExt};
 use gitbutler_reference::Refname;
 use gitbutler_repo::commit_message::CommitMessage;
@@ -27,7 +27,7 @@ const LAST_PUSHED_BASE_VERSION: &str = "1";
 pub struct VirtualBranches {
     /// This is the target/base that is set when a repo is added to gb
     pub default_target: Option<Target>,
-    /// The targets for each virtual branch
+    /// The targets of stacks that don't integrate with `default_target`, like release branches.
     branch_targets: HashMap<StackId, Target>,
     /// The current state of the virtual branches
     pub branches: HashMap<StackId, Stack>,
@@ -111,6 +111,54 @@ impl VirtualBranchesHandle {
         Ok(virtual_branches.default_target)
     }
 
+    /// Persists `target` as the target of the stack with `id`, so it's integrated with `target`
+    /// instead of the default target.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn set_stack_target(&self, id: StackId, target: Target) -> Result<()> {
+        let mut virtual_branches = self.read_file()?;
+        virtual_branches.branch_targets.insert(id, target);
+        self.write_file(&virtual_branches)?;
+        Ok(())
+    }
+
+    /// Removes the target of the stack with `id`, so it uses the default target again.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn remove_stack_target(&self, id: StackId) -> Result<()> {
+        let mut virtual_branches = self.read_file()?;
+        if virtual_branches.branch_targets.remove(&id).is_some() {
+            self.write_file(&virtual_branches)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the target of the stack with `id` if it has one of its own, or `None` if it uses the default target.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn maybe_get_stack_target(&self, id: StackId) -> Result<Option<Target>> {
+        let virtual_branches = self.read_file()?;
+        Ok(virtual_branches.branch_targets.get(&id).cloned())
+    }
+
+    /// Gets the target of the stack with `id`, which is the default target unless the stack has one of its own.
+    ///
+    /// Errors if the file cannot be read or written, or if there is no default target.
+    pub fn get_stack_target(&self, id: StackId) -> Result<Target> {
+        match self.maybe_get_stack_target(id)? {
+            Some(target) => Ok(target),
+            None => self.get_default_target(),
+        }
+    }
+
+    /// Lists the targets of all stacks that have one of their own.
+    ///
+    /// Errors if the file cannot be read or written.
+    pub fn list_stack_targets(&self) -> Result<HashMap<StackId, Target>> {
+        let virtual_branches = self.read_file()?;
+        Ok(virtual_branches.branch_targets)
+    }
+
     /// Sets the state of the given virtual branch.
     ///
     /// Errors if the file cannot be read or written.
@@ -121,6 +169,34 @@ impl VirtualBranchesHandle {
         Ok(())
     }
 
+    /// Sets the state of the given virtual branch, and points the references of its `branches` to their heads.
+    ///
+    /// The state file and the references are changed through a [journal](gitbutler_fs::journal), so even after
+    /// a crash they either all have their new values, or all keep their previous ones.
+    ///
+    /// Errors if the file cannot be read or written, or if a reference cannot be updated.
+    pub fn set_stack_and_references(
+        &self,
+        stack: Stack,
+        branches: &[String],
+        repo: &gix::Repository,
+    ) -> Result<()> {
+        let mut journal = Journal::new(self.file_path.parent().expect("joined in `new()`"));
+        for branch in stack
+            .heads
+            .iter()
+            .filter(|head| branches.contains(head.name()))
+        {
+            if let Some((name, id)) = branch.reference_to_head_value()? {
+                journal.update_reference(name, id);
+            }
+        }
+        let mut virtual_branches = self.read_file()?;
+        virtual_branches.branches.insert(stack.id, stack);
+        journal.write_file(&self.file_path, toml::to_string(&virtual_branches)?);
+        journal.commit(repo)
+    }
+
     /// Marks a particular branch as not in the workspace
     ///
     /// Errors if the file cannot be read or written.
@@ -268,6 +344,7 @@ impl VirtualBranchesHandle {
     pub fn delete_branch_entry(&self, branch_id: &StackId) -> Result<()> {
         let mut virtual_branches = self.read_file()?;
         virtual_branches.branches.remove(branch_id);
+        virtual_branches.branch_targets.remove(branch_id);
         self.write_file(&virtual_branches)?;
         Ok(())
     }
@@ -278,7 +355,8 @@ impl VirtualBranchesHandle {
     ///
     /// Also collects branches with a head oid pointing to a commit that can't be found in the repo
     pub fn garbage_collect(&self, repo: &Repository) -> Result<()> {
-        let target = self.get_default_target()?;
+        let default_target = self.get_default_target()?;
+        let stack_targets = self.list_stack_targets()?;
         let stacks_not_in_workspace = self
             .list_all_stacks()?
             .into_iter()
@@ -297,6 +375,7 @@ impl VirtualBranchesHandle {
                 } else {
                     // if there are no commits between the head and the merge base,
                     // i.e. the head is the merge base, we can GC the branch
+                    let target = stack_targets.get(&branch.id).unwrap_or(&default_target);
                     if branch_head == repo.merge_base(branch_head, target.sha)? {
                         to_remove.push(branch.id);
                     }
@@ -307,6 +386,7 @@ impl VirtualBranchesHandle {
             let mut virtual_branches = self.read_file()?;
             for branch_id in to_remove {
                 virtual_branches.branches.remove(&branch_id);
+                virtual_branches.branch_targets.remove(&branch_id);
             }
             // Perform all removals in one go (Windows doesn't like multiple writes in quick succession)
             self.write_file(&virtual_branches)?;
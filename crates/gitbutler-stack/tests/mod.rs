@@ -646,6 +646,31 @@ fn set_stack_head() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn set_stack_head_updates_reference_and_state_together() -> Result<()> {
+    let (ctx, _temp_dir) = command_ctx("multiple-commits")?;
+    let mut test_ctx = test_ctx(&ctx)?;
+    let commit = test_ctx.other_commits.last().unwrap();
+    let gix_repo = ctx.gix_repo()?;
+    test_ctx
+        .stack
+        .set_stack_head(&test_ctx.handle, &gix_repo, commit.id(), None)?;
+
+    let head = test_ctx.stack.heads.last().unwrap();
+    let mut reference = gix_repo.find_reference(&head.full_name()?)?;
+    assert_eq!(reference.peel_to_id_in_place()?, commit.id().to_gix());
+    let persisted = test_ctx.handle.get_stack(test_ctx.stack.id)?;
+    assert_eq!(
+        persisted.heads.last().unwrap().reference_to_head_value()?,
+        Some((reference.name().as_bstr().to_string(), commit.id().to_gix()))
+    );
+    assert!(
+        !gitbutler_fs::journal::is_pending(&ctx.project().gb_dir()),
+        "the journal is removed once it's applied"
+    );
+    Ok(())
+}
+
 #[test]
 fn archive_heads_noop() -> Result<()> {
     let (ctx, _temp_dir) = command_ctx("multiple-commits")?;
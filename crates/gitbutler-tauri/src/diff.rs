@@ -2,6 +2,7 @@ use crate::error::Error;
 use crate::from_json::HexHash;
 use anyhow::Context;
 use but_core::ui::{TreeChange, TreeChanges, WorktreeChanges};
+use but_core::unified_diff::DiffOptions;
 use but_workspace::StackId;
 use gitbutler_command_context::CommandContext;
 use gitbutler_oxidize::{ObjectIdExt, OidExt};
@@ -12,6 +13,7 @@ use tracing::instrument;
 
 /// Provide a unified diff for `change`, but fail if `change` is a [type-change](but_core::ModeFlags::TypeChange)
 /// or if it involves a change to a [submodule](gix::object::Kind::Commit).
+/// `diff_options` control how the hunks are computed, with the default being used if unset.
 #[tauri::command(async)]
 #[instrument(skip(projects, change, settings), err(Debug))]
 pub fn tree_change_diffs(
@@ -19,12 +21,17 @@ pub fn tree_change_diffs(
     settings: tauri::State<'_, but_settings::AppSettingsWithDiskSync>,
     project_id: ProjectId,
     change: TreeChange,
+    diff_options: Option<DiffOptions>,
 ) -> anyhow::Result<but_core::UnifiedDiff, Error> {
     let change: but_core::TreeChange = change.into();
     let project = projects.get(project_id)?;
     let repo = gix::open(project.path).map_err(anyhow::Error::from)?;
     change
-        .unified_diff(&repo, settings.get()?.context_lines)
+        .unified_diff_with_options(
+            &repo,
+            settings.get()?.context_lines,
+            diff_options.unwrap_or_default(),
+        )
         .map_err(Into::into)
 }
 
@@ -221,6 +221,7 @@ fn main() {
                     virtual_branches::commands::get_base_branch_data,
                     virtual_branches::commands::set_base_branch,
                     virtual_branches::commands::push_base_branch,
+                    virtual_branches::commands::set_stack_target,
                     virtual_branches::commands::integrate_upstream_commits,
                     virtual_branches::commands::update_virtual_branch,
                     virtual_branches::commands::update_branch_order,
@@ -233,6 +234,7 @@ fn main() {
                     virtual_branches::commands::list_commit_files,
                     virtual_branches::commands::reset_virtual_branch,
                     virtual_branches::commands::amend_virtual_branch,
+                    virtual_branches::commands::absorb,
                     virtual_branches::commands::move_commit_file,
                     virtual_branches::commands::undo_commit,
                     virtual_branches::commands::insert_blank_commit,
@@ -244,8 +246,10 @@ fn main() {
                     virtual_branches::commands::squash_commits,
                     virtual_branches::commands::fetch_from_remotes,
                     virtual_branches::commands::move_commit,
+                    virtual_branches::commands::move_branch,
                     virtual_branches::commands::normalize_branch_name,
                     virtual_branches::commands::upstream_integration_statuses,
+                    virtual_branches::commands::preview_upstream_integration,
                     virtual_branches::commands::integrate_upstream,
                     virtual_branches::commands::resolve_upstream_integration,
                     virtual_branches::commands::find_commit,
@@ -276,6 +280,9 @@ fn main() {
                     modes::save_edit_and_return_to_workspace,
                     modes::abort_edit_and_return_to_workspace,
                     modes::edit_initial_index_state,
+                    modes::conflicted_commits,
+                    modes::start_conflict_resolution,
+                    modes::continue_conflict_resolution,
                     open::open_url,
                     forge::commands::get_available_review_templates,
                     forge::commands::get_review_template_contents,
@@ -287,13 +294,19 @@ fn main() {
                     workspace::stack_details,
                     workspace::branch_details,
                     workspace::hunk_dependencies_for_workspace_changes,
+                    workspace::commit_dependency_graph,
                     workspace::create_commit_from_worktree_changes,
                     workspace::amend_commit_from_worktree_changes,
                     workspace::discard_worktree_changes,
                     workspace::stash_into_branch,
+                    workspace::create_stack_stash,
+                    workspace::stack_stashes,
+                    workspace::apply_stack_stash,
+                    workspace::remove_stack_stash,
                     workspace::canned_branch_name,
                     workspace::target_commits,
                     workspace::move_changes_between_commits,
+                    workspace::split_commit,
                     workspace::uncommit_changes,
                     diff::changes_in_worktree,
                     diff::commit_details,
@@ -3,7 +3,7 @@ use but_settings::AppSettingsWithDiskSync;
 use but_workspace::StackId;
 use gitbutler_branch_actions::RemoteBranchFile;
 use gitbutler_command_context::CommandContext;
-use gitbutler_edit_mode::ConflictEntryPresence;
+use gitbutler_edit_mode::{ConflictEntryPresence, ConflictedCommit};
 use gitbutler_operating_modes::EditModeMetadata;
 use gitbutler_operating_modes::OperatingMode;
 use gitbutler_project::Controller;
@@ -98,3 +98,46 @@ pub fn edit_initial_index_state(
 
     gitbutler_edit_mode::commands::starting_index_state(&ctx).map_err(Into::into)
 }
+
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn conflicted_commits(
+    projects: State<'_, Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<Vec<ConflictedCommit>, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+
+    gitbutler_edit_mode::commands::list_conflicted_commits(&ctx).map_err(Into::into)
+}
+
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn start_conflict_resolution(
+    projects: State<'_, Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<EditModeMetadata, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+
+    gitbutler_edit_mode::commands::start_conflict_resolution(&ctx).map_err(Into::into)
+}
+
+#[tauri::command(async)]
+#[instrument(skip(windows, projects, settings), err(Debug))]
+pub fn continue_conflict_resolution(
+    windows: State<'_, WindowState>,
+    projects: State<'_, Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<Option<EditModeMetadata>, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+
+    let next = gitbutler_edit_mode::commands::continue_conflict_resolution(&ctx)?;
+
+    emit_vbranches(&windows, project_id, ctx.app_settings());
+    Ok(next)
+}
@@ -7,12 +7,13 @@ pub mod commands {
     use gitbutler_branch_actions::branch_upstream_integration::IntegrationStrategy;
     use gitbutler_branch_actions::internal::StackListResult;
     use gitbutler_branch_actions::upstream_integration::{
-        BaseBranchResolution, BaseBranchResolutionApproach, IntegrationOutcome, Resolution,
-        StackStatuses,
+        BaseBranchResolution, BaseBranchResolutionApproach, IntegrationOutcome, IntegrationPreview,
+        Resolution, StackStatuses,
     };
     use gitbutler_branch_actions::{
-        BaseBranch, BranchListing, BranchListingDetails, BranchListingFilter, RemoteBranchData,
-        RemoteBranchFile, RemoteCommit, StackOrder, VirtualBranchHunkRangeMap, VirtualBranches,
+        AbsorbOutcome, BaseBranch, BranchDestination, BranchListing, BranchListingDetails,
+        BranchListingFilter, RemoteBranchData, RemoteBranchFile, RemoteCommit, StackOrder,
+        VirtualBranchHunkRangeMap, VirtualBranches,
     };
     use gitbutler_command_context::CommandContext;
     use gitbutler_oxidize::ObjectIdExt;
@@ -213,6 +214,27 @@ pub mod commands {
         Ok(())
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn set_stack_target(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        stack_id: StackId,
+        branch: Option<&str>, // the remote branch to target, or `None` to use the default target
+    ) -> Result<(), Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let branch_name: Option<RemoteRefname> = branch
+            .map(|branch| format!("refs/remotes/{}", branch).parse::<RemoteRefname>())
+            .transpose()
+            .context("Invalid branch name")?;
+        gitbutler_branch_actions::set_stack_target(&ctx, stack_id, branch_name.as_ref())?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(())
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn update_virtual_branch(
@@ -378,6 +400,21 @@ pub mod commands {
         Ok(oid.to_string())
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn absorb(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+    ) -> Result<AbsorbOutcome, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let outcome = gitbutler_branch_actions::absorb(&ctx)?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(outcome)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     #[allow(clippy::too_many_arguments)]
@@ -596,6 +633,29 @@ pub mod commands {
         Ok(())
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings, windows), err(Debug))]
+    pub fn move_branch(
+        windows: State<'_, WindowState>,
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        source_stack_id: StackId,
+        branch_name: String,
+        destination: BranchDestination,
+    ) -> Result<StackId, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        let stack_id = gitbutler_branch_actions::move_branch(
+            &ctx,
+            source_stack_id,
+            &branch_name,
+            destination,
+        )?;
+        emit_vbranches(&windows, project_id, ctx.app_settings());
+        Ok(stack_id)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn update_commit_message(
@@ -648,6 +708,24 @@ pub mod commands {
         )?)
     }
 
+    #[tauri::command(async)]
+    #[instrument(skip(projects, settings), err(Debug))]
+    pub fn preview_upstream_integration(
+        projects: State<'_, projects::Controller>,
+        settings: State<'_, AppSettingsWithDiskSync>,
+        project_id: ProjectId,
+        resolutions: Vec<Resolution>,
+        base_branch_resolution: Option<BaseBranchResolution>,
+    ) -> Result<IntegrationPreview, Error> {
+        let project = projects.get(project_id)?;
+        let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+        Ok(gitbutler_branch_actions::preview_upstream_integration(
+            &ctx,
+            &resolutions,
+            base_branch_resolution,
+        )?)
+    }
+
     #[tauri::command(async)]
     #[instrument(skip(projects, settings, windows), err(Debug))]
     pub fn integrate_upstream(
@@ -168,6 +168,7 @@ pub(crate) mod state {
                 handler,
                 worktree_dir,
                 project_id,
+                &project.file_monitor,
                 app_settings,
             )?;
             state_by_label.insert(
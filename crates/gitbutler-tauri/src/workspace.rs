@@ -3,9 +3,12 @@ use crate::from_json::HexHash;
 use crate::virtual_branches::commands::emit_vbranches;
 use crate::WindowState;
 use anyhow::Context;
+use but_core::unified_diff::DiffOptions;
 use but_hunk_dependency::ui::{
-    hunk_dependencies_for_workspace_changes_by_worktree_dir, HunkDependencies,
+    commit_dependency_graph_by_worktree_dir,
+    hunk_dependencies_for_workspace_changes_by_worktree_dir, Engine, HunkDependencies,
 };
+use but_hunk_dependency::CommitDependencyGraph;
 use but_settings::AppSettingsWithDiskSync;
 use but_workspace::commit_engine::StackSegmentId;
 use but_workspace::MoveChangesResult;
@@ -64,6 +67,7 @@ pub fn branch_details(
 }
 
 /// Retrieve all changes in the workspace and associate them with commits in the Workspace of `project_id`.
+/// `engine` is the algorithm used to associate them, with the default being used if unset.
 /// NOTE: right now there is no way to keep track of unassociated hunks.
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
@@ -71,6 +75,7 @@ pub fn hunk_dependencies_for_workspace_changes(
     projects: State<'_, projects::Controller>,
     settings: State<'_, AppSettingsWithDiskSync>,
     project_id: ProjectId,
+    engine: Option<Engine>,
 ) -> Result<HunkDependencies, Error> {
     let project = projects.get(project_id)?;
     let ctx = CommandContext::open(&project, settings.get()?.clone())?;
@@ -78,10 +83,26 @@ pub fn hunk_dependencies_for_workspace_changes(
         &ctx,
         &project.path,
         &project.gb_dir(),
+        engine.unwrap_or_default(),
     )?;
     Ok(dependencies)
 }
 
+/// Compute which commits in the workspace of `project_id` have to come before which others because their hunks overlap,
+/// including overlaps between commits of different stacks.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn commit_dependency_graph(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<CommitDependencyGraph, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let graph = commit_dependency_graph_by_worktree_dir(&ctx, &project.path, &project.gb_dir())?;
+    Ok(graph)
+}
+
 /// Create a new commit with `message` on top of `parent_id` that contains all `changes`.
 /// If `parent_id` is `None`, this API will infer the parent to be the head of the provided `stack_branch_name`.
 /// `stack_id` is the stack that contains the `parent_id`, and it's fatal if that's not the case.
@@ -90,6 +111,7 @@ pub fn hunk_dependencies_for_workspace_changes(
 /// hunks would fail.
 /// `stack_branch_name` is the short name of the reference that the UI knows is present in a given segment.
 /// It is necessary to insert the new commit into the right bucket.
+/// `diff_options` control how the hunks in `worktree_changes` are computed, and should match the ones used to display them.
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
 #[allow(clippy::too_many_arguments)]
@@ -102,6 +124,7 @@ pub fn create_commit_from_worktree_changes(
     worktree_changes: Vec<but_workspace::DiffSpec>,
     message: String,
     stack_branch_name: String,
+    diff_options: Option<DiffOptions>,
 ) -> Result<commit_engine::ui::CreateCommitOutcome, Error> {
     let project = projects.get(project_id)?;
     let repo = but_core::open_repo_for_merging(project.worktree_path())?;
@@ -139,6 +162,7 @@ pub fn create_commit_from_worktree_changes(
         None,
         worktree_changes,
         settings.get()?.context_lines,
+        diff_options.unwrap_or_default(),
         guard.write_permission(),
     );
 
@@ -164,6 +188,7 @@ pub fn create_commit_from_worktree_changes(
 /// All `changes` are meant to be relative to the worktree.
 /// Note that submodules *must* be provided as diffspec without hunks, as attempting to generate
 /// hunks would fail.
+/// `diff_options` control how the hunks in `worktree_changes` are computed, and should match the ones used to display them.
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
 pub fn amend_commit_from_worktree_changes(
@@ -173,6 +198,7 @@ pub fn amend_commit_from_worktree_changes(
     stack_id: StackId,
     commit_id: HexHash,
     worktree_changes: Vec<but_workspace::DiffSpec>,
+    diff_options: Option<DiffOptions>,
 ) -> Result<commit_engine::ui::CreateCommitOutcome, Error> {
     let project = projects.get(project_id)?;
     let mut guard = project.exclusive_worktree_access();
@@ -189,6 +215,7 @@ pub fn amend_commit_from_worktree_changes(
         None,
         worktree_changes,
         settings.get()?.context_lines,
+        diff_options.unwrap_or_default(),
         guard.write_permission(),
     )?;
     if !outcome.rejected_specs.is_empty() {
@@ -291,6 +318,46 @@ pub fn move_changes_between_commits(
     Ok(result.into())
 }
 
+/// Split the commit `commit_id` of the stack `stack_id` into one commit per group of `(message, changes)`, in order.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command(async)]
+#[instrument(skip(projects, settings, windows), err(Debug))]
+pub fn split_commit(
+    windows: State<'_, WindowState>,
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    stack_id: StackId,
+    commit_id: HexHash,
+    groups: Vec<(String, Vec<but_workspace::DiffSpec>)>,
+) -> Result<UIMoveChangesResult, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let mut guard = project.exclusive_worktree_access();
+
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::SplitCommit),
+        guard.write_permission(),
+    );
+    let result = but_workspace::split_commit_in_stack(
+        &ctx,
+        stack_id,
+        commit_id.into(),
+        groups,
+        settings.get()?.context_lines,
+    )?;
+
+    let vb_state = VirtualBranchesHandle::new(ctx.project().gb_dir());
+    update_workspace_commit(&vb_state, &ctx)?;
+
+    let app_settings = ctx.app_settings();
+    if !app_settings.feature_flags.v3 {
+        emit_vbranches(&windows, project_id, app_settings);
+    }
+
+    Ok(result.into())
+}
+
 #[allow(clippy::too_many_arguments)]
 #[tauri::command(async)]
 #[instrument(skip(projects, settings, windows), err(Debug))]
@@ -334,6 +401,7 @@ pub fn uncommit_changes(
 /// Unlike the regular stash, the user specifies a new branch where those changes will be 'saved'/committed.
 /// Immediatelly after the changes are committed, the branch is unapplied from the workspace, and the "stash" branch can be re-applied at a later time
 /// In theory it should be possible to specify an existing "dumping" branch for this, but currently this endpoint expects a new branch.
+/// `diff_options` control how the hunks in `worktree_changes` are computed, and should match the ones used to display them.
 #[tauri::command(async)]
 #[instrument(skip(projects, settings), err(Debug))]
 pub fn stash_into_branch(
@@ -342,6 +410,7 @@ pub fn stash_into_branch(
     project_id: ProjectId,
     branch_name: String,
     worktree_changes: Vec<but_workspace::DiffSpec>,
+    diff_options: Option<DiffOptions>,
 ) -> Result<commit_engine::ui::CreateCommitOutcome, Error> {
     let project = projects.get(project_id)?;
     let ctx = CommandContext::open(&project, settings.get()?.clone())?;
@@ -381,6 +450,7 @@ pub fn stash_into_branch(
         None,
         worktree_changes,
         settings.get()?.context_lines,
+        diff_options.unwrap_or_default(),
         perm,
     );
 
@@ -394,6 +464,124 @@ pub fn stash_into_branch(
     Ok(outcome.into())
 }
 
+/// Shelve `worktree_changes` on top of the stack with `stack_id` and remove them from the worktree, so they can be
+/// [applied](apply_stack_stash) again later without creating a commit in the stack.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn create_stack_stash(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    stack_id: StackId,
+    worktree_changes: Vec<but_workspace::DiffSpec>,
+) -> Result<but_workspace::stash::ui::CreateOutcome, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let repo = ctx.gix_repo_for_merging()?;
+    let mut guard = project.exclusive_worktree_access();
+
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::StashChanges),
+        guard.write_permission(),
+    );
+    let ref_name = stack_tip_ref_name(&project, stack_id)?;
+    let mut meta = ref_metadata(&project)?;
+    but_workspace::stash::create(
+        &repo,
+        ref_name.as_ref(),
+        worktree_changes,
+        settings.get()?.context_lines,
+        &mut meta,
+    )
+    .map(Into::into)
+    .map_err(Into::into)
+}
+
+/// List all stacks with stashed changes, along with these changes.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn stack_stashes(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+) -> Result<Vec<but_workspace::stash::ui::Stash>, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let repo = ctx.gix_repo()?;
+    let meta = ref_metadata(&project)?;
+    Ok(but_workspace::stash::list(&repo, &meta)?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+}
+
+/// Apply the stash of the stack with `stack_id` to the worktree. If `pop` is `true`, the stash is removed
+/// afterwards unless some of its changes conflicted.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn apply_stack_stash(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    stack_id: StackId,
+    pop: bool,
+) -> Result<but_workspace::stash::ui::ApplyOutcome, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let repo = ctx.gix_repo_for_merging()?;
+    let mut guard = project.exclusive_worktree_access();
+
+    let _ = ctx.create_snapshot(
+        SnapshotDetails::new(OperationKind::ApplyStash),
+        guard.write_permission(),
+    );
+    let ref_name = stack_tip_ref_name(&project, stack_id)?;
+    let mut meta = ref_metadata(&project)?;
+    let mode = if pop {
+        but_workspace::stash::ApplyMode::Pop
+    } else {
+        but_workspace::stash::ApplyMode::Apply
+    };
+    but_workspace::stash::apply(&repo, ref_name.as_ref(), mode, &mut meta)
+        .map(Into::into)
+        .map_err(Into::into)
+}
+
+/// Remove the stash of the stack with `stack_id` without applying it.
+#[tauri::command(async)]
+#[instrument(skip(projects, settings), err(Debug))]
+pub fn remove_stack_stash(
+    projects: State<'_, projects::Controller>,
+    settings: State<'_, AppSettingsWithDiskSync>,
+    project_id: ProjectId,
+    stack_id: StackId,
+) -> Result<bool, Error> {
+    let project = projects.get(project_id)?;
+    let ctx = CommandContext::open(&project, settings.get()?.clone())?;
+    let repo = ctx.gix_repo()?;
+    let _guard = project.exclusive_worktree_access();
+
+    let ref_name = stack_tip_ref_name(&project, stack_id)?;
+    let mut meta = ref_metadata(&project)?;
+    but_workspace::stash::remove(&repo, ref_name.as_ref(), &mut meta).map_err(Into::into)
+}
+
+fn stack_tip_ref_name(
+    project: &projects::Project,
+    stack_id: StackId,
+) -> anyhow::Result<gix::refs::FullName> {
+    let stack = VirtualBranchesHandle::new(project.gb_dir()).get_stack(stack_id)?;
+    Ok(format!("refs/heads/{}", stack.derived_name()?).try_into()?)
+}
+
+fn ref_metadata(
+    project: &projects::Project,
+) -> anyhow::Result<but_workspace::VirtualBranchesTomlMetadata> {
+    but_workspace::VirtualBranchesTomlMetadata::from_path(
+        project.gb_dir().join("virtual_branches.toml"),
+    )
+}
+
 /// Returns a new available branch name based on a simple template - user_initials-branch-count
 /// The main point of this is to be able to provide branch names that are not already taken.
 #[tauri::command(async)]
@@ -352,6 +352,14 @@ impl TestProject {
             .expect("failed to read references")
     }
 
+    pub fn remote_references(&self) -> Vec<git2::Reference<'_>> {
+        self.remote_repo
+            .references()
+            .expect("failed to get references")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("failed to read references")
+    }
+
     pub fn add_submodule(&self, url: &gitbutler_url::Url, path: &path::Path) {
         let mut submodule = self
             .local_repo
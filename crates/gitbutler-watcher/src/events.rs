@@ -110,3 +110,40 @@ pub enum Change {
         changes: but_core::WorktreeChanges,
     },
 }
+
+/// The kind of a [`Change`], without any of its data, useful for filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum ChangeKind {
+    GitFetch,
+    GitHead,
+    GitActivity,
+    VirtualBranches,
+    UncommitedFiles,
+    WorktreeChanges,
+}
+
+impl Change {
+    /// Return the id of the project this change belongs to.
+    pub fn project_id(&self) -> ProjectId {
+        match self {
+            Change::GitFetch(project_id) | Change::GitActivity(project_id) => *project_id,
+            Change::GitHead { project_id, .. }
+            | Change::VirtualBranches { project_id, .. }
+            | Change::UncommitedFiles { project_id, .. }
+            | Change::WorktreeChanges { project_id, .. } => *project_id,
+        }
+    }
+
+    /// Return the kind of this change.
+    pub fn kind(&self) -> ChangeKind {
+        match self {
+            Change::GitFetch(_) => ChangeKind::GitFetch,
+            Change::GitHead { .. } => ChangeKind::GitHead,
+            Change::GitActivity(_) => ChangeKind::GitActivity,
+            Change::VirtualBranches { .. } => ChangeKind::VirtualBranches,
+            Change::UncommitedFiles { .. } => ChangeKind::UncommitedFiles,
+            Change::WorktreeChanges { .. } => ChangeKind::WorktreeChanges,
+        }
+    }
+}
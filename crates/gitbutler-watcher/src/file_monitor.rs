@@ -1,9 +1,17 @@
-use std::{collections::HashSet, path::Path, time::Duration};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Context, Result};
 use gitbutler_notify_debouncer::{new_debouncer, Debouncer, NoCache};
 use gitbutler_oplog::OPLOG_FILE_NAME;
-use gitbutler_project::ProjectId;
+use gitbutler_project::{FileMonitorSettings, ProjectId};
 use notify::{RecommendedWatcher, Watcher};
 use tokio::task;
 use tracing::Level;
@@ -19,12 +27,101 @@ const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(60);
 // The internal rate at which the debouncer will update its state.
 const TICK_RATE: Duration = Duration::from_millis(250);
 
+// The smallest tick rate we accept, as a rate of zero would make the debouncer spin.
+const MIN_TICK_RATE: Duration = Duration::from_millis(1);
+
 // The number of TICK_RATE intervals required of "dead air" (i.e. no new events
 // arriving) before we will automatically flush pending events. This means that
 // after the disk is quiet for TICK_RATE * FLUSH_AFTER_EMPTY, we will process
 // the pending events, even if DEBOUNCE_TIMEOUT hasn't expired yet
 const FLUSH_AFTER_EMPTY: u32 = 3;
 
+// In adaptive mode, a batch of at least this many filesystem events is considered
+// a storm, and processing of worktree changes is delayed until it calms down.
+const STORM_EVENTS: usize = 1000;
+
+// In adaptive mode, if more than this amount of worktree paths changed, they are
+// reported as changes to their directories instead.
+const MAX_PATHS_PER_FLUSH: usize = 1000;
+
+/// The parameters of the file monitor, as derived from [`FileMonitorSettings`].
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    debounce_timeout: Duration,
+    tick_rate: Duration,
+    flush_after_empty: u32,
+    adaptive: bool,
+}
+
+impl From<&FileMonitorSettings> for Config {
+    fn from(settings: &FileMonitorSettings) -> Self {
+        Config {
+            debounce_timeout: settings
+                .debounce_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEBOUNCE_TIMEOUT),
+            tick_rate: settings
+                .tick_rate_ms
+                .map(|ms| Duration::from_millis(ms).max(MIN_TICK_RATE))
+                .unwrap_or(TICK_RATE),
+            flush_after_empty: settings.flush_after_empty.unwrap_or(FLUSH_AFTER_EMPTY),
+            adaptive: settings.adaptive,
+        }
+    }
+}
+
+impl Config {
+    /// The time to wait for more events after the first storm, doubling with each storm that follows.
+    fn initial_backoff(&self) -> Duration {
+        self.tick_rate * self.flush_after_empty.max(1)
+    }
+}
+
+/// Counters to understand what the file monitor is doing, as obtained by [`WatcherHandle::stats()`](crate::WatcherHandle::stats()).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileMonitorStats {
+    /// The amount of filesystem events received from the debouncer.
+    pub events_received: u64,
+    /// The amount of filesystem events that didn't cause any work, as none of their paths was interesting.
+    pub events_dropped: u64,
+    /// The amount of events sent to the handler.
+    pub flushes: u64,
+    /// The amount of paths sent to the handler, as part of all flushes.
+    pub paths_flushed: u64,
+    /// The amount of times changed paths were coalesced into their directories.
+    pub coalesced: u64,
+    /// The amount of times processing of worktree changes was delayed due to an event storm.
+    pub backoffs: u64,
+}
+
+/// The shared, atomic version of [`FileMonitorStats`].
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    events_received: AtomicU64,
+    events_dropped: AtomicU64,
+    flushes: AtomicU64,
+    paths_flushed: AtomicU64,
+    coalesced: AtomicU64,
+    backoffs: AtomicU64,
+}
+
+impl Counters {
+    fn add(counter: &AtomicU64, n: usize) {
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> FileMonitorStats {
+        FileMonitorStats {
+            events_received: self.events_received.load(Ordering::Relaxed),
+            events_dropped: self.events_dropped.load(Ordering::Relaxed),
+            flushes: self.flushes.load(Ordering::Relaxed),
+            paths_flushed: self.paths_flushed.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            backoffs: self.backoffs.load(Ordering::Relaxed),
+        }
+    }
+}
+
 /// This error is required only because `anyhow::Error` isn't implementing `std::error::Error`, and [`spawn()`]
 /// needs to wrap it into a `backoff::Error` which also has to implement the `Error` trait.
 #[derive(Debug, thiserror::Error)]
@@ -49,16 +146,27 @@ struct RunError {
 /// is chosen to allow all this state to live on the stack.
 ///
 /// Additionally, a channel plays better with how events are handled downstream.
+///
+/// ### Adaptive mode
+///
+/// If enabled in `config`, batches with a lot of events delay the processing of worktree changes with an exponential
+/// backoff, so builds don't cause a full worktree status for each batch. Once the filesystem calms down or the
+/// debounce timeout is reached, all collected paths are sent at once, and coalesced into their directories if there
+/// are too many of them. Changes to the `.git` directory are never delayed.
+///
+/// All activity is tracked in `counters`.
 pub fn spawn(
     project_id: ProjectId,
     worktree_path: &std::path::Path,
+    config: Config,
+    counters: Arc<Counters>,
     out: tokio::sync::mpsc::UnboundedSender<InternalEvent>,
 ) -> Result<Debouncer<RecommendedWatcher, NoCache>> {
     let (notify_tx, notify_rx) = std::sync::mpsc::channel();
     let mut debouncer = new_debouncer(
-        DEBOUNCE_TIMEOUT,
-        Some(TICK_RATE),
-        Some(FLUSH_AFTER_EMPTY),
+        config.debounce_timeout,
+        Some(config.tick_rate),
+        Some(config.flush_after_empty),
         notify_tx,
     )
     .context("failed to create debouncer")?;
@@ -115,7 +223,28 @@ pub fn spawn(
         let _runtime = tracing::span!(Level::INFO, "file monitor", %project_id ).entered();
         tracing::debug!(%project_id, "file watcher started");
 
-        'outer: for result in notify_rx {
+        let mut storm = Storm::default();
+        'outer: loop {
+            let result = match storm.flush_at {
+                Some(flush_at) => {
+                    match notify_rx.recv_timeout(flush_at.saturating_duration_since(Instant::now()))
+                    {
+                        Ok(result) => result,
+                        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                            let paths = storm.take(HashSet::new());
+                            if !send_worktree_paths(project_id, paths, &counters, &out) {
+                                break 'outer;
+                            }
+                            continue;
+                        }
+                        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break 'outer,
+                    }
+                }
+                None => match notify_rx.recv() {
+                    Ok(result) => result,
+                    Err(_) => break 'outer,
+                },
+            };
             let stats = tracing::span!(
                 Level::INFO,
                 "handle debounced events",
@@ -135,18 +264,28 @@ pub fn spawn(
                 }
                 Ok(events) => {
                     let num_events = events.len();
+                    Counters::add(&counters.events_received, num_events);
+                    // Each path remembers the index of its event, so events can be counted as dropped
+                    // if none of their paths cause any work.
                     let mut classified_file_paths: Vec<_> = events
                         .into_iter()
-                        .filter(|event| is_interesting_kind(event.kind))
-                        .flat_map(|event| event.event.paths)
-                        .map(|file| {
+                        .enumerate()
+                        .filter(|(_, event)| is_interesting_kind(event.kind))
+                        .flat_map(|(event_idx, event)| {
+                            event
+                                .event
+                                .paths
+                                .into_iter()
+                                .map(move |file| (event_idx, file))
+                        })
+                        .map(|(event_idx, file)| {
                             let kind = classify_file(&git_dir, &file);
-                            (file, kind)
+                            (event_idx, file, kind)
                         })
                         .collect();
                     if classified_file_paths
                         .iter()
-                        .any(|(_, kind)| *kind == FileKind::Project)
+                        .any(|(_, _, kind)| *kind == FileKind::Project)
                     {
                         if let Ok(repo) = gix::open(&worktree_path) {
                             if let Ok(index) = repo.index_or_empty() {
@@ -155,7 +294,7 @@ pub fn spawn(
                                     None,
                                     gix::worktree::stack::state::ignore::Source::WorktreeThenIdMappingIfNotSkipped,
                                 ) {
-                                    for (file_path, kind) in classified_file_paths.iter_mut() {
+                                    for (_, file_path, kind) in classified_file_paths.iter_mut() {
                                         if let Ok(relative_path) = file_path.strip_prefix(&worktree_path) {
                                             let is_excluded = excludes
                                                 .at_path(relative_path, None)
@@ -175,12 +314,14 @@ pub fn spawn(
                     let mut oplog_changed = false;
                     let (mut stripped_git_paths, mut worktree_relative_paths) =
                         (HashSet::new(), HashSet::new());
-                    for (file_path, kind) in classified_file_paths {
+                    let mut events_with_work = HashSet::new();
+                    for (event_idx, file_path, kind) in classified_file_paths {
                         match kind {
                             FileKind::ProjectIgnored => ignored += 1,
                             FileKind::GitUninteresting => git_noop += 1,
                             FileKind::GitButlerOplog => {
                                 oplog_changed = true;
+                                events_with_work.insert(event_idx);
                             }
                             FileKind::Project | FileKind::Git => match file_path
                                 .strip_prefix(&worktree_path)
@@ -189,6 +330,7 @@ pub fn spawn(
                                     if relative_file_path.as_os_str().is_empty() {
                                         continue;
                                     }
+                                    events_with_work.insert(event_idx);
                                     if let Ok(stripped) = relative_file_path.strip_prefix(".git") {
                                         stripped_git_paths.insert(stripped.to_owned());
                                     } else {
@@ -202,6 +344,10 @@ pub fn spawn(
                             },
                         }
                     }
+                    Counters::add(
+                        &counters.events_dropped,
+                        num_events - events_with_work.len(),
+                    );
 
                     stats.record("fs_events", num_events);
                     stats.record("ignored", ignored);
@@ -212,22 +358,35 @@ pub fn spawn(
                     if !stripped_git_paths.is_empty() {
                         let paths_dedup: Vec<_> = stripped_git_paths.into_iter().collect();
                         stats.record("git_dedup", paths_dedup.len());
+                        Counters::add(&counters.flushes, 1);
+                        Counters::add(&counters.paths_flushed, paths_dedup.len());
                         let event = InternalEvent::GitFilesChange(project_id, paths_dedup);
                         if out.send(event).is_err() {
                             tracing::info!("channel closed - stopping file watcher");
                             break 'outer;
                         }
                     }
-                    if !worktree_relative_paths.is_empty() {
-                        let paths_dedup: Vec<_> = worktree_relative_paths.into_iter().collect();
-                        stats.record("project_dedup", paths_dedup.len());
-                        let event = InternalEvent::ProjectFilesChange(project_id, paths_dedup);
-                        if out.send(event).is_err() {
-                            tracing::info!("channel closed - stopping file watcher");
+                    if config.adaptive && num_events >= STORM_EVENTS {
+                        storm.delay(worktree_relative_paths, &config);
+                        Counters::add(&counters.backoffs, 1);
+                        tracing::debug!(
+                            pending = storm.pending.len(),
+                            backoff = ?storm.backoff,
+                            "delaying worktree changes during event storm"
+                        );
+                    } else if !worktree_relative_paths.is_empty() || !storm.pending.is_empty() {
+                        let mut paths = storm.take(worktree_relative_paths);
+                        if config.adaptive && paths.len() > MAX_PATHS_PER_FLUSH {
+                            paths = coalesce_into_directories(paths, MAX_PATHS_PER_FLUSH);
+                            Counters::add(&counters.coalesced, 1);
+                        }
+                        stats.record("project_dedup", paths.len());
+                        if !send_worktree_paths(project_id, paths, &counters, &out) {
                             break 'outer;
                         }
                     }
                     if oplog_changed {
+                        Counters::add(&counters.flushes, 1);
                         let event = InternalEvent::GitButlerOplogChange(project_id);
                         if out.send(event).is_err() {
                             tracing::info!("channel closed - stopping file watcher");
@@ -241,6 +400,80 @@ pub fn spawn(
     Ok(debouncer)
 }
 
+/// Worktree paths whose processing is delayed while the filesystem is flooded with events.
+#[derive(Default)]
+struct Storm {
+    /// The paths collected so far.
+    pending: HashSet<PathBuf>,
+    /// The time to wait for the next batch, doubling with each storm.
+    backoff: Duration,
+    /// The time at which `pending` is sent at the latest, if set.
+    flush_at: Option<Instant>,
+    /// The time at which the first path was delayed.
+    started_at: Option<Instant>,
+}
+
+impl Storm {
+    /// Delay `paths` and back off further, but never beyond the debounce timeout of `config`.
+    fn delay(&mut self, paths: HashSet<PathBuf>, config: &Config) {
+        self.pending.extend(paths);
+        let now = Instant::now();
+        let started_at = *self.started_at.get_or_insert(now);
+        self.backoff = (self.backoff * 2)
+            .max(config.initial_backoff())
+            .min(config.debounce_timeout);
+        let deadline = started_at + config.debounce_timeout;
+        self.flush_at = Some((now + self.backoff).min(deadline));
+    }
+
+    /// Return all pending paths along with `paths`, and reset the backoff.
+    fn take(&mut self, paths: HashSet<PathBuf>) -> HashSet<PathBuf> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.extend(paths);
+        *self = Storm::default();
+        pending
+    }
+}
+
+/// Send `paths` as worktree change, and return `false` if the receiver is gone.
+fn send_worktree_paths(
+    project_id: ProjectId,
+    paths: HashSet<PathBuf>,
+    counters: &Counters,
+    out: &tokio::sync::mpsc::UnboundedSender<InternalEvent>,
+) -> bool {
+    if paths.is_empty() {
+        return true;
+    }
+    Counters::add(&counters.flushes, 1);
+    Counters::add(&counters.paths_flushed, paths.len());
+    let event = InternalEvent::ProjectFilesChange(project_id, paths.into_iter().collect());
+    if out.send(event).is_err() {
+        tracing::info!("channel closed - stopping file watcher");
+        return false;
+    }
+    true
+}
+
+/// Replace all `paths` with their parent directories, one level at a time, until there are
+/// no more than `max` of them or all of them are top-level paths.
+fn coalesce_into_directories(mut paths: HashSet<PathBuf>, max: usize) -> HashSet<PathBuf> {
+    while paths.len() > max {
+        let parents: HashSet<_> = paths
+            .iter()
+            .map(|path| match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+                _ => path.to_owned(),
+            })
+            .collect();
+        if parents == paths {
+            break;
+        }
+        paths = parents;
+    }
+    paths
+}
+
 #[cfg(target_family = "unix")]
 fn is_interesting_kind(kind: notify::EventKind) -> bool {
     matches!(
@@ -282,6 +515,7 @@ fn classify_file(git_dir: &Path, file_path: &Path) -> FileKind {
             || check_file_path == Path::new("HEAD")
             || check_file_path == Path::new("GB_FLUSH")
             || check_file_path == Path::new("index")
+            || check_file_path == Path::new("info/exclude")
         {
             FileKind::Git
         } else if check_file_path == Path::new("gitbutler").join(OPLOG_FILE_NAME) {
@@ -293,3 +527,142 @@ fn classify_file(git_dir: &Path, file_path: &Path) -> FileKind {
         FileKind::Project
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::from(&FileMonitorSettings {
+            debounce_timeout_ms: Some(10_000),
+            tick_rate_ms: Some(100),
+            flush_after_empty: Some(2),
+            adaptive: true,
+        })
+    }
+
+    fn paths(paths: &[&str]) -> HashSet<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    fn sorted(paths: HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = paths.into_iter().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn zero_tick_rate_is_clamped() {
+        let config = Config::from(&FileMonitorSettings {
+            tick_rate_ms: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(config.tick_rate, MIN_TICK_RATE);
+
+        let config = Config::from(&FileMonitorSettings::default());
+        assert_eq!(config.tick_rate, TICK_RATE, "unset values use the default");
+    }
+
+    #[test]
+    fn storm_backoff_doubles_until_debounce_timeout() {
+        let config = config();
+        let mut storm = Storm::default();
+        assert!(storm.flush_at.is_none(), "nothing is delayed initially");
+
+        storm.delay(paths(&["a"]), &config);
+        assert_eq!(storm.backoff, Duration::from_millis(200));
+        let started_at = storm.started_at.expect("set on first delay");
+        let flush_at = storm.flush_at.expect("set on delay");
+        assert!(flush_at >= started_at + storm.backoff);
+
+        storm.delay(paths(&["b"]), &config);
+        assert_eq!(storm.backoff, Duration::from_millis(400));
+        assert_eq!(
+            storm.started_at,
+            Some(started_at),
+            "the start of the storm is kept"
+        );
+
+        for _ in 0..10 {
+            storm.delay(HashSet::new(), &config);
+        }
+        assert_eq!(
+            storm.backoff, config.debounce_timeout,
+            "the backoff never exceeds the debounce timeout"
+        );
+        assert!(
+            storm.flush_at.expect("still set") <= started_at + config.debounce_timeout,
+            "pending paths are sent by the time the debounce timeout is reached"
+        );
+    }
+
+    #[test]
+    fn storm_take_returns_all_paths_and_resets() {
+        let config = config();
+        let mut storm = Storm::default();
+        storm.delay(paths(&["a", "b"]), &config);
+        storm.delay(paths(&["b", "c"]), &config);
+
+        let taken = storm.take(paths(&["d"]));
+        assert_eq!(
+            sorted(taken),
+            sorted(paths(&["a", "b", "c", "d"])),
+            "delayed and new paths are merged"
+        );
+        assert!(storm.pending.is_empty());
+        assert!(storm.flush_at.is_none());
+        assert!(storm.started_at.is_none());
+        assert_eq!(storm.backoff, Duration::ZERO);
+
+        storm.delay(HashSet::new(), &config);
+        assert_eq!(
+            storm.backoff,
+            config.initial_backoff(),
+            "the next storm starts with the initial backoff again"
+        );
+    }
+
+    #[test]
+    fn coalesce_keeps_paths_within_limit() {
+        let input = paths(&["a/b/c", "a/b/d"]);
+        assert_eq!(coalesce_into_directories(input.clone(), 2), input);
+    }
+
+    #[test]
+    fn coalesce_into_parent_directories_one_level_at_a_time() {
+        let input = paths(&["a/b/c", "a/b/d", "a/e/f", "g/h"]);
+        assert_eq!(
+            sorted(coalesce_into_directories(input.clone(), 3)),
+            sorted(paths(&["a/b", "a/e", "g"])),
+            "a single level suffices"
+        );
+        assert_eq!(
+            sorted(coalesce_into_directories(input, 2)),
+            sorted(paths(&["a", "g"]))
+        );
+    }
+
+    #[test]
+    fn coalesce_stops_at_top_level_paths() {
+        let input = paths(&["a/1", "b/2", "c", "d"]);
+        assert_eq!(
+            sorted(coalesce_into_directories(input, 1)),
+            sorted(paths(&["a", "b", "c", "d"])),
+            "top-level paths can't be coalesced further, so the limit may be exceeded"
+        );
+    }
+
+    #[test]
+    fn coalesce_many_paths_below_max_paths_per_flush() {
+        let input: HashSet<_> = (0..MAX_PATHS_PER_FLUSH * 2)
+            .map(|idx| PathBuf::from(format!("dir{}/sub/file{idx}", idx % 10)))
+            .collect();
+        let coalesced = coalesce_into_directories(input, MAX_PATHS_PER_FLUSH);
+        assert_eq!(
+            sorted(coalesced),
+            (0..10)
+                .map(|idx| PathBuf::from(format!("dir{idx}/sub")))
+                .collect::<Vec<_>>()
+        );
+    }
+}
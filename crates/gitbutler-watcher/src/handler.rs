@@ -1,4 +1,4 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{ffi::OsStr, path::PathBuf, sync::Arc};
 
 use anyhow::{Context, Result};
 use but_settings::{AppSettings, AppSettingsWithDiskSync};
@@ -16,7 +16,7 @@ use gitbutler_sync::cloud::{push_oplog, push_repo};
 use gitbutler_user as users;
 use tracing::instrument;
 
-use super::{events, Change};
+use super::{events, Change, Subscriptions};
 
 /// A type that contains enough state to make decisions based on changes in the filesystem, which themselves
 /// may trigger [Changes](Change)
@@ -34,6 +34,19 @@ pub struct Handler {
     /// A function to send events - decoupled from app-handle for testing purposes.
     #[allow(clippy::type_complexity)]
     send_event: Arc<dyn Fn(Change) -> Result<()> + Send + Sync + 'static>,
+    /// Additional consumers of all events we send.
+    subscriptions: Subscriptions,
+    /// The worktree changes we emitted last, to be able to update them incrementally when files change.
+    /// It's also a lock to assure these updates are serialized.
+    last_worktree_changes: Arc<parking_lot::Mutex<Option<LastWorktreeChanges>>>,
+}
+
+/// Worktree changes along with everything they depend on besides the files themselves.
+struct LastWorktreeChanges {
+    project_id: ProjectId,
+    /// The `HEAD^{tree}` the `changes` were computed against.
+    head_tree_id: gix::ObjectId,
+    changes: but_core::WorktreeChanges,
 }
 
 impl Handler {
@@ -48,9 +61,24 @@ impl Handler {
             projects,
             users,
             send_event: Arc::new(send_event),
+            subscriptions: Subscriptions::default(),
+            last_worktree_changes: Default::default(),
         }
     }
 
+    /// Publish all events to `subscriptions` instead of a registry of our own, which allows multiple handlers
+    /// to share the same subscribers.
+    pub fn with_subscriptions(mut self, subscriptions: Subscriptions) -> Self {
+        self.subscriptions = subscriptions;
+        self
+    }
+
+    /// Return the registry through which any number of consumers can [subscribe](Subscriptions::subscribe())
+    /// to the events this handler emits, in addition to the `send_event` function it was created with.
+    pub fn subscriptions(&self) -> &Subscriptions {
+        &self.subscriptions
+    }
+
     /// Handle the events that come in from the filesystem, or the public API.
     #[instrument(skip(self, app_settings), fields(event = %event), err(Debug))]
     pub(super) fn handle(
@@ -87,6 +115,7 @@ impl Handler {
     }
 
     fn emit_app_event(&self, event: Change) -> Result<()> {
+        self.subscriptions.publish(&event);
         (self.send_event)(event).context("failed to send event")
     }
 
@@ -149,7 +178,7 @@ impl Handler {
 
         if ctx.app_settings().feature_flags.v3 {
             // This is part of the v3 APIs set and in the future this fully replaces the list virtual branches flow
-            let _ = self.emit_worktree_changes(ctx.gix_repo()?, ctx.project().id);
+            let _ = self.emit_worktree_changes(ctx.gix_repo()?, ctx.project().id, Some(&paths));
         } else if in_open_workspace_mode(ctx) {
             self.maybe_create_snapshot(ctx).ok();
             self.calculate_virtual_branches(ctx, worktree_changes)?;
@@ -158,8 +187,42 @@ impl Handler {
         Ok(())
     }
 
-    fn emit_worktree_changes(&self, repo: gix::Repository, project_id: ProjectId) -> Result<()> {
-        let detailed_changes = but_core::diff::worktree_changes(&repo)?;
+    /// Emit the changes in the worktree of `repo`, and only recompute the changes at `dirty_paths`
+    /// if these are known and we have a previous result that was computed against the same `HEAD^{tree}`.
+    /// A changed `.gitignore` file may affect any path, so it also leads to recomputing all changes.
+    fn emit_worktree_changes(
+        &self,
+        repo: gix::Repository,
+        project_id: ProjectId,
+        dirty_paths: Option<&[PathBuf]>,
+    ) -> Result<()> {
+        let mut last_worktree_changes = self.last_worktree_changes.lock();
+        let head_tree_id = repo.head_tree_id_or_empty()?.detach();
+        let detailed_changes = match (dirty_paths, last_worktree_changes.take()) {
+            (Some(dirty_paths), Some(last))
+                if last.project_id == project_id
+                    && last.head_tree_id == head_tree_id
+                    && !dirty_paths
+                        .iter()
+                        .any(|path| path.file_name() == Some(OsStr::new(".gitignore"))) =>
+            {
+                but_core::diff::worktree_changes_incremental(
+                    &repo,
+                    last.changes,
+                    dirty_paths.iter().map(|path| {
+                        gix::path::to_unix_separators_on_windows(gix::path::into_bstr(path))
+                            .into_owned()
+                    }),
+                )?
+            }
+            _ => but_core::diff::worktree_changes(&repo)?,
+        };
+        *last_worktree_changes = Some(LastWorktreeChanges {
+            project_id,
+            head_tree_id,
+            changes: detailed_changes.clone(),
+        });
+        drop(last_worktree_changes);
         let _ = self.emit_app_event(Change::WorktreeChanges {
             project_id,
             changes: detailed_changes,
@@ -210,10 +273,15 @@ impl Handler {
                 }
                 "index" => {
                     if ctx.app_settings().feature_flags.v3 {
+                        // The index affects the status of all files, so it must be recomputed entirely.
                         let repo = gix::open(ctx.project().path.clone())?;
-                        let _ = self.emit_worktree_changes(repo, ctx.project().id);
+                        let _ = self.emit_worktree_changes(repo, ctx.project().id, None);
                     }
                 }
+                "info/exclude" => {
+                    // Exclude patterns may affect the status of all files.
+                    self.last_worktree_changes.lock().take();
+                }
                 "HEAD" => {
                     let head_ref = ctx.repo().head().context("failed to get head")?;
                     if let Some(head) = head_ref.name() {
@@ -3,13 +3,13 @@
 #![allow(clippy::doc_markdown, clippy::missing_errors_doc)]
 
 mod events;
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 
 use anyhow::{Context, Result};
 use but_settings::AppSettingsWithDiskSync;
 use events::InternalEvent;
-pub use events::{Action, Change};
-use gitbutler_project::ProjectId;
+pub use events::{Action, Change, ChangeKind};
+use gitbutler_project::{FileMonitorSettings, ProjectId};
 pub use handler::Handler;
 use tokio::{
     sync::mpsc::{unbounded_channel, UnboundedSender},
@@ -18,8 +18,12 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 mod file_monitor;
+pub use file_monitor::FileMonitorStats;
 mod handler;
 
+pub mod subscription;
+pub use subscription::Subscriptions;
+
 /// An abstraction over a link to the spawned watcher, which runs in the background.
 pub struct WatcherHandle {
     /// A way to post events and interact with the actual handler in the background.
@@ -27,6 +31,8 @@ pub struct WatcherHandle {
     /// The id of the project we are watching.
     project_id: ProjectId,
     signal_flush: UnboundedSender<()>,
+    /// Counters that are updated by the file monitor.
+    counters: Arc<file_monitor::Counters>,
     /// A way to tell the background process to stop handling events.
     cancellation_token: CancellationToken,
 }
@@ -55,6 +61,11 @@ impl WatcherHandle {
         self.signal_flush.send(())?;
         Ok(())
     }
+
+    /// Return a snapshot of the counters of the file monitor, for diagnosing its behaviour.
+    pub fn stats(&self) -> FileMonitorStats {
+        self.counters.snapshot()
+    }
 }
 
 /// Run our file watcher processing loop in the background and let `handler` deal with them.
@@ -72,22 +83,34 @@ impl WatcherHandle {
 /// up if they take longer to process than the 100ms window between them, causing high-CPU and possibly
 /// high-memory. However, the likelihood for this is much lower than it was before the architecture
 /// was changed to what it is now, which should be much less wasteful.
+///
+/// `monitor_settings` allow to tune the timings, and to enable an adaptive mode which is more suitable for
+/// large repositories that see a lot of changes at once.
 pub fn watch_in_background(
     handler: handler::Handler,
     worktree_path: impl AsRef<Path>,
     project_id: ProjectId,
+    monitor_settings: &FileMonitorSettings,
     app_settings: AppSettingsWithDiskSync,
 ) -> Result<WatcherHandle, anyhow::Error> {
     let (events_out, mut events_in) = unbounded_channel();
     let (flush_tx, mut flush_rx) = unbounded_channel();
 
-    let debounce = file_monitor::spawn(project_id, worktree_path.as_ref(), events_out.clone())?;
+    let counters = Arc::new(file_monitor::Counters::default());
+    let debounce = file_monitor::spawn(
+        project_id,
+        worktree_path.as_ref(),
+        monitor_settings.into(),
+        counters.clone(),
+        events_out.clone(),
+    )?;
 
     let cancellation_token = CancellationToken::new();
     let handle = WatcherHandle {
         tx: events_out,
         project_id,
         signal_flush: flush_tx,
+        counters: counters.clone(),
         cancellation_token: cancellation_token.clone(),
     };
     let handle_event =
@@ -111,7 +134,7 @@ pub fn watch_in_background(
                     debounce.flush_nonblocking();
                 }
                 () = cancellation_token.cancelled() => {
-                    tracing::debug!(%project_id, "stopped watcher");
+                    tracing::debug!(%project_id, stats = ?counters.snapshot(), "stopped watcher");
                     break;
                 }
             }
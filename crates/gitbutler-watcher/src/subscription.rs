@@ -0,0 +1,420 @@
+//! Allow any number of consumers to receive the [changes](Change) produced by a [`Handler`](crate::Handler),
+//! each with their own [`Filter`].
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use gitbutler_project::ProjectId;
+use gix::bstr::{BStr, BString, ByteSlice};
+use tokio::sync::mpsc;
+
+use crate::{Change, ChangeKind};
+
+/// The amount of events a subscriber can fall behind before events are dropped for it.
+const QUEUE_SIZE: usize = 256;
+
+/// Decide which [changes](Change) a subscriber wants to see.
+///
+/// All conditions must match for an event to be delivered, and conditions that are unset match everything.
+#[derive(Debug, Default, Clone)]
+pub struct Filter {
+    /// If set, only changes of these projects will be delivered.
+    pub project_ids: Option<BTreeSet<ProjectId>>,
+    /// If set, only changes of the given kinds will be delivered.
+    pub kinds: Option<BTreeSet<ChangeKind>>,
+    /// If non-empty, changes that carry paths will be delivered only if at least one of their
+    /// repo-relative paths matches one of these globs, like `src/**/*.rs`.
+    ///
+    /// [`Change::WorktreeChanges`] will only contain the matching changes, but retain all ignored changes.
+    /// Changes that don't carry paths, like [`Change::GitHead`], aren't affected by this.
+    pub paths: Vec<BString>,
+}
+
+impl Filter {
+    fn matches_path(&self, path: &BStr) -> bool {
+        self.paths.is_empty()
+            || self.paths.iter().any(|glob| {
+                gix::glob::wildmatch(
+                    glob.as_bstr(),
+                    path,
+                    gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+                )
+            })
+    }
+
+    /// Return `change` if it passes this filter, possibly with paths removed that don't pass.
+    fn apply(&self, change: &Change) -> Option<Change> {
+        if self
+            .project_ids
+            .as_ref()
+            .is_some_and(|project_ids| !project_ids.contains(&change.project_id()))
+        {
+            return None;
+        }
+        if self
+            .kinds
+            .as_ref()
+            .is_some_and(|kinds| !kinds.contains(&change.kind()))
+        {
+            return None;
+        }
+        match change {
+            Change::WorktreeChanges {
+                project_id,
+                changes,
+            } if !self.paths.is_empty() => {
+                let mut changes = changes.clone();
+                changes
+                    .changes
+                    .retain(|change| self.matches_path(change.path.as_bstr()));
+                (!changes.changes.is_empty()).then_some(Change::WorktreeChanges {
+                    project_id: *project_id,
+                    changes,
+                })
+            }
+            Change::UncommitedFiles { files, .. } if !self.paths.is_empty() => files
+                .iter()
+                .any(|file| self.matches_path(gix::path::into_bstr(&file.path).as_ref()))
+                .then(|| change.clone()),
+            _ => Some(change.clone()),
+        }
+    }
+}
+
+/// A change as delivered to a [`Subscription`].
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// The sequence number of the event, starting at 1 and incremented for each published change,
+    /// independently of the subscriber and its filter.
+    ///
+    /// It's the same for all subscribers that receive the change, so gaps are expected for changes
+    /// that don't match the filter.
+    pub seq: u64,
+    /// The amount of changes matching the filter that were dropped right before this one,
+    /// as the subscriber didn't keep up.
+    pub missed: u64,
+    /// The actual change.
+    pub change: Change,
+}
+
+struct Subscriber {
+    id: u64,
+    filter: Filter,
+    /// The amount of events matching `filter` that were dropped since the last one that was delivered.
+    missed: u64,
+    tx: mpsc::Sender<Event>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    /// The sequence number of the last published change.
+    seq: u64,
+    subscribers: Vec<Subscriber>,
+}
+
+/// A shared registry of subscribers, which receive all changes that are [published](Self::publish()).
+///
+/// Cloning it is cheap and all clones refer to the same subscribers.
+#[derive(Default, Clone)]
+pub struct Subscriptions {
+    state: Arc<parking_lot::Mutex<State>>,
+}
+
+impl Subscriptions {
+    /// Register a new subscriber which will receive all future changes that match `filter`.
+    /// It unsubscribes when the returned subscription is dropped.
+    pub fn subscribe(&self, filter: Filter) -> Subscription {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        let mut state = self.state.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.subscribers.push(Subscriber {
+            id,
+            filter,
+            missed: 0,
+            tx,
+        });
+        Subscription {
+            id,
+            rx,
+            subscriptions: self.clone(),
+        }
+    }
+
+    /// Send `change` to all subscribers whose filter matches it.
+    /// Subscribers that aren't receiving fast enough will miss the event.
+    pub fn publish(&self, change: &Change) {
+        let mut state = self.state.lock();
+        state.seq += 1;
+        let seq = state.seq;
+        state.subscribers.retain_mut(|subscriber| {
+            let Some(change) = subscriber.filter.apply(change) else {
+                return true;
+            };
+            match subscriber.tx.try_send(Event {
+                seq,
+                missed: subscriber.missed,
+                change,
+            }) {
+                Ok(()) => {
+                    subscriber.missed = 0;
+                    true
+                }
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    subscriber.missed += 1;
+                    tracing::warn!(
+                        subscriber = subscriber.id,
+                        seq,
+                        "Dropped event for subscriber that doesn't keep up"
+                    );
+                    true
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            }
+        });
+    }
+
+    /// Return the amount of currently registered subscribers.
+    pub fn len(&self) -> usize {
+        self.state.lock().subscribers.len()
+    }
+
+    /// Return `true` if there is no subscriber.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn set_filter(&self, id: u64, filter: Filter) {
+        if let Some(subscriber) = self
+            .state
+            .lock()
+            .subscribers
+            .iter_mut()
+            .find(|subscriber| subscriber.id == id)
+        {
+            subscriber.filter = filter;
+        }
+    }
+
+    fn unsubscribe(&self, id: u64) {
+        self.state
+            .lock()
+            .subscribers
+            .retain(|subscriber| subscriber.id != id);
+    }
+}
+
+/// A typed stream of [events](Event) matching the filter it was created with.
+pub struct Subscription {
+    id: u64,
+    rx: mpsc::Receiver<Event>,
+    subscriptions: Subscriptions,
+}
+
+impl Subscription {
+    /// Wait for the next event, or return `None` if no more events can arrive.
+    pub async fn recv(&mut self) -> Option<Event> {
+        self.rx.recv().await
+    }
+
+    /// Like [`Self::recv()`], but blocks the current thread, and thus must not be called in an `async` context.
+    pub fn blocking_recv(&mut self) -> Option<Event> {
+        self.rx.blocking_recv()
+    }
+
+    /// Return the next event if one is available right now.
+    pub fn try_recv(&mut self) -> Option<Event> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Receive only changes that match `filter` from now on.
+    /// Events that were already queued are delivered even if they don't match it.
+    pub fn set_filter(&self, filter: Filter) {
+        self.subscriptions.set_filter(self.id, filter);
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.subscriptions.unsubscribe(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use but_core::{ChangeState, TreeChange, TreeStatus, WorktreeChanges};
+
+    fn worktree_changes(project_id: ProjectId, paths: &[&str]) -> Change {
+        Change::WorktreeChanges {
+            project_id,
+            changes: WorktreeChanges {
+                changes: paths
+                    .iter()
+                    .map(|path| TreeChange {
+                        path: (*path).into(),
+                        status: TreeStatus::Addition {
+                            state: ChangeState {
+                                id: gix::hash::Kind::Sha1.null(),
+                                kind: gix::object::tree::EntryKind::Blob,
+                            },
+                            is_untracked: true,
+                        },
+                    })
+                    .collect(),
+                ignored_changes: vec![],
+            },
+        }
+    }
+
+    fn paths(event: &Event) -> Vec<String> {
+        match &event.change {
+            Change::WorktreeChanges { changes, .. } => changes
+                .changes
+                .iter()
+                .map(|change| change.path.to_string())
+                .collect(),
+            other => panic!("unexpected change: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn filter_by_project() {
+        let subscriptions = Subscriptions::default();
+        let (a, b) = (ProjectId::generate(), ProjectId::generate());
+        let mut sub = subscriptions.subscribe(Filter {
+            project_ids: Some([a].into()),
+            ..Default::default()
+        });
+
+        subscriptions.publish(&Change::GitFetch(b));
+        subscriptions.publish(&Change::GitFetch(a));
+        let event = sub.try_recv().expect("only the change of `a` is delivered");
+        assert!(matches!(event.change, Change::GitFetch(id) if id == a));
+        assert_eq!(
+            event.seq, 2,
+            "the sequence number is the one of the shared stream"
+        );
+        assert!(sub.try_recv().is_none());
+    }
+
+    #[test]
+    fn filter_by_kind() {
+        let subscriptions = Subscriptions::default();
+        let project_id = ProjectId::generate();
+        let mut sub = subscriptions.subscribe(Filter {
+            kinds: Some([ChangeKind::GitActivity].into()),
+            ..Default::default()
+        });
+
+        subscriptions.publish(&Change::GitFetch(project_id));
+        subscriptions.publish(&Change::GitActivity(project_id));
+        assert_eq!(
+            sub.try_recv().map(|event| event.change.kind()),
+            Some(ChangeKind::GitActivity)
+        );
+        assert!(sub.try_recv().is_none());
+    }
+
+    #[test]
+    fn filter_by_path_glob() {
+        let subscriptions = Subscriptions::default();
+        let project_id = ProjectId::generate();
+        let mut sub = subscriptions.subscribe(Filter {
+            paths: vec!["src/**/*.rs".into()],
+            ..Default::default()
+        });
+
+        subscriptions.publish(&worktree_changes(project_id, &["README.md"]));
+        subscriptions.publish(&worktree_changes(
+            project_id,
+            &["src/lib.rs", "src/a/b.rs", "src/a/b.txt", "lib.rs"],
+        ));
+        subscriptions.publish(&Change::GitFetch(project_id));
+
+        let event = sub
+            .try_recv()
+            .expect("the second change has matching paths");
+        assert_eq!(event.seq, 2);
+        assert_eq!(
+            paths(&event),
+            ["src/lib.rs", "src/a/b.rs"],
+            "non-matching paths are removed"
+        );
+        assert_eq!(
+            sub.try_recv().map(|event| event.change.kind()),
+            Some(ChangeKind::GitFetch),
+            "changes without paths pass"
+        );
+        assert!(sub.try_recv().is_none());
+    }
+
+    #[test]
+    fn sequence_numbers_are_shared_and_drops_are_counted() {
+        let subscriptions = Subscriptions::default();
+        let project_id = ProjectId::generate();
+        let mut all = subscriptions.subscribe(Filter::default());
+        let mut slow = subscriptions.subscribe(Filter::default());
+
+        for _ in 0..QUEUE_SIZE + 2 {
+            subscriptions.publish(&Change::GitFetch(project_id));
+            all.try_recv().expect("keeps up");
+        }
+        for expected_seq in 1..=QUEUE_SIZE as u64 {
+            let event = slow.try_recv().expect("queued");
+            assert_eq!(event.seq, expected_seq);
+            assert_eq!(event.missed, 0);
+        }
+        assert!(
+            slow.try_recv().is_none(),
+            "the last two events were dropped"
+        );
+
+        subscriptions.publish(&Change::GitFetch(project_id));
+        let event = slow.try_recv().expect("there is room again");
+        assert_eq!(
+            event.seq,
+            QUEUE_SIZE as u64 + 3,
+            "the gap shows in the sequence"
+        );
+        assert_eq!(event.missed, 2, "and the amount of dropped events is known");
+        assert_eq!(all.try_recv().map(|event| event.missed), Some(0));
+    }
+
+    #[test]
+    fn set_filter_affects_future_events() {
+        let subscriptions = Subscriptions::default();
+        let (a, b) = (ProjectId::generate(), ProjectId::generate());
+        let mut sub = subscriptions.subscribe(Filter {
+            project_ids: Some(BTreeSet::new()),
+            ..Default::default()
+        });
+        subscriptions.publish(&Change::GitFetch(a));
+        assert!(
+            sub.try_recv().is_none(),
+            "an empty set of projects matches nothing"
+        );
+
+        sub.set_filter(Filter {
+            project_ids: Some([a, b].into()),
+            ..Default::default()
+        });
+        subscriptions.publish(&Change::GitFetch(b));
+        assert!(
+            matches!(sub.try_recv().map(|event| event.change), Some(Change::GitFetch(id)) if id == b)
+        );
+    }
+
+    #[test]
+    fn dropped_subscriptions_are_removed() {
+        let subscriptions = Subscriptions::default();
+        let first = subscriptions.subscribe(Filter::default());
+        let second = subscriptions.subscribe(Filter::default());
+        assert_eq!(subscriptions.len(), 2);
+
+        drop(first);
+        assert_eq!(subscriptions.len(), 1);
+        drop(second);
+        assert!(subscriptions.is_empty());
+    }
+}
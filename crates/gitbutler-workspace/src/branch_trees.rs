@@ -1,9 +1,10 @@
 use anyhow::{bail, Result};
 use gitbutler_cherry_pick::RepositoryExt;
-use gitbutler_command_context::CommandContext;
+use gitbutler_command_context::{gix_repo_for_merging, CommandContext};
 use gitbutler_commit::commit_ext::CommitExt as _;
 use gitbutler_oxidize::{
-    git2_to_gix_object_id, gix_to_git2_oid, GixRepositoryExt, ObjectIdExt, OidExt, RepoExt,
+    git2_to_gix_object_id, gix_to_git2_index, gix_to_git2_oid, GixRepositoryExt, ObjectIdExt,
+    OidExt, RepoExt,
 };
 use gitbutler_project::access::{WorktreeReadPermission, WorktreeWritePermission};
 use gitbutler_project::AUTO_TRACK_LIMIT_BYTES;
@@ -174,28 +175,39 @@ fn move_tree_between_workspaces(
     old: WorkspaceState,
     new: WorkspaceState,
 ) -> Result<git2::Index> {
-    let old_workspace = merge_workspace(repo, old)?;
-    let new_workspace = merge_workspace(repo, new)?;
-    move_tree(repo, tree, old_workspace, new_workspace)
+    let gix_repo = gix_repo_for_merging(repo.path())?;
+    let old_workspace = merge_workspace(&gix_repo, old)?;
+    let new_workspace = merge_workspace(&gix_repo, new)?;
+    move_tree(&gix_repo, tree.to_gix(), old_workspace, new_workspace)
 }
 
 /// Cherry pick a tree from one base tree on to another, favoring the contents of the tree when conflicts occur
+///
+/// Merges are performed with `gitoxide` so `merge` attributes and configured merge drivers are honored.
 fn move_tree(
-    repo: &git2::Repository,
-    tree: git2::Oid,
-    old_workspace: git2::Oid,
-    new_workspace: git2::Oid,
+    repo: &gix::Repository,
+    tree: gix::ObjectId,
+    old_workspace: gix::ObjectId,
+    new_workspace: gix::ObjectId,
 ) -> Result<git2::Index> {
     // Read: Take the diff between old_workspace and tree, and apply it on top
     //   of new_workspace
-    let merge = repo.merge_trees(
-        &repo.find_tree(old_workspace)?,
-        &repo.find_tree(tree)?,
-        &repo.find_tree(new_workspace)?,
-        None,
+    let mut merge = repo.merge_trees(
+        old_workspace,
+        tree,
+        new_workspace,
+        repo.default_merge_labels(),
+        repo.tree_merge_options()?,
     )?;
-
-    Ok(merge)
+    let merged_tree_id = merge.tree.write()?;
+    let mut index = repo.index_from_tree(&merged_tree_id)?;
+    merge.index_changed_after_applying_conflicts(
+        &mut index,
+        gix::merge::tree::TreatAsUnresolved::git(),
+        gix::merge::tree::apply_index_entries::RemovalMode::Mark,
+    );
+
+    gix_to_git2_index(&index)
 }
 
 /// Octopus merge
@@ -203,21 +215,24 @@ fn move_tree(
 /// to the given base.
 ///
 /// If there are no heads provided, the base will be returned.
-fn merge_workspace(repo: &git2::Repository, workspace: WorkspaceState) -> Result<git2::Oid> {
-    let mut output = workspace.base;
+fn merge_workspace(repo: &gix::Repository, workspace: WorkspaceState) -> Result<gix::ObjectId> {
+    let base = workspace.base.to_gix();
+    let mut output = base;
 
+    let (merge_options_fail_fast, conflict_kind) = repo.merge_options_fail_fast()?;
     for head in workspace.heads {
-        let mut merge_options = git2::MergeOptions::new();
-        merge_options.fail_on_conflict(true);
-
         let mut merge = repo.merge_trees(
-            &repo.find_tree(workspace.base)?,
-            &repo.find_tree(output)?,
-            &repo.find_tree(head)?,
-            Some(&merge_options),
+            base,
+            output,
+            head.to_gix(),
+            repo.default_merge_labels(),
+            merge_options_fail_fast.clone(),
         )?;
+        if merge.has_unresolved_conflicts(conflict_kind) {
+            bail!("There appears to be conflicts between the virtual branches");
+        }
 
-        output = merge.write_tree_to(repo)?;
+        output = merge.tree.write()?.detach();
     }
 
     Ok(output)
@@ -314,3 +329,128 @@ pub fn compute_updated_branch_head_for_commits(
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gitbutler_testsupport::testing_repository::{assert_tree_matches, TestingRepository};
+
+    const ATTRIBUTES: (&str, &str) = (
+        ".gitattributes",
+        "*.union merge=union\n*.lock merge=keep-ours\n",
+    );
+
+    #[test]
+    fn moving_uncommitted_changes_honors_merge_drivers() -> Result<()> {
+        let test_repository = TestingRepository::open();
+        let repo = &test_repository.repository;
+        repo.config()?
+            .open_level(git2::ConfigLevel::Local)?
+            .set_str("merge.keep-ours.driver", "true")?;
+
+        let base = test_repository.commit_tree(
+            None,
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\n"),
+                ("file.lock", "base\n"),
+            ],
+        );
+        let stack = test_repository.commit_tree(
+            Some(&base),
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\nstack\n"),
+                ("file.lock", "stack\n"),
+            ],
+        );
+        let uncommitted = test_repository.commit_tree(
+            Some(&base),
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\nworktree\n"),
+                ("file.lock", "worktree\n"),
+            ],
+        );
+
+        let old = WorkspaceState {
+            heads: vec![],
+            base: base.tree_id(),
+        };
+        let new = WorkspaceState {
+            heads: vec![stack.tree_id()],
+            base: base.tree_id(),
+        };
+        let mut index = move_tree_between_workspaces(repo, uncommitted.tree_id(), old, new)?;
+        assert!(
+            !index.has_conflicts(),
+            "both files are resolved by their merge drivers"
+        );
+
+        let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+        assert_tree_matches(
+            repo,
+            &tree,
+            &[
+                ("file.union", b"base\nworktree\nstack\n"),
+                ("file.lock", b"worktree\n"),
+            ],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merging_the_workspace_honors_merge_drivers() -> Result<()> {
+        let test_repository = TestingRepository::open();
+        let repo = &test_repository.repository;
+        repo.config()?
+            .open_level(git2::ConfigLevel::Local)?
+            .set_str("merge.keep-ours.driver", "true")?;
+
+        let base = test_repository.commit_tree(
+            None,
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\n"),
+                ("file.lock", "base\n"),
+            ],
+        );
+        let first = test_repository.commit_tree(
+            Some(&base),
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\nfirst\n"),
+                ("file.lock", "first\n"),
+            ],
+        );
+        let second = test_repository.commit_tree(
+            Some(&base),
+            &[
+                ATTRIBUTES,
+                ("file.union", "base\nsecond\n"),
+                ("file.lock", "second\n"),
+            ],
+        );
+
+        let gix_repo = gix_repo_for_merging(repo.path())?;
+        let workspace = merge_workspace(
+            &gix_repo,
+            WorkspaceState {
+                heads: vec![first.tree_id(), second.tree_id()],
+                base: base.tree_id(),
+            },
+        )
+        .expect("stacks touching the same lines merge cleanly thanks to the drivers");
+
+        let tree = repo.find_tree(workspace.to_git2())?;
+        assert_tree_matches(
+            repo,
+            &tree,
+            &[
+                ("file.union", b"base\nfirst\nsecond\n"),
+                ("file.lock", b"first\n"),
+            ],
+        );
+        Ok(())
+    }
+}
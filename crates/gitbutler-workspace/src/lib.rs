@@ -24,12 +24,26 @@ pub fn workspace_base(
     let default_target = vb_state.get_default_target()?;
     let target_branch_commit = repo.find_commit(default_target.sha)?.id().to_gix();
     let stacks = vb_state.list_stacks_in_workspace()?;
+    let stack_targets = vb_state.list_stack_targets()?;
     let stack_heads = stacks
         .iter()
         .map(|b| b.head_oid(&gix_repo))
         .collect::<Result<Vec<_>>>()?;
+    // Stacks with a target of their own, like a release branch, pull the base down to where their target forked off.
+    let stack_target_commits = stacks
+        .iter()
+        .filter_map(|stack| stack_targets.get(&stack.id))
+        .map(|target| target.sha.to_gix())
+        .collect::<Vec<_>>();
     let merge_base_id = gix_repo
-        .merge_base_octopus([stack_heads, vec![target_branch_commit]].concat())?
+        .merge_base_octopus(
+            [
+                stack_heads,
+                stack_target_commits,
+                vec![target_branch_commit],
+            ]
+            .concat(),
+        )?
         .object()?
         .id()
         .detach();
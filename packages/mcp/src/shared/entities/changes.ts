@@ -55,6 +55,20 @@ export const TreeChangeSchema = z.object({
 	status: StatusSchema
 });
 
+export const BinaryResourceSchema = z.object({
+	path: z.string(),
+	id: z.string(),
+	sizeInBytes: z.number(),
+	mimeType: z.string().nullable(),
+	imageDimensions: z.object({ width: z.number(), height: z.number() }).nullable()
+});
+
+export const BinaryDiffSchema = z.object({
+	old: BinaryResourceSchema.nullable(),
+	new: BinaryResourceSchema.nullable(),
+	contentUnchanged: z.boolean()
+});
+
 export const TooLargeSchema = z.object({
 	sizeInBytes: z.number()
 });
@@ -77,7 +91,7 @@ export const PatchSchema = z.object({
 });
 
 export const UnifiedDiffSchema = z.discriminatedUnion('type', [
-	z.object({ type: z.literal('Binary') }),
+	z.object({ type: z.literal('Binary'), subject: BinaryDiffSchema }),
 	z.object({ type: z.literal('TooLarge'), subject: TooLargeSchema }),
 	z.object({ type: z.literal('Patch'), subject: PatchSchema })
 ]);